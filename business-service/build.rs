@@ -0,0 +1,4 @@
+// Embedded migrations are compiled into the binary, so rebuild when they change.
+fn main() {
+    println!("cargo:rerun-if-changed=migrations");
+}
@@ -0,0 +1,6 @@
+-- Monetary amounts carry an explicit ISO 4217 currency; all existing rows were priced in USD
+ALTER TABLE orders ADD COLUMN IF NOT EXISTS currency CHAR(3) NOT NULL DEFAULT 'USD';
+ALTER TABLE order_items ADD COLUMN IF NOT EXISTS currency CHAR(3) NOT NULL DEFAULT 'USD';
+
+ALTER TABLE orders ALTER COLUMN currency DROP DEFAULT;
+ALTER TABLE order_items ALTER COLUMN currency DROP DEFAULT;
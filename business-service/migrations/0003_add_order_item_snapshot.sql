@@ -0,0 +1,6 @@
+-- Line items keep the product name and SKU they were sold under; older rows predate the snapshot
+ALTER TABLE order_items ADD COLUMN IF NOT EXISTS product_name TEXT NOT NULL DEFAULT '';
+ALTER TABLE order_items ADD COLUMN IF NOT EXISTS product_sku TEXT NOT NULL DEFAULT '';
+
+ALTER TABLE order_items ALTER COLUMN product_name DROP DEFAULT;
+ALTER TABLE order_items ALTER COLUMN product_sku DROP DEFAULT;
@@ -0,0 +1,15 @@
+-- Progress of operations that span Postgres and MongoDB, so they can be resumed or
+-- compensated after a crash
+CREATE TABLE IF NOT EXISTS sagas (
+    id UUID PRIMARY KEY,
+    kind VARCHAR(50) NOT NULL,
+    payload JSONB NOT NULL,
+    status VARCHAR(20) NOT NULL,
+    completed_steps INTEGER NOT NULL,
+    error TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_sagas_unfinished ON sagas (updated_at)
+    WHERE status IN ('running', 'compensating');
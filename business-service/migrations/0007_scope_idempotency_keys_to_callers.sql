@@ -0,0 +1,3 @@
+-- Keys are now stored prefixed with the caller they belong to, which can take them past
+-- the length clients are allowed to send
+ALTER TABLE idempotency_keys ALTER COLUMN key TYPE TEXT;
@@ -0,0 +1,10 @@
+-- Failed publish attempts. An event that failed waits until `retry_at`, holding back the later
+-- events of its aggregate; one that keeps failing is dead-lettered and skipped until replayed.
+ALTER TABLE outbox_events ADD COLUMN IF NOT EXISTS attempts INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE outbox_events ADD COLUMN IF NOT EXISTS last_error TEXT;
+ALTER TABLE outbox_events ADD COLUMN IF NOT EXISTS retry_at TIMESTAMP WITH TIME ZONE;
+ALTER TABLE outbox_events ADD COLUMN IF NOT EXISTS dead_lettered_at TIMESTAMP WITH TIME ZONE;
+
+DROP INDEX IF EXISTS idx_outbox_events_unpublished;
+CREATE INDEX IF NOT EXISTS idx_outbox_events_pending ON outbox_events (sequence)
+    WHERE published_at IS NULL AND dead_lettered_at IS NULL;
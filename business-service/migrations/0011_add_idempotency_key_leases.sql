@@ -0,0 +1,5 @@
+-- How long a request that claimed a key may take. A key still without a response after that
+-- belongs to a request that died, and the next request with the key takes it over.
+ALTER TABLE idempotency_keys ADD COLUMN IF NOT EXISTS locked_until TIMESTAMP WITH TIME ZONE;
+UPDATE idempotency_keys SET locked_until = created_at WHERE locked_until IS NULL;
+ALTER TABLE idempotency_keys ALTER COLUMN locked_until SET NOT NULL;
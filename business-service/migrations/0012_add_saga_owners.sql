@@ -0,0 +1,3 @@
+-- The coordinator driving each saga. Saves from any other are refused, so a saga taken over
+-- after it stalled is only driven by the coordinator that took it over.
+ALTER TABLE sagas ADD COLUMN IF NOT EXISTS owner UUID;
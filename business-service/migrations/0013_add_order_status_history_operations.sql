@@ -0,0 +1,6 @@
+-- The saga action that made each status change, so a repeated action recognises its own
+-- change and a concurrent one made by another saga is not mistaken for it
+ALTER TABLE order_status_history ADD COLUMN IF NOT EXISTS operation_id UUID;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_order_status_history_operation_id
+    ON order_status_history (order_id, operation_id) WHERE operation_id IS NOT NULL;
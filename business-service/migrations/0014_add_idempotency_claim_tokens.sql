@@ -0,0 +1,3 @@
+-- Identifies the request holding each key's claim. Only that request may store a response or
+-- release the key, so one that outlived its lease cannot overwrite the request that took over.
+ALTER TABLE idempotency_keys ADD COLUMN IF NOT EXISTS claim_token UUID;
@@ -0,0 +1,13 @@
+-- Where each line item appears in its order, so items are read back in the order they were
+-- placed. Existing items are numbered in the order their rows were stored.
+ALTER TABLE order_items ADD COLUMN IF NOT EXISTS position INTEGER;
+
+UPDATE order_items
+SET position = numbered.position
+FROM (
+    SELECT id, ROW_NUMBER() OVER (PARTITION BY order_id ORDER BY ctid) - 1 AS position
+    FROM order_items
+) AS numbered
+WHERE order_items.id = numbered.id AND order_items.position IS NULL;
+
+ALTER TABLE order_items ALTER COLUMN position SET NOT NULL;
@@ -0,0 +1,80 @@
+use actix_web::{web, HttpResponse};
+use uuid::Uuid;
+use crate::errors::{Problem, ServiceResult};
+use crate::models::api_key::{ApiKey, CreateApiKeyDto, IssuedApiKey};
+use crate::services::ApiKeyService;
+
+#[utoipa::path(
+    get,
+    path = "/api/keys",
+    tag = "api-keys",
+    summary = "List API keys",
+    responses(
+        (status = 200, description = "All keys, including revoked ones", body = Vec<ApiKey>),
+    ),
+)]
+pub async fn get_all_api_keys(
+    service: web::Data<ApiKeyService>,
+) -> ServiceResult<HttpResponse> {
+    let keys = service.get_all_keys().await?;
+    Ok(HttpResponse::Ok().json(keys))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/keys",
+    tag = "api-keys",
+    summary = "Issue an API key",
+    request_body = CreateApiKeyDto,
+    responses(
+        (status = 201, description = "The key, including its secret", body = IssuedApiKey),
+        (status = 400, description = "Malformed body or unknown scope", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Blank name or no scopes", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+pub async fn create_api_key(
+    service: web::Data<ApiKeyService>,
+    api_key: web::Json<CreateApiKeyDto>,
+) -> ServiceResult<HttpResponse> {
+    let issued = service.issue(api_key.into_inner()).await?;
+    Ok(HttpResponse::Created().json(issued))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/keys/{id}/rotate",
+    tag = "api-keys",
+    summary = "Rotate an API key's secret",
+    params(("id" = Uuid, Path, description = "API key id")),
+    responses(
+        (status = 200, description = "The key with its new secret", body = IssuedApiKey),
+        (status = 404, description = "No such key", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "The key is revoked", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+pub async fn rotate_api_key(
+    service: web::Data<ApiKeyService>,
+    path: web::Path<Uuid>,
+) -> ServiceResult<HttpResponse> {
+    let issued = service.rotate(path.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(issued))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/keys/{id}",
+    tag = "api-keys",
+    summary = "Revoke an API key",
+    params(("id" = Uuid, Path, description = "API key id")),
+    responses(
+        (status = 204, description = "The key was revoked"),
+        (status = 404, description = "No such key", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+pub async fn revoke_api_key(
+    service: web::Data<ApiKeyService>,
+    path: web::Path<Uuid>,
+) -> ServiceResult<HttpResponse> {
+    service.revoke(path.into_inner()).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
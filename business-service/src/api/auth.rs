@@ -0,0 +1,60 @@
+use std::future::{ready, Ready};
+use actix_web::body::{BoxBody, MessageBody};
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::http::header;
+use actix_web::middleware::Next;
+use actix_web::{web, Error, FromRequest, HttpMessage, HttpRequest, ResponseError};
+use crate::errors::{ServiceError, ServiceResult};
+use crate::models::auth::Caller;
+use crate::services::{ApiKeyService, AuthService};
+
+pub const API_KEY_HEADER: &str = "X-Api-Key";
+
+// Requires either an `X-Api-Key` header or a valid `Authorization: Bearer <token>` header and
+// makes the caller available to handlers as a `Caller`. Anything else is answered with 401.
+pub async fn authenticate(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    match identify(&req).await {
+        Ok(caller) => {
+            req.extensions_mut().insert(caller);
+            next.call(req).await.map(ServiceResponse::map_into_boxed_body)
+        }
+        Err(e) => Ok(req.into_response(e.error_response())),
+    }
+}
+
+async fn identify(req: &ServiceRequest) -> ServiceResult<Caller> {
+    let header_value = |name: &str| {
+        req.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.trim().to_string())
+    };
+    
+    if let Some(key) = header_value(API_KEY_HEADER) {
+        let api_keys = req.app_data::<web::Data<ApiKeyService>>()
+            .ok_or_else(|| ServiceError::ConfigError("API keys are not configured".to_string()))?;
+        return api_keys.authenticate(&key).await.map(Caller::ApiKey);
+    }
+    
+    let auth = req.app_data::<web::Data<AuthService>>()
+        .ok_or_else(|| ServiceError::ConfigError("Authentication is not configured".to_string()))?;
+    let token = header_value(header::AUTHORIZATION.as_str())
+        .and_then(|value| value.strip_prefix("Bearer ").map(|token| token.trim().to_string()))
+        .ok_or_else(|| ServiceError::AuthError("Missing bearer token or API key".to_string()))?;
+    
+    auth.authenticate(&token).map(Caller::User)
+}
+
+impl FromRequest for Caller {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+    
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let caller = req.extensions().get::<Caller>().cloned();
+        
+        ready(caller.ok_or_else(|| ServiceError::AuthError("Request is not authenticated".to_string()).into()))
+    }
+}
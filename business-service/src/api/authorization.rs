@@ -0,0 +1,65 @@
+use std::future::{ready, Ready};
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::{Error, HttpMessage, ResponseError};
+use futures_util::future::LocalBoxFuture;
+use crate::errors::ServiceError;
+use crate::models::auth::{Caller, Permission};
+
+// Route middleware that only lets callers holding `permission` through, answering 403
+// otherwise. Declared per route in `api::routes`, inside the authenticated scopes.
+#[derive(Debug, Clone, Copy)]
+pub struct Require(pub Permission);
+
+impl<S, B> Transform<S, ServiceRequest> for Require
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = RequireMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+    
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequireMiddleware {
+            service,
+            permission: self.0,
+        }))
+    }
+}
+
+pub struct RequireMiddleware<S> {
+    service: S,
+    permission: Permission,
+}
+
+impl<S, B> Service<ServiceRequest> for RequireMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+    
+    forward_ready!(service);
+    
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let allowed = req.extensions()
+            .get::<Caller>()
+            .map(|caller| caller.can(self.permission));
+        
+        let error = match allowed {
+            Some(true) => {
+                let response = self.service.call(req);
+                return Box::pin(async move { response.await.map(ServiceResponse::map_into_left_body) });
+            }
+            Some(false) => ServiceError::ForbiddenError(format!("Missing permission {}", self.permission.as_str())),
+            None => ServiceError::AuthError("Request is not authenticated".to_string()),
+        };
+        
+        Box::pin(ready(Ok(req.into_response(error.error_response()).map_into_right_body())))
+    }
+}
@@ -0,0 +1,27 @@
+use actix_web::{web, HttpResponse};
+use crate::api::validation::ValidJson;
+use crate::errors::{Problem, ServiceResult};
+use crate::events::OutboxRelay;
+use crate::models::event::{ReplayEventsDto, ReplayEventsResponse};
+
+#[utoipa::path(
+    post,
+    path = "/api/events/replay",
+    tag = "events",
+    summary = "Publish domain events again",
+    description = "Marks events still in the outbox as unpublished from `from_sequence` on, optionally only \
+        those of one order or product. The relay then publishes them again in their original order.",
+    request_body = ReplayEventsDto,
+    responses(
+        (status = 202, description = "How many events were queued for publishing", body = ReplayEventsResponse),
+        (status = 400, description = "Malformed body", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Invalid sequence", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+pub async fn replay_events(
+    relay: web::Data<OutboxRelay>,
+    replay: ValidJson<ReplayEventsDto>,
+) -> ServiceResult<HttpResponse> {
+    let replayed = relay.replay(replay.into_inner()).await?;
+    Ok(HttpResponse::Accepted().json(ReplayEventsResponse { replayed }))
+}
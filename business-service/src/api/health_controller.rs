@@ -0,0 +1,49 @@
+use actix_web::{web, HttpResponse};
+use crate::models::health::HealthReport;
+use crate::services::HealthService;
+
+#[utoipa::path(
+    get,
+    path = "/health/live",
+    tag = "health",
+    summary = "Check that the process is running",
+    responses((status = 200, description = "The service is up")),
+)]
+pub async fn live() -> HttpResponse {
+    HttpResponse::Ok().json(serde_json::json!({
+        "status": "up",
+        "message": "Business service is running"
+    }))
+}
+
+// Kept for existing monitors; answers like `/health/live`
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "health",
+    summary = "Check that the process is running",
+    description = "Same as `/health/live`.",
+    responses((status = 200, description = "The service is up")),
+)]
+pub async fn health() -> HttpResponse {
+    live().await
+}
+
+#[utoipa::path(
+    get,
+    path = "/health/ready",
+    tag = "health",
+    summary = "Check that the service can reach its stores",
+    responses(
+        (status = 200, description = "Every required store is reachable", body = HealthReport),
+        (status = 503, description = "A required store is unreachable or too slow", body = HealthReport),
+    ),
+)]
+pub async fn ready(service: web::Data<HealthService>) -> HttpResponse {
+    let report = service.readiness().await;
+    if report.is_ready() {
+        HttpResponse::Ok().json(report)
+    } else {
+        HttpResponse::ServiceUnavailable().json(report)
+    }
+}
@@ -0,0 +1,141 @@
+use actix_web::body::{to_bytes, BoxBody, MessageBody};
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::http::{Method, StatusCode};
+use actix_web::middleware::Next;
+use actix_web::{web, Error, HttpMessage, HttpResponse, ResponseError};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+use crate::errors::ServiceError;
+use crate::models::auth::Caller;
+use crate::models::idempotency::{IdempotencyClaim, IdempotencyRecord, StoredResponse};
+use crate::repositories::IdempotencyStore;
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+pub const REPLAYED_HEADER: &str = "Idempotent-Replayed";
+const MAX_KEY_LENGTH: usize = 255;
+
+// Makes mutating requests that carry an `Idempotency-Key` safe to retry.
+// The first request with a key claims it and stores its response, which later requests
+// with the same key get back unchanged. A request reusing the key for a different method,
+// path or body gets 422, and one arriving while the first is still running gets 409.
+// Server errors are not stored, so the client can retry them under the same key; neither is
+// a response the store failed to keep. A request that outlived its claim's lease stores and
+// releases nothing, as the key now belongs to the request that took it over.
+pub async fn idempotency(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let mutating = matches!(*req.method(), Method::POST | Method::PUT | Method::PATCH | Method::DELETE);
+    let key = match req.headers().get(IDEMPOTENCY_KEY_HEADER) {
+        Some(value) if mutating => value.to_str().unwrap_or_default().to_string(),
+        _ => return next.call(req).await.map(ServiceResponse::map_into_boxed_body),
+    };
+    if key.is_empty() || key.len() > MAX_KEY_LENGTH {
+        let e = ServiceError::BadRequestError(format!(
+            "{} must be 1 to {} visible characters", IDEMPOTENCY_KEY_HEADER, MAX_KEY_LENGTH
+        ));
+        return Ok(req.into_response(e.error_response()));
+    }
+    
+    // Keys belong to the caller, so different callers can never see each other's responses
+    let key = match req.extensions().get::<Caller>() {
+        Some(caller) => format!("{}:{}", caller.subject(), key),
+        None => key,
+    };
+    
+    let store = req.app_data::<web::Data<dyn IdempotencyStore>>()
+        .cloned()
+        .ok_or_else(|| actix_web::error::ErrorInternalServerError("Idempotency store is not configured"))?;
+    
+    // Read the body to fingerprint the request, then hand it back for the handler
+    let body = req.extract::<web::Bytes>().await?;
+    let fingerprint = fingerprint(req.method(), &req.uri().to_string(), &body);
+    req.set_payload(Payload::from(body));
+    
+    let token = match store.claim(&key, &fingerprint).await {
+        Ok(IdempotencyClaim::Claimed(token)) => token,
+        Ok(IdempotencyClaim::Taken(record)) if record.fingerprint != fingerprint => {
+            let e = ServiceError::ValidationError(format!(
+                "{} was already used for a different request", IDEMPOTENCY_KEY_HEADER
+            ));
+            return Ok(req.into_response(e.error_response()));
+        }
+        Ok(IdempotencyClaim::Taken(IdempotencyRecord { response: Some(stored), .. })) => {
+            return Ok(req.into_response(replay(stored)));
+        }
+        Ok(IdempotencyClaim::Taken(_)) => {
+            let e = ServiceError::ConflictError("A request with this idempotency key is still being processed".to_string());
+            return Ok(req.into_response(e.error_response()));
+        }
+        Err(e) => {
+            return Ok(req.into_response(e.error_response()));
+        }
+    };
+    
+    let response = match next.call(req).await {
+        Ok(response) => response,
+        Err(e) => {
+            release(store.as_ref(), &key, token).await;
+            return Err(e);
+        }
+    };
+    
+    let (req, response) = response.into_parts();
+    let (response, body) = response.into_parts();
+    let body = match to_bytes(body).await {
+        Ok(body) => body,
+        Err(e) => {
+            release(store.as_ref(), &key, token).await;
+            let e: Box<dyn std::error::Error> = e.into();
+            return Err(actix_web::error::ErrorInternalServerError(e.to_string()));
+        }
+    };
+    
+    if response.status().is_server_error() {
+        release(store.as_ref(), &key, token).await;
+    } else {
+        let stored = StoredResponse {
+            status: response.status().as_u16(),
+            content_type: response.headers()
+                .get(header::CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string),
+            body: body.to_vec(),
+        };
+        if let Err(e) = store.complete(&key, token, &stored).await {
+            tracing::error!("Failed to store response for idempotency key {}: {}", key, e);
+            release(store.as_ref(), &key, token).await;
+        }
+    }
+    
+    Ok(ServiceResponse::new(req, response.set_body(BoxBody::new(body))))
+}
+
+fn fingerprint(method: &Method, uri: &str, body: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(method.as_str().as_bytes());
+    hasher.update(b" ");
+    hasher.update(uri.as_bytes());
+    hasher.update(b"\n");
+    hasher.update(body);
+    
+    format!("{:x}", hasher.finalize())
+}
+
+fn replay(stored: StoredResponse) -> HttpResponse {
+    let status = StatusCode::from_u16(stored.status).unwrap_or(StatusCode::OK);
+    let mut response = HttpResponse::build(status);
+    response.insert_header((REPLAYED_HEADER, HeaderValue::from_static("true")));
+    if let Some(content_type) = stored.content_type {
+        response.insert_header((header::CONTENT_TYPE, content_type));
+    }
+    
+    response.body(stored.body)
+}
+
+async fn release(store: &dyn IdempotencyStore, key: &str, token: Uuid) {
+    if let Err(e) = store.release(key, token).await {
+        tracing::error!("Failed to release idempotency key {}: {}", key, e);
+    }
+}
@@ -0,0 +1,39 @@
+use std::time::Instant;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::{Error, HttpResponse};
+use crate::metrics::metrics;
+
+// Counts and times every request under its route pattern (`/api/orders/{id}`), so ids
+// do not multiply the series. Requests that match no route are grouped as `unmatched`.
+pub async fn record_request_metrics(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let started = Instant::now();
+    let method = req.method().to_string();
+    
+    let result = next.call(req).await;
+    let (route, status) = match &result {
+        Ok(response) => (response.request().match_pattern(), response.status()),
+        Err(e) => (None, e.as_response_error().status_code()),
+    };
+    let route = route.unwrap_or_else(|| "unmatched".to_string());
+    metrics().record_http_request(&method, &route, status.as_u16(), started.elapsed());
+    
+    result
+}
+
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "health",
+    summary = "Prometheus metrics",
+    responses((status = 200, description = "Metrics in the Prometheus text format", body = String, content_type = "text/plain; version=0.0.4")),
+)]
+pub async fn prometheus_metrics() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4; charset=utf-8")
+        .body(metrics().render())
+}
@@ -1,5 +1,16 @@
 pub mod product_controller;
 pub mod order_controller;
+pub mod api_key_controller;
+pub mod event_controller;
+pub mod health_controller;
 pub mod routes;
+pub mod pagination;
+pub mod idempotency;
+pub mod auth;
+pub mod authorization;
+pub mod validation;
+pub mod openapi;
+pub mod metrics;
+pub mod telemetry;
 
 pub use routes::configure_routes;
\ No newline at end of file
@@ -0,0 +1,108 @@
+use utoipa::openapi::path::Operation;
+use utoipa::openapi::security::{self, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityRequirement, SecurityScheme};
+use utoipa::openapi::{Content, Ref, ResponseBuilder};
+use utoipa::{Modify, OpenApi};
+use crate::api::pagination::{FacetedPageResponse, PageLinks, PageResponse};
+use crate::api::{api_key_controller, auth::API_KEY_HEADER, event_controller, health_controller, metrics, order_controller, product_controller};
+use crate::errors::{FieldError, Problem, PROBLEM_CONTENT_TYPE};
+use crate::models::*;
+
+// The OpenAPI document for every route in `api::routes`, served at `/api/openapi.json`.
+// Handlers describe themselves with `#[utoipa::path]`; routes and spec are checked
+// against each other in `tests/openapi.rs`.
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Business Service API",
+        description = "Products, stock and orders for the Polyglot Persistence System",
+    ),
+    paths(
+        product_controller::get_all_products,
+        product_controller::search_products,
+        product_controller::create_product,
+        product_controller::get_product_by_id,
+        product_controller::update_product,
+        product_controller::delete_product,
+        product_controller::get_product_stock,
+        product_controller::adjust_product_stock,
+        order_controller::get_all_orders,
+        order_controller::create_order,
+        order_controller::get_order_by_id,
+        order_controller::update_order_status,
+        order_controller::get_order_history,
+        order_controller::delete_order,
+        api_key_controller::get_all_api_keys,
+        api_key_controller::create_api_key,
+        api_key_controller::rotate_api_key,
+        api_key_controller::revoke_api_key,
+        event_controller::replay_events,
+        health_controller::health,
+        health_controller::live,
+        health_controller::ready,
+        metrics::prometheus_metrics,
+    ),
+    components(schemas(
+        Product, CreateProductDto, UpdateProductDto, ProductStock, AdjustStockDto, StockLevel, ProductSearchHit,
+        ProductFacets, CategoryCount, PriceRangeCount, StockCount,
+        Money, Currency,
+        Order, OrderItem, OrderStatus, OrderStatusChange, CreateOrderDto, CreateOrderItemDto, UpdateOrderStatusDto,
+        ApiKey, ApiScope, CreateApiKeyDto, IssuedApiKey,
+        ReplayEventsDto, ReplayEventsResponse,
+        PageResponse<Product>, PageResponse<ProductSearchHit>, PageResponse<Order>, PageLinks,
+        FacetedPageResponse<Product>, FacetedPageResponse<ProductSearchHit>,
+        Problem, FieldError,
+        HealthReport, DependencyHealth, HealthStatus,
+    )),
+    tags(
+        (name = "products", description = "Catalog and stock"),
+        (name = "orders", description = "Orders and their status history"),
+        (name = "api-keys", description = "Credentials for service-to-service callers"),
+        (name = "events", description = "Domain events published to other services"),
+        (name = "health", description = "Service status"),
+    ),
+    modifiers(&Authentication),
+)]
+pub struct ApiDoc;
+
+// Declares the bearer token and API key schemes and applies them to everything under `/api`,
+// together with the 401 and 403 problems every such route can return
+struct Authentication;
+
+impl Modify for Authentication {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).bearer_format("JWT").build()),
+        );
+        components.add_security_scheme("api_key", SecurityScheme::ApiKey(security::ApiKey::Header(ApiKeyValue::new(API_KEY_HEADER))));
+        
+        for (path, item) in openapi.paths.paths.iter_mut() {
+            if !path.starts_with("/api/") {
+                continue;
+            }
+            
+            let operations = [
+                &mut item.get, &mut item.post, &mut item.put, &mut item.patch, &mut item.delete,
+            ];
+            for operation in operations.into_iter().flatten() {
+                secure(operation);
+            }
+        }
+    }
+}
+
+fn secure(operation: &mut Operation) {
+    operation.security = Some(vec![
+        SecurityRequirement::new("bearer", Vec::<String>::new()),
+        SecurityRequirement::new("api_key", Vec::<String>::new()),
+    ]);
+    
+    for (status, description) in [("401", "Missing or invalid credentials"), ("403", "The caller lacks the permission the route needs")] {
+        let response = ResponseBuilder::new()
+            .description(description)
+            .content(PROBLEM_CONTENT_TYPE, Content::new(Some(Ref::from_schema_name("Problem"))))
+            .build();
+        operation.responses.responses.entry(status.to_string()).or_insert(response.into());
+    }
+}
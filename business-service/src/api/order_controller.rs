@@ -1,65 +1,163 @@
-use actix_web::{web, HttpResponse, Responder};
+use std::collections::HashMap;
+use actix_web::{web, HttpRequest, HttpResponse};
 use uuid::Uuid;
-use crate::models::order::{CreateOrderDto, UpdateOrderStatusDto};
+use crate::api::pagination::PageResponse;
+use crate::api::validation::ValidJson;
+use crate::errors::{Problem, ServiceError, ServiceResult};
+use crate::models::auth::Caller;
+use crate::models::order::{Order, OrderStatusChange, CreateOrderDto, UpdateOrderStatusDto};
+use crate::repositories::{FilterOp, FilterValue, QuerySpec};
 use crate::services::OrderService;
 
-pub async fn get_all_orders(service: web::Data<OrderService>) -> impl Responder {
-    match service.get_all_orders().await {
-        Ok(orders) => HttpResponse::Ok().json(orders),
-        Err(e) => HttpResponse::InternalServerError().json(format!("Error: {}", e)),
+#[utoipa::path(
+    get,
+    path = "/api/orders",
+    tag = "orders",
+    summary = "List orders",
+    params(
+        ("limit" = Option<u64>, Query, description = "Page size, 1-100 (default 20)"),
+        ("offset" = Option<u64>, Query, description = "Number of matches to skip"),
+        ("cursor" = Option<String>, Query, description = "`next_cursor` of the previous page"),
+        ("sort" = Option<String>, Query, description = "Comma-separated fields, prefixed with `-` for descending"),
+        ("status" = Option<String>, Query, description = "Filter by status. Any filterable field works as `field=value` or `field[op]=value`"),
+    ),
+    responses(
+        (status = 200, description = "One page of orders; customers only see their own", body = PageResponse<Order>),
+        (status = 400, description = "Unknown filter or sort field, or a bad cursor", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+pub async fn get_all_orders(
+    req: HttpRequest,
+    service: web::Data<OrderService>,
+    params: web::Query<HashMap<String, String>>,
+    caller: Caller,
+) -> ServiceResult<HttpResponse> {
+    let mut query = QuerySpec::from_params::<Order>(&params)?;
+    if let Some(customer_id) = caller.customer_scope() {
+        query = query.with_filter("customer_id", FilterOp::Eq, FilterValue::Uuid(customer_id));
     }
+    
+    let page = service.get_all_orders(&query).await?;
+    Ok(HttpResponse::Ok().json(PageResponse::new(&req, page)))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/orders/{id}",
+    tag = "orders",
+    summary = "Get an order",
+    params(("id" = Uuid, Path, description = "Order id")),
+    responses(
+        (status = 200, description = "The order", body = Order),
+        (status = 404, description = "No such order, or it belongs to another customer", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
 pub async fn get_order_by_id(
     service: web::Data<OrderService>,
     path: web::Path<Uuid>,
-) -> impl Responder {
-    let id = path.into_inner();
-    
-    match service.get_order(id).await {
-        Ok(Some(order)) => HttpResponse::Ok().json(order),
-        Ok(None) => HttpResponse::NotFound().json("Order not found"),
-        Err(e) => HttpResponse::InternalServerError().json(format!("Error: {}", e)),
-    }
+    caller: Caller,
+) -> ServiceResult<HttpResponse> {
+    let order = find_visible_order(&service, path.into_inner(), &caller).await?;
+    Ok(HttpResponse::Ok().json(order))
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/orders",
+    tag = "orders",
+    summary = "Place an order",
+    request_body = CreateOrderDto,
+    responses(
+        (status = 201, description = "The placed order", body = Order),
+        (status = 400, description = "Malformed body", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "Insufficient stock", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Invalid fields or unknown products", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
 pub async fn create_order(
     service: web::Data<OrderService>,
-    order: web::Json<CreateOrderDto>,
-) -> impl Responder {
-    match service.create_order(order.into_inner()).await {
-        Ok(created) => HttpResponse::Created().json(created),
-        Err(e) => HttpResponse::InternalServerError().json(format!("Error: {}", e)),
+    order: ValidJson<CreateOrderDto>,
+    caller: Caller,
+) -> ServiceResult<HttpResponse> {
+    if !caller.acts_for(order.customer_id) {
+        return Err(ServiceError::ForbiddenError("Orders can only be placed for your own customer id".to_string()));
     }
+    
+    let created = service.create_order(order.into_inner()).await?;
+    Ok(HttpResponse::Created().json(created))
 }
 
+#[utoipa::path(
+    patch,
+    path = "/api/orders/{id}/status",
+    tag = "orders",
+    summary = "Change an order's status",
+    params(("id" = Uuid, Path, description = "Order id")),
+    request_body = UpdateOrderStatusDto,
+    responses(
+        (status = 200, description = "The updated order", body = Order),
+        (status = 404, description = "No such order", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "The transition is not allowed", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Invalid fields", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
 pub async fn update_order_status(
     service: web::Data<OrderService>,
     path: web::Path<Uuid>,
-    status: web::Json<UpdateOrderStatusDto>,
-) -> impl Responder {
+    status: ValidJson<UpdateOrderStatusDto>,
+    caller: Caller,
+) -> ServiceResult<HttpResponse> {
     let id = path.into_inner();
     
-    match service.update_order_status(id, status.into_inner()).await {
-        Ok(updated) => HttpResponse::Ok().json(updated),
-        Err(e) => match e {
-            crate::errors::ServiceError::NotFoundError(_) => HttpResponse::NotFound().json(format!("Error: {}", e)),
-            _ => HttpResponse::InternalServerError().json(format!("Error: {}", e)),
-        },
-    }
+    let updated = service.update_order_status(id, status.into_inner(), Some(caller.subject())).await?;
+    Ok(HttpResponse::Ok().json(updated))
 }
 
-pub async fn delete_order(
+#[utoipa::path(
+    get,
+    path = "/api/orders/{id}/history",
+    tag = "orders",
+    summary = "Get an order's status history",
+    params(("id" = Uuid, Path, description = "Order id")),
+    responses(
+        (status = 200, description = "Status changes in order", body = Vec<OrderStatusChange>),
+        (status = 404, description = "No such order, or it belongs to another customer", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+pub async fn get_order_history(
     service: web::Data<OrderService>,
     path: web::Path<Uuid>,
-) -> impl Responder {
+    caller: Caller,
+) -> ServiceResult<HttpResponse> {
     let id = path.into_inner();
+    find_visible_order(&service, id, &caller).await?;
     
-    match service.delete_order(id).await {
-        Ok(_) => HttpResponse::NoContent().finish(),
-        Err(e) => match e {
-            crate::errors::ServiceError::NotFoundError(_) => HttpResponse::NotFound().json(format!("Error: {}", e)),
-            _ => HttpResponse::InternalServerError().json(format!("Error: {}", e)),
-        },
-    }
-}
\ No newline at end of file
+    let history = service.get_order_history(id).await?;
+    Ok(HttpResponse::Ok().json(history))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/orders/{id}",
+    tag = "orders",
+    summary = "Delete an order",
+    params(("id" = Uuid, Path, description = "Order id")),
+    responses(
+        (status = 204, description = "The order was deleted"),
+        (status = 404, description = "No such order", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+pub async fn delete_order(
+    service: web::Data<OrderService>,
+    path: web::Path<Uuid>,
+) -> ServiceResult<HttpResponse> {
+    service.delete_order(path.into_inner()).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+// Other customers' orders are reported as missing rather than forbidden
+async fn find_visible_order(service: &OrderService, id: Uuid, caller: &Caller) -> ServiceResult<Order> {
+    service.get_order(id).await?
+        .filter(|order| caller.acts_for(order.customer_id))
+        .ok_or_else(|| ServiceError::NotFoundError(format!("Order with id {} not found", id)))
+}
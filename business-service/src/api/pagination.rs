@@ -0,0 +1,62 @@
+use actix_web::HttpRequest;
+use serde::Serialize;
+use utoipa::ToSchema;
+use crate::models::facet::ProductFacets;
+use crate::repositories::Page;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PageLinks {
+    #[serde(rename = "self")]
+    pub self_link: String,
+    pub next: Option<String>,
+}
+
+// List response body: one page of items with the total match count and a link to the next page
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PageResponse<T> {
+    pub items: Vec<T>,
+    pub total: u64,
+    pub limit: u64,
+    pub offset: u64,
+    pub next_cursor: Option<String>,
+    pub links: PageLinks,
+}
+
+impl<T: Serialize> PageResponse<T> {
+    pub fn new(req: &HttpRequest, page: Page<T>) -> Self {
+        let next_cursor = page.next_cursor();
+        let self_link = match req.query_string() {
+            "" => req.path().to_string(),
+            query => format!("{}?{}", req.path(), query),
+        };
+        let next = next_cursor.as_ref().map(|cursor| Self::link_with_cursor(req, cursor));
+        
+        Self {
+            items: page.items,
+            total: page.total,
+            limit: page.limit,
+            offset: page.offset,
+            next_cursor,
+            links: PageLinks { self_link, next },
+        }
+    }
+    
+    // Same request with its paging position replaced by `cursor`
+    fn link_with_cursor(req: &HttpRequest, cursor: &str) -> String {
+        let mut params: Vec<(String, String)> = serde_urlencoded::from_str(req.query_string())
+            .unwrap_or_default();
+        params.retain(|(key, _)| key != "cursor" && key != "offset");
+        params.push(("cursor".to_string(), cursor.to_string()));
+        
+        let query = serde_urlencoded::to_string(&params).unwrap_or_default();
+        format!("{}?{}", req.path(), query)
+    }
+}
+
+// A page of products with facet counts over all of the matches, not just this page
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FacetedPageResponse<T> {
+    #[serde(flatten)]
+    pub page: PageResponse<T>,
+    pub facets: ProductFacets,
+}
@@ -1,65 +1,210 @@
-use actix_web::{web, HttpResponse, Responder};
+use std::collections::HashMap;
+use actix_web::{web, HttpRequest, HttpResponse};
 use uuid::Uuid;
-use crate::models::product::{CreateProductDto, UpdateProductDto};
+use crate::api::pagination::{FacetedPageResponse, PageResponse};
+use crate::api::validation::ValidJson;
+use crate::errors::{Problem, ServiceError, ServiceResult};
+use crate::models::product::{Product, CreateProductDto, UpdateProductDto};
+use crate::models::search::ProductSearchHit;
+use crate::models::stock::{AdjustStockDto, ProductStock};
+use crate::repositories::QuerySpec;
 use crate::services::ProductService;
 
-pub async fn get_all_products(service: web::Data<ProductService>) -> impl Responder {
-    match service.get_all_products().await {
-        Ok(products) => HttpResponse::Ok().json(products),
-        Err(e) => HttpResponse::InternalServerError().json(format!("Error: {}", e)),
+#[utoipa::path(
+    get,
+    path = "/api/products",
+    tag = "products",
+    summary = "List products",
+    params(
+        ("limit" = Option<u64>, Query, description = "Page size, 1-100 (default 20)"),
+        ("offset" = Option<u64>, Query, description = "Number of matches to skip"),
+        ("cursor" = Option<String>, Query, description = "`next_cursor` of the previous page"),
+        ("sort" = Option<String>, Query, description = "Comma-separated fields, prefixed with `-` for descending"),
+        ("category" = Option<String>, Query, description = "Filter by category. Any filterable field works as `field=value` or `field[op]=value`"),
+    ),
+    responses(
+        (status = 200, description = "One page of products, with facet counts over all matching products", body = FacetedPageResponse<Product>),
+        (status = 400, description = "Unknown filter or sort field, or a bad cursor", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+pub async fn get_all_products(
+    req: HttpRequest,
+    service: web::Data<ProductService>,
+    params: web::Query<HashMap<String, String>>,
+) -> ServiceResult<HttpResponse> {
+    let query = QuerySpec::from_params::<Product>(&params)?;
+    
+    let (page, facets) = futures_util::try_join!(
+        service.get_all_products(&query),
+        service.get_product_facets(None, &query),
+    )?;
+    Ok(HttpResponse::Ok().json(FacetedPageResponse { page: PageResponse::new(&req, page), facets }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/products/search",
+    tag = "products",
+    summary = "Search products",
+    description = "Full-text search over name, SKU, category and description, most relevant first. \
+        Words match any of their forms, `\"quoted phrases\"` must all appear and `-word` excludes products containing it.",
+    params(
+        ("q" = String, Query, description = "Search text"),
+        ("limit" = Option<u64>, Query, description = "Page size, 1-100 (default 20)"),
+        ("offset" = Option<u64>, Query, description = "Number of matches to skip"),
+        ("cursor" = Option<String>, Query, description = "`next_cursor` of the previous page"),
+        ("category" = Option<String>, Query, description = "Filter by category. Any filterable field works as `field=value` or `field[op]=value`, e.g. `price[gte]=10` or `in_stock=true`"),
+    ),
+    responses(
+        (status = 200, description = "One page of matching products, with facet counts over all matches", body = FacetedPageResponse<ProductSearchHit>),
+        (status = 400, description = "Missing or empty `q`, a `sort`, an unknown filter field, or a bad cursor", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+pub async fn search_products(
+    req: HttpRequest,
+    service: web::Data<ProductService>,
+    params: web::Query<HashMap<String, String>>,
+) -> ServiceResult<HttpResponse> {
+    let mut params = params.into_inner();
+    let text = params.remove("q")
+        .ok_or_else(|| ServiceError::BadRequestError("q is required".to_string()))?;
+    if params.contains_key("sort") {
+        return Err(ServiceError::BadRequestError("Search results are ordered by relevance and cannot be sorted".to_string()));
     }
+    let query = QuerySpec::from_params::<Product>(&params)?;
+    
+    let (page, facets) = futures_util::try_join!(
+        service.search_products(&text, &query),
+        service.get_product_facets(Some(&text), &query),
+    )?;
+    Ok(HttpResponse::Ok().json(FacetedPageResponse { page: PageResponse::new(&req, page), facets }))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/products/{id}",
+    tag = "products",
+    summary = "Get a product",
+    params(("id" = Uuid, Path, description = "Product id")),
+    responses(
+        (status = 200, description = "The product", body = Product),
+        (status = 404, description = "No such product", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
 pub async fn get_product_by_id(
     service: web::Data<ProductService>,
     path: web::Path<Uuid>,
-) -> impl Responder {
+) -> ServiceResult<HttpResponse> {
     let id = path.into_inner();
     
-    match service.get_product(id).await {
-        Ok(Some(product)) => HttpResponse::Ok().json(product),
-        Ok(None) => HttpResponse::NotFound().json("Product not found"),
-        Err(e) => HttpResponse::InternalServerError().json(format!("Error: {}", e)),
-    }
+    let product = service.get_product(id).await?
+        .ok_or_else(|| ServiceError::NotFoundError(format!("Product with id {} not found", id)))?;
+    Ok(HttpResponse::Ok().json(product))
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/products",
+    tag = "products",
+    summary = "Create a product",
+    request_body = CreateProductDto,
+    responses(
+        (status = 201, description = "The created product", body = Product),
+        (status = 400, description = "Malformed body", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Invalid fields", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
 pub async fn create_product(
     service: web::Data<ProductService>,
-    product: web::Json<CreateProductDto>,
-) -> impl Responder {
-    match service.create_product(product.into_inner()).await {
-        Ok(created) => HttpResponse::Created().json(created),
-        Err(e) => HttpResponse::InternalServerError().json(format!("Error: {}", e)),
-    }
+    product: ValidJson<CreateProductDto>,
+) -> ServiceResult<HttpResponse> {
+    let created = service.create_product(product.into_inner()).await?;
+    Ok(HttpResponse::Created().json(created))
 }
 
+#[utoipa::path(
+    put,
+    path = "/api/products/{id}",
+    tag = "products",
+    summary = "Update a product",
+    params(("id" = Uuid, Path, description = "Product id")),
+    request_body = UpdateProductDto,
+    responses(
+        (status = 200, description = "The updated product", body = Product),
+        (status = 400, description = "Malformed body", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "No such product", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Invalid fields", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
 pub async fn update_product(
     service: web::Data<ProductService>,
     path: web::Path<Uuid>,
-    product: web::Json<UpdateProductDto>,
-) -> impl Responder {
+    product: ValidJson<UpdateProductDto>,
+) -> ServiceResult<HttpResponse> {
+    let updated = service.update_product(path.into_inner(), product.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(updated))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/products/{id}/stock",
+    tag = "products",
+    summary = "Get stock levels",
+    params(("id" = Uuid, Path, description = "Product id")),
+    responses(
+        (status = 200, description = "Current stock levels", body = ProductStock),
+        (status = 404, description = "No such product", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+pub async fn get_product_stock(
+    service: web::Data<ProductService>,
+    path: web::Path<Uuid>,
+) -> ServiceResult<HttpResponse> {
     let id = path.into_inner();
     
-    match service.update_product(id, product.into_inner()).await {
-        Ok(updated) => HttpResponse::Ok().json(updated),
-        Err(e) => match e {
-            crate::errors::ServiceError::NotFoundError(_) => HttpResponse::NotFound().json(format!("Error: {}", e)),
-            _ => HttpResponse::InternalServerError().json(format!("Error: {}", e)),
-        },
-    }
+    let stock = service.get_stock(id).await?
+        .ok_or_else(|| ServiceError::NotFoundError(format!("Product with id {} not found", id)))?;
+    Ok(HttpResponse::Ok().json(stock))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/api/products/{id}/stock",
+    tag = "products",
+    summary = "Adjust stock",
+    params(("id" = Uuid, Path, description = "Product id")),
+    request_body = AdjustStockDto,
+    responses(
+        (status = 200, description = "The new stock levels", body = ProductStock),
+        (status = 404, description = "No such product", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "Fewer units would be on hand than are reserved", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Invalid fields, or neither or both of `delta` and `on_hand` given", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+pub async fn adjust_product_stock(
+    service: web::Data<ProductService>,
+    path: web::Path<Uuid>,
+    adjustment: ValidJson<AdjustStockDto>,
+) -> ServiceResult<HttpResponse> {
+    let stock = service.adjust_stock(path.into_inner(), adjustment.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(stock))
 }
 
+#[utoipa::path(
+    delete,
+    path = "/api/products/{id}",
+    tag = "products",
+    summary = "Delete a product",
+    params(("id" = Uuid, Path, description = "Product id")),
+    responses(
+        (status = 204, description = "The product was deleted"),
+        (status = 404, description = "No such product", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
 pub async fn delete_product(
     service: web::Data<ProductService>,
     path: web::Path<Uuid>,
-) -> impl Responder {
-    let id = path.into_inner();
-    
-    match service.delete_product(id).await {
-        Ok(_) => HttpResponse::NoContent().finish(),
-        Err(e) => match e {
-            crate::errors::ServiceError::NotFoundError(_) => HttpResponse::NotFound().json(format!("Error: {}", e)),
-            _ => HttpResponse::InternalServerError().json(format!("Error: {}", e)),
-        },
-    }
-}
\ No newline at end of file
+) -> ServiceResult<HttpResponse> {
+    service.delete_product(path.into_inner()).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
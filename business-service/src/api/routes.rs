@@ -1,37 +1,126 @@
-use actix_web::web;
+use actix_web::http::Method;
+use actix_web::middleware::from_fn;
+use actix_web::{web, Route, Scope};
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
 use crate::api::{
     product_controller, 
-    order_controller
+    order_controller,
+    api_key_controller,
+    event_controller,
+    health_controller,
+    metrics::prometheus_metrics,
+    idempotency::idempotency,
+    auth::authenticate,
+    authorization::Require,
+    validation::{json_config, path_config, query_config},
+    openapi::ApiDoc,
 };
+use crate::models::auth::Permission;
 
-pub fn configure_routes(cfg: &mut web::ServiceConfig) {
-    // Product routes
-    cfg.service(
-        web::scope("/api/products")
-            .route("", web::get().to(product_controller::get_all_products))
-            .route("", web::post().to(product_controller::create_product))
-            .route("/{id}", web::get().to(product_controller::get_product_by_id))
-            .route("/{id}", web::put().to(product_controller::update_product))
-            .route("/{id}", web::delete().to(product_controller::delete_product))
-    );
-    
-    // Order routes
-    cfg.service(
-        web::scope("/api/orders")
-            .route("", web::get().to(order_controller::get_all_orders))
-            .route("", web::post().to(order_controller::create_order))
-            .route("/{id}", web::get().to(order_controller::get_order_by_id))
-            .route("/{id}/status", web::patch().to(order_controller::update_order_status))
-            .route("/{id}", web::delete().to(order_controller::delete_order))
-    );
-    
-    // Health check
-    cfg.route("/health", web::get().to(health_check));
+// A route of the service: the scope it is registered under, empty for none, its path within
+// that scope, and the permission it requires, `None` for public routes. `configure_routes`
+// registers exactly these, so tests can check the OpenAPI document against them.
+pub struct ApiRoute {
+    pub scope: &'static str,
+    pub path: &'static str,
+    pub method: Method,
+    pub permission: Option<Permission>,
+    handler: fn(Route) -> Route,
+}
+
+impl ApiRoute {
+    pub fn full_path(&self) -> String {
+        format!("{}{}", self.scope, self.path)
+    }
+    
+    fn route(&self) -> Route {
+        let route = (self.handler)(web::route().method(self.method.clone()));
+        match self.permission {
+            Some(permission) => route.wrap(Require(permission)),
+            None => route,
+        }
+    }
+}
+
+const fn api_route(
+    scope: &'static str,
+    path: &'static str,
+    method: Method,
+    permission: Option<Permission>,
+    handler: fn(Route) -> Route,
+) -> ApiRoute {
+    ApiRoute { scope, path, method, permission, handler }
+}
+
+// Every route, in registration order within each scope
+pub static ROUTES: &[ApiRoute] = &[
+    // Products
+    api_route("/api/products", "", Method::GET, Some(Permission::ReadCatalog), |route| route.to(product_controller::get_all_products)),
+    api_route("/api/products", "", Method::POST, Some(Permission::ManageCatalog), |route| route.to(product_controller::create_product)),
+    // Before `/{id}`, which would otherwise take `search` for an id
+    api_route("/api/products", "/search", Method::GET, Some(Permission::ReadCatalog), |route| route.to(product_controller::search_products)),
+    api_route("/api/products", "/{id}", Method::GET, Some(Permission::ReadCatalog), |route| route.to(product_controller::get_product_by_id)),
+    api_route("/api/products", "/{id}", Method::PUT, Some(Permission::ManageCatalog), |route| route.to(product_controller::update_product)),
+    api_route("/api/products", "/{id}", Method::DELETE, Some(Permission::ManageCatalog), |route| route.to(product_controller::delete_product)),
+    api_route("/api/products", "/{id}/stock", Method::GET, Some(Permission::ReadCatalog), |route| route.to(product_controller::get_product_stock)),
+    api_route("/api/products", "/{id}/stock", Method::PATCH, Some(Permission::ManageCatalog), |route| route.to(product_controller::adjust_product_stock)),
+    
+    // Orders. Callers without `AllCustomers` only see and place their own orders.
+    api_route("/api/orders", "", Method::GET, Some(Permission::ReadOrders), |route| route.to(order_controller::get_all_orders)),
+    api_route("/api/orders", "", Method::POST, Some(Permission::PlaceOrders), |route| route.to(order_controller::create_order)),
+    api_route("/api/orders", "/{id}", Method::GET, Some(Permission::ReadOrders), |route| route.to(order_controller::get_order_by_id)),
+    api_route("/api/orders", "/{id}/status", Method::PATCH, Some(Permission::ChangeOrderStatus), |route| route.to(order_controller::update_order_status)),
+    api_route("/api/orders", "/{id}/history", Method::GET, Some(Permission::ReadOrders), |route| route.to(order_controller::get_order_history)),
+    api_route("/api/orders", "/{id}", Method::DELETE, Some(Permission::DeleteOrders), |route| route.to(order_controller::delete_order)),
+    
+    // API key management
+    api_route("/api/keys", "", Method::GET, Some(Permission::ManageApiKeys), |route| route.to(api_key_controller::get_all_api_keys)),
+    api_route("/api/keys", "", Method::POST, Some(Permission::ManageApiKeys), |route| route.to(api_key_controller::create_api_key)),
+    api_route("/api/keys", "/{id}/rotate", Method::POST, Some(Permission::ManageApiKeys), |route| route.to(api_key_controller::rotate_api_key)),
+    api_route("/api/keys", "/{id}", Method::DELETE, Some(Permission::ManageApiKeys), |route| route.to(api_key_controller::revoke_api_key)),
+    
+    // Outbox administration
+    api_route("/api/events", "/replay", Method::POST, Some(Permission::ReplayEvents), |route| route.to(event_controller::replay_events)),
+    
+    // Health probes, all public. Liveness only shows the process is serving requests;
+    // readiness also checks the stores.
+    api_route("", "/health", Method::GET, None, |route| route.to(health_controller::health)),
+    api_route("", "/health/live", Method::GET, None, |route| route.to(health_controller::live)),
+    api_route("", "/health/ready", Method::GET, None, |route| route.to(health_controller::ready)),
+    
+    // Prometheus scrape endpoint, public like the probes
+    api_route("", "/metrics", Method::GET, None, |route| route.to(prometheus_metrics)),
+];
+
+// The routes of `ROUTES` registered under `scope`
+fn scope(path: &'static str) -> Scope {
+    ROUTES
+        .iter()
+        .filter(|route| route.scope == path)
+        .fold(web::scope(path), |scope, route| scope.route(route.path, route.route()))
 }
 
-async fn health_check() -> impl actix_web::Responder {
-    actix_web::HttpResponse::Ok().json(serde_json::json!({
-        "status": "up",
-        "message": "Business service is running"
-    }))
-}
\ No newline at end of file
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    // Requests that cannot be parsed get the same problem responses as every other error
+    cfg.app_data(json_config())
+        .app_data(path_config())
+        .app_data(query_config());
+    
+    // API routes require a bearer token or API key. Authentication is wrapped last so that it runs
+    // first and idempotency keys can be scoped to the caller. Routes that need more than
+    // an authenticated caller declare the permission they require.
+    cfg.service(scope("/api/products").wrap(from_fn(idempotency)).wrap(from_fn(authenticate)));
+    cfg.service(scope("/api/orders").wrap(from_fn(idempotency)).wrap(from_fn(authenticate)));
+    
+    // API key management is not wrapped in idempotency, which would store the issued secrets
+    cfg.service(scope("/api/keys").wrap(from_fn(authenticate)));
+    cfg.service(scope("/api/events").wrap(from_fn(authenticate)));
+    
+    for route in ROUTES.iter().filter(|route| route.scope.is_empty()) {
+        cfg.route(route.path, route.route());
+    }
+    
+    // OpenAPI document and Swagger UI, both public
+    cfg.service(SwaggerUi::new("/api/docs/{_:.*}").url("/api/openapi.json", ApiDoc::openapi()));
+}
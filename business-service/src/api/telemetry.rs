@@ -0,0 +1,63 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::HeaderMap;
+use actix_web::http::StatusCode;
+use actix_web::middleware::Next;
+use actix_web::Error;
+use opentelemetry::propagation::Extractor;
+use opentelemetry::trace::TraceContextExt;
+use tracing::field::Empty;
+use tracing::Instrument;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use crate::telemetry::extract_trace_context;
+
+// Runs every request in a server span. A `traceparent` header from the caller makes the
+// span part of the caller's trace; without one the request starts a new trace. The span is
+// renamed to the matched route pattern once routing is done, like the request metrics.
+pub async fn trace_requests(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let method = req.method().to_string();
+    let span = tracing::info_span!(
+        "request",
+        otel.name = %method,
+        otel.kind = "server",
+        otel.status_code = Empty,
+        http.request.method = %method,
+        url.path = %req.path(),
+        http.route = Empty,
+        http.response.status_code = Empty,
+    );
+    // Fails only when no OpenTelemetry layer is installed, and then there is nothing to continue
+    let _ = span.set_parent(extract_trace_context(&RequestHeaders(req.headers())));
+    
+    let result = next.call(req).instrument(span.clone()).await;
+    let (route, status) = match &result {
+        Ok(response) => (response.request().match_pattern(), response.status()),
+        Err(e) => (None, e.as_response_error().status_code()),
+    };
+    if let Some(route) = route {
+        // The span has started by now, so it is renamed directly rather than through `otel.name`
+        span.context().span().update_name(format!("{} {}", method, route));
+        span.record("http.route", route);
+    }
+    span.record("http.response.status_code", status.as_u16() as i64);
+    if status >= StatusCode::INTERNAL_SERVER_ERROR {
+        span.record("otel.status_code", "ERROR");
+    }
+    
+    result
+}
+
+struct RequestHeaders<'a>(&'a HeaderMap);
+
+impl Extractor for RequestHeaders<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+    
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|name| name.as_str()).collect()
+    }
+}
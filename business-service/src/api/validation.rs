@@ -0,0 +1,81 @@
+use std::ops::Deref;
+use actix_web::dev::Payload;
+use actix_web::error::{JsonPayloadError, PathError, QueryPayloadError};
+use actix_web::{web, Error, FromRequest, HttpRequest};
+use futures_util::future::LocalBoxFuture;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use crate::errors::ServiceError;
+use crate::models::validation::{FieldErrors, Validate};
+
+// A JSON body that has been validated field by field and then parsed, so handlers only
+// ever see acceptable values. Missing, wrongly typed and invalid fields are all reported
+// in one 422 response; only bodies that are not JSON at all get a 400.
+pub struct ValidJson<T>(pub T);
+
+impl<T> ValidJson<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for ValidJson<T> {
+    type Target = T;
+    
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: DeserializeOwned + Validate + 'static> FromRequest for ValidJson<T> {
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Error>>;
+    
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let json = web::Json::<Value>::from_request(req, payload);
+        Box::pin(async move {
+            let body = json.await?.into_inner();
+            let mut errors = FieldErrors::new();
+            errors.check(body.is_object(), "", "must be a JSON object");
+            errors.into_result()?;
+            T::validate(&body)?;
+            
+            // Every field was checked above, so this only fails for a check `T` is missing
+            let value = serde_json::from_value(body).map_err(|e| ServiceError::BadRequestError(format!("Malformed JSON body: {}", e)))?;
+            Ok(ValidJson(value))
+        })
+    }
+}
+
+// Extractor settings that report unparseable bodies, paths and query strings as 400 problems
+// instead of actix's plain text errors
+pub fn json_config() -> web::JsonConfig {
+    web::JsonConfig::default().error_handler(|err, _req| {
+        let detail = match &err {
+            JsonPayloadError::ContentType => "Content-Type must be application/json".to_string(),
+            JsonPayloadError::Deserialize(e) => format!("Malformed JSON body: {}", e),
+            other => other.to_string(),
+        };
+        ServiceError::BadRequestError(detail).into()
+    })
+}
+
+pub fn path_config() -> web::PathConfig {
+    web::PathConfig::default().error_handler(|err, req| {
+        let detail = match &err {
+            PathError::Deserialize(e) => format!("Invalid path segment in {}: {}", req.path(), e),
+            other => other.to_string(),
+        };
+        ServiceError::BadRequestError(detail).into()
+    })
+}
+
+pub fn query_config() -> web::QueryConfig {
+    web::QueryConfig::default().error_handler(|err, _req| {
+        let detail = match &err {
+            QueryPayloadError::Deserialize(e) => format!("Invalid query string: {}", e),
+            other => other.to_string(),
+        };
+        ServiceError::BadRequestError(detail).into()
+    })
+}
@@ -11,6 +11,7 @@ pub struct PostgresConfig {
     pub password: String,
     pub database: String,
     pub max_connections: u32,
+    pub run_migrations: bool,
 }
 
 impl PostgresConfig {
@@ -26,6 +27,9 @@ impl PostgresConfig {
 pub struct MongoConfig {
     pub uri: String,
     pub database: String,
+    pub run_migrations: bool,
+    // Units on hand given to each in-stock product that predates stock tracking
+    pub legacy_stock_on_hand: Option<i32>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -34,11 +38,100 @@ pub struct ServerConfig {
     pub port: u16,
 }
 
+#[derive(Debug, Deserialize, Clone)]
+pub struct IdempotencyConfig {
+    // How long a stored response can be replayed for its key
+    pub ttl_secs: u64,
+    // How long a request holds its key before another request with the key may take it over
+    pub lease_secs: u64,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct AuthConfig {
+    // Shared with auth-service, which signs its tokens with it
+    pub jwt_secret: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct HealthConfig {
+    // How long each readiness check may take before its dependency counts as down
+    pub check_timeout_ms: u64,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct RedisConfig {
+    pub url: String,
+}
+
+// Redis streams that events are published to, one per aggregate type: `<prefix>:order` and `<prefix>:product`
+#[derive(Debug, Deserialize, Clone)]
+pub struct EventStreamConfig {
+    pub prefix: String,
+    // Streams are trimmed to about this many entries
+    pub max_len: usize,
+}
+
+// Read-through caching of products, in Redis when it is configured and in process memory otherwise
+#[derive(Debug, Deserialize, Clone)]
+pub struct ProductCacheConfig {
+    pub enabled: bool,
+    // How long a cached product or listing is served before it is read again
+    pub ttl_secs: u64,
+    // The most values the in-process cache holds
+    pub capacity: usize,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct OutboxConfig {
+    // How often the relay looks for unpublished events
+    pub relay_interval_ms: u64,
+    // The most events published per outbox transaction
+    pub batch_size: i64,
+    // How long published events are kept for replay
+    pub retention_hours: u64,
+    // Failed attempts after which an event is dead-lettered
+    pub max_attempts: i32,
+    // How long a failed event waits before its first retry; the wait doubles with each attempt
+    pub retry_delay_ms: u64,
+}
+
+// Where finished spans go: an OTLP/HTTP collector, a file or stdout as JSON lines, or nowhere
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+pub enum TraceExporter {
+    Otlp { endpoint: String },
+    File { path: String },
+    Stdout,
+    Disabled,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct TelemetryConfig {
+    pub service_name: String,
+    pub exporter: TraceExporter,
+}
+
+// Where repositories keep their data: the real databases, or process memory for tests and local development
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum StorageBackend {
+    Database,
+    Memory,
+}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct AppConfig {
     pub server: ServerConfig,
     pub postgres: PostgresConfig,
     pub mongodb: MongoConfig,
+    pub storage: StorageBackend,
+    pub idempotency: IdempotencyConfig,
+    pub auth: AuthConfig,
+    pub health: HealthConfig,
+    pub telemetry: TelemetryConfig,
+    pub outbox: OutboxConfig,
+    // Not set when Redis is not configured
+    pub redis: Option<RedisConfig>,
+    pub event_streams: EventStreamConfig,
+    pub product_cache: ProductCacheConfig,
 }
 
 impl AppConfig {
@@ -66,17 +159,144 @@ impl AppConfig {
                 .unwrap_or_else(|_| "5".to_string())
                 .parse()
                 .map_err(|e| ServiceError::ConfigError(format!("Invalid max connections: {}", e)))?,
+            run_migrations: env::var("POSTGRES_RUN_MIGRATIONS")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse()
+                .map_err(|e| ServiceError::ConfigError(format!("Invalid run migrations flag: {}", e)))?,
         };
         
         let mongodb_config = MongoConfig {
             uri: env::var("MONGODB_URI").unwrap_or_else(|_| "mongodb://localhost:27017".to_string()),
             database: env::var("MONGODB_DATABASE").unwrap_or_else(|_| "business_service".to_string()),
+            run_migrations: env::var("MONGODB_RUN_MIGRATIONS")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse()
+                .map_err(|e| ServiceError::ConfigError(format!("Invalid run migrations flag: {}", e)))?,
+            legacy_stock_on_hand: env::var("MONGODB_LEGACY_STOCK_ON_HAND")
+                .ok()
+                .map(|units| units.parse())
+                .transpose()
+                .map_err(|e| ServiceError::ConfigError(format!("Invalid legacy stock on hand: {}", e)))?,
+        };
+        
+        let storage = match env::var("STORAGE_BACKEND").unwrap_or_else(|_| "database".to_string()).as_str() {
+            "database" => StorageBackend::Database,
+            "memory" => StorageBackend::Memory,
+            other => return Err(ServiceError::ConfigError(format!("Invalid storage backend: {}", other))),
+        };
+        
+        let idempotency_config = IdempotencyConfig {
+            ttl_secs: env::var("IDEMPOTENCY_TTL_SECS")
+                .unwrap_or_else(|_| "86400".to_string())
+                .parse()
+                .map_err(|e| ServiceError::ConfigError(format!("Invalid idempotency TTL: {}", e)))?,
+            lease_secs: env::var("IDEMPOTENCY_LEASE_SECS")
+                .unwrap_or_else(|_| "60".to_string())
+                .parse()
+                .map_err(|e| ServiceError::ConfigError(format!("Invalid idempotency lease: {}", e)))?,
+        };
+        
+        let auth_config = AuthConfig {
+            jwt_secret: env::var("JWT_SECRET")
+                .ok()
+                .filter(|secret| !secret.is_empty())
+                .ok_or_else(|| ServiceError::ConfigError("JWT_SECRET must be set".to_string()))?,
+        };
+        
+        let health_config = HealthConfig {
+            check_timeout_ms: env::var("HEALTH_CHECK_TIMEOUT_MS")
+                .unwrap_or_else(|_| "2000".to_string())
+                .parse()
+                .map_err(|e| ServiceError::ConfigError(format!("Invalid health check timeout: {}", e)))?,
+        };
+        
+        let outbox_config = OutboxConfig {
+            relay_interval_ms: env::var("OUTBOX_RELAY_INTERVAL_MS")
+                .unwrap_or_else(|_| "1000".to_string())
+                .parse()
+                .map_err(|e| ServiceError::ConfigError(format!("Invalid outbox relay interval: {}", e)))?,
+            batch_size: env::var("OUTBOX_BATCH_SIZE")
+                .unwrap_or_else(|_| "100".to_string())
+                .parse()
+                .ok()
+                .filter(|size| *size > 0)
+                .ok_or_else(|| ServiceError::ConfigError("OUTBOX_BATCH_SIZE must be a positive number".to_string()))?,
+            retention_hours: env::var("OUTBOX_RETENTION_HOURS")
+                .unwrap_or_else(|_| "168".to_string())
+                .parse()
+                .map_err(|e| ServiceError::ConfigError(format!("Invalid outbox retention: {}", e)))?,
+            max_attempts: env::var("OUTBOX_MAX_ATTEMPTS")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()
+                .ok()
+                .filter(|attempts| *attempts > 0)
+                .ok_or_else(|| ServiceError::ConfigError("OUTBOX_MAX_ATTEMPTS must be a positive number".to_string()))?,
+            retry_delay_ms: env::var("OUTBOX_RETRY_DELAY_MS")
+                .unwrap_or_else(|_| "1000".to_string())
+                .parse()
+                .map_err(|e| ServiceError::ConfigError(format!("Invalid outbox retry delay: {}", e)))?,
+        };
+        
+        let redis_config = env::var("REDIS_URL")
+            .ok()
+            .filter(|url| !url.is_empty())
+            .map(|url| RedisConfig { url });
+        
+        let event_stream_config = EventStreamConfig {
+            prefix: env::var("EVENT_STREAM_PREFIX").unwrap_or_else(|_| "business-events".to_string()),
+            max_len: env::var("EVENT_STREAM_MAX_LEN")
+                .unwrap_or_else(|_| "100000".to_string())
+                .parse()
+                .map_err(|e| ServiceError::ConfigError(format!("Invalid event stream length: {}", e)))?,
+        };
+        
+        let product_cache_config = ProductCacheConfig {
+            enabled: env::var("PRODUCT_CACHE_ENABLED")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse()
+                .map_err(|e| ServiceError::ConfigError(format!("Invalid product cache flag: {}", e)))?,
+            ttl_secs: env::var("PRODUCT_CACHE_TTL_SECS")
+                .unwrap_or_else(|_| "60".to_string())
+                .parse()
+                .ok()
+                .filter(|ttl| *ttl > 0)
+                .ok_or_else(|| ServiceError::ConfigError("PRODUCT_CACHE_TTL_SECS must be a positive number".to_string()))?,
+            capacity: env::var("PRODUCT_CACHE_CAPACITY")
+                .unwrap_or_else(|_| "10000".to_string())
+                .parse()
+                .ok()
+                .filter(|capacity| *capacity > 0)
+                .ok_or_else(|| ServiceError::ConfigError("PRODUCT_CACHE_CAPACITY must be a positive number".to_string()))?,
+        };
+        
+        // The standard OpenTelemetry variables; without an OTLP endpoint spans are written locally
+        let trace_exporter = match env::var("OTEL_TRACES_EXPORTER").ok().as_deref() {
+            Some("none") => TraceExporter::Disabled,
+            None | Some("otlp") => match (env::var("OTEL_EXPORTER_OTLP_ENDPOINT"), env::var("OTEL_TRACES_FILE")) {
+                (Ok(endpoint), _) if !endpoint.is_empty() => TraceExporter::Otlp { endpoint },
+                (_, Ok(path)) if !path.is_empty() => TraceExporter::File { path },
+                _ => TraceExporter::Stdout,
+            },
+            Some(other) => return Err(ServiceError::ConfigError(format!("Invalid traces exporter: {}", other))),
+        };
+        let telemetry_config = TelemetryConfig {
+            service_name: env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| "business-service".to_string()),
+            exporter: trace_exporter,
         };
         
         Ok(AppConfig {
             server: server_config,
             postgres: postgres_config,
             mongodb: mongodb_config,
+            storage,
+            idempotency: idempotency_config,
+            auth: auth_config,
+            health: health_config,
+            telemetry: telemetry_config,
+            outbox: outbox_config,
+            redis: redis_config,
+            event_streams: event_stream_config,
+            product_cache: product_cache_config,
         })
     }
 }
\ No newline at end of file
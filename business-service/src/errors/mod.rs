@@ -1,19 +1,45 @@
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
+use utoipa::ToSchema;
+
+pub mod problem;
+
+pub use problem::*;
 
 #[derive(Error, Debug)]
 pub enum ServiceError {
+    // A database that could not be reached or was briefly unable to serve; retrying may succeed
     #[error("Database error: {0}")]
     DatabaseError(String),
     
+    // Stored data, or a reply from a database, that the service cannot use. Retrying will not help.
+    #[error("Data error: {0}")]
+    DataError(String),
+    
     #[error("Entity not found: {0}")]
     NotFoundError(String),
     
+    // Input that is well-formed but not acceptable
     #[error("Validation error: {0}")]
     ValidationError(String),
     
+    // Every invalid field of a request body, found before it reaches a service
+    #[error("Validation error: {}", describe_fields(.0))]
+    FieldValidationError(Vec<FieldError>),
+    
+    // Input that could not be understood at all, such as an unparseable query string
+    #[error("Bad request: {0}")]
+    BadRequestError(String),
+    
+    #[error("Conflict: {0}")]
+    ConflictError(String),
+    
     #[error("Authentication error: {0}")]
     AuthError(String),
     
+    #[error("Forbidden: {0}")]
+    ForbiddenError(String),
+    
     #[error("Configuration error: {0}")]
     ConfigError(String),
     
@@ -21,4 +47,100 @@ pub enum ServiceError {
     UnknownError(String),
 }
 
-pub type ServiceResult<T> = Result<T, ServiceError>;
\ No newline at end of file
+pub type ServiceResult<T> = Result<T, ServiceError>;
+
+// A problem with one field, located by a JSON pointer such as `/items/0/quantity`
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, PartialEq, Eq)]
+pub struct FieldError {
+    pub pointer: String,
+    pub detail: String,
+}
+
+fn describe_fields(errors: &[FieldError]) -> String {
+    errors
+        .iter()
+        .map(|error| format!("{}: {}", error.pointer, error.detail))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+// Connection, pool and serialization-conflict failures are transient; anything else, such as
+// a row that does not decode or a violated constraint, is a data error
+impl From<sqlx::Error> for ServiceError {
+    fn from(error: sqlx::Error) -> Self {
+        let transient = match &error {
+            sqlx::Error::Io(_)
+            | sqlx::Error::Tls(_)
+            | sqlx::Error::PoolTimedOut
+            | sqlx::Error::PoolClosed
+            | sqlx::Error::WorkerCrashed => true,
+            // Connection exceptions, serialization failures and deadlocks, insufficient resources
+            // and operator intervention such as a server shutting down
+            sqlx::Error::Database(database) => database
+                .code()
+                .is_some_and(|code| ["08", "40", "53", "57"].iter().any(|class| code.starts_with(class))),
+            _ => false,
+        };
+        
+        if transient {
+            ServiceError::DatabaseError(error.to_string())
+        } else {
+            ServiceError::DataError(error.to_string())
+        }
+    }
+}
+
+impl From<sqlx::migrate::MigrateError> for ServiceError {
+    fn from(error: sqlx::migrate::MigrateError) -> Self {
+        match error {
+            sqlx::migrate::MigrateError::Execute(error) => error.into(),
+            other => ServiceError::DataError(other.to_string()),
+        }
+    }
+}
+
+// Network and server selection failures, and errors the server labels as retryable, are transient
+impl From<mongodb::error::Error> for ServiceError {
+    fn from(error: mongodb::error::Error) -> Self {
+        use mongodb::error::ErrorKind;
+        
+        let transient = matches!(
+            error.kind.as_ref(),
+            ErrorKind::Io(_)
+                | ErrorKind::ConnectionPoolCleared { .. }
+                | ErrorKind::ServerSelection { .. }
+                | ErrorKind::DnsResolve { .. }
+        ) || ["TransientTransactionError", "RetryableWriteError", "RetryableReadError"]
+            .iter()
+            .any(|label| error.contains_label(label));
+        
+        if transient {
+            ServiceError::DatabaseError(error.to_string())
+        } else {
+            ServiceError::DataError(error.to_string())
+        }
+    }
+}
+
+impl From<mongodb::bson::ser::Error> for ServiceError {
+    fn from(error: mongodb::bson::ser::Error) -> Self {
+        ServiceError::DataError(error.to_string())
+    }
+}
+
+impl From<mongodb::bson::de::Error> for ServiceError {
+    fn from(error: mongodb::bson::de::Error) -> Self {
+        ServiceError::DataError(error.to_string())
+    }
+}
+
+// Redis only backs caches and event streams; failing to reach it is transient
+impl From<redis::RedisError> for ServiceError {
+    fn from(error: redis::RedisError) -> Self {
+        if error.is_io_error() || error.is_timeout() || error.is_connection_dropped() || error.is_connection_refusal() {
+            ServiceError::DatabaseError(error.to_string())
+        } else {
+            ServiceError::DataError(error.to_string())
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use actix_web::http::{header, StatusCode};
+use actix_web::{HttpResponse, ResponseError};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use crate::errors::{FieldError, ServiceError};
+
+pub const PROBLEM_CONTENT_TYPE: &str = "application/problem+json";
+
+// An RFC 7807 problem document. `code` is a stable identifier clients can match on,
+// and `type` is derived from it.
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone)]
+pub struct Problem {
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: String,
+    pub status: u16,
+    pub code: String,
+    pub detail: String,
+    // Set when a request body has invalid fields
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
+
+impl Problem {
+    pub fn new(status: StatusCode, code: &str, title: &str, detail: impl Into<String>) -> Self {
+        Self {
+            problem_type: format!("urn:problem-type:business-service:{}", code),
+            title: title.to_string(),
+            status: status.as_u16(),
+            code: code.to_string(),
+            detail: detail.into(),
+            errors: Vec::new(),
+        }
+    }
+    
+    pub fn to_response(&self) -> HttpResponse {
+        let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        let mut response = HttpResponse::build(status);
+        if status == StatusCode::UNAUTHORIZED {
+            response.insert_header((header::WWW_AUTHENTICATE, "Bearer"));
+        }
+        
+        response
+            .content_type(PROBLEM_CONTENT_TYPE)
+            .body(serde_json::to_string(self).unwrap_or_default())
+    }
+}
+
+impl ServiceError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ServiceError::DatabaseError(_) => "database_unavailable",
+            ServiceError::NotFoundError(_) => "not_found",
+            ServiceError::ValidationError(_) | ServiceError::FieldValidationError(_) => "validation_failed",
+            ServiceError::BadRequestError(_) => "bad_request",
+            ServiceError::ConflictError(_) => "conflict",
+            ServiceError::AuthError(_) => "unauthenticated",
+            ServiceError::ForbiddenError(_) => "forbidden",
+            ServiceError::DataError(_) | ServiceError::ConfigError(_) | ServiceError::UnknownError(_) => "internal_error",
+        }
+    }
+    
+    fn title(&self) -> &'static str {
+        match self {
+            ServiceError::DatabaseError(_) => "Database unavailable",
+            ServiceError::NotFoundError(_) => "Resource not found",
+            ServiceError::ValidationError(_) | ServiceError::FieldValidationError(_) => "Validation failed",
+            ServiceError::BadRequestError(_) => "Bad request",
+            ServiceError::ConflictError(_) => "Conflict",
+            ServiceError::AuthError(_) => "Authentication required",
+            ServiceError::ForbiddenError(_) => "Forbidden",
+            ServiceError::DataError(_) | ServiceError::ConfigError(_) | ServiceError::UnknownError(_) => "Internal server error",
+        }
+    }
+    
+    // What the client is told. Server-side failures are described generically, since their
+    // messages can expose queries, hosts or internal state.
+    fn detail(&self) -> String {
+        match self {
+            ServiceError::DatabaseError(_) => "A database the service depends on failed; try again later".to_string(),
+            ServiceError::DataError(_) | ServiceError::ConfigError(_) | ServiceError::UnknownError(_) => {
+                "An unexpected error occurred".to_string()
+            }
+            ServiceError::FieldValidationError(errors) if errors.len() == 1 => "1 field is invalid".to_string(),
+            ServiceError::FieldValidationError(errors) => format!("{} fields are invalid", errors.len()),
+            ServiceError::NotFoundError(message)
+            | ServiceError::ValidationError(message)
+            | ServiceError::BadRequestError(message)
+            | ServiceError::ConflictError(message)
+            | ServiceError::AuthError(message)
+            | ServiceError::ForbiddenError(message) => message.clone(),
+        }
+    }
+    
+    pub fn to_problem(&self) -> Problem {
+        let mut problem = Problem::new(self.status_code(), self.code(), self.title(), self.detail());
+        if let ServiceError::FieldValidationError(errors) = self {
+            problem.errors = errors.clone();
+        }
+        
+        problem
+    }
+}
+
+impl ResponseError for ServiceError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ServiceError::DatabaseError(_) => StatusCode::SERVICE_UNAVAILABLE,
+            ServiceError::NotFoundError(_) => StatusCode::NOT_FOUND,
+            ServiceError::ValidationError(_) | ServiceError::FieldValidationError(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ServiceError::BadRequestError(_) => StatusCode::BAD_REQUEST,
+            ServiceError::ConflictError(_) => StatusCode::CONFLICT,
+            ServiceError::AuthError(_) => StatusCode::UNAUTHORIZED,
+            ServiceError::ForbiddenError(_) => StatusCode::FORBIDDEN,
+            ServiceError::DataError(_) | ServiceError::ConfigError(_) | ServiceError::UnknownError(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        }
+    }
+    
+    fn error_response(&self) -> HttpResponse {
+        if self.status_code().is_server_error() {
+            tracing::error!("Request failed: {}", self);
+        }
+        
+        self.to_problem().to_response()
+    }
+}
@@ -0,0 +1,41 @@
+use std::sync::{Arc, RwLock};
+use async_trait::async_trait;
+use uuid::Uuid;
+use crate::errors::ServiceResult;
+use crate::events::publisher::EventPublisher;
+use crate::models::event::EventEnvelope;
+
+// Keeps published events in memory, for tests
+#[derive(Clone, Default)]
+pub struct InMemoryPublisher {
+    published: Arc<RwLock<Vec<EventEnvelope>>>,
+}
+
+impl InMemoryPublisher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    // Every event published so far, in the order it was published
+    pub fn published(&self) -> Vec<EventEnvelope> {
+        self.published.read().unwrap().clone()
+    }
+    
+    pub fn published_for(&self, aggregate_id: Uuid) -> Vec<EventEnvelope> {
+        self.published
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|envelope| envelope.aggregate_id == aggregate_id)
+            .cloned()
+            .collect()
+    }
+}
+
+#[async_trait]
+impl EventPublisher for InMemoryPublisher {
+    async fn publish(&self, envelope: &EventEnvelope) -> ServiceResult<()> {
+        self.published.write().unwrap().push(envelope.clone());
+        Ok(())
+    }
+}
@@ -0,0 +1,9 @@
+pub mod publisher;
+pub mod in_memory;
+pub mod redis_streams;
+pub mod relay;
+
+pub use publisher::*;
+pub use in_memory::*;
+pub use redis_streams::*;
+pub use relay::*;
@@ -0,0 +1,29 @@
+use async_trait::async_trait;
+use crate::errors::ServiceResult;
+use crate::models::event::EventEnvelope;
+
+// Delivers domain events to other services. The outbox relay may hand the same event to
+// `publish` more than once, so consumers must tolerate duplicates, recognisable by event id.
+// Publishers report a broker they cannot reach as a `DatabaseError`, which the relay retries
+// without counting it against the event, and an event the broker refused as any other error.
+#[async_trait]
+pub trait EventPublisher: Send + Sync {
+    async fn publish(&self, envelope: &EventEnvelope) -> ServiceResult<()>;
+}
+
+// Writes events to the service log, for running without a message bus
+#[derive(Clone, Default)]
+pub struct LogPublisher;
+
+#[async_trait]
+impl EventPublisher for LogPublisher {
+    async fn publish(&self, envelope: &EventEnvelope) -> ServiceResult<()> {
+        tracing::info!(
+            event.id = %envelope.id,
+            event.aggregate_id = %envelope.aggregate_id,
+            "Published {} event",
+            envelope.event_type
+        );
+        Ok(())
+    }
+}
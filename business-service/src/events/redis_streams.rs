@@ -0,0 +1,79 @@
+use async_trait::async_trait;
+use tracing::Instrument;
+use crate::config::EventStreamConfig;
+use crate::errors::{ServiceError, ServiceResult};
+use crate::events::publisher::EventPublisher;
+use crate::models::event::EventEnvelope;
+use crate::repositories::RedisClient;
+use crate::telemetry::{publish_span, trace_context};
+
+// Appends each event to the Redis stream of its aggregate type, such as `business-events:order`,
+// where consumer groups can read it. Entries carry the event `type` and `aggregate_id` for
+// filtering, the JSON envelope, and the W3C trace context of the publish span.
+pub struct RedisStreamPublisher {
+    redis: RedisClient,
+    prefix: String,
+    max_len: usize,
+}
+
+impl RedisStreamPublisher {
+    pub fn new(redis: RedisClient, config: &EventStreamConfig) -> Self {
+        Self {
+            redis,
+            prefix: config.prefix.clone(),
+            max_len: config.max_len,
+        }
+    }
+    
+    pub fn stream_key(&self, envelope: &EventEnvelope) -> String {
+        format!("{}:{}", self.prefix, envelope.aggregate_type)
+    }
+}
+
+#[async_trait]
+impl EventPublisher for RedisStreamPublisher {
+    async fn publish(&self, envelope: &EventEnvelope) -> ServiceResult<()> {
+        let stream = self.stream_key(envelope);
+        let body = serde_json::to_string(envelope)
+            .map_err(|e| ServiceError::UnknownError(format!("Cannot serialize event {}: {}", envelope.id, e)))?;
+        let span = publish_span("redis", &stream, &envelope.id.to_string());
+        
+        let mut command = redis::cmd("XADD");
+        command
+            .arg(&stream)
+            .arg("MAXLEN")
+            .arg("~")
+            .arg(self.max_len)
+            .arg("*")
+            .arg("type")
+            .arg(envelope.event_type.as_str())
+            .arg("aggregate_id")
+            .arg(envelope.aggregate_id.to_string())
+            .arg("envelope")
+            .arg(body);
+        for (header, value) in span.in_scope(trace_context).into_iter().filter(|(_, value)| !value.is_empty()) {
+            command.arg(header).arg(value);
+        }
+        
+        let result = match self.redis.connection().await {
+            Ok(mut connection) => command
+                .query_async::<String>(&mut connection)
+                .instrument(span.clone())
+                .await
+                .map_err(ServiceError::from),
+            Err(e) => Err(e),
+        };
+        if result.is_err() {
+            span.record("otel.status_code", "ERROR");
+        }
+        
+        // Keep Redis being unreachable apart from a rejected event, as the trait asks
+        result.map(|_| ()).map_err(|e| {
+            let message = format!("Failed to publish event {} to {}: {}", envelope.id, stream, e);
+            match e {
+                ServiceError::DatabaseError(_) => ServiceError::DatabaseError(message),
+                _ => ServiceError::UnknownError(message),
+            }
+        })
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use chrono::{Duration, Utc};
+use uuid::Uuid;
+use crate::errors::{ServiceError, ServiceResult};
+use crate::events::publisher::EventPublisher;
+use crate::metrics::metrics;
+use crate::models::event::{EventEnvelope, OutboxEntry, ReplayEventsDto};
+use crate::repositories::{OutboxStore, TransactionManager, UnitOfWork};
+
+// Publishes outbox events in sequence order. Events are marked published in the transaction
+// that claimed them, after they were handed to the publisher, so a crash in between publishes
+// them again: delivery is at least once. When an event fails to publish, the later events of
+// its aggregate are held back until it goes through, so each aggregate's events arrive in order.
+// A failed event is retried after a delay that doubles with each attempt; after `max_attempts`
+// it is dead-lettered, letting its aggregate's later events through, until it is replayed.
+pub struct OutboxRelay {
+    outbox: Arc<dyn OutboxStore>,
+    transactions: Arc<dyn TransactionManager>,
+    publisher: Arc<dyn EventPublisher>,
+    batch_size: i64,
+    max_attempts: i32,
+    retry_delay: Duration,
+}
+
+impl OutboxRelay {
+    pub const DEFAULT_MAX_ATTEMPTS: i32 = 10;
+    // The longest a failed event waits before it is retried
+    const MAX_RETRY_DELAY_SECS: i64 = 300;
+    
+    pub fn new(
+        outbox: Arc<dyn OutboxStore>,
+        transactions: Arc<dyn TransactionManager>,
+        publisher: Arc<dyn EventPublisher>,
+        batch_size: i64,
+    ) -> Self {
+        Self {
+            outbox,
+            transactions,
+            publisher,
+            batch_size,
+            max_attempts: Self::DEFAULT_MAX_ATTEMPTS,
+            retry_delay: Duration::seconds(1),
+        }
+    }
+    
+    // Dead-letter events after `max_attempts` failed attempts, retrying the first after `retry_delay`
+    pub fn with_retries(mut self, max_attempts: i32, retry_delay: Duration) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self.retry_delay = retry_delay;
+        self
+    }
+    
+    // Publish the oldest pending events, up to one batch. Returns how many were published.
+    pub async fn relay_batch(&self) -> ServiceResult<usize> {
+        let mut uow = self.transactions.begin().await?;
+        let entries = self.outbox.claim_in(&mut uow, self.batch_size, Utc::now()).await?;
+        
+        let mut held_back: HashSet<Uuid> = HashSet::new();
+        let mut published = Vec::new();
+        for entry in entries {
+            let event = &entry.event;
+            if held_back.contains(&event.aggregate_id) {
+                continue;
+            }
+            
+            let result = self.publisher.publish(&EventEnvelope::from(event)).await;
+            metrics().record_event_published(event.event_type, result.is_ok());
+            match result {
+                Ok(()) => published.push(entry.sequence),
+                // Every event would fail the same way, so leave them for the next batch without
+                // counting an attempt against any
+                Err(ServiceError::DatabaseError(e)) => {
+                    tracing::warn!("Cannot reach the event publisher; stopping at event {}: {}", entry.sequence, e);
+                    break;
+                }
+                Err(e) => {
+                    held_back.insert(event.aggregate_id);
+                    self.record_failure(&mut uow, &entry, &e).await?;
+                }
+            }
+        }
+        
+        if !published.is_empty() {
+            self.outbox.mark_published_in(&mut uow, &published).await?;
+        }
+        uow.commit().await?;
+        
+        Ok(published.len())
+    }
+    
+    async fn record_failure(&self, uow: &mut UnitOfWork, entry: &OutboxEntry, error: &ServiceError) -> ServiceResult<()> {
+        let event = &entry.event;
+        let attempts = entry.attempts + 1;
+        if attempts >= self.max_attempts {
+            tracing::error!(
+                "Failed to publish event {} ({}) {} times; dead-lettering it: {}",
+                entry.sequence, event.event_type, attempts, error
+            );
+            metrics().record_event_dead_lettered(event.event_type);
+            return self.outbox.record_failure_in(uow, entry.sequence, &error.to_string(), None).await;
+        }
+        
+        let delay = (self.retry_delay * 2i32.pow((attempts - 1).min(16) as u32))
+            .min(Duration::seconds(Self::MAX_RETRY_DELAY_SECS));
+        tracing::warn!(
+            "Failed to publish event {} ({}); holding back {} {} for {}s: {}",
+            entry.sequence, event.event_type, event.event_type.aggregate_type(), event.aggregate_id, delay.num_seconds(), error
+        );
+        self.outbox.record_failure_in(uow, entry.sequence, &error.to_string(), Some(Utc::now() + delay)).await
+    }
+    
+    // Publish batches until the outbox is drained or an event fails. Returns how many were published.
+    pub async fn relay_pending(&self) -> ServiceResult<usize> {
+        let mut total = 0;
+        loop {
+            let published = self.relay_batch().await?;
+            total += published;
+            if (published as i64) < self.batch_size {
+                return Ok(total);
+            }
+        }
+    }
+    
+    // Publish already published events again, from a sequence on and optionally for one aggregate.
+    // They go out in their original order with later pending events. Returns how many were queued.
+    #[tracing::instrument(skip_all, fields(from_sequence = dto.from_sequence))]
+    pub async fn replay(&self, dto: ReplayEventsDto) -> ServiceResult<u64> {
+        let replayed = self.outbox.replay(dto.from_sequence, dto.aggregate_id).await?;
+        tracing::info!("Queued {} events for replay from sequence {}", replayed, dto.from_sequence);
+        
+        Ok(replayed)
+    }
+    
+    // Drop events published longer ago than the retention period; they can no longer be replayed
+    pub async fn purge(&self, retention_hours: u64) -> ServiceResult<u64> {
+        self.outbox.purge_published(Utc::now() - Duration::hours(retention_hours as i64)).await
+    }
+}
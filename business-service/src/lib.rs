@@ -1,7 +1,10 @@
 pub mod api;
 pub mod config;
 pub mod errors;
+pub mod events;
+pub mod metrics;
 pub mod models;
 pub mod repositories;
 pub mod services;
+pub mod telemetry;
 pub mod utils;
\ No newline at end of file
@@ -1,44 +1,274 @@
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+use std::time::Duration;
 use actix_web::{App, HttpServer, middleware, web};
+use actix_web::middleware::from_fn;
 use dotenv::dotenv;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use opentelemetry::trace::TracerProvider;
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, Layer};
 
-use business_service::config::AppConfig;
-use business_service::repositories::{PostgresClient, MongoClient, ProductRepository, OrderRepository};
-use business_service::services::{ProductService, OrderService};
+use business_service::config::{AppConfig, MongoConfig, StorageBackend};
+use business_service::models::{Order, Product};
+use business_service::repositories::{
+    PostgresClient, MongoClient, RedisClient, ProductRepository, OrderRepository, OrderHistoryRepository, SagaRepository,
+    IdempotencyRepository, IdempotencyStore, ApiKeyRepository, ApiKeyStore, OutboxRepository, MongoOutboxRepository, InMemoryRepository,
+    InMemoryOrderHistory, InMemorySagaStore, InMemoryIdempotencyStore, InMemoryApiKeyStore, InMemoryOutbox,
+    InMemoryTransactionManager, Instrumented, CacheStore, LocalCache, Cached, StockRepository,
+};
+use business_service::services::{ApiKeyService, AuthService, HealthService, ProductService, OrderService, SagaCoordinator};
+use business_service::events::{EventPublisher, LogPublisher, OutboxRelay, RedisStreamPublisher};
 use business_service::api::configure_routes;
+use business_service::api::metrics::record_request_metrics;
+use business_service::api::telemetry::trace_requests;
+use business_service::metrics::metrics;
+use business_service::telemetry::init_tracer_provider;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     // Initialize environment
     dotenv().ok();
     
-    // Initialize logging
+    // Load configuration
+    let config = AppConfig::from_env().expect("Failed to load configuration");
+    
+    // Initialize logging and tracing. Only this crate's spans are exported, whatever RUST_LOG says,
+    // so traces stay complete and the exporter's own HTTP client is never traced.
+    let tracer_provider = init_tracer_provider(&config.telemetry).expect("Failed to initialize tracing");
+    let otel_layer = tracer_provider.as_ref().map(|provider| {
+        tracing_opentelemetry::layer()
+            .with_tracer(provider.tracer("business-service"))
+            .with_filter(Targets::new().with_target("business_service", tracing::Level::INFO))
+    });
     tracing_subscriber::registry()
-        .with(tracing_subscriber::EnvFilter::new(
+        .with(otel_layer)
+        .with(tracing_subscriber::fmt::layer().with_filter(tracing_subscriber::EnvFilter::new(
             std::env::var("RUST_LOG").unwrap_or_else(|_| "info".into()),
-        ))
-        .with(tracing_subscriber::fmt::layer())
+        )))
         .init();
     
-    // Load configuration
-    let config = AppConfig::from_env().expect("Failed to load configuration");
+    // `business-service migrate [status]` manages the schema and exits
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("migrate") {
+        let (postgres_client, mongo_client) = connect_databases(&config).await;
+        return run_migrate_command(&postgres_client, &mongo_client, &config.mongodb, args.get(1).map(String::as_str)).await;
+    }
     
-    // Initialize database clients
-    let postgres_client = PostgresClient::new(&config.postgres)
-        .await
-        .expect("Failed to connect to PostgreSQL");
+    // Initialize repositories and services for the configured storage backend
+    let idempotency_ttl = config.idempotency.ttl_secs;
+    let idempotency_lease = config.idempotency.lease_secs;
+    let mut health_service = HealthService::new(Duration::from_millis(config.health.check_timeout_ms));
     
-    let mongo_client = MongoClient::new(&config.mongodb)
-        .await
-        .expect("Failed to connect to MongoDB");
+    // Events go to Redis streams when Redis is configured, and to the log otherwise. The outbox
+    // holds them while Redis is down, and product reads fall back to MongoDB, so Redis is not
+    // required for readiness, nor to start: the connection is made on first use.
+    let redis_client = match &config.redis {
+        Some(redis_config) => {
+            let redis_client = RedisClient::new(redis_config).expect("Invalid Redis configuration");
+            if let Err(e) = redis_client.connection().await {
+                tracing::warn!("Redis is not reachable yet, will keep trying: {}", e);
+            }
+            health_service = health_service.with_dependency("redis", Arc::new(redis_client.clone()), false);
+            Some(redis_client)
+        }
+        None => None,
+    };
+    let publisher: Arc<dyn EventPublisher> = match &redis_client {
+        Some(redis_client) => Arc::new(RedisStreamPublisher::new(redis_client.clone(), &config.event_streams)),
+        None => {
+            tracing::warn!("REDIS_URL is not set; domain events are only written to the log");
+            Arc::new(LogPublisher)
+        }
+    };
+    
+    // Product reads are cached in Redis, shared by all instances, or without it in process memory
+    let product_cache: Option<Arc<dyn CacheStore>> = match (&redis_client, config.product_cache.enabled) {
+        (_, false) => None,
+        (Some(redis_client), true) => Some(Arc::new(redis_client.clone())),
+        (None, true) => {
+            let capacity = NonZeroUsize::new(config.product_cache.capacity).expect("capacity is validated");
+            Some(Arc::new(LocalCache::new(capacity)))
+        }
+    };
+    let product_cache_ttl = Duration::from_secs(config.product_cache.ttl_secs);
+    let event_batch_size = config.outbox.batch_size;
+    let (product_service, order_service, idempotency_store, api_key_store, outbox_relay, health_service): (
+        _,
+        _,
+        Arc<dyn IdempotencyStore>,
+        Arc<dyn ApiKeyStore>,
+        _,
+        _,
+    ) = match config.storage {
+        StorageBackend::Memory => {
+            tracing::warn!("Using in-memory storage; data will be lost on restart");
+            let product_repository = Arc::new(InMemoryRepository::<Product>::new());
+            let outbox = Arc::new(InMemoryOutbox::new());
+            let transactions = Arc::new(InMemoryTransactionManager);
+            
+            (
+                ProductService::new(
+                    product_repository.clone(),
+                    product_repository.clone(),
+                    product_repository.clone(),
+                    product_repository.clone(),
+                    outbox.clone(),
+                    transactions.clone(),
+                ),
+                OrderService::new(
+                    Arc::new(InMemoryRepository::<Order>::new()),
+                    product_repository.clone(),
+                    product_repository,
+                    Arc::new(InMemoryOrderHistory::new()),
+                    outbox.clone(),
+                    transactions.clone(),
+                    Arc::new(InMemorySagaStore::new()),
+                ),
+                Arc::new(InMemoryIdempotencyStore::new(idempotency_ttl, idempotency_lease)),
+                Arc::new(InMemoryApiKeyStore::new()),
+                OutboxRelay::new(outbox, transactions, publisher, event_batch_size),
+                health_service,
+            )
+        }
+        StorageBackend::Database => {
+            let (postgres_client, mongo_client) = connect_databases(&config).await;
+            
+            if config.postgres.run_migrations {
+                postgres_client
+                    .run_migrations()
+                    .await
+                    .expect("Failed to apply database migrations");
+            }
+            
+            if config.mongodb.run_migrations {
+                mongo_client
+                    .run_migrations(&config.mongodb)
+                    .await
+                    .expect("Failed to apply MongoDB migrations");
+            }
+            
+            metrics()
+                .register_pool("postgres", postgres_client.pool.clone())
+                .expect("Failed to register connection pool metrics");
+            let health_service = health_service
+                .with_dependency("postgres", Arc::new(postgres_client.clone()), true)
+                .with_dependency("mongodb", Arc::new(mongo_client.clone()), true);
+            // Every repository method is traced and timed into `db_operation_duration_seconds`,
+            // so with the cache enabled only product reads that miss it are
+            let product_repository = || Instrumented::new(ProductRepository::new(mongo_client.clone()), "mongodb", "products");
+            let outbox = Arc::new(Instrumented::new(OutboxRepository::new(postgres_client.clone()), "postgres", "outbox"));
+            let transactions = Arc::new(postgres_client.clone());
+            // Catalog changes are MongoDB transactions, with their events staged in MongoDB
+            // until the relay moves them to the outbox
+            let product_outbox = Arc::new(MongoOutboxRepository::new(mongo_client.clone(), outbox.clone()));
+            let product_transactions = Arc::new(mongo_client.clone());
+            let (product_service, stock): (ProductService, Arc<dyn StockRepository>) = match product_cache {
+                Some(cache) => {
+                    let cached = Arc::new(Cached::new(product_repository(), cache, "products", product_cache_ttl));
+                    let service = ProductService::new(
+                        cached.clone(),
+                        Arc::new(product_repository()),
+                        cached.clone(),
+                        cached.clone(),
+                        product_outbox.clone(),
+                        product_transactions.clone(),
+                    );
+                    (service, cached)
+                }
+                None => {
+                    let product_repository = Arc::new(product_repository());
+                    let service = ProductService::new(
+                        product_repository.clone(),
+                        product_repository.clone(),
+                        product_repository.clone(),
+                        product_repository.clone(),
+                        product_outbox.clone(),
+                        product_transactions.clone(),
+                    );
+                    (service, product_repository)
+                }
+            };
+            
+            (
+                product_service,
+                // Orders are priced from MongoDB rather than from a cache that may lag behind it.
+                // Their stock changes still go through the cache, which drops the products they change.
+                OrderService::new(
+                    Arc::new(Instrumented::new(OrderRepository::new(postgres_client.clone()), "postgres", "orders")),
+                    Arc::new(product_repository()),
+                    stock,
+                    Arc::new(Instrumented::new(OrderHistoryRepository::new(postgres_client.clone()), "postgres", "order_history")),
+                    outbox.clone(),
+                    transactions.clone(),
+                    Arc::new(Instrumented::new(SagaRepository::new(postgres_client.clone()), "postgres", "sagas")),
+                ),
+                Arc::new(Instrumented::new(IdempotencyRepository::new(postgres_client.clone(), idempotency_ttl, idempotency_lease), "postgres", "idempotency_keys")),
+                Arc::new(Instrumented::new(ApiKeyRepository::new(postgres_client), "postgres", "api_keys")),
+                // The relay claims through the product outbox, which moves staged events along first
+                OutboxRelay::new(product_outbox, transactions, publisher, event_batch_size),
+                health_service,
+            )
+        }
+    };
+    let product_service = web::Data::new(product_service);
+    let order_service = web::Data::new(order_service);
+    let idempotency_store = web::Data::from(idempotency_store);
+    let auth_service = web::Data::new(AuthService::new(&config.auth));
+    let api_key_service = web::Data::new(ApiKeyService::new(api_key_store));
+    let health_service = web::Data::new(health_service);
+    let retry_delay = chrono::Duration::milliseconds(config.outbox.retry_delay_ms as i64);
+    let outbox_relay = web::Data::new(outbox_relay.with_retries(config.outbox.max_attempts, retry_delay));
+    
+    // Resume sagas a previous run left unfinished, then keep sweeping for abandoned ones
+    let recovering_service = order_service.clone();
+    actix_web::rt::spawn(async move {
+        let period = std::time::Duration::from_secs(SagaCoordinator::STALE_AFTER_SECS as u64);
+        let mut interval = actix_web::rt::time::interval(period);
+        loop {
+            interval.tick().await;
+            if let Err(e) = recovering_service.recover_sagas().await {
+                tracing::error!("Saga recovery failed: {}", e);
+            }
+        }
+    });
+    
+    // Drop expired idempotency keys once an hour
+    let purging_store = idempotency_store.clone();
+    actix_web::rt::spawn(async move {
+        let mut interval = actix_web::rt::time::interval(std::time::Duration::from_secs(3600));
+        loop {
+            interval.tick().await;
+            if let Err(e) = purging_store.purge_expired().await {
+                tracing::error!("Failed to purge expired idempotency keys: {}", e);
+            }
+        }
+    });
     
-    // Initialize repositories
-    let product_repository = ProductRepository::new(mongo_client);
-    let order_repository = OrderRepository::new(postgres_client);
+    // Publish domain events recorded in the outbox
+    let relay = outbox_relay.clone();
+    let relay_interval = std::time::Duration::from_millis(config.outbox.relay_interval_ms);
+    actix_web::rt::spawn(async move {
+        let mut interval = actix_web::rt::time::interval(relay_interval);
+        loop {
+            interval.tick().await;
+            if let Err(e) = relay.relay_pending().await {
+                tracing::error!("Outbox relay failed: {}", e);
+            }
+        }
+    });
     
-    // Initialize services
-    let product_service = web::Data::new(ProductService::new(product_repository));
-    let order_service = web::Data::new(OrderService::new(order_repository));
+    // Drop published events past their retention once an hour
+    let purging_relay = outbox_relay.clone();
+    let retention_hours = config.outbox.retention_hours;
+    actix_web::rt::spawn(async move {
+        let mut interval = actix_web::rt::time::interval(std::time::Duration::from_secs(3600));
+        loop {
+            interval.tick().await;
+            if let Err(e) = purging_relay.purge(retention_hours).await {
+                tracing::error!("Failed to purge published events: {}", e);
+            }
+        }
+    });
     
     // Start HTTP server
     tracing::info!("Starting server at {}:{}", config.server.host, config.server.port);
@@ -46,11 +276,73 @@ async fn main() -> std::io::Result<()> {
     HttpServer::new(move || {
         App::new()
             .wrap(middleware::Logger::default())
+            .wrap(from_fn(record_request_metrics))
+            .wrap(from_fn(trace_requests))
             .app_data(product_service.clone())
             .app_data(order_service.clone())
+            .app_data(idempotency_store.clone())
+            .app_data(auth_service.clone())
+            .app_data(api_key_service.clone())
+            .app_data(health_service.clone())
+            .app_data(outbox_relay.clone())
             .configure(configure_routes)
     })
     .bind((config.server.host.clone(), config.server.port))?
     .run()
-    .await
-}
\ No newline at end of file
+    .await?;
+    
+    // Flush spans still waiting in the batch
+    if let Some(provider) = tracer_provider {
+        if let Err(e) = provider.shutdown() {
+            tracing::error!("Failed to flush traces: {}", e);
+        }
+    }
+    
+    Ok(())
+}
+
+async fn connect_databases(config: &AppConfig) -> (PostgresClient, MongoClient) {
+    let postgres_client = PostgresClient::new(&config.postgres)
+        .await
+        .expect("Failed to connect to PostgreSQL");
+    
+    let mongo_client = MongoClient::new(&config.mongodb)
+        .await
+        .expect("Failed to connect to MongoDB");
+    
+    (postgres_client, mongo_client)
+}
+
+async fn run_migrate_command(
+    postgres_client: &PostgresClient,
+    mongo_client: &MongoClient,
+    mongo_config: &MongoConfig,
+    subcommand: Option<&str>,
+) -> std::io::Result<()> {
+    let to_io_error = |e: business_service::errors::ServiceError| std::io::Error::other(e.to_string());
+    
+    match subcommand {
+        None | Some("run") => {
+            postgres_client.run_migrations().await.map_err(to_io_error)?;
+            mongo_client.run_migrations(mongo_config).await.map_err(to_io_error)?;
+            tracing::info!("Database migrations applied");
+        }
+        Some("status") => {
+            let mut migrations = postgres_client.migration_status().await.map_err(to_io_error)?;
+            migrations.extend(mongo_client.migration_status().await.map_err(to_io_error)?);
+            
+            for migration in migrations {
+                let state = if migration.applied { "applied" } else { "pending" };
+                println!("{:<8}  {:>6}  {:<8}  {}", migration.store, migration.version, state, migration.description);
+            }
+        }
+        Some(other) => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("Unknown migrate subcommand: {}", other),
+            ));
+        }
+    }
+    
+    Ok(())
+}
@@ -0,0 +1,213 @@
+use std::sync::LazyLock;
+use std::time::Duration;
+use prometheus::core::{Collector, Desc};
+use prometheus::proto::MetricFamily;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+use rust_decimal::prelude::ToPrimitive;
+use sqlx::PgPool;
+use crate::errors::{ServiceError, ServiceResult};
+use crate::models::event::EventType;
+use crate::models::order::{Order, OrderStatus};
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+// The process-wide metrics, served in Prometheus text format at `/metrics`
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+pub struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_request_duration: HistogramVec,
+    db_operation_duration: HistogramVec,
+    orders_created: IntCounterVec,
+    order_status_changes: IntCounterVec,
+    order_value: HistogramVec,
+    product_changes: IntCounterVec,
+    events_published: IntCounterVec,
+    event_publish_failures: IntCounterVec,
+    events_dead_lettered: IntCounterVec,
+    cache_lookups: IntCounterVec,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new();
+        
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests handled, by route pattern and status"),
+            &["method", "route", "status"],
+        ).unwrap();
+        let http_request_duration = HistogramVec::new(
+            HistogramOpts::new("http_request_duration_seconds", "Time to handle HTTP requests, by route pattern and status"),
+            &["method", "route", "status"],
+        ).unwrap();
+        let db_operation_duration = HistogramVec::new(
+            HistogramOpts::new("db_operation_duration_seconds", "Time spent in repository methods, by store and method")
+                .buckets(vec![0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0]),
+            &["store", "repository", "method", "outcome"],
+        ).unwrap();
+        let orders_created = IntCounterVec::new(
+            Opts::new("business_orders_created_total", "Orders placed, by the status they were created with"),
+            &["status"],
+        ).unwrap();
+        let order_status_changes = IntCounterVec::new(
+            Opts::new("business_order_status_changes_total", "Order status changes, by the new status"),
+            &["status"],
+        ).unwrap();
+        let order_value = HistogramVec::new(
+            HistogramOpts::new("business_order_value", "Totals of placed orders, by currency; `_sum` is the value ordered")
+                .buckets(vec![10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0, 10000.0]),
+            &["currency"],
+        ).unwrap();
+        let product_changes = IntCounterVec::new(
+            Opts::new("business_product_changes_total", "Catalog changes: products created, updated and deleted"),
+            &["action"],
+        ).unwrap();
+        let events_published = IntCounterVec::new(
+            Opts::new("business_events_published_total", "Domain events published by the outbox relay, by event type"),
+            &["type"],
+        ).unwrap();
+        let event_publish_failures = IntCounterVec::new(
+            Opts::new("business_event_publish_failures_total", "Failed attempts to publish domain events, by event type"),
+            &["type"],
+        ).unwrap();
+        let events_dead_lettered = IntCounterVec::new(
+            Opts::new("business_events_dead_lettered_total", "Domain events the outbox relay gave up on after repeated failures, by event type"),
+            &["type"],
+        ).unwrap();
+        let cache_lookups = IntCounterVec::new(
+            Opts::new("cache_lookups_total", "Reads served from a cache, by cache, repository method and result (`hit` or `miss`)"),
+            &["cache", "method", "result"],
+        ).unwrap();
+        
+        let collectors: [Box<dyn Collector>; 11] = [
+            Box::new(http_requests.clone()),
+            Box::new(http_request_duration.clone()),
+            Box::new(db_operation_duration.clone()),
+            Box::new(orders_created.clone()),
+            Box::new(order_status_changes.clone()),
+            Box::new(order_value.clone()),
+            Box::new(product_changes.clone()),
+            Box::new(events_published.clone()),
+            Box::new(event_publish_failures.clone()),
+            Box::new(events_dead_lettered.clone()),
+            Box::new(cache_lookups.clone()),
+        ];
+        for collector in collectors {
+            registry.register(collector).expect("metric names are unique");
+        }
+        
+        Self {
+            registry,
+            http_requests,
+            http_request_duration,
+            db_operation_duration,
+            orders_created,
+            order_status_changes,
+            order_value,
+            product_changes,
+            events_published,
+            event_publish_failures,
+            events_dead_lettered,
+            cache_lookups,
+        }
+    }
+    
+    // `route` is the matched route pattern, never the raw path, to keep label values bounded
+    pub fn record_http_request(&self, method: &str, route: &str, status: u16, elapsed: Duration) {
+        let status = status.to_string();
+        let labels = [method, route, status.as_str()];
+        self.http_requests.with_label_values(&labels).inc();
+        self.http_request_duration.with_label_values(&labels).observe(elapsed.as_secs_f64());
+    }
+    
+    pub fn record_db_operation(&self, store: &str, repository: &str, method: &str, succeeded: bool, elapsed: Duration) {
+        let outcome = if succeeded { "ok" } else { "error" };
+        self.db_operation_duration
+            .with_label_values(&[store, repository, method, outcome])
+            .observe(elapsed.as_secs_f64());
+    }
+    
+    pub fn record_order_created(&self, order: &Order) {
+        self.orders_created.with_label_values(&[order.status.as_str()]).inc();
+        
+        let currency = order.total.currency.code();
+        let value = order.total.amount.to_f64().unwrap_or_default();
+        self.order_value.with_label_values(&[currency]).observe(value);
+    }
+    
+    pub fn record_order_status_change(&self, status: OrderStatus) {
+        self.order_status_changes.with_label_values(&[status.as_str()]).inc();
+    }
+    
+    // `action` is one of `created`, `updated` or `deleted`
+    pub fn record_product_change(&self, action: &str) {
+        self.product_changes.with_label_values(&[action]).inc();
+    }
+    
+    pub fn record_event_published(&self, event_type: EventType, succeeded: bool) {
+        let counter = if succeeded { &self.events_published } else { &self.event_publish_failures };
+        counter.with_label_values(&[event_type.as_str()]).inc();
+    }
+    
+    pub fn record_event_dead_lettered(&self, event_type: EventType) {
+        self.events_dead_lettered.with_label_values(&[event_type.as_str()]).inc();
+    }
+    
+    pub fn record_cache_lookup(&self, cache: &str, method: &str, hit: bool) {
+        let result = if hit { "hit" } else { "miss" };
+        self.cache_lookups.with_label_values(&[cache, method, result]).inc();
+    }
+    
+    // Report the connections of a Postgres pool on every scrape
+    pub fn register_pool(&self, name: &str, pool: PgPool) -> ServiceResult<()> {
+        self.registry
+            .register(Box::new(PoolCollector::new(name, pool)))
+            .map_err(|e| ServiceError::ConfigError(format!("Failed to register pool metrics: {}", e)))
+    }
+    
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        if let Err(e) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            tracing::error!("Failed to encode metrics: {}", e);
+        }
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
+
+// Idle and in-use connections of a pool, read when metrics are gathered
+struct PoolCollector {
+    pool: PgPool,
+    connections: IntGaugeVec,
+}
+
+impl PoolCollector {
+    fn new(name: &str, pool: PgPool) -> Self {
+        let connections = IntGaugeVec::new(
+            Opts::new("db_pool_connections", "Open connections in the database pool, by state")
+                .const_label("pool", name),
+            &["state"],
+        ).unwrap();
+        
+        Self { pool, connections }
+    }
+}
+
+impl Collector for PoolCollector {
+    fn desc(&self) -> Vec<&Desc> {
+        self.connections.desc()
+    }
+    
+    fn collect(&self) -> Vec<MetricFamily> {
+        let size = i64::from(self.pool.size());
+        let idle = self.pool.num_idle() as i64;
+        self.connections.with_label_values(&["idle"]).set(idle);
+        self.connections.with_label_values(&["in_use"]).set(size - idle);
+        
+        self.connections.collect()
+    }
+}
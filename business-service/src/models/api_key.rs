@@ -0,0 +1,108 @@
+use std::fmt;
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use utoipa::ToSchema;
+use uuid::Uuid;
+use crate::errors::ServiceError;
+use crate::models::auth::Permission;
+
+// What a service-to-service key may do. Write scopes include reading the same resources.
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ApiScope {
+    #[serde(rename = "products:read")]
+    ProductsRead,
+    #[serde(rename = "products:write")]
+    ProductsWrite,
+    #[serde(rename = "orders:read")]
+    OrdersRead,
+    #[serde(rename = "orders:write")]
+    OrdersWrite,
+}
+
+impl ApiScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiScope::ProductsRead => "products:read",
+            ApiScope::ProductsWrite => "products:write",
+            ApiScope::OrdersRead => "orders:read",
+            ApiScope::OrdersWrite => "orders:write",
+        }
+    }
+    
+    // Keys are never granted order deletion or key management
+    pub fn permissions(&self) -> &'static [Permission] {
+        match self {
+            ApiScope::ProductsRead => &[Permission::ReadCatalog],
+            ApiScope::ProductsWrite => &[Permission::ReadCatalog, Permission::ManageCatalog],
+            ApiScope::OrdersRead => &[Permission::ReadOrders],
+            ApiScope::OrdersWrite => &[
+                Permission::ReadOrders,
+                Permission::PlaceOrders,
+                Permission::ChangeOrderStatus,
+            ],
+        }
+    }
+}
+
+impl FromStr for ApiScope {
+    type Err = ServiceError;
+    
+    fn from_str(scope: &str) -> Result<Self, Self::Err> {
+        match scope {
+            "products:read" => Ok(ApiScope::ProductsRead),
+            "products:write" => Ok(ApiScope::ProductsWrite),
+            "orders:read" => Ok(ApiScope::OrdersRead),
+            "orders:write" => Ok(ApiScope::OrdersWrite),
+            other => Err(ServiceError::ValidationError(format!("Unknown API key scope: {}", other))),
+        }
+    }
+}
+
+impl fmt::Display for ApiScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// A service-to-service credential. Only the argon2 hash of its secret is stored.
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone)]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub name: String,
+    pub scopes: Vec<ApiScope>,
+    #[serde(skip)]
+    pub key_hash: String,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    #[schema(value_type = i64)]
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "chrono::serde::ts_seconds_option")]
+    #[schema(value_type = Option<i64>)]
+    pub rotated_at: Option<DateTime<Utc>>,
+    #[serde(with = "chrono::serde::ts_seconds_option")]
+    #[schema(value_type = Option<i64>)]
+    pub last_used_at: Option<DateTime<Utc>>,
+    #[serde(with = "chrono::serde::ts_seconds_option")]
+    #[schema(value_type = Option<i64>)]
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl ApiKey {
+    pub fn is_revoked(&self) -> bool {
+        self.revoked_at.is_some()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CreateApiKeyDto {
+    pub name: String,
+    pub scopes: Vec<ApiScope>,
+}
+
+// Returned when a key is issued or rotated; the only time its plaintext value is shown
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct IssuedApiKey {
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+    pub key: String,
+}
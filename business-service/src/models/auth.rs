@@ -0,0 +1,183 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use crate::models::api_key::ApiScope;
+
+// Claims of the tokens issued by auth-service on login. Tokens without roles belong to customers.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Claims {
+    pub id: i64,
+    pub email: String,
+    #[serde(default)]
+    pub roles: Vec<String>,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    Admin,
+    CatalogManager,
+    Support,
+    Customer,
+}
+
+impl Role {
+    pub fn from_name(name: &str) -> Option<Role> {
+        match name {
+            "admin" => Some(Role::Admin),
+            "catalog_manager" => Some(Role::CatalogManager),
+            "support" => Some(Role::Support),
+            "customer" => Some(Role::Customer),
+            _ => None,
+        }
+    }
+    
+    pub fn permissions(&self) -> &'static [Permission] {
+        match self {
+            Role::Admin => &[
+                Permission::ReadCatalog,
+                Permission::ManageCatalog,
+                Permission::PlaceOrders,
+                Permission::ReadOrders,
+                Permission::ChangeOrderStatus,
+                Permission::DeleteOrders,
+                Permission::AllCustomers,
+                Permission::ManageApiKeys,
+                Permission::ReplayEvents,
+            ],
+            Role::CatalogManager => &[Permission::ReadCatalog, Permission::ManageCatalog],
+            Role::Support => &[
+                Permission::ReadCatalog,
+                Permission::ReadOrders,
+                Permission::ChangeOrderStatus,
+                Permission::AllCustomers,
+            ],
+            Role::Customer => &[Permission::ReadCatalog, Permission::PlaceOrders, Permission::ReadOrders],
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+    ReadCatalog,
+    // Create, update and delete products and adjust their stock
+    ManageCatalog,
+    PlaceOrders,
+    ReadOrders,
+    ChangeOrderStatus,
+    DeleteOrders,
+    // Order permissions cover every customer's orders rather than only the caller's own
+    AllCustomers,
+    // Issue, rotate and revoke service-to-service API keys
+    ManageApiKeys,
+    // Publish outbox events again
+    ReplayEvents,
+}
+
+impl Permission {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Permission::ReadCatalog => "read_catalog",
+            Permission::ManageCatalog => "manage_catalog",
+            Permission::PlaceOrders => "place_orders",
+            Permission::ReadOrders => "read_orders",
+            Permission::ChangeOrderStatus => "change_order_status",
+            Permission::DeleteOrders => "delete_orders",
+            Permission::AllCustomers => "all_customers",
+            Permission::ManageApiKeys => "manage_api_keys",
+            Permission::ReplayEvents => "replay_events",
+        }
+    }
+}
+
+// The caller a request was authenticated as
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct AuthenticatedUser {
+    pub id: i64,
+    pub email: String,
+    pub roles: Vec<Role>,
+}
+
+impl AuthenticatedUser {
+    // Stable name for the caller, recorded as the actor of changes
+    pub fn subject(&self) -> String {
+        format!("user:{}", self.id)
+    }
+    
+    // Orders identify customers by UUID; a user's is their auth-service id in the low 64 bits
+    pub fn customer_id(&self) -> Uuid {
+        Uuid::from_u64_pair(0, self.id as u64)
+    }
+    
+    pub fn can(&self, permission: Permission) -> bool {
+        self.roles.iter().any(|role| role.permissions().contains(&permission))
+    }
+}
+
+impl From<Claims> for AuthenticatedUser {
+    fn from(claims: Claims) -> Self {
+        // Roles this service does not know about grant nothing
+        let mut roles: Vec<Role> = claims.roles.iter().filter_map(|name| Role::from_name(name)).collect();
+        if claims.roles.is_empty() {
+            roles.push(Role::Customer);
+        }
+        
+        Self {
+            id: claims.id,
+            email: claims.email,
+            roles,
+        }
+    }
+}
+
+// A service authenticated with an API key
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ApiKeyIdentity {
+    pub id: Uuid,
+    pub name: String,
+    pub scopes: Vec<ApiScope>,
+}
+
+// Whoever made a request: a user with a token from auth-service, or a service with an API key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Caller {
+    User(AuthenticatedUser),
+    ApiKey(ApiKeyIdentity),
+}
+
+impl Caller {
+    // Stable name for the caller, recorded as the actor of changes
+    pub fn subject(&self) -> String {
+        match self {
+            Caller::User(user) => user.subject(),
+            Caller::ApiKey(key) => format!("api_key:{}", key.id),
+        }
+    }
+    
+    // Services act for every customer, so keys hold `AllCustomers` implicitly
+    pub fn can(&self, permission: Permission) -> bool {
+        match self {
+            Caller::User(user) => user.can(permission),
+            Caller::ApiKey(key) => {
+                permission == Permission::AllCustomers
+                    || key.scopes.iter().any(|scope| scope.permissions().contains(&permission))
+            }
+        }
+    }
+    
+    // The only customer whose orders the caller may touch, or None if it may touch anyone's
+    pub fn customer_scope(&self) -> Option<Uuid> {
+        match self {
+            _ if self.can(Permission::AllCustomers) => None,
+            Caller::User(user) => Some(user.customer_id()),
+            Caller::ApiKey(_) => None,
+        }
+    }
+    
+    // Whether the caller may act on orders placed by the given customer
+    pub fn acts_for(&self, customer_id: Uuid) -> bool {
+        self.customer_scope().is_none_or(|own| own == customer_id)
+    }
+}
@@ -0,0 +1,169 @@
+use std::fmt;
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use chrono::{DateTime, Utc};
+use utoipa::ToSchema;
+use uuid::Uuid;
+use crate::errors::{ServiceError, ServiceResult};
+use crate::models::validation::{FieldErrors, Validate};
+
+// Something that happened to an order or product that other services may want to know about
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EventType {
+    OrderCreated,
+    OrderStatusChanged,
+    OrderDeleted,
+    // A deleted order put back because the rest of its deletion failed
+    OrderRestored,
+    ProductCreated,
+    ProductUpdated,
+    // Emitted alongside `ProductUpdated` when the update changed the price
+    ProductPriceChanged,
+    ProductDeleted,
+}
+
+impl EventType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventType::OrderCreated => "OrderCreated",
+            EventType::OrderStatusChanged => "OrderStatusChanged",
+            EventType::OrderDeleted => "OrderDeleted",
+            EventType::OrderRestored => "OrderRestored",
+            EventType::ProductCreated => "ProductCreated",
+            EventType::ProductUpdated => "ProductUpdated",
+            EventType::ProductPriceChanged => "ProductPriceChanged",
+            EventType::ProductDeleted => "ProductDeleted",
+        }
+    }
+    
+    // The kind of entity the event's `aggregate_id` refers to
+    pub fn aggregate_type(&self) -> &'static str {
+        match self {
+            EventType::OrderCreated
+            | EventType::OrderStatusChanged
+            | EventType::OrderDeleted
+            | EventType::OrderRestored => "order",
+            EventType::ProductCreated
+            | EventType::ProductUpdated
+            | EventType::ProductPriceChanged
+            | EventType::ProductDeleted => "product",
+        }
+    }
+}
+
+impl FromStr for EventType {
+    type Err = ServiceError;
+    
+    fn from_str(event_type: &str) -> Result<Self, Self::Err> {
+        match event_type {
+            "OrderCreated" => Ok(EventType::OrderCreated),
+            "OrderStatusChanged" => Ok(EventType::OrderStatusChanged),
+            "OrderDeleted" => Ok(EventType::OrderDeleted),
+            "OrderRestored" => Ok(EventType::OrderRestored),
+            "ProductCreated" => Ok(EventType::ProductCreated),
+            "ProductUpdated" => Ok(EventType::ProductUpdated),
+            "ProductPriceChanged" => Ok(EventType::ProductPriceChanged),
+            "ProductDeleted" => Ok(EventType::ProductDeleted),
+            other => Err(ServiceError::ValidationError(format!("Unknown event type: {}", other))),
+        }
+    }
+}
+
+impl fmt::Display for EventType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DomainEvent {
+    pub id: Uuid,
+    pub event_type: EventType,
+    pub aggregate_id: Uuid,
+    pub occurred_at: DateTime<Utc>,
+    pub payload: serde_json::Value,
+}
+
+impl DomainEvent {
+    pub fn new<T: Serialize>(event_type: EventType, aggregate_id: Uuid, payload: &T) -> ServiceResult<Self> {
+        let payload = serde_json::to_value(payload)
+            .map_err(|e| ServiceError::UnknownError(format!("Cannot serialize {} payload: {}", event_type, e)))?;
+        
+        Ok(Self {
+            id: Uuid::new_v4(),
+            event_type,
+            aggregate_id,
+            occurred_at: Utc::now(),
+            payload,
+        })
+    }
+}
+
+// The JSON form in which events are published. `schema_version` changes whenever the
+// envelope or a payload changes incompatibly, so consumers can tell the formats apart.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct EventEnvelope {
+    pub id: Uuid,
+    #[serde(rename = "type")]
+    pub event_type: EventType,
+    pub aggregate_type: String,
+    pub aggregate_id: Uuid,
+    pub occurred_at: DateTime<Utc>,
+    pub schema_version: u32,
+    pub payload: serde_json::Value,
+}
+
+impl EventEnvelope {
+    pub const SCHEMA_VERSION: u32 = 1;
+}
+
+impl From<&DomainEvent> for EventEnvelope {
+    fn from(event: &DomainEvent) -> Self {
+        Self {
+            id: event.id,
+            event_type: event.event_type,
+            aggregate_type: event.event_type.aggregate_type().to_string(),
+            aggregate_id: event.aggregate_id,
+            occurred_at: event.occurred_at,
+            schema_version: Self::SCHEMA_VERSION,
+            payload: event.payload.clone(),
+        }
+    }
+}
+
+// An event in the outbox. Sequences increase in commit order for each aggregate.
+#[derive(Debug, Clone)]
+pub struct OutboxEntry {
+    pub sequence: i64,
+    pub event: DomainEvent,
+    // Failed attempts to publish it so far
+    pub attempts: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ReplayEventsDto {
+    // Republish events from this outbox sequence on
+    pub from_sequence: i64,
+    // Only republish this aggregate's events
+    #[serde(default)]
+    pub aggregate_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ReplayEventsResponse {
+    // Published events marked for publishing again
+    pub replayed: u64,
+}
+
+impl Validate for ReplayEventsDto {
+    fn validate(body: &Value) -> ServiceResult<()> {
+        let mut errors = FieldErrors::new();
+        if let Some(from_sequence) = errors.required::<i64>(body, "/from_sequence") {
+            errors.check(from_sequence >= 1, "/from_sequence", "must be at least 1");
+        }
+        errors.optional::<Uuid>(body, "/aggregate_id");
+        
+        errors.into_result()
+    }
+}
@@ -0,0 +1,81 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, PartialEq)]
+pub struct CategoryCount {
+    pub category: String,
+    pub count: u64,
+}
+
+// Products priced from `min` up to but not including `max`; the last range has no `max`
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, PartialEq)]
+pub struct PriceRangeCount {
+    pub min: u32,
+    pub max: Option<u32>,
+    pub count: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, PartialEq)]
+pub struct StockCount {
+    pub in_stock: bool,
+    pub count: u64,
+}
+
+// How the products matching a listing or search divide up by category, price and
+// availability. Categories are listed most common first and only when they have products;
+// every price range and both availabilities are always listed, with their counts possibly 0.
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, PartialEq)]
+pub struct ProductFacets {
+    pub categories: Vec<CategoryCount>,
+    pub price_ranges: Vec<PriceRangeCount>,
+    pub in_stock: Vec<StockCount>,
+}
+
+impl ProductFacets {
+    // Lower bounds of the price ranges, in the price's currency units whatever the currency
+    pub const PRICE_RANGES: &'static [u32] = &[0, 25, 50, 100, 250, 500];
+    
+    // Facets counting nothing yet
+    pub fn empty() -> Self {
+        Self {
+            categories: Vec::new(),
+            price_ranges: Self::PRICE_RANGES
+                .iter()
+                .enumerate()
+                .map(|(i, min)| PriceRangeCount {
+                    min: *min,
+                    max: Self::PRICE_RANGES.get(i + 1).copied(),
+                    count: 0,
+                })
+                .collect(),
+            in_stock: [true, false].into_iter().map(|in_stock| StockCount { in_stock, count: 0 }).collect(),
+        }
+    }
+    
+    // Index of the price range containing `amount`
+    pub fn price_range(amount: Decimal) -> usize {
+        Self::PRICE_RANGES
+            .iter()
+            .rposition(|min| amount >= Decimal::from(*min))
+            .unwrap_or(0)
+    }
+    
+    pub fn add_category(&mut self, category: &str, count: u64) {
+        match self.categories.iter_mut().find(|counted| counted.category == category) {
+            Some(counted) => counted.count += count,
+            None => self.categories.push(CategoryCount { category: category.to_string(), count }),
+        }
+    }
+    
+    pub fn add_stock(&mut self, in_stock: bool, count: u64) {
+        if let Some(counted) = self.in_stock.iter_mut().find(|counted| counted.in_stock == in_stock) {
+            counted.count += count;
+        }
+    }
+    
+    // Order categories by count, most common first, then by name
+    pub fn sort_categories(&mut self) {
+        self.categories.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.category.cmp(&b.category)));
+    }
+}
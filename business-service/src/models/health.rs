@@ -0,0 +1,41 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Up,
+    Down,
+}
+
+// The outcome of checking one dependency. `error` never contains the underlying
+// database error, which is only logged.
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone)]
+pub struct DependencyHealth {
+    pub name: String,
+    pub status: HealthStatus,
+    pub required: bool,
+    pub latency_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// Readiness of the service: down as soon as any required dependency is down
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone)]
+pub struct HealthReport {
+    pub status: HealthStatus,
+    pub checks: Vec<DependencyHealth>,
+}
+
+impl HealthReport {
+    pub fn new(checks: Vec<DependencyHealth>) -> Self {
+        let ready = checks.iter().all(|check| !check.required || check.status == HealthStatus::Up);
+        let status = if ready { HealthStatus::Up } else { HealthStatus::Down };
+        
+        Self { status, checks }
+    }
+    
+    pub fn is_ready(&self) -> bool {
+        self.status == HealthStatus::Up
+    }
+}
@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// The response first returned for an idempotency key
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct StoredResponse {
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+}
+
+// What is known about a key that was already claimed. `response` is empty while the
+// first request is still being processed.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct IdempotencyRecord {
+    pub fingerprint: String,
+    pub response: Option<StoredResponse>,
+}
+
+// The outcome of claiming a key. A claimed key is held under a token that completing or
+// releasing it must present, so a request whose claim was taken over cannot touch the key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdempotencyClaim {
+    Claimed(Uuid),
+    Taken(IdempotencyRecord),
+}
@@ -1,5 +1,27 @@
+pub mod validation;
 pub mod product;
 pub mod order;
+pub mod money;
+pub mod stock;
+pub mod saga;
+pub mod idempotency;
+pub mod auth;
+pub mod api_key;
+pub mod health;
+pub mod event;
+pub mod search;
+pub mod facet;
 
+pub use validation::*;
 pub use product::*;
-pub use order::*;
\ No newline at end of file
+pub use order::*;
+pub use money::*;
+pub use stock::*;
+pub use saga::*;
+pub use idempotency::*;
+pub use auth::*;
+pub use api_key::*;
+pub use health::*;
+pub use event::*;
+pub use search::*;
+pub use facet::*;
\ No newline at end of file
@@ -0,0 +1,209 @@
+use std::fmt;
+use std::str::FromStr;
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use crate::errors::{ServiceError, ServiceResult};
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Currency {
+    #[default]
+    #[serde(rename = "USD")]
+    Usd,
+    #[serde(rename = "EUR")]
+    Eur,
+    #[serde(rename = "GBP")]
+    Gbp,
+    #[serde(rename = "INR")]
+    Inr,
+    #[serde(rename = "JPY")]
+    Jpy,
+}
+
+impl Currency {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Currency::Usd => "USD",
+            Currency::Eur => "EUR",
+            Currency::Gbp => "GBP",
+            Currency::Inr => "INR",
+            Currency::Jpy => "JPY",
+        }
+    }
+
+    // Number of decimal places the currency is settled in
+    pub fn minor_units(&self) -> u32 {
+        match self {
+            Currency::Jpy => 0,
+            _ => 2,
+        }
+    }
+}
+
+impl FromStr for Currency {
+    type Err = ServiceError;
+
+    fn from_str(code: &str) -> Result<Self, Self::Err> {
+        match code.trim() {
+            "USD" => Ok(Currency::Usd),
+            "EUR" => Ok(Currency::Eur),
+            "GBP" => Ok(Currency::Gbp),
+            "INR" => Ok(Currency::Inr),
+            "JPY" => Ok(Currency::Jpy),
+            other => Err(ServiceError::ValidationError(format!("Unsupported currency: {}", other))),
+        }
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+// An exact monetary amount. Amounts are always held at the currency's minor-unit
+// scale: arithmetic results are rounded half away from zero, while amounts coming
+// from clients must already fit that scale and are rejected otherwise.
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(try_from = "RawMoney")]
+pub struct Money {
+    #[schema(value_type = String, example = "19.99")]
+    pub amount: Decimal,
+    pub currency: Currency,
+}
+
+#[derive(Deserialize)]
+struct RawMoney {
+    amount: Decimal,
+    #[serde(default)]
+    currency: Currency,
+}
+
+impl TryFrom<RawMoney> for Money {
+    type Error = ServiceError;
+
+    fn try_from(raw: RawMoney) -> Result<Self, Self::Error> {
+        Money::exact(raw.amount, raw.currency)
+    }
+}
+
+impl Money {
+    // Build an amount, rounding it to the currency's minor units
+    pub fn new(amount: Decimal, currency: Currency) -> Self {
+        let amount = amount.round_dp_with_strategy(currency.minor_units(), RoundingStrategy::MidpointAwayFromZero);
+        Self { amount, currency }
+    }
+
+    // Build an amount that must already be representable in the currency's minor units
+    pub fn exact(amount: Decimal, currency: Currency) -> ServiceResult<Self> {
+        if amount.normalize().scale() > currency.minor_units() {
+            return Err(ServiceError::ValidationError(format!(
+                "Amount {} has more than {} decimal places for {}",
+                amount, currency.minor_units(), currency
+            )));
+        }
+
+        Ok(Self::new(amount, currency))
+    }
+
+    pub fn zero(currency: Currency) -> Self {
+        Self::new(Decimal::ZERO, currency)
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.amount.is_sign_negative() && !self.amount.is_zero()
+    }
+
+    pub fn checked_add(&self, other: &Money) -> ServiceResult<Money> {
+        if self.currency != other.currency {
+            return Err(ServiceError::ValidationError(format!(
+                "Cannot add {} to {}", other.currency, self.currency
+            )));
+        }
+
+        let amount = self.amount.checked_add(other.amount)
+            .ok_or_else(|| ServiceError::ValidationError("Monetary amount overflow".to_string()))?;
+
+        Ok(Money::new(amount, self.currency))
+    }
+
+    pub fn checked_sub(&self, other: &Money) -> ServiceResult<Money> {
+        if self.currency != other.currency {
+            return Err(ServiceError::ValidationError(format!(
+                "Cannot subtract {} from {}", other.currency, self.currency
+            )));
+        }
+
+        let amount = self.amount.checked_sub(other.amount)
+            .ok_or_else(|| ServiceError::ValidationError("Monetary amount overflow".to_string()))?;
+
+        Ok(Money::new(amount, self.currency))
+    }
+
+    pub fn checked_mul(&self, quantity: i32) -> ServiceResult<Money> {
+        let amount = self.amount.checked_mul(Decimal::from(quantity))
+            .ok_or_else(|| ServiceError::ValidationError("Monetary amount overflow".to_string()))?;
+
+        Ok(Money::new(amount, self.currency))
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.amount, self.currency)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn new_rounds_half_way_amounts_away_from_zero() {
+        assert_eq!(Money::new(dec("2.345"), Currency::Usd).amount, dec("2.35"));
+        assert_eq!(Money::new(dec("-2.345"), Currency::Usd).amount, dec("-2.35"));
+        assert_eq!(Money::new(dec("2.344"), Currency::Usd).amount, dec("2.34"));
+        assert_eq!(Money::new(dec("0.005"), Currency::Eur).amount, dec("0.01"));
+    }
+
+    #[test]
+    fn exact_rejects_more_precision_than_the_currency_has() {
+        assert!(Money::exact(dec("1.234"), Currency::Usd).is_err());
+        assert!(Money::exact(dec("0.001"), Currency::Inr).is_err());
+
+        // Trailing zeros are not extra precision
+        assert_eq!(Money::exact(dec("1.2300"), Currency::Usd).unwrap().amount, dec("1.23"));
+        assert!(serde_json::from_str::<Money>(r#"{ "amount": "19.999", "currency": "USD" }"#).is_err());
+    }
+
+    #[test]
+    fn arithmetic_refuses_to_mix_currencies() {
+        let dollars = Money::new(dec("10.00"), Currency::Usd);
+        let euros = Money::new(dec("3.00"), Currency::Eur);
+
+        assert!(matches!(dollars.checked_add(&euros), Err(ServiceError::ValidationError(_))));
+        assert!(matches!(dollars.checked_sub(&euros), Err(ServiceError::ValidationError(_))));
+
+        let change = Money::new(dec("2.50"), Currency::Usd);
+        assert_eq!(dollars.checked_add(&change).unwrap(), Money::new(dec("12.50"), Currency::Usd));
+        assert_eq!(dollars.checked_sub(&change).unwrap(), Money::new(dec("7.50"), Currency::Usd));
+        assert!(change.checked_sub(&dollars).unwrap().is_negative());
+    }
+
+    #[test]
+    fn yen_has_no_minor_units() {
+        assert_eq!(Currency::Jpy.minor_units(), 0);
+        assert_eq!(Money::new(dec("1499.5"), Currency::Jpy).amount, dec("1500"));
+        assert_eq!(Money::new(dec("-0.5"), Currency::Jpy).amount, dec("-1"));
+        assert!(Money::exact(dec("1499.5"), Currency::Jpy).is_err());
+        assert_eq!(Money::exact(dec("1500.00"), Currency::Jpy).unwrap().amount, dec("1500"));
+
+        let price = Money::exact(dec("1500"), Currency::Jpy).unwrap();
+        assert_eq!(price.checked_mul(3).unwrap().to_string(), "4500 JPY");
+        assert!(serde_json::from_str::<Money>(r#"{ "amount": "0.5", "currency": "JPY" }"#).is_err());
+    }
+}
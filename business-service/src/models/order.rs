@@ -1,15 +1,29 @@
+use std::fmt;
+use std::str::FromStr;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use chrono::{DateTime, Utc};
+use utoipa::ToSchema;
 use uuid::Uuid;
+use crate::errors::{ServiceError, ServiceResult};
+use crate::models::money::Money;
+use crate::models::validation::{FieldErrors, Validate};
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+const MAX_ORDER_ITEMS: usize = 100;
+const MAX_REASON_CHARS: usize = 500;
+
+// A line item with a snapshot of the catalog entry at the time the order was placed,
+// so later catalog edits do not change historical orders
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone)]
 pub struct OrderItem {
     pub product_id: Uuid,
+    pub name: String,
+    pub sku: String,
     pub quantity: i32,
-    pub price: f64,
+    pub price: Money,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, Copy, PartialEq, Eq)]
 pub enum OrderStatus {
     #[serde(rename = "pending")]
     Pending,
@@ -23,26 +37,91 @@ pub enum OrderStatus {
     Cancelled,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+impl OrderStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrderStatus::Pending => "pending",
+            OrderStatus::Processing => "processing",
+            OrderStatus::Shipped => "shipped",
+            OrderStatus::Delivered => "delivered",
+            OrderStatus::Cancelled => "cancelled",
+        }
+    }
+    
+    // The transition table: statuses an order may move to from this one.
+    // Delivered and cancelled orders are final.
+    pub fn allowed_transitions(&self) -> &'static [OrderStatus] {
+        match self {
+            OrderStatus::Pending => &[OrderStatus::Processing, OrderStatus::Cancelled],
+            OrderStatus::Processing => &[OrderStatus::Shipped, OrderStatus::Cancelled],
+            OrderStatus::Shipped => &[OrderStatus::Delivered],
+            OrderStatus::Delivered => &[],
+            OrderStatus::Cancelled => &[],
+        }
+    }
+    
+    pub fn can_transition_to(&self, next: OrderStatus) -> bool {
+        self.allowed_transitions().contains(&next)
+    }
+}
+
+impl FromStr for OrderStatus {
+    type Err = ServiceError;
+    
+    fn from_str(status: &str) -> Result<Self, Self::Err> {
+        match status {
+            "pending" => Ok(OrderStatus::Pending),
+            "processing" => Ok(OrderStatus::Processing),
+            "shipped" => Ok(OrderStatus::Shipped),
+            "delivered" => Ok(OrderStatus::Delivered),
+            "cancelled" => Ok(OrderStatus::Cancelled),
+            other => Err(ServiceError::ValidationError(format!("Unknown order status: {}", other))),
+        }
+    }
+}
+
+impl fmt::Display for OrderStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// One entry in an order's status history. `from` is empty for the entry recorded
+// when the order is created.
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone)]
+pub struct OrderStatusChange {
+    pub order_id: Uuid,
+    pub from: Option<OrderStatus>,
+    pub to: OrderStatus,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    #[schema(value_type = i64)]
+    pub changed_at: DateTime<Utc>,
+    pub actor: Option<String>,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone)]
 pub struct Order {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
     pub id: Option<Uuid>,
     pub customer_id: Uuid,
     pub items: Vec<OrderItem>,
-    pub total: f64,
+    pub total: Money,
     pub status: OrderStatus,
     #[serde(with = "chrono::serde::ts_seconds")]
+    #[schema(value_type = i64)]
     pub created_at: DateTime<Utc>,
     #[serde(with = "chrono::serde::ts_seconds")]
+    #[schema(value_type = i64)]
     pub updated_at: DateTime<Utc>,
 }
 
 impl Order {
-    pub fn new(customer_id: Uuid, items: Vec<OrderItem>) -> Self {
+    pub fn new(customer_id: Uuid, items: Vec<OrderItem>) -> ServiceResult<Self> {
         let now = Utc::now();
-        let total = items.iter().fold(0.0, |acc, item| acc + (item.price * item.quantity as f64));
+        let total = Self::calculate_total(&items)?;
         
-        Self {
+        Ok(Self {
             id: Some(Uuid::new_v4()),
             customer_id,
             items,
@@ -50,17 +129,76 @@ impl Order {
             status: OrderStatus::Pending,
             created_at: now,
             updated_at: now,
-        }
+        })
     }
+    
+    // Sum of price * quantity over all items; every item must share one currency
+    pub fn calculate_total(items: &[OrderItem]) -> ServiceResult<Money> {
+        let currency = items.first().map(|item| item.price.currency).unwrap_or_default();
+        
+        items.iter().try_fold(Money::zero(currency), |acc, item| {
+            acc.checked_add(&item.price.checked_mul(item.quantity)?)
+        })
+    }
+}
+
+// Clients only choose products and quantities; prices always come from the catalog
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CreateOrderItemDto {
+    pub product_id: Uuid,
+    pub quantity: i32,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct CreateOrderDto {
     pub customer_id: Uuid,
-    pub items: Vec<OrderItem>,
+    pub items: Vec<CreateOrderItemDto>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct UpdateOrderStatusDto {
     pub status: OrderStatus,
-}
\ No newline at end of file
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+impl Validate for CreateOrderDto {
+    fn validate(body: &Value) -> ServiceResult<()> {
+        let mut errors = FieldErrors::new();
+        if let Some(customer_id) = errors.required::<Uuid>(body, "/customer_id") {
+            errors.check(!customer_id.is_nil(), "/customer_id", "must not be the nil UUID");
+        }
+        if let Some(items) = errors.required::<Vec<Value>>(body, "/items") {
+            errors.check(!items.is_empty(), "/items", "must contain at least one item");
+            errors.check(
+                items.len() <= MAX_ORDER_ITEMS,
+                "/items",
+                format!("must contain at most {} items", MAX_ORDER_ITEMS),
+            );
+            for index in 0..items.len() {
+                let pointer = format!("/items/{}/product_id", index);
+                if let Some(product_id) = errors.required::<Uuid>(body, &pointer) {
+                    errors.check(!product_id.is_nil(), pointer, "must not be the nil UUID");
+                }
+                let pointer = format!("/items/{}/quantity", index);
+                if let Some(quantity) = errors.required::<i32>(body, &pointer) {
+                    errors.check(quantity > 0, pointer, "must be greater than 0");
+                }
+            }
+        }
+        
+        errors.into_result()
+    }
+}
+
+impl Validate for UpdateOrderStatusDto {
+    fn validate(body: &Value) -> ServiceResult<()> {
+        let mut errors = FieldErrors::new();
+        errors.required::<OrderStatus>(body, "/status");
+        if let Some(reason) = errors.optional::<String>(body, "/reason") {
+            errors.required_text("/reason", &reason, MAX_REASON_CHARS);
+        }
+        
+        errors.into_result()
+    }
+}
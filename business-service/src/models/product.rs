@@ -1,25 +1,50 @@
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use chrono::{DateTime, Utc};
+use utoipa::ToSchema;
 use uuid::Uuid;
+use crate::models::money::{Currency, Money};
+use crate::errors::ServiceResult;
+use crate::models::stock::StockLevel;
+use crate::models::validation::{FieldErrors, Validate};
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+const MAX_NAME_CHARS: usize = 200;
+const MAX_DESCRIPTION_CHARS: usize = 5000;
+const MAX_SKU_CHARS: usize = 64;
+const MAX_CATEGORY_CHARS: usize = 100;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone)]
 pub struct Product {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
     pub id: Option<Uuid>,
     pub name: String,
     pub description: String,
-    pub price: f64,
+    pub price: Money,
     pub sku: String,
     pub category: String,
+    // Derived from `stock`: true while any units are available
     pub in_stock: bool,
+    #[serde(default)]
+    pub stock: StockLevel,
     #[serde(with = "chrono::serde::ts_seconds")]
+    #[schema(value_type = i64)]
     pub created_at: DateTime<Utc>,
     #[serde(with = "chrono::serde::ts_seconds")]
+    #[schema(value_type = i64)]
     pub updated_at: DateTime<Utc>,
 }
 
 impl Product {
-    pub fn new(name: String, description: String, price: f64, sku: String, category: String) -> Self {
+    // Fields covered by the text index, with the weight of a match in each
+    pub const SEARCH_WEIGHTS: &'static [(&'static str, i32)] = &[
+        ("name", 10),
+        ("sku", 8),
+        ("category", 4),
+        ("description", 1),
+    ];
+    
+    pub fn new(name: String, description: String, price: Money, sku: String, category: String) -> Self {
         let now = Utc::now();
         Self {
             id: Some(Uuid::new_v4()),
@@ -28,28 +53,110 @@ impl Product {
             price,
             sku,
             category,
-            in_stock: true,
+            in_stock: false,
+            stock: StockLevel::default(),
             created_at: now,
             updated_at: now,
         }
     }
+    
+    pub fn set_stock(&mut self, stock: StockLevel) {
+        self.in_stock = stock.available() > 0;
+        self.stock = stock;
+    }
+    
+    pub fn search_field(&self, field: &str) -> Option<&str> {
+        match field {
+            "name" => Some(&self.name),
+            "sku" => Some(&self.sku),
+            "category" => Some(&self.category),
+            "description" => Some(&self.description),
+            _ => None,
+        }
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct CreateProductDto {
     pub name: String,
     pub description: String,
-    pub price: f64,
+    pub price: Money,
     pub sku: String,
     pub category: String,
+    // Units on hand when the product is created
+    #[serde(default)]
+    pub on_hand: i32,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct UpdateProductDto {
     pub name: Option<String>,
     pub description: Option<String>,
-    pub price: Option<f64>,
+    pub price: Option<Money>,
     pub sku: Option<String>,
     pub category: Option<String>,
-    pub in_stock: Option<bool>,
-}
\ No newline at end of file
+}
+
+impl Validate for CreateProductDto {
+    fn validate(body: &Value) -> ServiceResult<()> {
+        let mut errors = FieldErrors::new();
+        if let Some(name) = errors.required::<String>(body, "/name") {
+            errors.required_text("/name", &name, MAX_NAME_CHARS);
+        }
+        if let Some(description) = errors.required::<String>(body, "/description") {
+            errors.max_length("/description", &description, MAX_DESCRIPTION_CHARS);
+        }
+        if errors.required::<Map<String, Value>>(body, "/price").is_some() {
+            check_price(&mut errors, body);
+        }
+        if let Some(sku) = errors.required::<String>(body, "/sku") {
+            errors.required_text("/sku", &sku, MAX_SKU_CHARS);
+        }
+        if let Some(category) = errors.required::<String>(body, "/category") {
+            errors.required_text("/category", &category, MAX_CATEGORY_CHARS);
+        }
+        if let Some(on_hand) = errors.optional::<i32>(body, "/on_hand") {
+            errors.check(on_hand >= 0, "/on_hand", "must not be negative");
+        }
+        
+        errors.into_result()
+    }
+}
+
+// Only the fields being changed are checked
+impl Validate for UpdateProductDto {
+    fn validate(body: &Value) -> ServiceResult<()> {
+        let mut errors = FieldErrors::new();
+        if let Some(name) = errors.optional::<String>(body, "/name") {
+            errors.required_text("/name", &name, MAX_NAME_CHARS);
+        }
+        if let Some(description) = errors.optional::<String>(body, "/description") {
+            errors.max_length("/description", &description, MAX_DESCRIPTION_CHARS);
+        }
+        if errors.optional::<Map<String, Value>>(body, "/price").is_some() {
+            check_price(&mut errors, body);
+        }
+        if let Some(sku) = errors.optional::<String>(body, "/sku") {
+            errors.required_text("/sku", &sku, MAX_SKU_CHARS);
+        }
+        if let Some(category) = errors.optional::<String>(body, "/category") {
+            errors.required_text("/category", &category, MAX_CATEGORY_CHARS);
+        }
+        
+        errors.into_result()
+    }
+}
+
+// The amount and currency of a body's `price` object
+fn check_price(errors: &mut FieldErrors, body: &Value) {
+    let amount = errors.required::<Decimal>(body, "/price/amount");
+    let currency = errors.optional::<Currency>(body, "/price/currency").unwrap_or_default();
+    if let Some(amount) = amount {
+        errors.check(
+            Money::exact(amount, currency).is_ok(),
+            "/price/amount",
+            format!("must have at most {} decimal places for {}", currency.minor_units(), currency),
+        );
+        errors.check(!Money::new(amount, currency).is_negative(), "/price/amount", "must not be negative");
+    }
+}
@@ -0,0 +1,89 @@
+use std::fmt;
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+use crate::errors::ServiceError;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum SagaStatus {
+    #[serde(rename = "running")]
+    Running,
+    #[serde(rename = "completed")]
+    Completed,
+    #[serde(rename = "compensating")]
+    Compensating,
+    #[serde(rename = "compensated")]
+    Compensated,
+}
+
+impl SagaStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SagaStatus::Running => "running",
+            SagaStatus::Completed => "completed",
+            SagaStatus::Compensating => "compensating",
+            SagaStatus::Compensated => "compensated",
+        }
+    }
+    
+    pub fn is_finished(&self) -> bool {
+        matches!(self, SagaStatus::Completed | SagaStatus::Compensated)
+    }
+}
+
+impl FromStr for SagaStatus {
+    type Err = ServiceError;
+    
+    fn from_str(status: &str) -> Result<Self, Self::Err> {
+        match status {
+            "running" => Ok(SagaStatus::Running),
+            "completed" => Ok(SagaStatus::Completed),
+            "compensating" => Ok(SagaStatus::Compensating),
+            "compensated" => Ok(SagaStatus::Compensated),
+            other => Err(ServiceError::ValidationError(format!("Unknown saga status: {}", other))),
+        }
+    }
+}
+
+impl fmt::Display for SagaStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// Persisted progress of a saga. While running, `completed_steps` counts the steps that
+// have executed; while compensating, it counts the steps still to be undone. `owner` is
+// the coordinator driving it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SagaRecord {
+    pub id: Uuid,
+    pub kind: String,
+    pub payload: serde_json::Value,
+    pub status: SagaStatus,
+    pub completed_steps: i32,
+    pub error: Option<String>,
+    #[serde(default)]
+    pub owner: Option<Uuid>,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub updated_at: DateTime<Utc>,
+}
+
+impl SagaRecord {
+    pub fn new(kind: &str, payload: serde_json::Value) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            kind: kind.to_string(),
+            payload,
+            status: SagaStatus::Running,
+            completed_steps: 0,
+            error: None,
+            owner: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
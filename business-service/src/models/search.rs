@@ -0,0 +1,196 @@
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use crate::errors::{ServiceError, ServiceResult};
+use crate::models::product::Product;
+
+const MAX_SEARCH_CHARS: usize = 200;
+
+// A product matching a text search, with its relevance as computed by the store
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ScoredProduct {
+    pub product: Product,
+    pub score: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone)]
+pub struct ProductSearchHit {
+    pub product: Product,
+    // Relevance to the search; higher is better. Only comparable within one search.
+    pub score: f64,
+    // Searched fields containing a term, HTML-escaped, with each matched word in `<mark>`.
+    // Words are matched with an approximation of MongoDB's English stemming, so some words it
+    // matched may be left unmarked; when none of a product's words match that way, words
+    // starting with a searched word's stem are marked instead.
+    pub highlights: BTreeMap<String, String>,
+}
+
+// Search text in MongoDB `$text` syntax: words match any of their forms, `"quoted phrases"`
+// must all appear, and `-word` excludes products containing it. At least one word or phrase
+// must be given.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchTerms {
+    pub text: String,
+    pub words: Vec<String>,
+    pub phrases: Vec<String>,
+    pub excluded: Vec<String>,
+}
+
+impl SearchTerms {
+    pub fn parse(text: &str) -> ServiceResult<Self> {
+        let text = text.trim();
+        if text.chars().count() > MAX_SEARCH_CHARS {
+            return Err(ServiceError::BadRequestError(format!("q must be at most {} characters", MAX_SEARCH_CHARS)));
+        }
+        
+        let mut terms = Self {
+            text: text.to_string(),
+            words: Vec::new(),
+            phrases: Vec::new(),
+            excluded: Vec::new(),
+        };
+        for (i, part) in text.split('"').enumerate() {
+            // Odd parts are between quotes
+            if i % 2 == 1 {
+                let phrase = tokens(part).collect::<Vec<_>>().join(" ").to_lowercase();
+                if !phrase.is_empty() {
+                    terms.words.extend(tokens(&phrase).map(stem));
+                    terms.phrases.push(phrase);
+                }
+                continue;
+            }
+            for word in part.split_whitespace() {
+                match word.strip_prefix('-') {
+                    Some(excluded) => terms.excluded.extend(tokens(excluded).map(stem)),
+                    None => terms.words.extend(tokens(word).map(stem)),
+                }
+            }
+        }
+        terms.words.sort();
+        terms.words.dedup();
+        
+        if terms.words.is_empty() {
+            return Err(ServiceError::BadRequestError("q must contain a word to search for".to_string()));
+        }
+        Ok(terms)
+    }
+    
+    fn matches_word(&self, word: &str) -> bool {
+        let stemmed = stem(word);
+        self.words.contains(&stemmed)
+    }
+    
+    // Relevance of a product, or `None` when it does not match. Each matched word counts the
+    // weight of its field, approximating MongoDB's scoring for stores without a text index.
+    pub fn score(&self, product: &Product) -> Option<f64> {
+        let fields: Vec<(&str, i32)> = Product::SEARCH_WEIGHTS
+            .iter()
+            .filter_map(|(field, weight)| Some((product.search_field(field)?, *weight)))
+            .collect();
+        let normalized: Vec<String> = fields
+            .iter()
+            .map(|(text, _)| format!(" {} ", tokens(text).collect::<Vec<_>>().join(" ").to_lowercase()))
+            .collect();
+        
+        let excluded = fields
+            .iter()
+            .any(|(text, _)| tokens(text).any(|word| self.excluded.contains(&stem(word))));
+        let has_all_phrases = self.phrases
+            .iter()
+            .all(|phrase| normalized.iter().any(|text| text.contains(&format!(" {} ", phrase))));
+        if excluded || !has_all_phrases {
+            return None;
+        }
+        
+        let score: f64 = fields
+            .iter()
+            .map(|(text, weight)| tokens(text).filter(|word| self.matches_word(word)).count() as f64 * f64::from(*weight))
+            .sum();
+        (score > 0.0).then_some(score)
+    }
+    
+    // Searched fields of `product` that contain a term, with the matched words marked. A
+    // product MongoDB found may match none of the approximated stems, in which case words
+    // beginning with a searched stem are marked, so that most hits show why they matched.
+    pub fn highlight(&self, product: &Product) -> BTreeMap<String, String> {
+        let highlights = self.mark(product, |word| self.matches_word(word));
+        if !highlights.is_empty() {
+            return highlights;
+        }
+        
+        self.mark(product, |word| {
+            let word = word.to_lowercase();
+            self.words.iter().any(|stem| word.starts_with(stem.as_str()))
+        })
+    }
+    
+    // Searched fields of `product` with a word `matches`, each such word in `<mark>`
+    fn mark(&self, product: &Product, matches: impl Fn(&str) -> bool) -> BTreeMap<String, String> {
+        Product::SEARCH_WEIGHTS
+            .iter()
+            .filter_map(|(field, _)| {
+                let text = product.search_field(field)?;
+                let mut highlighted = String::with_capacity(text.len());
+                let mut matched = false;
+                let mut rest = text;
+                while let Some(start) = rest.find(char::is_alphanumeric) {
+                    let end = rest[start..].find(|c: char| !c.is_alphanumeric()).map_or(rest.len(), |end| start + end);
+                    let word = &rest[start..end];
+                    highlighted.push_str(&escape_html(&rest[..start]));
+                    if matches(word) {
+                        matched = true;
+                        highlighted.push_str(&format!("<mark>{}</mark>", escape_html(word)));
+                    } else {
+                        highlighted.push_str(&escape_html(word));
+                    }
+                    rest = &rest[end..];
+                }
+                highlighted.push_str(&escape_html(rest));
+                
+                matched.then(|| (field.to_string(), highlighted))
+            })
+            .collect()
+    }
+}
+
+// Words of `text`, split on anything that is not a letter or digit as the text index does
+fn tokens(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !c.is_alphanumeric()).filter(|word| !word.is_empty())
+}
+
+// Reduce a word to a common form so that, for example, `lamps` finds `lamp` and `running`
+// finds `run`. A rough approximation of the stemming MongoDB applies for English.
+fn stem(word: &str) -> String {
+    let word = word.to_lowercase();
+    let stemmed = if let Some(base) = word.strip_suffix("ing").or_else(|| word.strip_suffix("ed")) {
+        undouble(base)
+    } else if let Some(base) = word.strip_suffix("es").filter(|base| ["s", "x", "z", "ch", "sh"].iter().any(|end| base.ends_with(end))) {
+        base
+    } else if let Some(base) = word.strip_suffix('s').filter(|base| !base.ends_with('s')) {
+        base
+    } else {
+        &word
+    };
+    
+    if stemmed.chars().count() >= 3 { stemmed.to_string() } else { word }
+}
+
+// Drop the last of a doubled final consonant, as in `runn` or `stopp`, except for the
+// `l`, `s` and `z` that English keeps doubled
+fn undouble(base: &str) -> &str {
+    let mut chars = base.chars().rev();
+    match (chars.next(), chars.next()) {
+        (Some(last), Some(before)) if last == before && !"aeiouylsz".contains(last) && last.is_alphabetic() => {
+            &base[..base.len() - last.len_utf8()]
+        }
+        _ => base,
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use crate::errors::ServiceResult;
+use crate::models::validation::{FieldErrors, Validate};
+
+// Stock held for a product. `reserved` units belong to placed orders that have not
+// shipped yet; only the remainder can be sold.
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, PartialEq, Eq, Default)]
+pub struct StockLevel {
+    pub on_hand: i32,
+    pub reserved: i32,
+}
+
+impl StockLevel {
+    pub fn new(on_hand: i32, reserved: i32) -> Self {
+        Self { on_hand, reserved }
+    }
+    
+    pub fn available(&self) -> i32 {
+        self.on_hand - self.reserved
+    }
+    
+    // Apply a change, or return None if it would leave the level inconsistent
+    // (negative reservations, or more reserved than on hand)
+    pub fn apply(&self, change: StockChange) -> Option<StockLevel> {
+        let next = StockLevel {
+            on_hand: self.on_hand.checked_add(change.on_hand)?,
+            reserved: self.reserved.checked_add(change.reserved)?,
+        };
+        
+        if next.reserved < 0 || next.reserved > next.on_hand {
+            return None;
+        }
+        
+        Some(next)
+    }
+}
+
+// A relative change to a stock level
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StockChange {
+    pub on_hand: i32,
+    pub reserved: i32,
+}
+
+impl StockChange {
+    // Hold units for a placed order
+    pub fn reserve(quantity: i32) -> Self {
+        Self { on_hand: 0, reserved: quantity }
+    }
+    
+    // Return held units when an order is cancelled
+    pub fn release(quantity: i32) -> Self {
+        Self { on_hand: 0, reserved: -quantity }
+    }
+    
+    // Remove held units from stock when an order ships
+    pub fn commit(quantity: i32) -> Self {
+        Self { on_hand: -quantity, reserved: -quantity }
+    }
+    
+    // Receive (positive) or write off (negative) units
+    pub fn adjust(delta: i32) -> Self {
+        Self { on_hand: delta, reserved: 0 }
+    }
+    
+    pub fn inverse(&self) -> Self {
+        Self { on_hand: -self.on_hand, reserved: -self.reserved }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ProductStock {
+    pub product_id: Uuid,
+    pub on_hand: i32,
+    pub reserved: i32,
+    pub available: i32,
+}
+
+impl ProductStock {
+    pub fn new(product_id: Uuid, level: &StockLevel) -> Self {
+        Self {
+            product_id,
+            on_hand: level.on_hand,
+            reserved: level.reserved,
+            available: level.available(),
+        }
+    }
+}
+
+// Either a relative `delta` or an absolute `on_hand` count, but not both
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AdjustStockDto {
+    pub delta: Option<i32>,
+    pub on_hand: Option<i32>,
+}
+
+impl Validate for AdjustStockDto {
+    fn validate(body: &Value) -> ServiceResult<()> {
+        let mut errors = FieldErrors::new();
+        let delta = errors.optional::<i32>(body, "/delta");
+        let on_hand = errors.optional::<i32>(body, "/on_hand");
+        if let Some(on_hand) = on_hand {
+            errors.check(on_hand >= 0, "/on_hand", "must not be negative");
+        }
+        
+        let given = |pointer| body.pointer(pointer).is_some_and(|value| !value.is_null());
+        match (given("/delta"), given("/on_hand")) {
+            (false, false) => {
+                errors.add("/delta", "is required unless /on_hand is given");
+                errors.add("/on_hand", "is required unless /delta is given");
+            }
+            (true, true) if delta.is_some() && on_hand.is_some() => {
+                errors.add("/on_hand", "must not be given together with /delta");
+            }
+            _ => {}
+        }
+        
+        errors.into_result()
+    }
+}
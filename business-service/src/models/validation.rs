@@ -0,0 +1,81 @@
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use crate::errors::{FieldError, ServiceError, ServiceResult};
+
+// Request bodies that check their own fields before reaching a service. The checks run on the
+// JSON as sent, so a missing or wrongly typed field is reported along with every other problem.
+pub trait Validate {
+    fn validate(body: &Value) -> ServiceResult<()>;
+}
+
+// Collects every field error of a request so they can be reported together
+#[derive(Debug, Default)]
+pub struct FieldErrors(Vec<FieldError>);
+
+impl FieldErrors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    pub fn add(&mut self, pointer: impl Into<String>, detail: impl Into<String>) {
+        self.0.push(FieldError {
+            pointer: pointer.into(),
+            detail: detail.into(),
+        });
+    }
+    
+    pub fn check(&mut self, valid: bool, pointer: impl Into<String>, detail: impl Into<String>) {
+        if !valid {
+            self.add(pointer, detail);
+        }
+    }
+    
+    // The value at `pointer` in `body`, or `None` when it is absent or null. A value that is not
+    // a `T` is recorded as an error.
+    pub fn optional<T: DeserializeOwned>(&mut self, body: &Value, pointer: &str) -> Option<T> {
+        let value = body.pointer(pointer).filter(|value| !value.is_null())?;
+        match T::deserialize(value) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                self.add(pointer, e.to_string());
+                None
+            }
+        }
+    }
+    
+    // Like `optional`, but an absent or null value is an error too
+    pub fn required<T: DeserializeOwned>(&mut self, body: &Value, pointer: &str) -> Option<T> {
+        if body.pointer(pointer).is_none_or(Value::is_null) {
+            self.add(pointer, "is required");
+            return None;
+        }
+        
+        self.optional(body, pointer)
+    }
+    
+    // Text that must contain something other than whitespace, up to `max_chars` characters
+    pub fn required_text(&mut self, pointer: &str, value: &str, max_chars: usize) {
+        if value.trim().is_empty() {
+            self.add(pointer, "must not be blank");
+        } else {
+            self.max_length(pointer, value, max_chars);
+        }
+    }
+    
+    pub fn max_length(&mut self, pointer: &str, value: &str, max_chars: usize) {
+        let length = value.chars().count();
+        self.check(
+            length <= max_chars,
+            pointer,
+            format!("must be at most {} characters, got {}", max_chars, length),
+        );
+    }
+    
+    pub fn into_result(self) -> ServiceResult<()> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(ServiceError::FieldValidationError(self.0))
+        }
+    }
+}
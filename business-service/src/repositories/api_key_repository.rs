@@ -0,0 +1,164 @@
+use async_trait::async_trait;
+use std::str::FromStr;
+use sqlx::postgres::PgRow;
+use sqlx::Row;
+use chrono::{DateTime, Utc};
+use tracing::Instrument;
+use uuid::Uuid;
+use crate::errors::{ServiceError, ServiceResult};
+use crate::models::api_key::{ApiKey, ApiScope};
+use crate::repositories::PostgresClient;
+use crate::telemetry::sql_span;
+
+#[async_trait]
+pub trait ApiKeyStore: Send + Sync {
+    async fn create(&self, api_key: &ApiKey) -> ServiceResult<()>;
+    async fn find_by_id(&self, id: Uuid) -> ServiceResult<Option<ApiKey>>;
+    async fn find_all(&self) -> ServiceResult<Vec<ApiKey>>;
+    // Replace the hash of a key that has not been revoked
+    async fn rotate(&self, id: Uuid, key_hash: &str, rotated_at: DateTime<Utc>) -> ServiceResult<Option<ApiKey>>;
+    async fn revoke(&self, id: Uuid, revoked_at: DateTime<Utc>) -> ServiceResult<Option<ApiKey>>;
+    async fn touch(&self, id: Uuid, used_at: DateTime<Utc>) -> ServiceResult<()>;
+}
+
+pub struct ApiKeyRepository {
+    pg_client: PostgresClient,
+}
+
+impl ApiKeyRepository {
+    const API_KEY_COLUMNS: &'static str = "id, name, scopes, key_hash, created_at, rotated_at, last_used_at, revoked_at";
+
+    pub fn new(pg_client: PostgresClient) -> Self {
+        Self { pg_client }
+    }
+
+    fn map_api_key(row: &PgRow) -> ServiceResult<ApiKey> {
+        let scopes: Vec<String> = row.try_get("scopes")
+            .map_err(ServiceError::from)?;
+
+        Ok(ApiKey {
+            id: row.try_get("id")
+                .map_err(ServiceError::from)?,
+            name: row.try_get("name")
+                .map_err(ServiceError::from)?,
+            scopes: scopes
+                .iter()
+                .map(|scope| ApiScope::from_str(scope).map_err(|e| ServiceError::DataError(e.to_string())))
+                .collect::<ServiceResult<Vec<_>>>()?,
+            key_hash: row.try_get("key_hash")
+                .map_err(ServiceError::from)?,
+            created_at: row.try_get("created_at")
+                .map_err(ServiceError::from)?,
+            rotated_at: row.try_get("rotated_at")
+                .map_err(ServiceError::from)?,
+            last_used_at: row.try_get("last_used_at")
+                .map_err(ServiceError::from)?,
+            revoked_at: row.try_get("revoked_at")
+                .map_err(ServiceError::from)?,
+        })
+    }
+}
+
+#[async_trait]
+impl ApiKeyStore for ApiKeyRepository {
+    async fn create(&self, api_key: &ApiKey) -> ServiceResult<()> {
+        let scopes: Vec<&str> = api_key.scopes.iter().map(ApiScope::as_str).collect();
+
+        let statement = r#"
+            INSERT INTO api_keys (id, name, scopes, key_hash, created_at)
+            VALUES ($1, $2, $3, $4, $5)
+            "#;
+        sqlx::query(statement)
+            .bind(api_key.id)
+            .bind(&api_key.name)
+            .bind(&scopes)
+            .bind(&api_key.key_hash)
+            .bind(api_key.created_at)
+            .execute(&self.pg_client.pool)
+            .instrument(sql_span(statement))
+            .await
+            .map_err(ServiceError::from)?;
+
+        Ok(())
+    }
+
+    async fn find_by_id(&self, id: Uuid) -> ServiceResult<Option<ApiKey>> {
+        let query = format!("SELECT {} FROM api_keys WHERE id = $1", Self::API_KEY_COLUMNS);
+        let row = sqlx::query(&query)
+            .bind(id)
+            .fetch_optional(&self.pg_client.pool)
+            .instrument(sql_span(&query))
+            .await
+            .map_err(ServiceError::from)?;
+
+        row.as_ref().map(Self::map_api_key).transpose()
+    }
+
+    async fn find_all(&self) -> ServiceResult<Vec<ApiKey>> {
+        let query = format!("SELECT {} FROM api_keys ORDER BY created_at ASC", Self::API_KEY_COLUMNS);
+        let rows = sqlx::query(&query)
+            .fetch_all(&self.pg_client.pool)
+            .instrument(sql_span(&query))
+            .await
+            .map_err(ServiceError::from)?;
+
+        rows.iter().map(Self::map_api_key).collect()
+    }
+
+    async fn rotate(&self, id: Uuid, key_hash: &str, rotated_at: DateTime<Utc>) -> ServiceResult<Option<ApiKey>> {
+        let query = format!(
+            r#"
+            UPDATE api_keys
+            SET key_hash = $2, rotated_at = $3
+            WHERE id = $1 AND revoked_at IS NULL
+            RETURNING {}
+            "#,
+            Self::API_KEY_COLUMNS
+        );
+        let row = sqlx::query(&query)
+            .bind(id)
+            .bind(key_hash)
+            .bind(rotated_at)
+            .fetch_optional(&self.pg_client.pool)
+            .instrument(sql_span(&query))
+            .await
+            .map_err(ServiceError::from)?;
+
+        row.as_ref().map(Self::map_api_key).transpose()
+    }
+
+    async fn revoke(&self, id: Uuid, revoked_at: DateTime<Utc>) -> ServiceResult<Option<ApiKey>> {
+        // Revoking twice keeps the original timestamp
+        let query = format!(
+            r#"
+            UPDATE api_keys
+            SET revoked_at = COALESCE(revoked_at, $2)
+            WHERE id = $1
+            RETURNING {}
+            "#,
+            Self::API_KEY_COLUMNS
+        );
+        let row = sqlx::query(&query)
+            .bind(id)
+            .bind(revoked_at)
+            .fetch_optional(&self.pg_client.pool)
+            .instrument(sql_span(&query))
+            .await
+            .map_err(ServiceError::from)?;
+
+        row.as_ref().map(Self::map_api_key).transpose()
+    }
+
+    async fn touch(&self, id: Uuid, used_at: DateTime<Utc>) -> ServiceResult<()> {
+        let statement = "UPDATE api_keys SET last_used_at = $2 WHERE id = $1";
+        sqlx::query(statement)
+            .bind(id)
+            .bind(used_at)
+            .execute(&self.pg_client.pool)
+            .instrument(sql_span(statement))
+            .await
+            .map_err(ServiceError::from)?;
+
+        Ok(())
+    }
+}
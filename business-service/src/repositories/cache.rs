@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use async_trait::async_trait;
+use lru::LruCache;
+use crate::errors::{ServiceError, ServiceResult};
+use crate::repositories::RedisClient;
+
+// A key-value store for cached reads. Values expire after their TTL; counters never expire,
+// so they can version groups of values that are invalidated together.
+#[async_trait]
+pub trait CacheStore: Send + Sync {
+    async fn get(&self, key: &str) -> ServiceResult<Option<String>>;
+    async fn set(&self, key: &str, value: &str, ttl: Duration) -> ServiceResult<()>;
+    async fn delete(&self, key: &str) -> ServiceResult<()>;
+    // The current value of a counter, 0 if it was never incremented
+    async fn counter(&self, key: &str) -> ServiceResult<i64>;
+    async fn increment(&self, key: &str) -> ServiceResult<i64>;
+}
+
+#[async_trait]
+impl CacheStore for RedisClient {
+    async fn get(&self, key: &str) -> ServiceResult<Option<String>> {
+        redis::cmd("GET")
+            .arg(key)
+            .query_async(&mut self.connection().await?)
+            .await
+            .map_err(ServiceError::from)
+    }
+
+    async fn set(&self, key: &str, value: &str, ttl: Duration) -> ServiceResult<()> {
+        redis::cmd("SET")
+            .arg(key)
+            .arg(value)
+            .arg("PX")
+            .arg(ttl.as_millis().max(1) as u64)
+            .query_async::<()>(&mut self.connection().await?)
+            .await
+            .map_err(ServiceError::from)
+    }
+
+    async fn delete(&self, key: &str) -> ServiceResult<()> {
+        redis::cmd("DEL")
+            .arg(key)
+            .query_async::<()>(&mut self.connection().await?)
+            .await
+            .map_err(ServiceError::from)
+    }
+
+    async fn counter(&self, key: &str) -> ServiceResult<i64> {
+        redis::cmd("GET")
+            .arg(key)
+            .query_async::<Option<i64>>(&mut self.connection().await?)
+            .await
+            .map(Option::unwrap_or_default)
+            .map_err(ServiceError::from)
+    }
+
+    async fn increment(&self, key: &str) -> ServiceResult<i64> {
+        redis::cmd("INCR")
+            .arg(key)
+            .query_async(&mut self.connection().await?)
+            .await
+            .map_err(ServiceError::from)
+    }
+}
+
+// A process-local cache holding at most `capacity` values, evicting the least recently used.
+// Counters are kept apart from the values, so eviction cannot reset them.
+pub struct LocalCache {
+    values: Mutex<LruCache<String, (String, Instant)>>,
+    counters: Mutex<HashMap<String, i64>>,
+}
+
+impl LocalCache {
+    pub fn new(capacity: NonZeroUsize) -> Self {
+        Self {
+            values: Mutex::new(LruCache::new(capacity)),
+            counters: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+#[async_trait]
+impl CacheStore for LocalCache {
+    async fn get(&self, key: &str) -> ServiceResult<Option<String>> {
+        let mut values = self.values.lock().unwrap();
+        match values.get(key) {
+            Some((value, expires_at)) if *expires_at > Instant::now() => Ok(Some(value.clone())),
+            Some(_) => {
+                values.pop(key);
+                Ok(None)
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn set(&self, key: &str, value: &str, ttl: Duration) -> ServiceResult<()> {
+        self.values.lock().unwrap().put(key.to_string(), (value.to_string(), Instant::now() + ttl));
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> ServiceResult<()> {
+        self.values.lock().unwrap().pop(key);
+        Ok(())
+    }
+
+    async fn counter(&self, key: &str) -> ServiceResult<i64> {
+        Ok(self.counters.lock().unwrap().get(key).copied().unwrap_or_default())
+    }
+
+    async fn increment(&self, key: &str) -> ServiceResult<i64> {
+        let mut counters = self.counters.lock().unwrap();
+        let counter = counters.entry(key.to_string()).or_default();
+        *counter += 1;
+        Ok(*counter)
+    }
+}
@@ -0,0 +1,324 @@
+use std::collections::HashMap;
+use std::fmt::{Debug, Display};
+use std::future::Future;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+use crate::errors::ServiceResult;
+use crate::metrics::metrics;
+use crate::models::facet::ProductFacets;
+use crate::models::search::{ScoredProduct, SearchTerms};
+use crate::models::stock::{StockChange, StockLevel};
+use crate::repositories::{
+    CacheStore, Filter, Page, ProductSearch, QuerySpec, Repository, StockRepository, TransactionalRepository, UnitOfWork,
+};
+
+// Wraps a repository so `find_by_id`, `find_all`, product `search` and `facets` read through
+// a cache. Writes made through the wrapper drop the item they change and every cached listing,
+// search and facet count, whether or not they succeed; writes in a unit of work do so once it
+// commits. Each item, and the listings as a whole, are versioned by a counter that writes
+// increment, so listings are invalidated without knowing which ones contain the item, and a
+// value loaded while a write was made is never served. Other writers, such as another
+// instance with its own local cache, are only seen once the cached values expire.
+pub struct Cached<R> {
+    inner: R,
+    cache: Arc<dyn CacheStore>,
+    name: &'static str,
+    ttl: Duration,
+    // Loads from the repository in progress. Callers missing the same key wait for the
+    // first one's load instead of all hitting the repository at once.
+    loads: Loads,
+}
+
+type Loads = Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>;
+
+// A caller's place in the load of one key. Dropping it removes the key's entry from `loads`
+// if it is still the one the caller joined, so the entry goes away even when the caller is
+// dropped mid-load or the load panics.
+struct LoadEntry<'a> {
+    loads: &'a Loads,
+    key: &'a str,
+    in_progress: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl<'a> LoadEntry<'a> {
+    fn join(loads: &'a Loads, key: &'a str) -> Self {
+        let in_progress = loads.lock().unwrap().entry(key.to_string()).or_default().clone();
+        Self { loads, key, in_progress }
+    }
+}
+
+impl Drop for LoadEntry<'_> {
+    fn drop(&mut self) {
+        let mut loads = self.loads.lock().unwrap_or_else(PoisonError::into_inner);
+        if loads.get(self.key).is_some_and(|load| Arc::ptr_eq(load, &self.in_progress)) {
+            loads.remove(self.key);
+        }
+    }
+}
+
+// Where a value is cached, and the counter and version it was cached under
+struct CacheKey {
+    key: String,
+    version_key: String,
+    version: i64,
+}
+
+impl<R> Cached<R> {
+    pub fn new(inner: R, cache: Arc<dyn CacheStore>, name: &'static str, ttl: Duration) -> Self {
+        Self {
+            inner,
+            cache,
+            name,
+            ttl,
+            loads: Mutex::new(HashMap::new()),
+        }
+    }
+
+    async fn item_key(&self, id: &str) -> ServiceResult<CacheKey> {
+        let version_key = item_version_key(self.name, id);
+        let version = self.cache.counter(&version_key).await?;
+        Ok(CacheKey {
+            key: format!("{}:{}", version_key, version),
+            version_key,
+            version,
+        })
+    }
+
+    // Listings and searches, keyed by what was asked for
+    async fn list_key(&self, query: &impl Debug) -> ServiceResult<CacheKey> {
+        let version_key = lists_key(self.name);
+        let version = self.cache.counter(&version_key).await?;
+        let digest = Sha256::digest(format!("{:?}", query).as_bytes());
+        Ok(CacheKey {
+            key: format!("business-cache:{}:list:{}:{:x}", self.name, version, digest),
+            version_key,
+            version,
+        })
+    }
+
+    async fn lookup<V: DeserializeOwned>(&self, key: &str) -> Option<V> {
+        match self.cache.get(key).await {
+            Ok(Some(cached)) => match serde_json::from_str(&cached) {
+                Ok(value) => Some(value),
+                Err(e) => {
+                    tracing::warn!("Discarding unreadable cache entry {}: {}", key, e);
+                    None
+                }
+            },
+            Ok(None) => None,
+            Err(e) => {
+                tracing::warn!("Cache lookup of {} failed: {}", key, e);
+                None
+            }
+        }
+    }
+
+    async fn store<V: Serialize>(&self, key: &str, value: &V) {
+        let result = match serde_json::to_string(value) {
+            Ok(serialized) => self.cache.set(key, &serialized, self.ttl).await,
+            Err(e) => {
+                tracing::warn!("Cannot serialize cache entry {}: {}", key, e);
+                return;
+            }
+        };
+        if let Err(e) = result {
+            tracing::warn!("Failed to cache {}: {}", key, e);
+        }
+    }
+
+    // Serve `key` from the cache, or load it from the repository and cache the result, unless
+    // a write moved the version on meanwhile and the result may be stale. A cache that is down
+    // only costs the lookup; the repository still answers.
+    async fn read_through<V>(
+        &self,
+        method: &str,
+        key: ServiceResult<CacheKey>,
+        load: impl Future<Output = ServiceResult<V>>,
+    ) -> ServiceResult<V>
+    where
+        V: Serialize + DeserializeOwned,
+    {
+        let key = match key {
+            Ok(key) => key,
+            Err(e) => {
+                tracing::warn!("Cache lookup for {} {} failed: {}", self.name, method, e);
+                return load.await;
+            }
+        };
+        if let Some(value) = self.lookup(&key.key).await {
+            metrics().record_cache_lookup(self.name, method, true);
+            return Ok(value);
+        }
+
+        let entry = LoadEntry::join(&self.loads, &key.key);
+        let _loading = entry.in_progress.lock().await;
+        if let Some(value) = self.lookup(&key.key).await {
+            metrics().record_cache_lookup(self.name, method, true);
+            return Ok(value);
+        }
+        metrics().record_cache_lookup(self.name, method, false);
+
+        let result = load.await;
+        if let Ok(value) = &result {
+            match self.cache.counter(&key.version_key).await {
+                Ok(version) if version == key.version => self.store(&key.key, value).await,
+                Ok(_) => {}
+                Err(e) => tracing::warn!("Not caching {}, its version is unknown: {}", key.key, e),
+            }
+        }
+
+        result
+    }
+
+    async fn invalidate(&self, item_id: Option<String>) {
+        invalidate(self.cache.as_ref(), self.name, item_id).await
+    }
+
+    // Invalidate once `uow` commits; until then other readers still see, and may cache, the old values
+    fn invalidate_after(&self, uow: &mut UnitOfWork, item_id: Option<String>) {
+        let cache = self.cache.clone();
+        let name = self.name;
+        uow.after_commit(async move { invalidate(cache.as_ref(), name, item_id).await });
+    }
+}
+
+fn item_version_key(name: &str, id: &str) -> String {
+    format!("business-cache:{}:item:{}", name, id)
+}
+
+fn lists_key(name: &str) -> String {
+    format!("business-cache:{}:lists", name)
+}
+
+// Drop a changed item, if any, and all listings
+async fn invalidate(cache: &dyn CacheStore, name: &str, item_id: Option<String>) {
+    if let Some(id) = item_id {
+        let version_key = item_version_key(name, &id);
+        match cache.increment(&version_key).await {
+            // The old version can no longer be read; free its space early
+            Ok(version) => {
+                if let Err(e) = cache.delete(&format!("{}:{}", version_key, version - 1)).await {
+                    tracing::warn!("Failed to drop cached {}: {}", version_key, e);
+                }
+            }
+            Err(e) => tracing::error!("Failed to invalidate cached {}: {}", version_key, e),
+        }
+    }
+    if let Err(e) = cache.increment(&lists_key(name)).await {
+        tracing::error!("Failed to invalidate cached {} listings: {}", name, e);
+    }
+}
+
+#[async_trait]
+impl<T, ID, R> Repository<T, ID> for Cached<R>
+where
+    T: Serialize + DeserializeOwned + Send + Sync + 'static,
+    ID: Display + Send + 'static,
+    R: Repository<T, ID>,
+{
+    async fn find_by_id(&self, id: ID) -> ServiceResult<Option<T>> {
+        let key = self.item_key(&id.to_string()).await;
+        self.read_through("find_by_id", key, self.inner.find_by_id(id)).await
+    }
+
+    async fn find_all(&self, query: &QuerySpec) -> ServiceResult<Page<T>> {
+        let key = self.list_key(query).await;
+        self.read_through("find_all", key, self.inner.find_all(query)).await
+    }
+
+    async fn create(&self, item: T) -> ServiceResult<T> {
+        let result = self.inner.create(item).await;
+        self.invalidate(None).await;
+        result
+    }
+
+    async fn update(&self, id: ID, item: T) -> ServiceResult<T> {
+        let key = id.to_string();
+        let result = self.inner.update(id, item).await;
+        self.invalidate(Some(key)).await;
+        result
+    }
+
+    async fn delete(&self, id: ID) -> ServiceResult<()> {
+        let key = id.to_string();
+        let result = self.inner.delete(id).await;
+        self.invalidate(Some(key)).await;
+        result
+    }
+}
+
+#[async_trait]
+impl<T, ID, R> TransactionalRepository<T, ID> for Cached<R>
+where
+    T: Serialize + DeserializeOwned + Send + Sync + 'static,
+    ID: Display + Send + 'static,
+    R: TransactionalRepository<T, ID>,
+{
+    // Reads in a unit of work are what a change is based on, so they skip the cache
+    async fn find_by_id_in(&self, uow: &mut UnitOfWork, id: ID) -> ServiceResult<Option<T>> {
+        self.inner.find_by_id_in(uow, id).await
+    }
+
+    async fn create_in(&self, uow: &mut UnitOfWork, item: T) -> ServiceResult<T> {
+        let result = self.inner.create_in(uow, item).await;
+        self.invalidate_after(uow, None);
+        result
+    }
+
+    async fn update_in(&self, uow: &mut UnitOfWork, id: ID, item: T) -> ServiceResult<T> {
+        let key = id.to_string();
+        let result = self.inner.update_in(uow, id, item).await;
+        self.invalidate_after(uow, Some(key));
+        result
+    }
+
+    async fn delete_in(&self, uow: &mut UnitOfWork, id: ID) -> ServiceResult<()> {
+        let key = id.to_string();
+        let result = self.inner.delete_in(uow, id).await;
+        self.invalidate_after(uow, Some(key));
+        result
+    }
+}
+
+// Search results and facet counts are cached and dropped like listings
+#[async_trait]
+impl<R: ProductSearch> ProductSearch for Cached<R> {
+    async fn search(&self, terms: &SearchTerms, query: &QuerySpec) -> ServiceResult<Page<ScoredProduct>> {
+        let key = self.list_key(&(&terms.text, query)).await;
+        self.read_through("search", key, self.inner.search(terms, query)).await
+    }
+
+    async fn facets(&self, terms: Option<&SearchTerms>, filters: &[Filter]) -> ServiceResult<ProductFacets> {
+        let key = self.list_key(&("facets", terms.map(|terms| &terms.text), filters)).await;
+        self.read_through("facets", key, self.inner.facets(terms, filters)).await
+    }
+}
+
+// Stock levels are part of the cached products, so stock changes invalidate them too
+#[async_trait]
+impl<R: StockRepository> StockRepository for Cached<R> {
+    async fn find_stock(&self, product_id: Uuid) -> ServiceResult<Option<StockLevel>> {
+        self.inner.find_stock(product_id).await
+    }
+
+    async fn change_stock(&self, product_id: Uuid, change: StockChange, operation: Uuid) -> ServiceResult<StockLevel> {
+        let result = self.inner.change_stock(product_id, change, operation).await;
+        self.invalidate(Some(product_id.to_string())).await;
+        result
+    }
+
+    async fn set_on_hand(&self, product_id: Uuid, on_hand: i32, operation: Uuid) -> ServiceResult<StockLevel> {
+        let result = self.inner.set_on_hand(product_id, on_hand, operation).await;
+        self.invalidate(Some(product_id.to_string())).await;
+        result
+    }
+
+    async fn has_applied(&self, product_id: Uuid, operation: Uuid) -> ServiceResult<bool> {
+        self.inner.has_applied(product_id, operation).await
+    }
+}
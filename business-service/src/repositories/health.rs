@@ -0,0 +1,8 @@
+use async_trait::async_trait;
+use crate::errors::ServiceResult;
+
+// A store that can report whether it is reachable, for the readiness probe
+#[async_trait]
+pub trait HealthCheck: Send + Sync {
+    async fn health_check(&self) -> ServiceResult<()>;
+}
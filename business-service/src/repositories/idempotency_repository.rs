@@ -0,0 +1,184 @@
+use async_trait::async_trait;
+use sqlx::Row;
+use chrono::{Duration, Utc};
+use tracing::Instrument;
+use uuid::Uuid;
+use crate::errors::{ServiceError, ServiceResult};
+use crate::models::idempotency::{IdempotencyClaim, IdempotencyRecord, StoredResponse};
+use crate::repositories::PostgresClient;
+use crate::telemetry::sql_span;
+
+// Responses stored per idempotency key. Keys expire after the store's TTL, after which
+// they can be claimed again. A claim without a response lapses after the store's lease, so
+// a key whose request died is not stuck until it expires. Completing or releasing a key
+// only takes effect while the caller's claim token still holds it.
+#[async_trait]
+pub trait IdempotencyStore: Send + Sync {
+    // Claim a key for a request with the given fingerprint. The key now belongs to the
+    // caller if it was free, expired or its claim had lapsed; otherwise the existing record
+    // is returned.
+    async fn claim(&self, key: &str, fingerprint: &str) -> ServiceResult<IdempotencyClaim>;
+    async fn complete(&self, key: &str, token: Uuid, response: &StoredResponse) -> ServiceResult<()>;
+    // Give up a claimed key without storing a response, so the request can be retried
+    async fn release(&self, key: &str, token: Uuid) -> ServiceResult<()>;
+    async fn purge_expired(&self) -> ServiceResult<u64>;
+}
+
+pub struct IdempotencyRepository {
+    pg_client: PostgresClient,
+    ttl: Duration,
+    lease: Duration,
+}
+
+impl IdempotencyRepository {
+    pub fn new(pg_client: PostgresClient, ttl_secs: u64, lease_secs: u64) -> Self {
+        Self {
+            pg_client,
+            ttl: Duration::seconds(ttl_secs as i64),
+            lease: Duration::seconds(lease_secs as i64),
+        }
+    }
+}
+
+impl IdempotencyRepository {
+    // Claim `key` under `token` if it is free, expired or its claim has lapsed
+    async fn try_claim(&self, key: &str, fingerprint: &str, token: Uuid) -> ServiceResult<bool> {
+        let now = Utc::now();
+
+        // An expired key or a lapsed claim is free again; replace it in the same statement
+        // that claims it
+        let statement = r#"
+            INSERT INTO idempotency_keys (key, fingerprint, created_at, expires_at, locked_until, claim_token)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (key) DO UPDATE
+            SET fingerprint = EXCLUDED.fingerprint,
+                status_code = NULL,
+                content_type = NULL,
+                body = NULL,
+                created_at = EXCLUDED.created_at,
+                expires_at = EXCLUDED.expires_at,
+                locked_until = EXCLUDED.locked_until,
+                claim_token = EXCLUDED.claim_token
+            WHERE idempotency_keys.expires_at <= EXCLUDED.created_at
+            OR (idempotency_keys.status_code IS NULL AND idempotency_keys.locked_until <= EXCLUDED.created_at)
+            "#;
+        let claimed = sqlx::query(statement)
+            .bind(key)
+            .bind(fingerprint)
+            .bind(now)
+            .bind(now + self.ttl)
+            .bind(now + self.lease)
+            .bind(token)
+            .execute(&self.pg_client.pool)
+            .instrument(sql_span(statement))
+            .await
+            .map_err(ServiceError::from)?;
+
+        Ok(claimed.rows_affected() == 1)
+    }
+
+    async fn find_record(&self, key: &str) -> ServiceResult<Option<IdempotencyRecord>> {
+        let statement = r#"
+            SELECT fingerprint, status_code, content_type, body
+            FROM idempotency_keys
+            WHERE key = $1
+            "#;
+        let row = sqlx::query(statement)
+            .bind(key)
+            .fetch_optional(&self.pg_client.pool)
+            .instrument(sql_span(statement))
+            .await
+            .map_err(ServiceError::from)?;
+        let Some(row) = row else {
+            return Ok(None);
+        };
+
+        let fingerprint: String = row.try_get("fingerprint")
+            .map_err(ServiceError::from)?;
+        let status: Option<i16> = row.try_get("status_code")
+            .map_err(ServiceError::from)?;
+        let content_type: Option<String> = row.try_get("content_type")
+            .map_err(ServiceError::from)?;
+        let body: Option<Vec<u8>> = row.try_get("body")
+            .map_err(ServiceError::from)?;
+
+        Ok(Some(IdempotencyRecord {
+            fingerprint,
+            response: status.map(|status| StoredResponse {
+                status: status as u16,
+                content_type,
+                body: body.unwrap_or_default(),
+            }),
+        }))
+    }
+}
+
+#[async_trait]
+impl IdempotencyStore for IdempotencyRepository {
+    // The key may be released between failing to claim it and reading it back. It is free
+    // then, so the claim is tried again.
+    async fn claim(&self, key: &str, fingerprint: &str) -> ServiceResult<IdempotencyClaim> {
+        let token = Uuid::new_v4();
+        loop {
+            if self.try_claim(key, fingerprint, token).await? {
+                return Ok(IdempotencyClaim::Claimed(token));
+            }
+            if let Some(record) = self.find_record(key).await? {
+                return Ok(IdempotencyClaim::Taken(record));
+            }
+        }
+    }
+
+    async fn complete(&self, key: &str, token: Uuid, response: &StoredResponse) -> ServiceResult<()> {
+        let statement = r#"
+            UPDATE idempotency_keys
+            SET status_code = $1, content_type = $2, body = $3
+            WHERE key = $4 AND claim_token = $5 AND status_code IS NULL
+            "#;
+        let completed = sqlx::query(statement)
+            .bind(response.status as i16)
+            .bind(&response.content_type)
+            .bind(&response.body)
+            .bind(key)
+            .bind(token)
+            .execute(&self.pg_client.pool)
+            .instrument(sql_span(statement))
+            .await
+            .map_err(ServiceError::from)?;
+
+        if completed.rows_affected() == 0 {
+            tracing::warn!("Not storing response for idempotency key {}: its claim was taken over", key);
+        }
+
+        Ok(())
+    }
+
+    async fn release(&self, key: &str, token: Uuid) -> ServiceResult<()> {
+        let statement = "DELETE FROM idempotency_keys WHERE key = $1 AND claim_token = $2 AND status_code IS NULL";
+        let released = sqlx::query(statement)
+            .bind(key)
+            .bind(token)
+            .execute(&self.pg_client.pool)
+            .instrument(sql_span(statement))
+            .await
+            .map_err(ServiceError::from)?;
+
+        if released.rows_affected() == 0 {
+            tracing::warn!("Not releasing idempotency key {}: its claim was taken over", key);
+        }
+
+        Ok(())
+    }
+
+    async fn purge_expired(&self) -> ServiceResult<u64> {
+        let statement = "DELETE FROM idempotency_keys WHERE expires_at <= $1";
+        let result = sqlx::query(statement)
+            .bind(Utc::now())
+            .execute(&self.pg_client.pool)
+            .instrument(sql_span(statement))
+            .await
+            .map_err(ServiceError::from)?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -0,0 +1,763 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+use std::sync::{Arc, RwLock};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use uuid::Uuid;
+use crate::errors::{ServiceError, ServiceResult};
+use crate::models::api_key::ApiKey;
+use crate::models::event::{DomainEvent, OutboxEntry};
+use crate::models::facet::ProductFacets;
+use crate::models::order::{Order, OrderStatusChange};
+use crate::models::idempotency::{IdempotencyClaim, IdempotencyRecord, StoredResponse};
+use crate::models::product::Product;
+use crate::models::saga::SagaRecord;
+use crate::models::search::{ScoredProduct, SearchTerms};
+use crate::models::stock::{StockChange, StockLevel};
+use crate::repositories::{
+    ApiKeyStore, Filter, FilterOp, FilterValue, IdempotencyStore, OrderHistoryStore, OutboxStore, Page, ProductSearch, QuerySpec, Queryable,
+    Repository, SagaStore, SortDirection, StockRepository, TransactionManager, TransactionalRepository, UnitOfWork,
+};
+
+// Entities stored by an in-memory repository
+pub trait Entity: Queryable + Clone + Send + Sync + 'static {
+    const NAME: &'static str;
+
+    fn id(&self) -> Option<Uuid>;
+    fn set_id(&mut self, id: Uuid);
+
+    // Carry over fields an update must not overwrite from the stored value
+    fn preserve(&mut self, _existing: &Self) {}
+}
+
+impl Entity for Product {
+    const NAME: &'static str = "Product";
+
+    fn id(&self) -> Option<Uuid> {
+        self.id
+    }
+
+    fn set_id(&mut self, id: Uuid) {
+        self.id = Some(id);
+    }
+
+    // Stock only changes through `StockRepository`, as in the Mongo repository
+    fn preserve(&mut self, existing: &Self) {
+        self.set_stock(existing.stock.clone());
+    }
+}
+
+impl Entity for Order {
+    const NAME: &'static str = "Order";
+
+    fn id(&self) -> Option<Uuid> {
+        self.id
+    }
+
+    fn set_id(&mut self, id: Uuid) {
+        self.id = Some(id);
+    }
+}
+
+// A process-local repository for tests and local development. Clones share the same data.
+pub struct InMemoryRepository<T> {
+    items: Arc<RwLock<HashMap<Uuid, T>>>,
+    // Stock operations applied to products, by product and operation id
+    operations: Arc<RwLock<HashSet<(Uuid, Uuid)>>>,
+}
+
+impl<T> Clone for InMemoryRepository<T> {
+    fn clone(&self) -> Self {
+        Self { items: self.items.clone(), operations: self.operations.clone() }
+    }
+}
+
+impl<T> Default for InMemoryRepository<T> {
+    fn default() -> Self {
+        Self {
+            items: Arc::new(RwLock::new(HashMap::new())),
+            operations: Arc::new(RwLock::new(HashSet::new())),
+        }
+    }
+}
+
+impl<T: Entity> InMemoryRepository<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn not_found(id: Uuid) -> ServiceError {
+        ServiceError::NotFoundError(format!("{} with ID {} not found", T::NAME, id))
+    }
+
+    fn compare(left: &FilterValue, right: &FilterValue) -> Option<Ordering> {
+        match (left, right) {
+            (FilterValue::Text(a), FilterValue::Text(b)) => Some(a.cmp(b)),
+            (FilterValue::Number(a), FilterValue::Number(b)) => Some(a.cmp(b)),
+            (FilterValue::Bool(a), FilterValue::Bool(b)) => Some(a.cmp(b)),
+            (FilterValue::Uuid(a), FilterValue::Uuid(b)) => Some(a.cmp(b)),
+            (FilterValue::Timestamp(a), FilterValue::Timestamp(b)) => Some(a.cmp(b)),
+            _ => None,
+        }
+    }
+
+    fn matches(item: &T, filter: &Filter) -> bool {
+        let ordering = item
+            .field_value(&filter.field)
+            .and_then(|value| Self::compare(&value, &filter.value));
+
+        match (ordering, filter.op) {
+            (Some(ordering), FilterOp::Eq) => ordering == Ordering::Equal,
+            (Some(ordering), FilterOp::Ne) => ordering != Ordering::Equal,
+            (Some(ordering), FilterOp::Gt) => ordering == Ordering::Greater,
+            (Some(ordering), FilterOp::Gte) => ordering != Ordering::Less,
+            (Some(ordering), FilterOp::Lt) => ordering == Ordering::Less,
+            (Some(ordering), FilterOp::Lte) => ordering != Ordering::Greater,
+            (None, _) => false,
+        }
+    }
+
+    fn write(&self, uow: &mut UnitOfWork, id: Uuid, item: Option<T>) {
+        let mut items = self.items.write().unwrap();
+        let previous = match item {
+            Some(mut item) => {
+                if let Some(existing) = items.get(&id) {
+                    item.preserve(existing);
+                }
+                items.insert(id, item)
+            }
+            None => items.remove(&id),
+        };
+
+        // Restore the previous value if the unit of work is rolled back
+        let store = self.items.clone();
+        uow.on_rollback(move || {
+            let mut items = store.write().unwrap();
+            match previous {
+                Some(previous) => items.insert(id, previous),
+                None => items.remove(&id),
+            };
+        });
+    }
+}
+
+#[async_trait]
+impl<T: Entity> Repository<T, Uuid> for InMemoryRepository<T> {
+    async fn find_by_id(&self, id: Uuid) -> ServiceResult<Option<T>> {
+        Ok(self.items.read().unwrap().get(&id).cloned())
+    }
+
+    async fn find_all(&self, query: &QuerySpec) -> ServiceResult<Page<T>> {
+        let mut matching: Vec<(Uuid, T)> = self.items
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(_, item)| query.filters.iter().all(|f| Self::matches(item, f)))
+            .map(|(id, item)| (*id, item.clone()))
+            .collect();
+
+        matching.sort_by(|(a_id, a), (b_id, b)| {
+            for key in &query.sort {
+                let ordering = match (a.field_value(&key.field), b.field_value(&key.field)) {
+                    (Some(x), Some(y)) => Self::compare(&x, &y).unwrap_or(Ordering::Equal),
+                    _ => Ordering::Equal,
+                };
+                let ordering = match key.direction {
+                    SortDirection::Asc => ordering,
+                    SortDirection::Desc => ordering.reverse(),
+                };
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            a_id.cmp(b_id)
+        });
+
+        let total = matching.len() as u64;
+        let items = matching
+            .into_iter()
+            .skip(query.offset as usize)
+            .take(query.limit as usize)
+            .map(|(_, item)| item)
+            .collect();
+
+        Ok(Page {
+            items,
+            total,
+            limit: query.limit,
+            offset: query.offset,
+        })
+    }
+
+    async fn create(&self, item: T) -> ServiceResult<T> {
+        let mut uow = UnitOfWork::in_memory();
+        let created = self.create_in(&mut uow, item).await?;
+        uow.commit().await?;
+
+        Ok(created)
+    }
+
+    async fn update(&self, id: Uuid, item: T) -> ServiceResult<T> {
+        let mut uow = UnitOfWork::in_memory();
+        let updated = self.update_in(&mut uow, id, item).await?;
+        uow.commit().await?;
+
+        Ok(updated)
+    }
+
+    async fn delete(&self, id: Uuid) -> ServiceResult<()> {
+        let mut uow = UnitOfWork::in_memory();
+        self.delete_in(&mut uow, id).await?;
+        uow.commit().await
+    }
+}
+
+#[async_trait]
+impl<T: Entity> TransactionalRepository<T, Uuid> for InMemoryRepository<T> {
+    async fn find_by_id_in(&self, _uow: &mut UnitOfWork, id: Uuid) -> ServiceResult<Option<T>> {
+        self.find_by_id(id).await
+    }
+
+    async fn create_in(&self, uow: &mut UnitOfWork, item: T) -> ServiceResult<T> {
+        let id = item.id().unwrap_or_else(Uuid::new_v4);
+        if self.items.read().unwrap().contains_key(&id) {
+            return Err(ServiceError::DataError(format!("{} with ID {} already exists", T::NAME, id)));
+        }
+
+        let mut created_item = item;
+        created_item.set_id(id);
+        self.write(uow, id, Some(created_item.clone()));
+
+        Ok(created_item)
+    }
+
+    async fn update_in(&self, uow: &mut UnitOfWork, id: Uuid, item: T) -> ServiceResult<T> {
+        if !self.items.read().unwrap().contains_key(&id) {
+            return Err(Self::not_found(id));
+        }
+
+        let mut updated_item = item;
+        updated_item.set_id(id);
+        self.write(uow, id, Some(updated_item.clone()));
+
+        Ok(updated_item)
+    }
+
+    async fn delete_in(&self, uow: &mut UnitOfWork, id: Uuid) -> ServiceResult<()> {
+        if !self.items.read().unwrap().contains_key(&id) {
+            return Err(Self::not_found(id));
+        }
+
+        self.write(uow, id, None);
+
+        Ok(())
+    }
+}
+
+impl InMemoryRepository<Product> {
+    fn update_stock(
+        &self,
+        product_id: Uuid,
+        operation: Uuid,
+        update: impl FnOnce(&StockLevel) -> Option<StockLevel>,
+    ) -> ServiceResult<StockLevel> {
+        let mut items = self.items.write().unwrap();
+        let product = items.get_mut(&product_id).ok_or_else(|| Self::not_found(product_id))?;
+        let mut operations = self.operations.write().unwrap();
+        if operations.contains(&(product_id, operation)) {
+            return Ok(product.stock.clone());
+        }
+
+        let stock = update(&product.stock)
+            .ok_or_else(|| ServiceError::ConflictError(format!("Insufficient stock for product {}", product_id)))?;
+
+        product.set_stock(stock.clone());
+        operations.insert((product_id, operation));
+        Ok(stock)
+    }
+}
+
+#[async_trait]
+impl StockRepository for InMemoryRepository<Product> {
+    async fn find_stock(&self, product_id: Uuid) -> ServiceResult<Option<StockLevel>> {
+        Ok(self.items.read().unwrap().get(&product_id).map(|product| product.stock.clone()))
+    }
+
+    async fn change_stock(&self, product_id: Uuid, change: StockChange, operation: Uuid) -> ServiceResult<StockLevel> {
+        self.update_stock(product_id, operation, |stock| stock.apply(change))
+    }
+
+    async fn set_on_hand(&self, product_id: Uuid, on_hand: i32, operation: Uuid) -> ServiceResult<StockLevel> {
+        self.update_stock(product_id, operation, |stock| {
+            (stock.reserved <= on_hand).then(|| StockLevel { on_hand, ..stock.clone() })
+        })
+    }
+
+    async fn has_applied(&self, product_id: Uuid, operation: Uuid) -> ServiceResult<bool> {
+        Ok(self.operations.read().unwrap().contains(&(product_id, operation)))
+    }
+}
+
+// Matches and scores with `SearchTerms`, which approximates MongoDB's text search
+#[async_trait]
+impl ProductSearch for InMemoryRepository<Product> {
+    async fn search(&self, terms: &SearchTerms, query: &QuerySpec) -> ServiceResult<Page<ScoredProduct>> {
+        let mut matching: Vec<(Uuid, ScoredProduct)> = self.items
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(_, product)| query.filters.iter().all(|f| Self::matches(product, f)))
+            .filter_map(|(id, product)| {
+                let score = terms.score(product)?;
+                Some((*id, ScoredProduct { product: product.clone(), score }))
+            })
+            .collect();
+
+        matching.sort_by(|(a_id, a), (b_id, b)| b.score.total_cmp(&a.score).then_with(|| a_id.cmp(b_id)));
+
+        let total = matching.len() as u64;
+        let items = matching
+            .into_iter()
+            .skip(query.offset as usize)
+            .take(query.limit as usize)
+            .map(|(_, scored)| scored)
+            .collect();
+
+        Ok(Page {
+            items,
+            total,
+            limit: query.limit,
+            offset: query.offset,
+        })
+    }
+
+    async fn facets(&self, terms: Option<&SearchTerms>, filters: &[Filter]) -> ServiceResult<ProductFacets> {
+        let mut facets = ProductFacets::empty();
+        let items = self.items.read().unwrap();
+        let matching = items
+            .values()
+            .filter(|product| filters.iter().all(|f| Self::matches(product, f)))
+            .filter(|product| terms.is_none_or(|terms| terms.score(product).is_some()));
+        for product in matching {
+            facets.add_category(&product.category, 1);
+            facets.price_ranges[ProductFacets::price_range(product.price.amount)].count += 1;
+            facets.add_stock(product.in_stock, 1);
+        }
+        facets.sort_categories();
+
+        Ok(facets)
+    }
+}
+
+// A history entry with its sequence number and the saga action that recorded it
+type HistoryEntry = (u64, Option<Uuid>, OrderStatusChange);
+
+// In-memory order status history. Entries carry a sequence number so a rolled-back
+// unit of work removes exactly the entries it added.
+#[derive(Clone, Default)]
+pub struct InMemoryOrderHistory {
+    entries: Arc<RwLock<Vec<HistoryEntry>>>,
+    next_seq: Arc<AtomicU64>,
+}
+
+impl InMemoryOrderHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl OrderHistoryStore for InMemoryOrderHistory {
+    async fn record_in(
+        &self,
+        uow: &mut UnitOfWork,
+        change: OrderStatusChange,
+        operation: Option<Uuid>,
+    ) -> ServiceResult<OrderStatusChange> {
+        let seq = self.next_seq.fetch_add(1, AtomicOrdering::SeqCst);
+        self.entries.write().unwrap().push((seq, operation, change.clone()));
+
+        let store = self.entries.clone();
+        uow.on_rollback(move || {
+            store.write().unwrap().retain(|(entry_seq, _, _)| *entry_seq != seq);
+        });
+
+        Ok(change)
+    }
+
+    async fn has_recorded_in(&self, _uow: &mut UnitOfWork, order_id: Uuid, operation: Uuid) -> ServiceResult<bool> {
+        Ok(self.entries
+            .read()
+            .unwrap()
+            .iter()
+            .any(|(_, entry_operation, change)| change.order_id == order_id && *entry_operation == Some(operation)))
+    }
+
+    async fn find_by_order(&self, order_id: Uuid) -> ServiceResult<Vec<OrderStatusChange>> {
+        Ok(self.entries
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(_, _, change)| change.order_id == order_id)
+            .map(|(_, _, change)| change.clone())
+            .collect())
+    }
+}
+
+// In-memory saga progress
+#[derive(Clone, Default)]
+pub struct InMemorySagaStore {
+    sagas: Arc<RwLock<HashMap<Uuid, SagaRecord>>>,
+}
+
+impl InMemorySagaStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl SagaStore for InMemorySagaStore {
+    async fn save(&self, saga: &SagaRecord) -> ServiceResult<()> {
+        let mut sagas = self.sagas.write().unwrap();
+        if let Some(existing) = sagas.get(&saga.id) {
+            if existing.owner != saga.owner {
+                return Err(ServiceError::ConflictError(format!("Saga {} was taken over by another coordinator", saga.id)));
+            }
+        }
+        sagas.insert(saga.id, saga.clone());
+        Ok(())
+    }
+
+    async fn claim_unfinished(&self, owner: Uuid, updated_before: DateTime<Utc>) -> ServiceResult<Vec<SagaRecord>> {
+        let now = Utc::now();
+        let mut sagas: Vec<SagaRecord> = self.sagas
+            .write()
+            .unwrap()
+            .values_mut()
+            .filter(|saga| !saga.status.is_finished() && saga.updated_at < updated_before)
+            .map(|saga| {
+                saga.owner = Some(owner);
+                saga.updated_at = now;
+                saga.clone()
+            })
+            .collect();
+        sagas.sort_by_key(|saga| saga.created_at);
+
+        Ok(sagas)
+    }
+}
+
+// An idempotency record, when it expires, until when its claim holds and the claim's token
+type ExpiringRecord = (IdempotencyRecord, DateTime<Utc>, DateTime<Utc>, Uuid);
+
+// In-memory idempotency keys
+#[derive(Clone)]
+pub struct InMemoryIdempotencyStore {
+    keys: Arc<RwLock<HashMap<String, ExpiringRecord>>>,
+    ttl: Duration,
+    lease: Duration,
+}
+
+impl InMemoryIdempotencyStore {
+    pub fn new(ttl_secs: u64, lease_secs: u64) -> Self {
+        Self {
+            keys: Arc::new(RwLock::new(HashMap::new())),
+            ttl: Duration::seconds(ttl_secs as i64),
+            lease: Duration::seconds(lease_secs as i64),
+        }
+    }
+}
+
+#[async_trait]
+impl IdempotencyStore for InMemoryIdempotencyStore {
+    async fn claim(&self, key: &str, fingerprint: &str) -> ServiceResult<IdempotencyClaim> {
+        let now = Utc::now();
+        let mut keys = self.keys.write().unwrap();
+
+        if let Some((record, expires_at, locked_until, _)) = keys.get(key) {
+            let lapsed = record.response.is_none() && *locked_until <= now;
+            if *expires_at > now && !lapsed {
+                return Ok(IdempotencyClaim::Taken(record.clone()));
+            }
+        }
+
+        let token = Uuid::new_v4();
+        let record = IdempotencyRecord { fingerprint: fingerprint.to_string(), response: None };
+        keys.insert(key.to_string(), (record, now + self.ttl, now + self.lease, token));
+
+        Ok(IdempotencyClaim::Claimed(token))
+    }
+
+    async fn complete(&self, key: &str, token: Uuid, response: &StoredResponse) -> ServiceResult<()> {
+        match self.keys.write().unwrap().get_mut(key) {
+            Some((record, _, _, claim)) if *claim == token && record.response.is_none() => {
+                record.response = Some(response.clone());
+            }
+            _ => tracing::warn!("Not storing response for idempotency key {}: its claim was taken over", key),
+        }
+
+        Ok(())
+    }
+
+    async fn release(&self, key: &str, token: Uuid) -> ServiceResult<()> {
+        let mut keys = self.keys.write().unwrap();
+        if keys.get(key).is_some_and(|(record, _, _, claim)| *claim == token && record.response.is_none()) {
+            keys.remove(key);
+        } else {
+            tracing::warn!("Not releasing idempotency key {}: its claim was taken over", key);
+        }
+
+        Ok(())
+    }
+
+    async fn purge_expired(&self) -> ServiceResult<u64> {
+        let now = Utc::now();
+        let mut keys = self.keys.write().unwrap();
+        let before = keys.len();
+        keys.retain(|_, (_, expires_at, _, _)| *expires_at > now);
+
+        Ok((before - keys.len()) as u64)
+    }
+}
+
+// Units of work for in-memory repositories, which roll back through registered undo actions
+#[derive(Clone, Default)]
+pub struct InMemoryTransactionManager;
+
+#[async_trait]
+impl TransactionManager for InMemoryTransactionManager {
+    async fn begin(&self) -> ServiceResult<UnitOfWork> {
+        Ok(UnitOfWork::in_memory())
+    }
+}
+
+// In-memory API keys
+#[derive(Clone, Default)]
+pub struct InMemoryApiKeyStore {
+    keys: Arc<RwLock<HashMap<Uuid, ApiKey>>>,
+}
+
+impl InMemoryApiKeyStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn update(&self, id: Uuid, change: impl FnOnce(&mut ApiKey) -> bool) -> Option<ApiKey> {
+        let mut keys = self.keys.write().unwrap();
+        let api_key = keys.get_mut(&id)?;
+        change(api_key).then(|| api_key.clone())
+    }
+}
+
+#[async_trait]
+impl ApiKeyStore for InMemoryApiKeyStore {
+    async fn create(&self, api_key: &ApiKey) -> ServiceResult<()> {
+        self.keys.write().unwrap().insert(api_key.id, api_key.clone());
+        Ok(())
+    }
+
+    async fn find_by_id(&self, id: Uuid) -> ServiceResult<Option<ApiKey>> {
+        Ok(self.keys.read().unwrap().get(&id).cloned())
+    }
+
+    async fn find_all(&self) -> ServiceResult<Vec<ApiKey>> {
+        let mut keys: Vec<ApiKey> = self.keys.read().unwrap().values().cloned().collect();
+        keys.sort_by_key(|api_key| api_key.created_at);
+
+        Ok(keys)
+    }
+
+    async fn rotate(&self, id: Uuid, key_hash: &str, rotated_at: DateTime<Utc>) -> ServiceResult<Option<ApiKey>> {
+        Ok(self.update(id, |api_key| {
+            if api_key.is_revoked() {
+                return false;
+            }
+            api_key.key_hash = key_hash.to_string();
+            api_key.rotated_at = Some(rotated_at);
+            true
+        }))
+    }
+
+    async fn revoke(&self, id: Uuid, revoked_at: DateTime<Utc>) -> ServiceResult<Option<ApiKey>> {
+        Ok(self.update(id, |api_key| {
+            api_key.revoked_at.get_or_insert(revoked_at);
+            true
+        }))
+    }
+
+    async fn touch(&self, id: Uuid, used_at: DateTime<Utc>) -> ServiceResult<()> {
+        self.update(id, |api_key| {
+            api_key.last_used_at = Some(used_at);
+            true
+        });
+        Ok(())
+    }
+}
+
+// An outbox event, when it was published, and its failed attempts to publish it
+#[derive(Clone)]
+struct StoredEvent {
+    entry: OutboxEntry,
+    published_at: Option<DateTime<Utc>>,
+    retry_at: Option<DateTime<Utc>>,
+    dead_lettered: bool,
+}
+
+impl StoredEvent {
+    fn is_pending(&self) -> bool {
+        self.published_at.is_none() && !self.dead_lettered
+    }
+}
+
+// In-memory outbox. Like the other in-memory repositories, appended events are visible
+// before their unit of work commits and are removed again if it rolls back.
+#[derive(Clone, Default)]
+pub struct InMemoryOutbox {
+    events: Arc<RwLock<Vec<StoredEvent>>>,
+    last_sequence: Arc<AtomicU64>,
+}
+
+impl InMemoryOutbox {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Every event still in the outbox, published or not, in sequence order
+    pub fn entries(&self) -> Vec<OutboxEntry> {
+        self.events.read().unwrap().iter().map(|stored| stored.entry.clone()).collect()
+    }
+
+    // Events that failed too often to be retried, in sequence order
+    pub fn dead_letters(&self) -> Vec<OutboxEntry> {
+        self.events
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|stored| stored.dead_lettered)
+            .map(|stored| stored.entry.clone())
+            .collect()
+    }
+
+    // Change an event, putting it back as it was if `uow` rolls back
+    fn update(&self, uow: &mut UnitOfWork, sequences: &[i64], change: impl Fn(&mut StoredEvent)) {
+        let mut previous = Vec::new();
+        for stored in self.events.write().unwrap().iter_mut() {
+            if sequences.contains(&stored.entry.sequence) {
+                previous.push(stored.clone());
+                change(stored);
+            }
+        }
+
+        let store = self.events.clone();
+        uow.on_rollback(move || {
+            for stored in store.write().unwrap().iter_mut() {
+                if let Some(before) = previous.iter().find(|before| before.entry.sequence == stored.entry.sequence) {
+                    *stored = before.clone();
+                }
+            }
+        });
+    }
+}
+
+#[async_trait]
+impl OutboxStore for InMemoryOutbox {
+    async fn append_in(&self, uow: &mut UnitOfWork, events: &[DomainEvent]) -> ServiceResult<()> {
+        let mut stored = self.events.write().unwrap();
+        let new_events: Vec<&DomainEvent> = events
+            .iter()
+            .filter(|event| !stored.iter().any(|existing| existing.entry.event.id == event.id))
+            .collect();
+        let sequences: Vec<i64> = new_events
+            .into_iter()
+            .map(|event| {
+                let sequence = self.last_sequence.fetch_add(1, AtomicOrdering::SeqCst) as i64 + 1;
+                stored.push(StoredEvent {
+                    entry: OutboxEntry { sequence, event: event.clone(), attempts: 0 },
+                    published_at: None,
+                    retry_at: None,
+                    dead_lettered: false,
+                });
+                sequence
+            })
+            .collect();
+
+        let store = self.events.clone();
+        uow.on_rollback(move || {
+            store.write().unwrap().retain(|stored| !sequences.contains(&stored.entry.sequence));
+        });
+
+        Ok(())
+    }
+
+    async fn claim_in(&self, _uow: &mut UnitOfWork, limit: i64, now: DateTime<Utc>) -> ServiceResult<Vec<OutboxEntry>> {
+        // Aggregates with an event waiting to be retried, which holds back their later events
+        let mut waiting = HashSet::new();
+        Ok(self.events
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|stored| stored.is_pending())
+            .filter(|stored| {
+                let aggregate_id = stored.entry.event.aggregate_id;
+                if stored.retry_at.is_some_and(|at| at > now) {
+                    waiting.insert(aggregate_id);
+                }
+                !waiting.contains(&aggregate_id)
+            })
+            .take(limit.max(0) as usize)
+            .map(|stored| stored.entry.clone())
+            .collect())
+    }
+
+    async fn mark_published_in(&self, uow: &mut UnitOfWork, sequences: &[i64]) -> ServiceResult<()> {
+        let now = Utc::now();
+        self.update(uow, sequences, |stored| stored.published_at = Some(now));
+
+        Ok(())
+    }
+
+    async fn record_failure_in(
+        &self,
+        uow: &mut UnitOfWork,
+        sequence: i64,
+        _error: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> ServiceResult<()> {
+        self.update(uow, &[sequence], |stored| {
+            stored.entry.attempts += 1;
+            stored.retry_at = retry_at;
+            stored.dead_lettered = retry_at.is_none();
+        });
+
+        Ok(())
+    }
+
+    async fn replay(&self, from_sequence: i64, aggregate_id: Option<Uuid>) -> ServiceResult<u64> {
+        let mut replayed = 0;
+        for stored in self.events.write().unwrap().iter_mut() {
+            let selected = stored.entry.sequence >= from_sequence
+                && aggregate_id.is_none_or(|id| stored.entry.event.aggregate_id == id);
+            if selected && !stored.is_pending() {
+                *stored = StoredEvent {
+                    entry: OutboxEntry { attempts: 0, ..stored.entry.clone() },
+                    published_at: None,
+                    retry_at: None,
+                    dead_lettered: false,
+                };
+                replayed += 1;
+            }
+        }
+
+        Ok(replayed)
+    }
+
+    async fn purge_published(&self, published_before: DateTime<Utc>) -> ServiceResult<u64> {
+        let mut events = self.events.write().unwrap();
+        let before = events.len();
+        events.retain(|stored| stored.published_at.is_none_or(|at| at >= published_before));
+
+        Ok((before - events.len()) as u64)
+    }
+}
@@ -0,0 +1,245 @@
+use std::future::Future;
+use std::time::Instant;
+use async_trait::async_trait;
+use tracing::Instrument;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+use crate::errors::ServiceResult;
+use crate::metrics::metrics;
+use crate::models::api_key::ApiKey;
+use crate::models::event::{DomainEvent, OutboxEntry};
+use crate::models::idempotency::{IdempotencyClaim, StoredResponse};
+use crate::models::order::OrderStatusChange;
+use crate::models::saga::SagaRecord;
+use crate::models::facet::ProductFacets;
+use crate::models::search::{ScoredProduct, SearchTerms};
+use crate::models::stock::{StockChange, StockLevel};
+use crate::repositories::{
+    ApiKeyStore, Filter, IdempotencyStore, OrderHistoryStore, OutboxStore, Page, ProductSearch, QuerySpec, Repository, SagaStore,
+    StockRepository, TransactionalRepository, UnitOfWork,
+};
+
+// Wraps a repository so each of its methods runs in a `repository` span and is timed in
+// `db_operation_duration_seconds`, labelled with the store and repository names.
+// The statements a method issues show up as child spans of its `repository` span.
+pub struct Instrumented<R> {
+    inner: R,
+    store: &'static str,
+    repository: &'static str,
+}
+
+impl<R> Instrumented<R> {
+    pub fn new(inner: R, store: &'static str, repository: &'static str) -> Self {
+        Self { inner, store, repository }
+    }
+
+    async fn observe<T>(&self, method: &str, operation: impl Future<Output = ServiceResult<T>>) -> ServiceResult<T> {
+        let span = tracing::info_span!(
+            "repository",
+            otel.name = %format_args!("{}.{}", self.repository, method),
+            store = self.store,
+            repository = self.repository,
+            method,
+            otel.status_code = tracing::field::Empty,
+        );
+        let started = Instant::now();
+        let result = operation.instrument(span.clone()).await;
+        if result.is_err() {
+            span.record("otel.status_code", "ERROR");
+        }
+        metrics().record_db_operation(self.store, self.repository, method, result.is_ok(), started.elapsed());
+        result
+    }
+}
+
+#[async_trait]
+impl<T, ID, R> Repository<T, ID> for Instrumented<R>
+where
+    T: Send + 'static,
+    ID: Send + 'static,
+    R: Repository<T, ID>,
+{
+    async fn find_by_id(&self, id: ID) -> ServiceResult<Option<T>> {
+        self.observe("find_by_id", self.inner.find_by_id(id)).await
+    }
+
+    async fn find_all(&self, query: &QuerySpec) -> ServiceResult<Page<T>> {
+        self.observe("find_all", self.inner.find_all(query)).await
+    }
+
+    async fn create(&self, item: T) -> ServiceResult<T> {
+        self.observe("create", self.inner.create(item)).await
+    }
+
+    async fn update(&self, id: ID, item: T) -> ServiceResult<T> {
+        self.observe("update", self.inner.update(id, item)).await
+    }
+
+    async fn delete(&self, id: ID) -> ServiceResult<()> {
+        self.observe("delete", self.inner.delete(id)).await
+    }
+}
+
+#[async_trait]
+impl<T, ID, R> TransactionalRepository<T, ID> for Instrumented<R>
+where
+    T: Send + 'static,
+    ID: Send + 'static,
+    R: TransactionalRepository<T, ID>,
+{
+    async fn find_by_id_in(&self, uow: &mut UnitOfWork, id: ID) -> ServiceResult<Option<T>> {
+        self.observe("find_by_id_in", self.inner.find_by_id_in(uow, id)).await
+    }
+
+    async fn create_in(&self, uow: &mut UnitOfWork, item: T) -> ServiceResult<T> {
+        self.observe("create_in", self.inner.create_in(uow, item)).await
+    }
+
+    async fn update_in(&self, uow: &mut UnitOfWork, id: ID, item: T) -> ServiceResult<T> {
+        self.observe("update_in", self.inner.update_in(uow, id, item)).await
+    }
+
+    async fn delete_in(&self, uow: &mut UnitOfWork, id: ID) -> ServiceResult<()> {
+        self.observe("delete_in", self.inner.delete_in(uow, id)).await
+    }
+}
+
+#[async_trait]
+impl<R: StockRepository> StockRepository for Instrumented<R> {
+    async fn find_stock(&self, product_id: Uuid) -> ServiceResult<Option<StockLevel>> {
+        self.observe("find_stock", self.inner.find_stock(product_id)).await
+    }
+
+    async fn change_stock(&self, product_id: Uuid, change: StockChange, operation: Uuid) -> ServiceResult<StockLevel> {
+        self.observe("change_stock", self.inner.change_stock(product_id, change, operation)).await
+    }
+
+    async fn set_on_hand(&self, product_id: Uuid, on_hand: i32, operation: Uuid) -> ServiceResult<StockLevel> {
+        self.observe("set_on_hand", self.inner.set_on_hand(product_id, on_hand, operation)).await
+    }
+
+    async fn has_applied(&self, product_id: Uuid, operation: Uuid) -> ServiceResult<bool> {
+        self.observe("has_applied", self.inner.has_applied(product_id, operation)).await
+    }
+}
+
+#[async_trait]
+impl<R: ProductSearch> ProductSearch for Instrumented<R> {
+    async fn search(&self, terms: &SearchTerms, query: &QuerySpec) -> ServiceResult<Page<ScoredProduct>> {
+        self.observe("search", self.inner.search(terms, query)).await
+    }
+
+    async fn facets(&self, terms: Option<&SearchTerms>, filters: &[Filter]) -> ServiceResult<ProductFacets> {
+        self.observe("facets", self.inner.facets(terms, filters)).await
+    }
+}
+
+#[async_trait]
+impl<R: OrderHistoryStore> OrderHistoryStore for Instrumented<R> {
+    async fn record_in(
+        &self,
+        uow: &mut UnitOfWork,
+        change: OrderStatusChange,
+        operation: Option<Uuid>,
+    ) -> ServiceResult<OrderStatusChange> {
+        self.observe("record_in", self.inner.record_in(uow, change, operation)).await
+    }
+
+    async fn has_recorded_in(&self, uow: &mut UnitOfWork, order_id: Uuid, operation: Uuid) -> ServiceResult<bool> {
+        self.observe("has_recorded_in", self.inner.has_recorded_in(uow, order_id, operation)).await
+    }
+
+    async fn find_by_order(&self, order_id: Uuid) -> ServiceResult<Vec<OrderStatusChange>> {
+        self.observe("find_by_order", self.inner.find_by_order(order_id)).await
+    }
+}
+
+#[async_trait]
+impl<R: SagaStore> SagaStore for Instrumented<R> {
+    async fn save(&self, saga: &SagaRecord) -> ServiceResult<()> {
+        self.observe("save", self.inner.save(saga)).await
+    }
+
+    async fn claim_unfinished(&self, owner: Uuid, updated_before: DateTime<Utc>) -> ServiceResult<Vec<SagaRecord>> {
+        self.observe("claim_unfinished", self.inner.claim_unfinished(owner, updated_before)).await
+    }
+}
+
+#[async_trait]
+impl<R: IdempotencyStore> IdempotencyStore for Instrumented<R> {
+    async fn claim(&self, key: &str, fingerprint: &str) -> ServiceResult<IdempotencyClaim> {
+        self.observe("claim", self.inner.claim(key, fingerprint)).await
+    }
+
+    async fn complete(&self, key: &str, token: Uuid, response: &StoredResponse) -> ServiceResult<()> {
+        self.observe("complete", self.inner.complete(key, token, response)).await
+    }
+
+    async fn release(&self, key: &str, token: Uuid) -> ServiceResult<()> {
+        self.observe("release", self.inner.release(key, token)).await
+    }
+
+    async fn purge_expired(&self) -> ServiceResult<u64> {
+        self.observe("purge_expired", self.inner.purge_expired()).await
+    }
+}
+
+#[async_trait]
+impl<R: ApiKeyStore> ApiKeyStore for Instrumented<R> {
+    async fn create(&self, api_key: &ApiKey) -> ServiceResult<()> {
+        self.observe("create", self.inner.create(api_key)).await
+    }
+
+    async fn find_by_id(&self, id: Uuid) -> ServiceResult<Option<ApiKey>> {
+        self.observe("find_by_id", self.inner.find_by_id(id)).await
+    }
+
+    async fn find_all(&self) -> ServiceResult<Vec<ApiKey>> {
+        self.observe("find_all", self.inner.find_all()).await
+    }
+
+    async fn rotate(&self, id: Uuid, key_hash: &str, rotated_at: DateTime<Utc>) -> ServiceResult<Option<ApiKey>> {
+        self.observe("rotate", self.inner.rotate(id, key_hash, rotated_at)).await
+    }
+
+    async fn revoke(&self, id: Uuid, revoked_at: DateTime<Utc>) -> ServiceResult<Option<ApiKey>> {
+        self.observe("revoke", self.inner.revoke(id, revoked_at)).await
+    }
+
+    async fn touch(&self, id: Uuid, used_at: DateTime<Utc>) -> ServiceResult<()> {
+        self.observe("touch", self.inner.touch(id, used_at)).await
+    }
+}
+
+#[async_trait]
+impl<R: OutboxStore> OutboxStore for Instrumented<R> {
+    async fn append_in(&self, uow: &mut UnitOfWork, events: &[DomainEvent]) -> ServiceResult<()> {
+        self.observe("append_in", self.inner.append_in(uow, events)).await
+    }
+
+    async fn claim_in(&self, uow: &mut UnitOfWork, limit: i64, now: DateTime<Utc>) -> ServiceResult<Vec<OutboxEntry>> {
+        self.observe("claim_in", self.inner.claim_in(uow, limit, now)).await
+    }
+
+    async fn mark_published_in(&self, uow: &mut UnitOfWork, sequences: &[i64]) -> ServiceResult<()> {
+        self.observe("mark_published_in", self.inner.mark_published_in(uow, sequences)).await
+    }
+
+    async fn record_failure_in(
+        &self,
+        uow: &mut UnitOfWork,
+        sequence: i64,
+        error: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> ServiceResult<()> {
+        self.observe("record_failure_in", self.inner.record_failure_in(uow, sequence, error, retry_at)).await
+    }
+
+    async fn replay(&self, from_sequence: i64, aggregate_id: Option<Uuid>) -> ServiceResult<u64> {
+        self.observe("replay", self.inner.replay(from_sequence, aggregate_id)).await
+    }
+
+    async fn purge_published(&self, published_before: DateTime<Utc>) -> ServiceResult<u64> {
+        self.observe("purge_published", self.inner.purge_published(published_before)).await
+    }
+}
\ No newline at end of file
@@ -0,0 +1,287 @@
+use mongodb::bson::{doc, Document};
+use mongodb::options::IndexOptions;
+use mongodb::IndexModel;
+use sqlx::migrate::{Migrate, Migrator};
+use crate::config::MongoConfig;
+use crate::errors::{ServiceError, ServiceResult};
+use crate::models::product::Product;
+use crate::repositories::{MongoClient, MongoOutboxRepository, PostgresClient, ProductRepository};
+
+// Migrations are embedded from `./migrations` at compile time and recorded in `_sqlx_migrations`
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+// MongoDB data migrations, applied in order and recorded in `_migrations`
+pub const MONGO_MIGRATIONS: &[(i64, &str)] = &[
+    (1, "convert product prices to money"),
+    (2, "add product stock levels"),
+    (3, "create product text index"),
+    (4, "create staged event index"),
+    (5, "move stock operations to their own collection"),
+    (6, "index staged events by staging time"),
+];
+
+const MONGO_MIGRATIONS_COLLECTION: &str = "_migrations";
+
+pub const PRODUCT_TEXT_INDEX: &str = "product_text";
+
+#[derive(Debug, Clone)]
+pub struct MigrationStatus {
+    pub store: &'static str,
+    pub version: i64,
+    pub description: String,
+    pub applied: bool,
+}
+
+impl PostgresClient {
+    pub async fn run_migrations(&self) -> ServiceResult<()> {
+        MIGRATOR
+            .run(&self.pool)
+            .await
+            .map_err(ServiceError::from)
+    }
+    
+    pub async fn migration_status(&self) -> ServiceResult<Vec<MigrationStatus>> {
+        let mut conn = self.pool.acquire().await
+            .map_err(ServiceError::from)?;
+            
+        conn.ensure_migrations_table().await
+            .map_err(ServiceError::from)?;
+            
+        let applied = conn.list_applied_migrations().await
+            .map_err(ServiceError::from)?;
+        
+        let mut status = Vec::new();
+        for migration in MIGRATOR.iter() {
+            let applied_migration = applied.iter().find(|m| m.version == migration.version);
+            
+            // A recorded checksum that differs from the embedded file means it was edited after being applied
+            if let Some(applied_migration) = applied_migration {
+                if applied_migration.checksum != migration.checksum {
+                    return Err(ServiceError::DataError(format!(
+                        "Migration {} was modified after it was applied",
+                        migration.version
+                    )));
+                }
+            }
+            
+            status.push(MigrationStatus {
+                store: "postgres",
+                version: migration.version,
+                description: migration.description.to_string(),
+                applied: applied_migration.is_some(),
+            });
+        }
+        
+        Ok(status)
+    }
+}
+
+
+impl MongoClient {
+    pub async fn run_migrations(&self, config: &MongoConfig) -> ServiceResult<()> {
+        let history = self.database.collection::<Document>(MONGO_MIGRATIONS_COLLECTION);
+        
+        for (version, description) in MONGO_MIGRATIONS {
+            let applied = history.find_one(doc! { "_id": version }, None).await
+                .map_err(ServiceError::from)?;
+            if applied.is_some() {
+                continue;
+            }
+            
+            self.apply_migration(*version, config).await?;
+            
+            history
+                .insert_one(
+                    doc! {
+                        "_id": version,
+                        "description": description,
+                        "applied_at": mongodb::bson::DateTime::now(),
+                    },
+                    None,
+                )
+                .await
+                .map_err(ServiceError::from)?;
+        }
+        
+        Ok(())
+    }
+    
+    pub async fn migration_status(&self) -> ServiceResult<Vec<MigrationStatus>> {
+        let history = self.database.collection::<Document>(MONGO_MIGRATIONS_COLLECTION);
+        
+        let mut status = Vec::new();
+        for (version, description) in MONGO_MIGRATIONS {
+            let applied = history.find_one(doc! { "_id": version }, None).await
+                .map_err(ServiceError::from)?;
+                
+            status.push(MigrationStatus {
+                store: "mongodb",
+                version: *version,
+                description: description.to_string(),
+                applied: applied.is_some(),
+            });
+        }
+        
+        Ok(status)
+    }
+    
+    async fn apply_migration(&self, version: i64, config: &MongoConfig) -> ServiceResult<()> {
+        match version {
+            // Legacy products stored `price` as a double; rewrite it as an exact USD amount
+            1 => {
+                let products = self.database.collection::<Document>("products");
+                let pipeline = vec![doc! {
+                    "$set": {
+                        "price": {
+                            "amount": { "$round": [{ "$toDecimal": "$price" }, 2] },
+                            "currency": "USD",
+                        }
+                    }
+                }];
+                
+                products
+                    .update_many(doc! { "price": { "$type": "number" } }, pipeline, None)
+                    .await
+                    .map(|_| ())
+                    .map_err(ServiceError::from)
+            }
+            // Products predating stock tracking only had an `in_stock` flag. Those out of stock
+            // start with nothing on hand; those in stock get `legacy_stock_on_hand` units each.
+            // Without it the migration refuses to run rather than make them unorderable, until
+            // their stock levels have been written.
+            2 => {
+                let products = self.database.collection::<Document>("products");
+                let untracked = doc! { "stock": { "$exists": false } };
+                let in_stock = doc! { "stock": { "$exists": false }, "in_stock": true };
+                
+                let in_stock_count = products.count_documents(in_stock.clone(), None).await
+                    .map_err(ServiceError::from)?;
+                if in_stock_count > 0 {
+                    let on_hand = config.legacy_stock_on_hand.ok_or_else(|| ServiceError::ConfigError(format!(
+                        "{} in-stock products predate stock tracking; write their stock levels or set \
+                         MONGODB_LEGACY_STOCK_ON_HAND to the units each should start with",
+                        in_stock_count
+                    )))?;
+                    let update = doc! {
+                        "$set": {
+                            "stock": { "on_hand": on_hand, "reserved": 0 },
+                            "in_stock": on_hand > 0,
+                        }
+                    };
+                    products.update_many(in_stock, update, None).await
+                        .map_err(ServiceError::from)?;
+                }
+                
+                let update = doc! {
+                    "$set": {
+                        "stock": { "on_hand": 0, "reserved": 0 },
+                        "in_stock": false,
+                    }
+                };
+                products
+                    .update_many(untracked, update, None)
+                    .await
+                    .map(|_| ())
+                    .map_err(ServiceError::from)
+            }
+            // Product search. Changing the fields or weights needs a new migration that drops
+            // and recreates the index, as a collection can only have one text index.
+            3 => {
+                let products = self.database.collection::<Document>("products");
+                let mut keys = Document::new();
+                let mut weights = Document::new();
+                for (field, weight) in Product::SEARCH_WEIGHTS {
+                    keys.insert(*field, "text");
+                    weights.insert(*field, *weight);
+                }
+                let index = IndexModel::builder()
+                    .keys(keys)
+                    .options(
+                        IndexOptions::builder()
+                            .name(PRODUCT_TEXT_INDEX.to_string())
+                            .weights(weights)
+                            .default_language("english".to_string())
+                            .build(),
+                    )
+                    .build();
+                
+                products
+                    .create_index(index, None)
+                    .await
+                    .map(|_| ())
+                    .map_err(ServiceError::from)
+            }
+            // Events staged with catalog changes are moved to the outbox per aggregate, in order
+            4 => {
+                let staged = self.database.collection::<Document>(MongoOutboxRepository::STAGED_COLLECTION);
+                let index = IndexModel::builder()
+                    .keys(doc! { "aggregate_id": 1, "aggregate_sequence": 1 })
+                    .options(IndexOptions::builder().unique(true).build())
+                    .build();
+                
+                staged
+                    .create_index(index, None)
+                    .await
+                    .map(|_| ())
+                    .map_err(ServiceError::from)
+            }
+            // Stock operation ids were kept in a bounded list on each product, which forgot ids
+            // a saga might still repeat. They now live in their own collection, one per
+            // operation, until they expire.
+            5 => {
+                let operations = self.database.collection::<Document>(ProductRepository::STOCK_OPERATIONS_COLLECTION);
+                let retention = std::time::Duration::from_secs(ProductRepository::STOCK_OPERATION_RETENTION_SECS);
+                let indexes = vec![
+                    IndexModel::builder()
+                        .keys(doc! { "product_id": 1, "operation": 1 })
+                        .options(IndexOptions::builder().unique(true).build())
+                        .build(),
+                    IndexModel::builder()
+                        .keys(doc! { "applied_at": 1 })
+                        .options(IndexOptions::builder().expire_after(retention).build())
+                        .build(),
+                ];
+                operations.create_indexes(indexes, None).await
+                    .map_err(ServiceError::from)?;
+                
+                let products = self.database.collection::<Document>("products");
+                let pipeline = vec![
+                    doc! { "$match": { "stock.operations.0": { "$exists": true } } },
+                    doc! { "$unwind": "$stock.operations" },
+                    doc! { "$project": { "_id": 0, "product_id": "$_id", "operation": "$stock.operations", "applied_at": "$$NOW" } },
+                    doc! {
+                        "$merge": {
+                            "into": ProductRepository::STOCK_OPERATIONS_COLLECTION,
+                            "on": ["product_id", "operation"],
+                            "whenMatched": "keepExisting",
+                            "whenNotMatched": "insert",
+                        }
+                    },
+                ];
+                products.aggregate(pipeline, None).await
+                    .map_err(ServiceError::from)?;
+                
+                products
+                    .update_many(doc! { "stock.operations": { "$exists": true } }, doc! { "$unset": { "stock.operations": "" } }, None)
+                    .await
+                    .map(|_| ())
+                    .map_err(ServiceError::from)
+            }
+            // Staged events are moved oldest first rather than by aggregate id, so a busy
+            // aggregate cannot hold back the others
+            6 => {
+                let staged = self.database.collection::<Document>(MongoOutboxRepository::STAGED_COLLECTION);
+                let index = IndexModel::builder()
+                    .keys(doc! { "staged_at": 1, "aggregate_id": 1, "aggregate_sequence": 1 })
+                    .build();
+                
+                staged
+                    .create_index(index, None)
+                    .await
+                    .map(|_| ())
+                    .map_err(ServiceError::from)
+            }
+            _ => Err(ServiceError::DataError(format!("Unknown MongoDB migration {}", version))),
+        }
+    }
+}
\ No newline at end of file
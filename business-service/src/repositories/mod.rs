@@ -1,11 +1,41 @@
 pub mod postgres;
+pub mod migrations;
+pub mod unit_of_work;
 pub mod mongodb;
+pub mod redis;
+pub mod cache;
 pub mod repository;
+pub mod query;
 pub mod product_repository;
 pub mod order_repository;
+pub mod order_history_repository;
+pub mod saga_repository;
+pub mod idempotency_repository;
+pub mod api_key_repository;
+pub mod outbox_repository;
+pub mod mongo_outbox_repository;
+pub mod in_memory;
+pub mod health;
+pub mod instrumented;
+pub mod cached;
 
 pub use postgres::*;
+pub use migrations::*;
+pub use unit_of_work::*;
 pub use mongodb::*;
+pub use redis::*;
+pub use cache::*;
 pub use repository::*;
+pub use query::*;
 pub use product_repository::*;
-pub use order_repository::*;
\ No newline at end of file
+pub use order_repository::*;
+pub use order_history_repository::*;
+pub use saga_repository::*;
+pub use idempotency_repository::*;
+pub use api_key_repository::*;
+pub use outbox_repository::*;
+pub use mongo_outbox_repository::*;
+pub use in_memory::*;
+pub use health::*;
+pub use instrumented::*;
+pub use cached::*;
\ No newline at end of file
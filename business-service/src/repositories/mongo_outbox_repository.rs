@@ -0,0 +1,182 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures_util::TryStreamExt;
+use mongodb::bson::{doc, DateTime as BsonDateTime, Document};
+use mongodb::options::{FindOneAndUpdateOptions, FindOptions, ReturnDocument};
+use mongodb::Collection;
+use tracing::Instrument;
+use uuid::Uuid;
+use crate::errors::{ServiceError, ServiceResult};
+use crate::models::event::{DomainEvent, OutboxEntry};
+use crate::repositories::{MongoClient, OutboxStore, UnitOfWork};
+use crate::telemetry::mongo_span;
+
+// The outbox for changes made in MongoDB. Their events are staged in MongoDB's `outbox_events`
+// in the transaction that makes the change, and moved into the Postgres outbox when the relay
+// claims events, so relaying, replay and retention work the same for every event. Events
+// appended in any other unit of work go to the Postgres outbox directly.
+pub struct MongoOutboxRepository {
+    mongo_client: MongoClient,
+    outbox: Arc<dyn OutboxStore>,
+}
+
+impl MongoOutboxRepository {
+    pub const STAGED_COLLECTION: &'static str = "outbox_events";
+    // The last staged sequence of each aggregate, and when it was staged
+    pub const SEQUENCES_COLLECTION: &'static str = "outbox_sequences";
+
+    pub fn new(mongo_client: MongoClient, outbox: Arc<dyn OutboxStore>) -> Self {
+        Self { mongo_client, outbox }
+    }
+
+    fn staged(&self) -> Collection<Document> {
+        self.mongo_client.database.collection(Self::STAGED_COLLECTION)
+    }
+
+    // Number each aggregate's events after those it staged before. Two transactions staging
+    // events for one aggregate conflict on its counter, so the numbers follow commit order.
+    // Events are stamped with the time they were staged, never earlier than the aggregate's
+    // previous events, so staging order never contradicts an aggregate's sequence.
+    async fn stage_in(&self, uow: &mut UnitOfWork, events: &[DomainEvent]) -> ServiceResult<()> {
+        let sequences = self.mongo_client.database.collection::<Document>(Self::SEQUENCES_COLLECTION);
+        let mut aggregates: Vec<Uuid> = events.iter().map(|event| event.aggregate_id).collect();
+        aggregates.sort();
+        aggregates.dedup();
+
+        let now = BsonDateTime::now();
+        let mut documents = Vec::new();
+        for aggregate_id in aggregates {
+            let staged: Vec<&DomainEvent> = events.iter().filter(|event| event.aggregate_id == aggregate_id).collect();
+            let filter = doc! { "_id": aggregate_id.to_string() };
+            let update = doc! { "$inc": { "last": staged.len() as i64 }, "$max": { "staged_at": now } };
+            let options = FindOneAndUpdateOptions::builder()
+                .upsert(true)
+                .return_document(ReturnDocument::After)
+                .build();
+            let span = mongo_span("findAndModify", Self::SEQUENCES_COLLECTION, Some(&filter));
+            let counter = sequences.find_one_and_update_with_session(filter, update, options, uow.session()?)
+                .instrument(span)
+                .await
+                .map_err(ServiceError::from)?
+                .ok_or_else(|| ServiceError::DataError(format!("No event sequence for aggregate {}", aggregate_id)))?;
+            let last = counter.get_i64("last")
+                .map_err(|e| ServiceError::DataError(e.to_string()))?;
+            let staged_at = *counter.get_datetime("staged_at")
+                .map_err(|e| ServiceError::DataError(e.to_string()))?;
+
+            for (sequence, event) in (last - staged.len() as i64 + 1..).zip(staged) {
+                let event_json = serde_json::to_string(event)
+                    .map_err(|e| ServiceError::DataError(format!("Cannot serialize event {}: {}", event.id, e)))?;
+                documents.push(doc! {
+                    "_id": event.id.to_string(),
+                    "aggregate_id": aggregate_id.to_string(),
+                    "aggregate_sequence": sequence,
+                    "staged_at": staged_at,
+                    "event": event_json,
+                });
+            }
+        }
+
+        let span = mongo_span("insert", Self::STAGED_COLLECTION, None);
+        self.staged().insert_many_with_session(documents, None, uow.session()?)
+            .instrument(span)
+            .await
+            .map_err(ServiceError::from)?;
+
+        Ok(())
+    }
+
+    // The oldest staged events, each aggregate's in the order they were staged. An aggregate
+    // cut off by `limit` continues with the next batch. Events staged before they were
+    // stamped sort first.
+    async fn staged_events(&self, limit: i64) -> ServiceResult<Vec<DomainEvent>> {
+        let options = FindOptions::builder()
+            .sort(doc! { "staged_at": 1, "aggregate_id": 1, "aggregate_sequence": 1 })
+            .limit(limit)
+            .build();
+        let span = mongo_span("find", Self::STAGED_COLLECTION, None);
+        let documents: Vec<Document> = async { self.staged().find(None, options).await?.try_collect().await }
+            .instrument(span)
+            .await
+            .map_err(ServiceError::from)?;
+
+        documents
+            .iter()
+            .map(|document| {
+                let event_json = document.get_str("event")
+                    .map_err(|e| ServiceError::DataError(e.to_string()))?;
+                serde_json::from_str(event_json)
+                    .map_err(|e| ServiceError::DataError(format!("Unreadable staged event: {}", e)))
+            })
+            .collect()
+    }
+
+    // Append staged events to the Postgres outbox in `uow`, and drop them from MongoDB once it
+    // commits. Events moved before, but not yet dropped, are skipped by the append.
+    async fn forward_in(&self, uow: &mut UnitOfWork, limit: i64) -> ServiceResult<()> {
+        let events = match self.staged_events(limit).await {
+            Ok(events) => events,
+            // Events recorded in Postgres still go out while MongoDB is unavailable
+            Err(e) => {
+                tracing::warn!("Cannot read events staged in MongoDB: {}", e);
+                return Ok(());
+            }
+        };
+        if events.is_empty() {
+            return Ok(());
+        }
+
+        self.outbox.append_in(uow, &events).await?;
+
+        let staged = self.staged();
+        let ids: Vec<String> = events.iter().map(|event| event.id.to_string()).collect();
+        uow.after_commit(async move {
+            let filter = doc! { "_id": { "$in": ids } };
+            let span = mongo_span("delete", Self::STAGED_COLLECTION, Some(&filter));
+            if let Err(e) = staged.delete_many(filter, None).instrument(span).await {
+                tracing::warn!("Failed to drop events moved to the outbox from MongoDB: {}", e);
+            }
+        });
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl OutboxStore for MongoOutboxRepository {
+    async fn append_in(&self, uow: &mut UnitOfWork, events: &[DomainEvent]) -> ServiceResult<()> {
+        if uow.is_mongo() {
+            self.stage_in(uow, events).await
+        } else {
+            self.outbox.append_in(uow, events).await
+        }
+    }
+
+    async fn claim_in(&self, uow: &mut UnitOfWork, limit: i64, now: DateTime<Utc>) -> ServiceResult<Vec<OutboxEntry>> {
+        self.forward_in(uow, limit).await?;
+        self.outbox.claim_in(uow, limit, now).await
+    }
+
+    async fn mark_published_in(&self, uow: &mut UnitOfWork, sequences: &[i64]) -> ServiceResult<()> {
+        self.outbox.mark_published_in(uow, sequences).await
+    }
+
+    async fn record_failure_in(
+        &self,
+        uow: &mut UnitOfWork,
+        sequence: i64,
+        error: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> ServiceResult<()> {
+        self.outbox.record_failure_in(uow, sequence, error, retry_at).await
+    }
+
+    async fn replay(&self, from_sequence: i64, aggregate_id: Option<Uuid>) -> ServiceResult<u64> {
+        self.outbox.replay(from_sequence, aggregate_id).await
+    }
+
+    async fn purge_published(&self, published_before: DateTime<Utc>) -> ServiceResult<u64> {
+        self.outbox.purge_published(published_before).await
+    }
+}
@@ -1,7 +1,12 @@
+use std::str::FromStr;
+use async_trait::async_trait;
 use mongodb::{Client, Database, options::ClientOptions};
+use mongodb::bson::{doc, Bson, Decimal128, Document};
 use crate::config::MongoConfig;
 use crate::errors::{ServiceError, ServiceResult};
+use crate::repositories::{Filter, FilterOp, FilterValue, HealthCheck, SortDirection, SortKey, TransactionManager, UnitOfWork};
 
+#[derive(Clone)]
 pub struct MongoClient {
     pub client: Client,
     pub database: Database,
@@ -11,21 +16,87 @@ impl MongoClient {
     pub async fn new(config: &MongoConfig) -> ServiceResult<Self> {
         let client_options = ClientOptions::parse(&config.uri)
             .await
-            .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
+            .map_err(ServiceError::from)?;
             
         let client = Client::with_options(client_options)
-            .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
+            .map_err(ServiceError::from)?;
             
         let database = client.database(&config.database);
         
         Ok(Self { client, database })
     }
-    
-    pub async fn health_check(&self) -> ServiceResult<bool> {
+}
+
+#[async_trait]
+impl HealthCheck for MongoClient {
+    async fn health_check(&self) -> ServiceResult<()> {
         self.database
-            .run_command(mongodb::bson::doc! { "ping": 1 }, None)
+            .run_command(doc! { "ping": 1 }, None)
             .await
-            .map(|_| true)
-            .map_err(|e| ServiceError::DatabaseError(e.to_string()))
+            .map(|_| ())
+            .map_err(ServiceError::from)
     }
+}
+
+// Units of work over MongoDB transactions, for writes that span collections
+#[async_trait]
+impl TransactionManager for MongoClient {
+    async fn begin(&self) -> ServiceResult<UnitOfWork> {
+        UnitOfWork::begin_mongo(&self.client).await
+    }
+}
+
+// Translate query filters into a Mongo filter document; `path` maps a field name to its document path
+pub fn filter_document(filters: &[Filter], path: impl Fn(&str) -> &str) -> ServiceResult<Document> {
+    let mut filter = Document::new();
+    
+    for f in filters {
+        let value = match &f.value {
+            FilterValue::Text(text) => Bson::String(text.clone()),
+            FilterValue::Number(number) => Bson::Decimal128(
+                Decimal128::from_str(&number.to_string())
+                    .map_err(|e| ServiceError::ValidationError(e.to_string()))?,
+            ),
+            FilterValue::Bool(flag) => Bson::Boolean(*flag),
+            FilterValue::Uuid(id) => Bson::String(id.to_string()),
+            // Timestamps are stored as unix seconds
+            FilterValue::Timestamp(at) => Bson::Int64(at.timestamp()),
+        };
+        let op = match f.op {
+            FilterOp::Eq => "$eq",
+            FilterOp::Ne => "$ne",
+            FilterOp::Gt => "$gt",
+            FilterOp::Gte => "$gte",
+            FilterOp::Lt => "$lt",
+            FilterOp::Lte => "$lte",
+        };
+        
+        let field_path = path(&f.field).to_string();
+        match filter.get_document_mut(&field_path) {
+            Ok(conditions) => {
+                conditions.insert(op, value);
+            }
+            Err(_) => {
+                filter.insert(field_path, doc! { op: value });
+            }
+        }
+    }
+    
+    Ok(filter)
+}
+
+// Sort document for the given keys, with `_id` as the final tie-breaker for stable paging
+pub fn sort_document(sort: &[SortKey], path: impl Fn(&str) -> &str) -> Document {
+    let mut document = Document::new();
+    
+    for key in sort {
+        let direction = match key.direction {
+            SortDirection::Asc => 1,
+            SortDirection::Desc => -1,
+        };
+        document.insert(path(&key.field), direction);
+    }
+    document.insert("_id", 1);
+    
+    document
 }
\ No newline at end of file
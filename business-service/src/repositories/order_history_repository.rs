@@ -0,0 +1,123 @@
+use async_trait::async_trait;
+use std::str::FromStr;
+use sqlx::postgres::PgRow;
+use sqlx::Row;
+use chrono::{DateTime, Utc};
+use tracing::Instrument;
+use uuid::Uuid;
+use crate::errors::{ServiceError, ServiceResult};
+use crate::models::order::{OrderStatus, OrderStatusChange};
+use crate::repositories::{PostgresClient, UnitOfWork};
+use crate::telemetry::sql_span;
+
+// Append-only log of order status changes. Entries are written in the same unit of
+// work as the status change itself, tagged with the saga action that made them if any.
+#[async_trait]
+pub trait OrderHistoryStore: Send + Sync {
+    async fn record_in(
+        &self,
+        uow: &mut UnitOfWork,
+        change: OrderStatusChange,
+        operation: Option<Uuid>,
+    ) -> ServiceResult<OrderStatusChange>;
+    async fn has_recorded_in(&self, uow: &mut UnitOfWork, order_id: Uuid, operation: Uuid) -> ServiceResult<bool>;
+    async fn find_by_order(&self, order_id: Uuid) -> ServiceResult<Vec<OrderStatusChange>>;
+}
+
+pub struct OrderHistoryRepository {
+    pg_client: PostgresClient,
+}
+
+impl OrderHistoryRepository {
+    pub fn new(pg_client: PostgresClient) -> Self {
+        Self { pg_client }
+    }
+
+    fn map_change(row: &PgRow) -> ServiceResult<OrderStatusChange> {
+        let order_id: Uuid = row.try_get("order_id")
+            .map_err(ServiceError::from)?;
+        let from: Option<String> = row.try_get("from_status")
+            .map_err(ServiceError::from)?;
+        let to: String = row.try_get("to_status")
+            .map_err(ServiceError::from)?;
+        let changed_at: DateTime<Utc> = row.try_get("changed_at")
+            .map_err(ServiceError::from)?;
+        let actor: Option<String> = row.try_get("actor")
+            .map_err(ServiceError::from)?;
+        let reason: Option<String> = row.try_get("reason")
+            .map_err(ServiceError::from)?;
+
+        let parse = |status: &str| OrderStatus::from_str(status)
+            .map_err(|e| ServiceError::DataError(e.to_string()));
+
+        Ok(OrderStatusChange {
+            order_id,
+            from: from.as_deref().map(parse).transpose()?,
+            to: parse(&to)?,
+            changed_at,
+            actor,
+            reason,
+        })
+    }
+}
+
+#[async_trait]
+impl OrderHistoryStore for OrderHistoryRepository {
+    async fn record_in(
+        &self,
+        uow: &mut UnitOfWork,
+        change: OrderStatusChange,
+        operation: Option<Uuid>,
+    ) -> ServiceResult<OrderStatusChange> {
+        let statement = r#"
+            INSERT INTO order_status_history (order_id, from_status, to_status, actor, reason, changed_at, operation_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#;
+        sqlx::query(statement)
+            .bind(change.order_id)
+            .bind(change.from.map(|status| status.as_str()))
+            .bind(change.to.as_str())
+            .bind(&change.actor)
+            .bind(&change.reason)
+            .bind(change.changed_at)
+            .bind(operation)
+            .execute(uow.connection()?)
+            .instrument(sql_span(statement))
+            .await
+            .map_err(ServiceError::from)?;
+
+        Ok(change)
+    }
+
+    async fn has_recorded_in(&self, uow: &mut UnitOfWork, order_id: Uuid, operation: Uuid) -> ServiceResult<bool> {
+        let statement = r#"
+            SELECT EXISTS (
+                SELECT 1 FROM order_status_history WHERE order_id = $1 AND operation_id = $2
+            )
+            "#;
+        sqlx::query_scalar(statement)
+            .bind(order_id)
+            .bind(operation)
+            .fetch_one(uow.connection()?)
+            .instrument(sql_span(statement))
+            .await
+            .map_err(ServiceError::from)
+    }
+
+    async fn find_by_order(&self, order_id: Uuid) -> ServiceResult<Vec<OrderStatusChange>> {
+        let statement = r#"
+            SELECT order_id, from_status, to_status, actor, reason, changed_at
+            FROM order_status_history
+            WHERE order_id = $1
+            ORDER BY id ASC
+            "#;
+        let rows = sqlx::query(statement)
+            .bind(order_id)
+            .fetch_all(&self.pg_client.pool)
+            .instrument(sql_span(statement))
+            .await
+            .map_err(ServiceError::from)?;
+
+        rows.iter().map(Self::map_change).collect()
+    }
+}
@@ -1,280 +1,327 @@
 use async_trait::async_trait;
-use sqlx::Row;
+use std::collections::HashMap;
+use std::str::FromStr;
+use rust_decimal::Decimal;
+use sqlx::postgres::{PgConnection, PgRow};
+use sqlx::{QueryBuilder, Row};
 use chrono::{DateTime, Utc};
+use tracing::Instrument;
 use uuid::Uuid;
 use crate::errors::{ServiceError, ServiceResult};
+use crate::models::money::{Currency, Money};
 use crate::models::order::{Order, OrderItem, OrderStatus};
-use crate::repositories::{Repository, PostgresClient};
+use crate::repositories::{
+    push_filters, push_order_by, FieldKind, FilterValue, Page, PostgresClient, QuerySpec, Queryable, Repository,
+    TransactionManager, TransactionalRepository, UnitOfWork,
+};
+use crate::telemetry::sql_span;
+
+impl Queryable for Order {
+    const FIELDS: &'static [(&'static str, FieldKind)] = &[
+        ("customer_id", FieldKind::Uuid),
+        ("status", FieldKind::Text),
+        ("total", FieldKind::Number),
+        ("currency", FieldKind::Text),
+        ("created_at", FieldKind::Timestamp),
+        ("updated_at", FieldKind::Timestamp),
+    ];
+
+    fn field_value(&self, field: &str) -> Option<FilterValue> {
+        match field {
+            "customer_id" => Some(FilterValue::Uuid(self.customer_id)),
+            "status" => Some(FilterValue::Text(self.status.as_str().to_string())),
+            "total" => Some(FilterValue::Number(self.total.amount)),
+            "currency" => Some(FilterValue::Text(self.total.currency.code().to_string())),
+            "created_at" => Some(FilterValue::Timestamp(self.created_at)),
+            "updated_at" => Some(FilterValue::Timestamp(self.updated_at)),
+            _ => None,
+        }
+    }
+}
 
 pub struct OrderRepository {
     pg_client: PostgresClient,
 }
 
 impl OrderRepository {
+    const ORDER_COLUMNS: &'static str = "id, customer_id, total, currency, status, created_at, updated_at";
+
     pub fn new(pg_client: PostgresClient) -> Self {
         Self { pg_client }
     }
 
-    async fn setup_tables(&self) -> ServiceResult<()> {
-        // Create orders table
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS orders (
-                id UUID PRIMARY KEY,
-                customer_id UUID NOT NULL,
-                total DECIMAL(10, 2) NOT NULL,
-                status VARCHAR(20) NOT NULL,
-                created_at TIMESTAMP WITH TIME ZONE NOT NULL,
-                updated_at TIMESTAMP WITH TIME ZONE NOT NULL
-            )
-            "#
-        )
-        .execute(&self.pg_client.pool)
-        .await
-        .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
-
-        // Create order_items table
-        sqlx::query(
+    // SQL column of a queryable order field
+    fn column(field: &str) -> Option<&'static str> {
+        match field {
+            "customer_id" => Some("customer_id"),
+            "status" => Some("status"),
+            "total" => Some("total"),
+            "currency" => Some("currency"),
+            "created_at" => Some("created_at"),
+            "updated_at" => Some("updated_at"),
+            _ => None,
+        }
+    }
+
+    // Stored statuses are parsed strictly; an unknown value means the row is corrupt
+    fn status_from_row(row: &PgRow, column: &str) -> ServiceResult<OrderStatus> {
+        let status: String = row.try_get(column)
+            .map_err(ServiceError::from)?;
+
+        OrderStatus::from_str(&status)
+            .map_err(|e| ServiceError::DataError(e.to_string()))
+    }
+
+    fn money_from_row(row: &PgRow, amount_column: &str, currency_column: &str) -> ServiceResult<Money> {
+        let amount: Decimal = row.try_get(amount_column)
+            .map_err(ServiceError::from)?;
+        let currency: String = row.try_get(currency_column)
+            .map_err(ServiceError::from)?;
+        let currency = Currency::from_str(&currency)
+            .map_err(|e| ServiceError::DataError(e.to_string()))?;
+
+        Ok(Money::new(amount, currency))
+    }
+
+    // Fetch one order with its items on the given connection; `for_update` locks the
+    // order row until the surrounding transaction ends
+    async fn fetch_order(conn: &mut PgConnection, id: Uuid, for_update: bool) -> ServiceResult<Option<Order>> {
+        let lock = if for_update { "FOR UPDATE" } else { "" };
+        let query = format!(
             r#"
-            CREATE TABLE IF NOT EXISTS order_items (
-                id UUID PRIMARY KEY,
-                order_id UUID NOT NULL REFERENCES orders(id) ON DELETE CASCADE,
-                product_id UUID NOT NULL,
-                quantity INTEGER NOT NULL,
-                price DECIMAL(10, 2) NOT NULL
-            )
-            "#
-        )
-        .execute(&self.pg_client.pool)
-        .await
-        .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
+            SELECT {}
+            FROM orders
+            WHERE id = $1
+            {}
+            "#,
+            Self::ORDER_COLUMNS,
+            lock
+        );
+        let rows = sqlx::query(&query)
+            .bind(id)
+            .fetch_all(&mut *conn)
+            .instrument(sql_span(&query))
+            .await
+            .map_err(ServiceError::from)?;
 
-        Ok(())
+        Ok(Self::load_orders(conn, rows).await?.into_iter().next())
+    }
+
+    // Attach line items to a batch of order rows using a single extra query
+    async fn load_orders(conn: &mut PgConnection, rows: Vec<PgRow>) -> ServiceResult<Vec<Order>> {
+        let order_ids = rows
+            .iter()
+            .map(|row| row.try_get::<Uuid, _>("id"))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(ServiceError::from)?;
+
+        let mut items = Self::fetch_items(conn, &order_ids).await?;
+
+        rows.iter()
+            .map(|row| {
+                let order_id: Uuid = row.try_get("id")
+                    .map_err(ServiceError::from)?;
+                Self::map_order(row, items.remove(&order_id).unwrap_or_default())
+            })
+            .collect()
     }
-    
-    // Helper method to convert status string to enum
-    fn status_from_str(status: &str) -> OrderStatus {
-        match status {
-            "pending" => OrderStatus::Pending,
-            "processing" => OrderStatus::Processing,
-            "shipped" => OrderStatus::Shipped,
-            "delivered" => OrderStatus::Delivered,
-            "cancelled" => OrderStatus::Cancelled,
-            _ => OrderStatus::Pending,
+
+    async fn fetch_items(conn: &mut PgConnection, order_ids: &[Uuid]) -> ServiceResult<HashMap<Uuid, Vec<OrderItem>>> {
+        let mut items: HashMap<Uuid, Vec<OrderItem>> = HashMap::new();
+        if order_ids.is_empty() {
+            return Ok(items);
         }
+
+        let statement = r#"
+            SELECT order_id, product_id, product_name, product_sku, quantity, price, currency
+            FROM order_items
+            WHERE order_id = ANY($1)
+            ORDER BY order_id, position
+            "#;
+        let rows = sqlx::query(statement)
+            .bind(order_ids)
+            .fetch_all(conn)
+            .instrument(sql_span(statement))
+            .await
+            .map_err(ServiceError::from)?;
+
+        for row in rows {
+            let order_id: Uuid = row.try_get("order_id")
+                .map_err(ServiceError::from)?;
+            let product_id: Uuid = row.try_get("product_id")
+                .map_err(ServiceError::from)?;
+            let name: String = row.try_get("product_name")
+                .map_err(ServiceError::from)?;
+            let sku: String = row.try_get("product_sku")
+                .map_err(ServiceError::from)?;
+            let quantity: i32 = row.try_get("quantity")
+                .map_err(ServiceError::from)?;
+            let price = Self::money_from_row(&row, "price", "currency")?;
+
+            items.entry(order_id).or_default().push(OrderItem {
+                product_id,
+                name,
+                sku,
+                quantity,
+                price,
+            });
+        }
+
+        Ok(items)
     }
-    
-    // Helper method to convert status enum to string
-    fn status_to_str(status: &OrderStatus) -> &'static str {
-        match status {
-            OrderStatus::Pending => "pending",
-            OrderStatus::Processing => "processing",
-            OrderStatus::Shipped => "shipped",
-            OrderStatus::Delivered => "delivered",
-            OrderStatus::Cancelled => "cancelled",
+
+    // The single row-to-order mapping shared by every read path
+    fn map_order(row: &PgRow, items: Vec<OrderItem>) -> ServiceResult<Order> {
+        let order_id: Uuid = row.try_get("id")
+            .map_err(ServiceError::from)?;
+        let customer_id: Uuid = row.try_get("customer_id")
+            .map_err(ServiceError::from)?;
+        let total = Self::money_from_row(row, "total", "currency")?;
+        let status = Self::status_from_row(row, "status")?;
+        let created_at: DateTime<Utc> = row.try_get("created_at")
+            .map_err(ServiceError::from)?;
+        let updated_at: DateTime<Utc> = row.try_get("updated_at")
+            .map_err(ServiceError::from)?;
+
+        Ok(Order {
+            id: Some(order_id),
+            customer_id,
+            items,
+            total,
+            status,
+            created_at,
+            updated_at,
+        })
+    }
+
+    async fn insert_items(conn: &mut PgConnection, order_id: Uuid, items: &[OrderItem]) -> ServiceResult<()> {
+        for (position, item_data) in items.iter().enumerate() {
+            let item_id = Uuid::new_v4();
+            let statement = r#"
+                INSERT INTO order_items (id, order_id, product_id, product_name, product_sku, quantity, price, currency, position)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                "#;
+            sqlx::query(statement)
+                .bind(item_id)
+                .bind(order_id)
+                .bind(item_data.product_id)
+                .bind(&item_data.name)
+                .bind(&item_data.sku)
+                .bind(item_data.quantity)
+                .bind(item_data.price.amount)
+                .bind(item_data.price.currency.code())
+                .bind(position as i32)
+                .execute(&mut *conn)
+                .instrument(sql_span(statement))
+                .await
+                .map_err(ServiceError::from)?;
         }
+
+        Ok(())
     }
 }
 
 #[async_trait]
 impl Repository<Order, Uuid> for OrderRepository {
     async fn find_by_id(&self, id: Uuid) -> ServiceResult<Option<Order>> {
-        // First fetch the order
-        let order = sqlx::query(
-            r#"
-            SELECT id, customer_id, total, status, created_at, updated_at
-            FROM orders
-            WHERE id = $1
-            "#
-        )
-        .bind(id)
-        .fetch_optional(&self.pg_client.pool)
-        .await
-        .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
-
-        if let Some(order_row) = order {
-            // Extract order fields
-            let order_id: Uuid = order_row.try_get("id")
-                .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
-            let customer_id: Uuid = order_row.try_get("customer_id")
-                .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
-            let total: f64 = order_row.try_get::<f64, _>("total")
-                .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
-            let status: String = order_row.try_get("status")
-                .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
-            let created_at: DateTime<Utc> = order_row.try_get("created_at")
-                .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
-            let updated_at: DateTime<Utc> = order_row.try_get("updated_at")
-                .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
-
-            // Then fetch its items
-            let items = sqlx::query(
-                r#"
-                SELECT product_id, quantity, price
-                FROM order_items
-                WHERE order_id = $1
-                "#
-            )
-            .bind(id)
-            .fetch_all(&self.pg_client.pool)
-            .await
-            .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
-
-            let order_items: Vec<OrderItem> = items
-                .into_iter()
-                .map(|item| -> Result<OrderItem, ServiceError> {
-                    let product_id: Uuid = item.try_get("product_id")
-                        .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
-                    let quantity: i32 = item.try_get("quantity")
-                        .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
-                    let price: f64 = item.try_get::<f64, _>("price")
-                        .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
-                    
-                    Ok(OrderItem {
-                        product_id,
-                        quantity,
-                        price,
-                    })
-                })
-                .collect::<Result<Vec<_>, _>>()?;
-
-            let order = Order {
-                id: Some(order_id),
-                customer_id,
-                items: order_items,
-                total,
-                status: Self::status_from_str(&status),
-                created_at,
-                updated_at,
-            };
-
-            Ok(Some(order))
-        } else {
-            Ok(None)
-        }
+        let mut conn = self.pg_client.pool.acquire().await
+            .map_err(ServiceError::from)?;
+
+        Self::fetch_order(&mut conn, id, false).await
     }
 
-    async fn find_all(&self) -> ServiceResult<Vec<Order>> {
-        // Fetch all orders
-        let orders = sqlx::query(
-            r#"
-            SELECT id, customer_id, total, status, created_at, updated_at
-            FROM orders
-            ORDER BY created_at DESC
-            "#
-        )
-        .fetch_all(&self.pg_client.pool)
-        .await
-        .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
-
-        let mut result = Vec::with_capacity(orders.len());
-
-        // For each order, fetch its items
-        for order_row in orders {
-            let order_id: Uuid = order_row.try_get("id")
-                .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
-            let customer_id: Uuid = order_row.try_get("customer_id")
-                .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
-            let total: f64 = order_row.try_get::<f64, _>("total")
-                .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
-            let status: String = order_row.try_get("status")
-                .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
-            let created_at: DateTime<Utc> = order_row.try_get("created_at")
-                .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
-            let updated_at: DateTime<Utc> = order_row.try_get("updated_at")
-                .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
-
-            let items = sqlx::query(
-                r#"
-                SELECT product_id, quantity, price
-                FROM order_items
-                WHERE order_id = $1
-                "#
-            )
-            .bind(order_id)
-            .fetch_all(&self.pg_client.pool)
+    async fn find_all(&self, query: &QuerySpec) -> ServiceResult<Page<Order>> {
+        let mut conn = self.pg_client.pool.acquire().await
+            .map_err(ServiceError::from)?;
+
+        // Count every matching order, then fetch the requested page
+        let mut count = QueryBuilder::new("SELECT COUNT(*) FROM orders WHERE TRUE");
+        push_filters(&mut count, &query.filters, Self::column)?;
+        let span = sql_span(count.sql());
+        let total: i64 = count
+            .build_query_scalar()
+            .fetch_one(&mut *conn)
+            .instrument(span)
             .await
-            .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
-
-            let order_items: Vec<OrderItem> = items
-                .into_iter()
-                .map(|item| -> Result<OrderItem, ServiceError> {
-                    let product_id: Uuid = item.try_get("product_id")
-                        .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
-                    let quantity: i32 = item.try_get("quantity")
-                        .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
-                    let price: f64 = item.try_get::<f64, _>("price")
-                        .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
-                    
-                    Ok(OrderItem {
-                        product_id,
-                        quantity,
-                        price,
-                    })
-                })
-                .collect::<Result<Vec<_>, _>>()?;
-
-            let order = Order {
-                id: Some(order_id),
-                customer_id,
-                items: order_items,
-                total,
-                status: Self::status_from_str(&status),
-                created_at,
-                updated_at,
-            };
-
-            result.push(order);
-        }
-
-        Ok(result)
+            .map_err(ServiceError::from)?;
+
+        let mut select = QueryBuilder::new(format!("SELECT {} FROM orders WHERE TRUE", Self::ORDER_COLUMNS));
+        push_filters(&mut select, &query.filters, Self::column)?;
+        push_order_by(&mut select, &query.sort, Self::column, "created_at DESC")?;
+        select
+            .push(" LIMIT ")
+            .push_bind(query.limit as i64)
+            .push(" OFFSET ")
+            .push_bind(query.offset as i64);
+
+        let span = sql_span(select.sql());
+        let rows = select
+            .build()
+            .fetch_all(&mut *conn)
+            .instrument(span)
+            .await
+            .map_err(ServiceError::from)?;
+
+        Ok(Page {
+            items: Self::load_orders(&mut conn, rows).await?,
+            total: total as u64,
+            limit: query.limit,
+            offset: query.offset,
+        })
     }
 
     async fn create(&self, item: Order) -> ServiceResult<Order> {
-        let transaction = self.pg_client.pool.begin().await
-            .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
+        let mut uow = self.pg_client.begin().await?;
+        let created = self.create_in(&mut uow, item).await?;
+        uow.commit().await?;
 
+        Ok(created)
+    }
+
+    async fn update(&self, id: Uuid, item: Order) -> ServiceResult<Order> {
+        let mut uow = self.pg_client.begin().await?;
+        let updated = self.update_in(&mut uow, id, item).await?;
+        uow.commit().await?;
+
+        Ok(updated)
+    }
+
+    async fn delete(&self, id: Uuid) -> ServiceResult<()> {
+        let mut uow = self.pg_client.begin().await?;
+        self.delete_in(&mut uow, id).await?;
+        uow.commit().await
+    }
+}
+
+#[async_trait]
+impl TransactionalRepository<Order, Uuid> for OrderRepository {
+    async fn find_by_id_in(&self, uow: &mut UnitOfWork, id: Uuid) -> ServiceResult<Option<Order>> {
+        Self::fetch_order(uow.connection()?, id, true).await
+    }
+
+    async fn create_in(&self, uow: &mut UnitOfWork, item: Order) -> ServiceResult<Order> {
         let id = item.id.unwrap_or_else(Uuid::new_v4);
         
         // Insert the order
-        sqlx::query(
-            r#"
-            INSERT INTO orders (id, customer_id, total, status, created_at, updated_at)
-            VALUES ($1, $2, $3, $4, $5, $6)
-            "#
-        )
-        .bind(id)
-        .bind(item.customer_id)
-        .bind(item.total)
-        .bind(Self::status_to_str(&item.status))
-        .bind(item.created_at)
-        .bind(item.updated_at)
-        .execute(&self.pg_client.pool)
-        .await
-        .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
-
-        // Insert all order items
-        for item_data in &item.items {
-            let item_id = Uuid::new_v4();
-            sqlx::query(
-                r#"
-                INSERT INTO order_items (id, order_id, product_id, quantity, price)
-                VALUES ($1, $2, $3, $4, $5)
-                "#
-            )
-            .bind(item_id)
+        let statement = r#"
+            INSERT INTO orders (id, customer_id, total, currency, status, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#;
+        sqlx::query(statement)
             .bind(id)
-            .bind(item_data.product_id)
-            .bind(item_data.quantity)
-            .bind(item_data.price)
-            .execute(&self.pg_client.pool)
+            .bind(item.customer_id)
+            .bind(item.total.amount)
+            .bind(item.total.currency.code())
+            .bind(item.status.as_str())
+            .bind(item.created_at)
+            .bind(item.updated_at)
+            .execute(uow.connection()?)
+            .instrument(sql_span(statement))
             .await
-            .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
-        }
+            .map_err(ServiceError::from)?;
 
-        // Commit the transaction
-        transaction.commit().await
-            .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
+        // Insert all order items
+        Self::insert_items(uow.connection()?, id, &item.items).await?;
 
         let mut created_item = item;
         created_item.id = Some(id);
@@ -282,66 +329,41 @@ impl Repository<Order, Uuid> for OrderRepository {
         Ok(created_item)
     }
 
-    async fn update(&self, id: Uuid, item: Order) -> ServiceResult<Order> {
-        // Check if order exists
-        let existing = self.find_by_id(id).await?;
-        if existing.is_none() {
-            return Err(ServiceError::NotFoundError(format!("Order with ID {} not found", id)));
-        }
-
-        let transaction = self.pg_client.pool.begin().await
-            .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
-
+    async fn update_in(&self, uow: &mut UnitOfWork, id: Uuid, item: Order) -> ServiceResult<Order> {
         // Update the order
-        sqlx::query(
-            r#"
+        let statement = r#"
             UPDATE orders
-            SET total = $1, status = $2, updated_at = $3
-            WHERE id = $4
-            "#
-        )
-        .bind(item.total)
-        .bind(Self::status_to_str(&item.status))
-        .bind(item.updated_at)
-        .bind(id)
-        .execute(&self.pg_client.pool)
-        .await
-        .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
+            SET total = $1, currency = $2, status = $3, updated_at = $4
+            WHERE id = $5
+            "#;
+        let result = sqlx::query(statement)
+            .bind(item.total.amount)
+            .bind(item.total.currency.code())
+            .bind(item.status.as_str())
+            .bind(item.updated_at)
+            .bind(id)
+            .execute(uow.connection()?)
+            .instrument(sql_span(statement))
+            .await
+            .map_err(ServiceError::from)?;
+
+        if result.rows_affected() == 0 {
+            return Err(ServiceError::NotFoundError(format!("Order with ID {} not found", id)));
+        }
 
         // Delete existing items and insert new ones
-        sqlx::query(
-            r#"
+        let statement = r#"
             DELETE FROM order_items
             WHERE order_id = $1
-            "#
-        )
-        .bind(id)
-        .execute(&self.pg_client.pool)
-        .await
-        .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
-
-        // Insert all order items
-        for item_data in &item.items {
-            let item_id = Uuid::new_v4();
-            sqlx::query(
-                r#"
-                INSERT INTO order_items (id, order_id, product_id, quantity, price)
-                VALUES ($1, $2, $3, $4, $5)
-                "#
-            )
-            .bind(item_id)
+            "#;
+        sqlx::query(statement)
             .bind(id)
-            .bind(item_data.product_id)
-            .bind(item_data.quantity)
-            .bind(item_data.price)
-            .execute(&self.pg_client.pool)
+            .execute(uow.connection()?)
+            .instrument(sql_span(statement))
             .await
-            .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
-        }
+            .map_err(ServiceError::from)?;
 
-        // Commit the transaction
-        transaction.commit().await
-            .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
+        Self::insert_items(uow.connection()?, id, &item.items).await?;
 
         let mut updated_item = item;
         updated_item.id = Some(id);
@@ -349,24 +371,22 @@ impl Repository<Order, Uuid> for OrderRepository {
         Ok(updated_item)
     }
 
-    async fn delete(&self, id: Uuid) -> ServiceResult<()> {
-        // Check if order exists
-        let existing = self.find_by_id(id).await?;
-        if existing.is_none() {
-            return Err(ServiceError::NotFoundError(format!("Order with ID {} not found", id)));
-        }
-
+    async fn delete_in(&self, uow: &mut UnitOfWork, id: Uuid) -> ServiceResult<()> {
         // The items will be deleted automatically due to ON DELETE CASCADE
-        sqlx::query(
-            r#"
+        let statement = r#"
             DELETE FROM orders
             WHERE id = $1
-            "#
-        )
-        .bind(id)
-        .execute(&self.pg_client.pool)
-        .await
-        .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
+            "#;
+        let result = sqlx::query(statement)
+            .bind(id)
+            .execute(uow.connection()?)
+            .instrument(sql_span(statement))
+            .await
+            .map_err(ServiceError::from)?;
+
+        if result.rows_affected() == 0 {
+            return Err(ServiceError::NotFoundError(format!("Order with ID {} not found", id)));
+        }
 
         Ok(())
     }
@@ -0,0 +1,236 @@
+use async_trait::async_trait;
+use std::str::FromStr;
+use sqlx::postgres::PgRow;
+use sqlx::Row;
+use chrono::{DateTime, Utc};
+use tracing::Instrument;
+use uuid::Uuid;
+use crate::errors::{ServiceError, ServiceResult};
+use crate::models::event::{DomainEvent, EventType, OutboxEntry};
+use crate::repositories::{PostgresClient, UnitOfWork};
+use crate::telemetry::sql_span;
+
+// Domain events waiting to be published. Events are appended in the unit of work that makes
+// the change they describe, so they are recorded if and only if the change commits.
+#[async_trait]
+pub trait OutboxStore: Send + Sync {
+    // Append events in order, skipping any already in the outbox. Concurrent appends for the
+    // same aggregate are serialized until the unit of work ends, so each aggregate's sequences
+    // follow commit order.
+    async fn append_in(&self, uow: &mut UnitOfWork, events: &[DomainEvent]) -> ServiceResult<()>;
+    // The oldest pending events, in sequence order, leaving out dead-lettered events and, for each
+    // event waiting to be retried after `now`, the rest of its aggregate's events. Returns nothing
+    // while another relay holds the claim, so only one relay publishes at a time.
+    async fn claim_in(&self, uow: &mut UnitOfWork, limit: i64, now: DateTime<Utc>) -> ServiceResult<Vec<OutboxEntry>>;
+    async fn mark_published_in(&self, uow: &mut UnitOfWork, sequences: &[i64]) -> ServiceResult<()>;
+    // Count a failed attempt to publish an event. It is retried from `retry_at` on, or never
+    // again when that is `None`: the event is dead-lettered until replayed.
+    async fn record_failure_in(
+        &self,
+        uow: &mut UnitOfWork,
+        sequence: i64,
+        error: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> ServiceResult<()>;
+    // Mark published and dead-lettered events from `from_sequence` on as pending again, with
+    // no failed attempts, optionally only those of one aggregate. Returns how many were marked.
+    async fn replay(&self, from_sequence: i64, aggregate_id: Option<Uuid>) -> ServiceResult<u64>;
+    async fn purge_published(&self, published_before: DateTime<Utc>) -> ServiceResult<u64>;
+}
+
+pub struct OutboxRepository {
+    pg_client: PostgresClient,
+}
+
+impl OutboxRepository {
+    pub fn new(pg_client: PostgresClient) -> Self {
+        Self { pg_client }
+    }
+
+    fn map_entry(row: &PgRow) -> ServiceResult<OutboxEntry> {
+        let sequence: i64 = row.try_get("sequence")
+            .map_err(ServiceError::from)?;
+        let id: Uuid = row.try_get("id")
+            .map_err(ServiceError::from)?;
+        let event_type: String = row.try_get("event_type")
+            .map_err(ServiceError::from)?;
+        let aggregate_id: Uuid = row.try_get("aggregate_id")
+            .map_err(ServiceError::from)?;
+        let payload: serde_json::Value = row.try_get("payload")
+            .map_err(ServiceError::from)?;
+        let occurred_at: DateTime<Utc> = row.try_get("occurred_at")
+            .map_err(ServiceError::from)?;
+        let attempts: i32 = row.try_get("attempts")
+            .map_err(ServiceError::from)?;
+
+        Ok(OutboxEntry {
+            sequence,
+            event: DomainEvent {
+                id,
+                event_type: EventType::from_str(&event_type)
+                    .map_err(|e| ServiceError::DataError(e.to_string()))?,
+                aggregate_id,
+                occurred_at,
+                payload,
+            },
+            attempts,
+        })
+    }
+}
+
+#[async_trait]
+impl OutboxStore for OutboxRepository {
+    async fn append_in(&self, uow: &mut UnitOfWork, events: &[DomainEvent]) -> ServiceResult<()> {
+        // Lock aggregates in a fixed order so two units of work cannot wait on each other
+        let mut aggregates: Vec<Uuid> = events.iter().map(|event| event.aggregate_id).collect();
+        aggregates.sort();
+        aggregates.dedup();
+
+        let statement = "SELECT pg_advisory_xact_lock(hashtextextended($1, 0))";
+        for aggregate_id in aggregates {
+            sqlx::query(statement)
+                .bind(aggregate_id.to_string())
+                .execute(uow.connection()?)
+                .instrument(sql_span(statement))
+                .await
+                .map_err(ServiceError::from)?;
+        }
+
+        let statement = r#"
+            INSERT INTO outbox_events (id, event_type, aggregate_type, aggregate_id, payload, occurred_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (id) DO NOTHING
+            "#;
+        for event in events {
+            sqlx::query(statement)
+                .bind(event.id)
+                .bind(event.event_type.as_str())
+                .bind(event.event_type.aggregate_type())
+                .bind(event.aggregate_id)
+                .bind(&event.payload)
+                .bind(event.occurred_at)
+                .execute(uow.connection()?)
+                .instrument(sql_span(statement))
+                .await
+                .map_err(ServiceError::from)?;
+        }
+
+        Ok(())
+    }
+
+    async fn claim_in(&self, uow: &mut UnitOfWork, limit: i64, now: DateTime<Utc>) -> ServiceResult<Vec<OutboxEntry>> {
+        let statement = "SELECT pg_try_advisory_xact_lock(hashtextextended('outbox_relay', 0)) AS claimed";
+        let claimed: bool = sqlx::query(statement)
+            .fetch_one(uow.connection()?)
+            .instrument(sql_span(statement))
+            .await
+            .and_then(|row| row.try_get("claimed"))
+            .map_err(ServiceError::from)?;
+
+        if !claimed {
+            return Ok(Vec::new());
+        }
+
+        let statement = r#"
+            SELECT sequence, id, event_type, aggregate_id, payload, occurred_at, attempts
+            FROM outbox_events event
+            WHERE published_at IS NULL
+            AND dead_lettered_at IS NULL
+            AND NOT EXISTS (
+                SELECT 1 FROM outbox_events waiting
+                WHERE waiting.aggregate_id = event.aggregate_id
+                AND waiting.sequence <= event.sequence
+                AND waiting.published_at IS NULL
+                AND waiting.dead_lettered_at IS NULL
+                AND waiting.retry_at > $2
+            )
+            ORDER BY sequence ASC
+            LIMIT $1
+            "#;
+        let rows = sqlx::query(statement)
+            .bind(limit)
+            .bind(now)
+            .fetch_all(uow.connection()?)
+            .instrument(sql_span(statement))
+            .await
+            .map_err(ServiceError::from)?;
+
+        rows.iter().map(Self::map_entry).collect()
+    }
+
+    async fn mark_published_in(&self, uow: &mut UnitOfWork, sequences: &[i64]) -> ServiceResult<()> {
+        let statement = r#"
+            UPDATE outbox_events
+            SET published_at = $2
+            WHERE sequence = ANY($1)
+            "#;
+        sqlx::query(statement)
+            .bind(sequences)
+            .bind(Utc::now())
+            .execute(uow.connection()?)
+            .instrument(sql_span(statement))
+            .await
+            .map_err(ServiceError::from)?;
+
+        Ok(())
+    }
+
+    async fn record_failure_in(
+        &self,
+        uow: &mut UnitOfWork,
+        sequence: i64,
+        error: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> ServiceResult<()> {
+        let statement = r#"
+            UPDATE outbox_events
+            SET attempts = attempts + 1,
+                last_error = $2,
+                retry_at = $3,
+                dead_lettered_at = CASE WHEN $3::timestamptz IS NULL THEN $4 END
+            WHERE sequence = $1
+            "#;
+        sqlx::query(statement)
+            .bind(sequence)
+            .bind(error)
+            .bind(retry_at)
+            .bind(Utc::now())
+            .execute(uow.connection()?)
+            .instrument(sql_span(statement))
+            .await
+            .map_err(ServiceError::from)?;
+
+        Ok(())
+    }
+
+    async fn replay(&self, from_sequence: i64, aggregate_id: Option<Uuid>) -> ServiceResult<u64> {
+        let statement = r#"
+            UPDATE outbox_events
+            SET published_at = NULL, dead_lettered_at = NULL, attempts = 0, last_error = NULL, retry_at = NULL
+            WHERE sequence >= $1
+            AND (published_at IS NOT NULL OR dead_lettered_at IS NOT NULL)
+            AND ($2::uuid IS NULL OR aggregate_id = $2)
+            "#;
+        let result = sqlx::query(statement)
+            .bind(from_sequence)
+            .bind(aggregate_id)
+            .execute(&self.pg_client.pool)
+            .instrument(sql_span(statement))
+            .await
+            .map_err(ServiceError::from)?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn purge_published(&self, published_before: DateTime<Utc>) -> ServiceResult<u64> {
+        let statement = "DELETE FROM outbox_events WHERE published_at < $1";
+        let result = sqlx::query(statement)
+            .bind(published_before)
+            .execute(&self.pg_client.pool)
+            .instrument(sql_span(statement))
+            .await
+            .map_err(ServiceError::from)?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -1,7 +1,11 @@
-use sqlx::postgres::{PgPool, PgPoolOptions};
+use async_trait::async_trait;
+use sqlx::postgres::{PgPool, PgPoolOptions, Postgres};
+use sqlx::QueryBuilder;
 use crate::config::PostgresConfig;
 use crate::errors::{ServiceError, ServiceResult};
+use crate::repositories::{Filter, FilterOp, FilterValue, HealthCheck, SortDirection, SortKey, TransactionManager, UnitOfWork};
 
+#[derive(Clone)]
 pub struct PostgresClient {
     pub pool: PgPool,
 }
@@ -12,16 +16,83 @@ impl PostgresClient {
             .max_connections(config.max_connections)
             .connect(&config.connection_string())
             .await
-            .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
+            .map_err(ServiceError::from)?;
             
         Ok(Self { pool })
     }
-    
-    pub async fn health_check(&self) -> ServiceResult<bool> {
+}
+
+#[async_trait]
+impl HealthCheck for PostgresClient {
+    async fn health_check(&self) -> ServiceResult<()> {
         sqlx::query("SELECT 1")
             .fetch_one(&self.pool)
             .await
-            .map(|_| true)
-            .map_err(|e| ServiceError::DatabaseError(e.to_string()))
+            .map(|_| ())
+            .map_err(ServiceError::from)
+    }
+}
+
+#[async_trait]
+impl TransactionManager for PostgresClient {
+    async fn begin(&self) -> ServiceResult<UnitOfWork> {
+        UnitOfWork::begin(&self.pool).await
+    }
+}
+
+// Append `AND column op $n` for each filter; `column` maps a field name to its SQL column
+pub fn push_filters(
+    builder: &mut QueryBuilder<'_, Postgres>,
+    filters: &[Filter],
+    column: impl Fn(&str) -> Option<&'static str>,
+) -> ServiceResult<()> {
+    for f in filters {
+        let column = column(&f.field)
+            .ok_or_else(|| ServiceError::BadRequestError(format!("Cannot filter by {}", f.field)))?;
+        let op = match f.op {
+            FilterOp::Eq => " = ",
+            FilterOp::Ne => " <> ",
+            FilterOp::Gt => " > ",
+            FilterOp::Gte => " >= ",
+            FilterOp::Lt => " < ",
+            FilterOp::Lte => " <= ",
+        };
+        
+        builder.push(" AND ").push(column).push(op);
+        match &f.value {
+            FilterValue::Text(text) => builder.push_bind(text.clone()),
+            FilterValue::Number(number) => builder.push_bind(*number),
+            FilterValue::Bool(flag) => builder.push_bind(*flag),
+            FilterValue::Uuid(id) => builder.push_bind(*id),
+            FilterValue::Timestamp(at) => builder.push_bind(*at),
+        };
+    }
+    
+    Ok(())
+}
+
+// Append an ORDER BY clause, falling back to `default` and always ending with `id` for stable paging
+pub fn push_order_by(
+    builder: &mut QueryBuilder<'_, Postgres>,
+    sort: &[SortKey],
+    column: impl Fn(&str) -> Option<&'static str>,
+    default: &str,
+) -> ServiceResult<()> {
+    builder.push(" ORDER BY ");
+    
+    if sort.is_empty() {
+        builder.push(default).push(", ");
     }
+    for key in sort {
+        let column = column(&key.field)
+            .ok_or_else(|| ServiceError::BadRequestError(format!("Cannot sort by {}", key.field)))?;
+        let direction = match key.direction {
+            SortDirection::Asc => " ASC, ",
+            SortDirection::Desc => " DESC, ",
+        };
+        builder.push(column).push(direction);
+    }
+    builder.push("id ASC");
+    
+    Ok(())
 }
\ No newline at end of file
@@ -1,11 +1,49 @@
 use async_trait::async_trait;
 use futures_util::StreamExt; // Change to StreamExt instead of TryStreamExt
-use mongodb::bson::{doc, from_document, to_document};
+use std::str::FromStr;
+use mongodb::bson::{doc, from_document, to_document, Bson, DateTime, Decimal128, Document};
+use mongodb::options::{FindOneAndUpdateOptions, FindOneOptions, FindOptions, ReturnDocument};
 use mongodb::Collection;
+use rust_decimal::Decimal;
+use tracing::Instrument;
 use uuid::Uuid;
 use crate::errors::{ServiceError, ServiceResult};
+use crate::models::facet::ProductFacets;
 use crate::models::product::Product;
-use crate::repositories::{Repository, MongoClient};
+use crate::models::search::{ScoredProduct, SearchTerms};
+use crate::models::stock::{StockChange, StockLevel};
+use crate::repositories::{
+    filter_document, sort_document, FieldKind, Filter, FilterValue, MongoClient, Page, ProductSearch, QuerySpec, Queryable,
+    Repository, StockRepository, TransactionManager, TransactionalRepository, UnitOfWork,
+};
+use crate::telemetry::mongo_span;
+
+impl Queryable for Product {
+    const FIELDS: &'static [(&'static str, FieldKind)] = &[
+        ("name", FieldKind::Text),
+        ("sku", FieldKind::Text),
+        ("category", FieldKind::Text),
+        ("in_stock", FieldKind::Bool),
+        ("price", FieldKind::Number),
+        ("currency", FieldKind::Text),
+        ("created_at", FieldKind::Timestamp),
+        ("updated_at", FieldKind::Timestamp),
+    ];
+    
+    fn field_value(&self, field: &str) -> Option<FilterValue> {
+        match field {
+            "name" => Some(FilterValue::Text(self.name.clone())),
+            "sku" => Some(FilterValue::Text(self.sku.clone())),
+            "category" => Some(FilterValue::Text(self.category.clone())),
+            "in_stock" => Some(FilterValue::Bool(self.in_stock)),
+            "price" => Some(FilterValue::Number(self.price.amount)),
+            "currency" => Some(FilterValue::Text(self.price.currency.code().to_string())),
+            "created_at" => Some(FilterValue::Timestamp(self.created_at)),
+            "updated_at" => Some(FilterValue::Timestamp(self.updated_at)),
+            _ => None,
+        }
+    }
+}
 
 pub struct ProductRepository {
     mongo_client: MongoClient,
@@ -13,6 +51,14 @@ pub struct ProductRepository {
 }
 
 impl ProductRepository {
+    // Bucket of the prices from the last price range's lower bound up
+    const LAST_PRICE_BUCKET: &'static str = "above";
+    // Applied stock operations, kept for `STOCK_OPERATION_RETENTION_SECS` so repeats are recognised
+    pub const STOCK_OPERATIONS_COLLECTION: &'static str = "stock_operations";
+    pub const STOCK_OPERATION_RETENTION_SECS: u64 = 30 * 24 * 3600;
+    // How often a stock change that lost to a concurrent one is tried
+    const STOCK_WRITE_ATTEMPTS: u32 = 5;
+    
     pub fn new(mongo_client: MongoClient) -> Self {
         Self {
             mongo_client,
@@ -24,6 +70,245 @@ impl ProductRepository {
     fn collection(&self) -> Collection<mongodb::bson::Document> {
         self.mongo_client.database.collection(&self.collection_name)
     }
+    
+    fn stock_operations(&self) -> Collection<mongodb::bson::Document> {
+        self.mongo_client.database.collection(Self::STOCK_OPERATIONS_COLLECTION)
+    }
+    
+    // Document path of a queryable product field
+    fn document_path(field: &str) -> &str {
+        match field {
+            "price" => "price.amount",
+            "currency" => "price.currency",
+            other => other,
+        }
+    }
+    
+    // Prices are stored as Decimal128 so Mongo can compare and sort them exactly,
+    // while the serde representation of `Money` carries the amount as a string
+    fn to_product_document(item: &Product) -> ServiceResult<Document> {
+        let mut document = to_document(item)
+            .map_err(ServiceError::from)?;
+            
+        if let Ok(price) = document.get_document_mut("price") {
+            if let Some(Bson::String(amount)) = price.get("amount") {
+                let amount = Decimal128::from_str(amount)
+                    .map_err(|e| ServiceError::DataError(e.to_string()))?;
+                price.insert("amount", amount);
+            }
+        }
+        
+        Ok(document)
+    }
+    
+    // The document stored for a new product, and its id
+    fn new_product_document(item: &Product) -> ServiceResult<(Uuid, Document)> {
+        let id = item.id.unwrap_or_else(Uuid::new_v4);
+        let mut document = Self::to_product_document(item)?;
+        document.insert("_id", id.to_string());
+        
+        Ok((id, document))
+    }
+    
+    // The update a catalog edit makes
+    fn edit_document(item: &Product) -> ServiceResult<Document> {
+        let mut document = Self::to_product_document(item)?;
+            
+        // Remove _id from the document (we don't want to update it)
+        document.remove("_id");
+        
+        // Stock is only written through `StockRepository`, so catalog edits cannot
+        // overwrite reservations made since the product was read
+        document.remove("stock");
+        document.remove("in_stock");
+        
+        Ok(doc! { "$set": document })
+    }
+    
+    fn found_product(id: Uuid, document: Option<Document>) -> ServiceResult<Option<Product>> {
+        match document {
+            Some(document) => {
+                let mut product = Self::from_product_document(document)?;
+                product.id = Some(id);
+                Ok(Some(product))
+            },
+            None => Ok(None),
+        }
+    }
+    
+    fn not_found(id: Uuid) -> ServiceError {
+        ServiceError::NotFoundError(format!("Product with ID {} not found", id))
+    }
+    
+    fn from_product_document(mut document: Document) -> ServiceResult<Product> {
+        if let Ok(price) = document.get_document_mut("price") {
+            if let Some(Bson::Decimal128(amount)) = price.get("amount") {
+                let text = amount.to_string();
+                let amount = Decimal::from_str(&text)
+                    .or_else(|_| Decimal::from_scientific(&text))
+                    .map_err(|e| ServiceError::DataError(e.to_string()))?;
+                price.insert("amount", amount.to_string());
+            }
+        }
+        
+        from_document(document)
+            .map_err(|e| ServiceError::DataError(e.to_string()))
+    }
+    
+    fn stock_from_document(document: &Document) -> ServiceResult<StockLevel> {
+        let stock = document.get_document("stock")
+            .map_err(|e| ServiceError::DataError(e.to_string()))?;
+            
+        from_document(stock.clone())
+            .map_err(ServiceError::from)
+    }
+    
+    // Counts from the output of the `$facet` stage built by `facets`
+    pub fn facets_from_document(document: &Document) -> ServiceResult<ProductFacets> {
+        let mut facets = ProductFacets::empty();
+        for (category, count) in Self::facet_groups(document, "categories")? {
+            match category {
+                Bson::String(category) => facets.add_category(&category, count),
+                other => return Err(ServiceError::DataError(format!("Unexpected category: {:?}", other))),
+            }
+        }
+        facets.sort_categories();
+        for (bucket, count) in Self::facet_groups(document, "price_ranges")? {
+            let range = match &bucket {
+                Bson::Int64(min) => ProductFacets::PRICE_RANGES.iter().position(|bound| i64::from(*bound) == *min),
+                Bson::String(last) if last == Self::LAST_PRICE_BUCKET => Some(ProductFacets::PRICE_RANGES.len() - 1),
+                _ => None,
+            }
+            .ok_or_else(|| ServiceError::DataError(format!("Unexpected price bucket: {:?}", bucket)))?;
+            facets.price_ranges[range].count += count;
+        }
+        for (in_stock, count) in Self::facet_groups(document, "in_stock")? {
+            match in_stock {
+                Bson::Boolean(in_stock) => facets.add_stock(in_stock, count),
+                other => return Err(ServiceError::DataError(format!("Unexpected in_stock: {:?}", other))),
+            }
+        }
+        
+        Ok(facets)
+    }
+    
+    // `_id` and `count` of each group in one output of a `$facet` stage
+    fn facet_groups(document: &Document, facet: &str) -> ServiceResult<Vec<(Bson, u64)>> {
+        let groups = document.get_array(facet)
+            .map_err(|e| ServiceError::DataError(e.to_string()))?;
+            
+        groups.iter().map(|group| {
+            let group = group.as_document()
+                .ok_or_else(|| ServiceError::DataError(format!("Unexpected {} facet: {:?}", facet, group)))?;
+            let count = match group.get("count") {
+                Some(Bson::Int32(count)) => *count as u64,
+                Some(Bson::Int64(count)) => *count as u64,
+                other => return Err(ServiceError::DataError(format!("Unexpected {} facet count: {:?}", facet, other))),
+            };
+            Ok((group.get("_id").cloned().unwrap_or(Bson::Null), count))
+        })
+        .collect()
+    }
+    
+    // Apply a stock update if `condition` holds and the operation has not been applied yet,
+    // recording the operation and re-deriving `in_stock` in one transaction. Concurrent
+    // changes to a product conflict, and the transaction that lost is run again; the
+    // operation record makes that safe even when its commit did go through.
+    async fn update_stock(
+        &self,
+        product_id: Uuid,
+        operation: Uuid,
+        condition: Document,
+        stock: Document,
+    ) -> ServiceResult<StockLevel> {
+        let mut attempt = 1;
+        loop {
+            let mut uow = self.mongo_client.begin().await?;
+            let result = match self.update_stock_in(&mut uow, product_id, operation, condition.clone(), stock.clone()).await {
+                Ok(level) => uow.commit().await.map(|_| level),
+                Err(e) => Err(e),
+            };
+            
+            match result {
+                Err(ServiceError::DatabaseError(e)) if attempt < Self::STOCK_WRITE_ATTEMPTS => {
+                    tracing::debug!("Retrying stock change {} for product {}: {}", operation, product_id, e);
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+    
+    async fn update_stock_in(
+        &self,
+        uow: &mut UnitOfWork,
+        product_id: Uuid,
+        operation: Uuid,
+        condition: Document,
+        stock: Document,
+    ) -> ServiceResult<StockLevel> {
+        let collection = self.collection();
+        let operations = self.stock_operations();
+        let operation_filter = doc! { "product_id": product_id.to_string(), "operation": operation.to_string() };
+        
+        let span = mongo_span("find", Self::STOCK_OPERATIONS_COLLECTION, Some(&operation_filter));
+        let applied = operations.find_one_with_session(operation_filter.clone(), None, uow.session()?)
+            .instrument(span)
+            .await
+            .map_err(ServiceError::from)?;
+        if applied.is_some() {
+            return self.find_stock_in(uow, product_id).await?
+                .ok_or_else(|| ServiceError::NotFoundError(format!("Product with ID {} not found", product_id)));
+        }
+        
+        let mut filter = doc! { "_id": product_id.to_string() };
+        filter.extend(condition);
+        let pipeline = vec![
+            doc! { "$set": stock },
+            doc! { "$set": { "in_stock": { "$gt": ["$stock.on_hand", "$stock.reserved"] } } },
+        ];
+        let options = FindOneAndUpdateOptions::builder()
+            .projection(doc! { "stock": 1 })
+            .return_document(ReturnDocument::After)
+            .build();
+            
+        let span = mongo_span("findAndModify", &self.collection_name, Some(&filter));
+        let updated = collection.find_one_and_update_with_session(filter, pipeline, options, uow.session()?)
+            .instrument(span)
+            .await
+            .map_err(ServiceError::from)?;
+        let level = match updated {
+            Some(document) => Self::stock_from_document(&document)?,
+            None => {
+                return match self.find_stock_in(uow, product_id).await? {
+                    Some(_) => Err(ServiceError::ConflictError(format!("Insufficient stock for product {}", product_id))),
+                    None => Err(ServiceError::NotFoundError(format!("Product with ID {} not found", product_id))),
+                };
+            }
+        };
+        
+        let mut record = operation_filter;
+        record.insert("applied_at", DateTime::now());
+        let span = mongo_span("insert", Self::STOCK_OPERATIONS_COLLECTION, None);
+        operations.insert_one_with_session(record, None, uow.session()?)
+            .instrument(span)
+            .await
+            .map_err(ServiceError::from)?;
+            
+        Ok(level)
+    }
+    
+    async fn find_stock_in(&self, uow: &mut UnitOfWork, product_id: Uuid) -> ServiceResult<Option<StockLevel>> {
+        let filter = doc! { "_id": product_id.to_string() };
+        let options = FindOneOptions::builder().projection(doc! { "stock": 1 }).build();
+        let span = mongo_span("find", &self.collection_name, Some(&filter));
+        let result = self.collection().find_one_with_session(filter, options, uow.session()?)
+            .instrument(span)
+            .await
+            .map_err(ServiceError::from)?;
+            
+        result.as_ref().map(Self::stock_from_document).transpose()
+    }
 }
 
 #[async_trait]
@@ -32,57 +317,64 @@ impl Repository<Product, Uuid> for ProductRepository {
         let collection = self.collection();
         
         let filter = doc! { "_id": id.to_string() };
-        let result = collection.find_one(filter, None).await
-            .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
+        let span = mongo_span("find", &self.collection_name, Some(&filter));
+        let result = collection.find_one(filter, None).instrument(span).await
+            .map_err(ServiceError::from)?;
             
-        match result {
-            Some(document) => {
-                let mut product: Product = from_document(document)
-                    .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
-                product.id = Some(id);
-                Ok(Some(product))
-            },
-            None => Ok(None),
-        }
+        Self::found_product(id, result)
     }
     
-    async fn find_all(&self) -> ServiceResult<Vec<Product>> {
+    async fn find_all(&self, query: &QuerySpec) -> ServiceResult<Page<Product>> {
         let collection = self.collection();
         
-        let cursor = collection.find(None, None).await
-            .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
+        let filter = filter_document(&query.filters, Self::document_path)?;
+        let options = FindOptions::builder()
+            .sort(sort_document(&query.sort, Self::document_path))
+            .skip(query.offset)
+            .limit(query.limit as i64)
+            .build();
             
-        // Use StreamExt::collect to convert the stream into a vector
-        let documents: Vec<Result<mongodb::bson::Document, _>> = cursor.collect().await;
+        let span = mongo_span("count", &self.collection_name, Some(&filter));
+        let total = collection.count_documents(filter.clone(), None).instrument(span).await
+            .map_err(ServiceError::from)?;
+        // The span covers reading every batch of the cursor, not just the first
+        let span = mongo_span("find", &self.collection_name, Some(&filter));
+        let documents: Vec<Result<mongodb::bson::Document, _>> = async {
+            let cursor = collection.find(filter, options).await?;
+            // Use StreamExt::collect to convert the stream into a vector
+            Ok::<_, mongodb::error::Error>(cursor.collect().await)
+        }
+        .instrument(span)
+        .await
+        .map_err(ServiceError::from)?;
         
         // Process the results
         let mut products = Vec::new();
         for doc_result in documents {
             match doc_result {
                 Ok(doc) => {
-                    match from_document::<Product>(doc) {
-                        Ok(product) => products.push(product),
-                        Err(e) => return Err(ServiceError::DatabaseError(e.to_string())),
-                    }
+                    products.push(Self::from_product_document(doc)?);
                 }
-                Err(e) => return Err(ServiceError::DatabaseError(e.to_string())),
+                Err(e) => return Err(e.into()),
             }
         }
         
-        Ok(products)
+        Ok(Page {
+            items: products,
+            total,
+            limit: query.limit,
+            offset: query.offset,
+        })
     }
     
     async fn create(&self, item: Product) -> ServiceResult<Product> {
         let collection = self.collection();
         
-        let id = item.id.unwrap_or_else(Uuid::new_v4);
-        let mut document = to_document(&item)
-            .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
-            
-        document.insert("_id", id.to_string());
+        let (id, document) = Self::new_product_document(&item)?;
         
-        collection.insert_one(document, None).await
-            .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
+        let span = mongo_span("insert", &self.collection_name, None);
+        collection.insert_one(document, None).instrument(span).await
+            .map_err(ServiceError::from)?;
             
         let mut created_item = item;
         created_item.id = Some(id);
@@ -94,19 +386,80 @@ impl Repository<Product, Uuid> for ProductRepository {
         let collection = self.collection();
         
         let filter = doc! { "_id": id.to_string() };
-        let mut document = to_document(&item)
-            .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
+        let update = Self::edit_document(&item)?;
+        
+        let span = mongo_span("update", &self.collection_name, Some(&filter));
+        let result = collection.update_one(filter, update, None).instrument(span).await
+            .map_err(ServiceError::from)?;
             
-        // Remove _id from the document (we don't want to update it)
-        document.remove("_id");
+        if result.matched_count == 0 {
+            return Err(Self::not_found(id));
+        }
+        
+        let mut updated_item = item;
+        updated_item.id = Some(id);
+        
+        Ok(updated_item)
+    }
+    
+    async fn delete(&self, id: Uuid) -> ServiceResult<()> {
+        let collection = self.collection();
+        
+        let filter = doc! { "_id": id.to_string() };
+        let span = mongo_span("delete", &self.collection_name, Some(&filter));
+        let result = collection.delete_one(filter, None).instrument(span).await
+            .map_err(ServiceError::from)?;
+            
+        if result.deleted_count == 0 {
+            return Err(Self::not_found(id));
+        }
+        
+        Ok(())
+    }
+}
+
+// The same writes inside a MongoDB transaction, so that they commit together with the
+// events describing them
+#[async_trait]
+impl TransactionalRepository<Product, Uuid> for ProductRepository {
+    async fn find_by_id_in(&self, uow: &mut UnitOfWork, id: Uuid) -> ServiceResult<Option<Product>> {
+        let collection = self.collection();
+        
+        let filter = doc! { "_id": id.to_string() };
+        let span = mongo_span("find", &self.collection_name, Some(&filter));
+        let result = collection.find_one_with_session(filter, None, uow.session()?).instrument(span).await
+            .map_err(ServiceError::from)?;
+            
+        Self::found_product(id, result)
+    }
+    
+    async fn create_in(&self, uow: &mut UnitOfWork, item: Product) -> ServiceResult<Product> {
+        let collection = self.collection();
+        
+        let (id, document) = Self::new_product_document(&item)?;
+        
+        let span = mongo_span("insert", &self.collection_name, None);
+        collection.insert_one_with_session(document, None, uow.session()?).instrument(span).await
+            .map_err(ServiceError::from)?;
+            
+        let mut created_item = item;
+        created_item.id = Some(id);
+        
+        Ok(created_item)
+    }
+    
+    async fn update_in(&self, uow: &mut UnitOfWork, id: Uuid, item: Product) -> ServiceResult<Product> {
+        let collection = self.collection();
         
-        let update = doc! { "$set": document };
+        let filter = doc! { "_id": id.to_string() };
+        let update = Self::edit_document(&item)?;
         
-        let result = collection.update_one(filter, update, None).await
-            .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
+        let span = mongo_span("update", &self.collection_name, Some(&filter));
+        let result = collection.update_one_with_session(filter, update, None, uow.session()?).instrument(span).await
+            .map_err(ServiceError::from)?;
             
         if result.matched_count == 0 {
-            return Err(ServiceError::NotFoundError(format!("Product with ID {} not found", id)));
+            return Err(Self::not_found(id));
         }
         
         let mut updated_item = item;
@@ -115,17 +468,159 @@ impl Repository<Product, Uuid> for ProductRepository {
         Ok(updated_item)
     }
     
-    async fn delete(&self, id: Uuid) -> ServiceResult<()> {
+    async fn delete_in(&self, uow: &mut UnitOfWork, id: Uuid) -> ServiceResult<()> {
         let collection = self.collection();
         
         let filter = doc! { "_id": id.to_string() };
-        let result = collection.delete_one(filter, None).await
-            .map_err(|e| ServiceError::DatabaseError(e.to_string()))?;
+        let span = mongo_span("delete", &self.collection_name, Some(&filter));
+        let result = collection.delete_one_with_session(filter, None, uow.session()?).instrument(span).await
+            .map_err(ServiceError::from)?;
             
         if result.deleted_count == 0 {
-            return Err(ServiceError::NotFoundError(format!("Product with ID {} not found", id)));
+            return Err(Self::not_found(id));
         }
         
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[async_trait]
+impl StockRepository for ProductRepository {
+    async fn find_stock(&self, product_id: Uuid) -> ServiceResult<Option<StockLevel>> {
+        let collection = self.collection();
+        
+        let filter = doc! { "_id": product_id.to_string() };
+        let options = FindOneOptions::builder().projection(doc! { "stock": 1 }).build();
+        let span = mongo_span("find", &self.collection_name, Some(&filter));
+        let result = collection.find_one(filter, options).instrument(span).await
+            .map_err(ServiceError::from)?;
+            
+        result.as_ref().map(Self::stock_from_document).transpose()
+    }
+    
+    async fn change_stock(&self, product_id: Uuid, change: StockChange, operation: Uuid) -> ServiceResult<StockLevel> {
+        let on_hand = doc! { "$add": ["$stock.on_hand", change.on_hand] };
+        let reserved = doc! { "$add": ["$stock.reserved", change.reserved] };
+        
+        let condition = doc! {
+            "$expr": {
+                "$and": [
+                    { "$gte": [reserved.clone(), 0] },
+                    { "$lte": [reserved.clone(), on_hand.clone()] },
+                ]
+            }
+        };
+        let stock = doc! { "stock.on_hand": on_hand, "stock.reserved": reserved };
+        
+        self.update_stock(product_id, operation, condition, stock).await
+    }
+    
+    async fn set_on_hand(&self, product_id: Uuid, on_hand: i32, operation: Uuid) -> ServiceResult<StockLevel> {
+        let condition = doc! { "stock.reserved": { "$lte": on_hand } };
+        let stock = doc! { "stock.on_hand": on_hand };
+        
+        self.update_stock(product_id, operation, condition, stock).await
+    }
+    
+    async fn has_applied(&self, product_id: Uuid, operation: Uuid) -> ServiceResult<bool> {
+        let filter = doc! { "product_id": product_id.to_string(), "operation": operation.to_string() };
+        let span = mongo_span("find", Self::STOCK_OPERATIONS_COLLECTION, Some(&filter));
+        let applied = self.stock_operations().find_one(filter, None).instrument(span).await
+            .map_err(ServiceError::from)?;
+            
+        Ok(applied.is_some())
+    }
+}
+
+#[async_trait]
+impl ProductSearch for ProductRepository {
+    // Uses the `product_text` index; `$text` understands the phrases and exclusions in `terms`
+    async fn search(&self, terms: &SearchTerms, query: &QuerySpec) -> ServiceResult<Page<ScoredProduct>> {
+        let collection = self.collection();
+        
+        let mut filter = filter_document(&query.filters, Self::document_path)?;
+        filter.insert("$text", doc! { "$search": &terms.text, "$language": "english" });
+        let options = FindOptions::builder()
+            .projection(doc! { "score": { "$meta": "textScore" } })
+            .sort(doc! { "score": { "$meta": "textScore" }, "_id": 1 })
+            .skip(query.offset)
+            .limit(query.limit as i64)
+            .build();
+            
+        let span = mongo_span("count", &self.collection_name, Some(&filter));
+        let total = collection.count_documents(filter.clone(), None).instrument(span).await
+            .map_err(ServiceError::from)?;
+        let span = mongo_span("find", &self.collection_name, Some(&filter));
+        let documents: Vec<Result<Document, _>> = async {
+            let cursor = collection.find(filter, options).await?;
+            Ok::<_, mongodb::error::Error>(cursor.collect().await)
+        }
+        .instrument(span)
+        .await
+        .map_err(ServiceError::from)?;
+        
+        let mut matches = Vec::new();
+        for document in documents {
+            let mut document = document.map_err(ServiceError::from)?;
+            let score = match document.remove("score") {
+                Some(Bson::Double(score)) => score,
+                other => return Err(ServiceError::DataError(format!("Unexpected text score: {:?}", other))),
+            };
+            matches.push(ScoredProduct { product: Self::from_product_document(document)?, score });
+        }
+        
+        Ok(Page {
+            items: matches,
+            total,
+            limit: query.limit,
+            offset: query.offset,
+        })
+    }
+    
+    // One aggregation: the products matching are split three ways by a `$facet` stage
+    async fn facets(&self, terms: Option<&SearchTerms>, filters: &[Filter]) -> ServiceResult<ProductFacets> {
+        let collection = self.collection();
+        
+        let mut filter = filter_document(filters, Self::document_path)?;
+        if let Some(terms) = terms {
+            filter.insert("$text", doc! { "$search": &terms.text, "$language": "english" });
+        }
+        let boundaries: Vec<Bson> = ProductFacets::PRICE_RANGES.iter().map(|min| Bson::Int64(i64::from(*min))).collect();
+        let pipeline = vec![
+            // Comes first, as a `$text` match must
+            doc! { "$match": filter.clone() },
+            doc! {
+                "$facet": {
+                    "categories": [{ "$group": { "_id": "$category", "count": { "$sum": 1 } } }],
+                    // Each bucket is identified by its lower bound. Prices from the last
+                    // bound up fall outside the boundaries, into the default bucket, so
+                    // products without a numeric price are left out rather than counted there.
+                    "price_ranges": [
+                        { "$match": { "price.amount": { "$type": "number" } } },
+                        {
+                            "$bucket": {
+                                "groupBy": "$price.amount",
+                                "boundaries": boundaries,
+                                "default": Self::LAST_PRICE_BUCKET,
+                                "output": { "count": { "$sum": 1 } },
+                            }
+                        },
+                    ],
+                    "in_stock": [{ "$group": { "_id": "$in_stock", "count": { "$sum": 1 } } }],
+                }
+            },
+        ];
+        
+        let span = mongo_span("aggregate", &self.collection_name, Some(&filter));
+        let document = async {
+            let mut cursor = collection.aggregate(pipeline, None).await?;
+            cursor.next().await.transpose()
+        }
+        .instrument(span)
+        .await
+        .map_err(ServiceError::from)?
+        .ok_or_else(|| ServiceError::DataError("Facet aggregation returned no document".to_string()))?;
+        
+        Self::facets_from_document(&document)
+    }
+}
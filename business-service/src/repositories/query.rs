@@ -0,0 +1,342 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use crate::errors::{ServiceError, ServiceResult};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterOp {
+    Eq,
+    Ne,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+}
+
+impl FromStr for FilterOp {
+    type Err = ServiceError;
+
+    fn from_str(op: &str) -> Result<Self, Self::Err> {
+        match op {
+            "eq" => Ok(FilterOp::Eq),
+            "ne" => Ok(FilterOp::Ne),
+            "gt" => Ok(FilterOp::Gt),
+            "gte" => Ok(FilterOp::Gte),
+            "lt" => Ok(FilterOp::Lt),
+            "lte" => Ok(FilterOp::Lte),
+            other => Err(ServiceError::BadRequestError(format!("Unknown filter operator: {}", other))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilterValue {
+    Text(String),
+    Number(Decimal),
+    Bool(bool),
+    Uuid(Uuid),
+    Timestamp(DateTime<Utc>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldKind {
+    Text,
+    Number,
+    Bool,
+    Uuid,
+    Timestamp,
+}
+
+impl FieldKind {
+    pub fn parse(&self, field: &str, raw: &str) -> ServiceResult<FilterValue> {
+        let invalid = |e: &dyn std::fmt::Display| {
+            ServiceError::BadRequestError(format!("Invalid value for {}: {}", field, e))
+        };
+
+        match self {
+            FieldKind::Text => Ok(FilterValue::Text(raw.to_string())),
+            FieldKind::Number => Decimal::from_str(raw).map(FilterValue::Number).map_err(|e| invalid(&e)),
+            FieldKind::Bool => bool::from_str(raw).map(FilterValue::Bool).map_err(|e| invalid(&e)),
+            FieldKind::Uuid => Uuid::from_str(raw).map(FilterValue::Uuid).map_err(|e| invalid(&e)),
+            // Accept RFC 3339 or unix seconds, matching how timestamps are serialized
+            FieldKind::Timestamp => DateTime::parse_from_rfc3339(raw)
+                .map(|t| t.with_timezone(&Utc))
+                .or_else(|e| {
+                    raw.parse::<i64>()
+                        .ok()
+                        .and_then(|secs| DateTime::from_timestamp(secs, 0))
+                        .ok_or_else(|| invalid(&e))
+                })
+                .map(FilterValue::Timestamp),
+        }
+    }
+}
+
+// Models that can be listed through a `QuerySpec` declare their filterable and sortable fields
+pub trait Queryable {
+    const FIELDS: &'static [(&'static str, FieldKind)];
+
+    // Current value of a queryable field, used when evaluating a spec in memory
+    fn field_value(&self, field: &str) -> Option<FilterValue>;
+
+    fn field_kind(field: &str) -> Option<FieldKind> {
+        Self::FIELDS.iter().find(|(name, _)| *name == field).map(|(_, kind)| *kind)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    pub field: String,
+    pub op: FilterOp,
+    pub value: FilterValue,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortDirection {
+    Asc,
+    Desc,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SortKey {
+    pub field: String,
+    pub direction: SortDirection,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuerySpec {
+    pub filters: Vec<Filter>,
+    pub sort: Vec<SortKey>,
+    pub limit: u64,
+    pub offset: u64,
+}
+
+impl Default for QuerySpec {
+    fn default() -> Self {
+        Self {
+            filters: Vec::new(),
+            sort: Vec::new(),
+            limit: Self::DEFAULT_LIMIT,
+            offset: 0,
+        }
+    }
+}
+
+// Position encoded in an opaque cursor token
+#[derive(Serialize, Deserialize)]
+struct Cursor {
+    offset: u64,
+}
+
+impl QuerySpec {
+    pub const DEFAULT_LIMIT: u64 = 20;
+    pub const MAX_LIMIT: u64 = 100;
+
+    // Build a spec from query string parameters:
+    //   field=value / field[op]=value   filters (op: eq, ne, gt, gte, lt, lte)
+    //   sort=-price,name                sort keys, `-` for descending
+    //   limit, offset, cursor           paging; a cursor overrides offset
+    pub fn from_params<T: Queryable>(params: &HashMap<String, String>) -> ServiceResult<Self> {
+        let mut spec = QuerySpec::default();
+        let mut cursor = None;
+
+        // Iterate in a stable order so identical requests produce identical specs
+        let mut keys: Vec<&String> = params.keys().collect();
+        keys.sort();
+
+        for key in keys {
+            let raw = &params[key];
+            match key.as_str() {
+                "limit" => {
+                    spec.limit = raw.parse()
+                        .map_err(|_| ServiceError::BadRequestError(format!("Invalid limit: {}", raw)))?;
+                }
+                "offset" => {
+                    spec.offset = raw.parse()
+                        .map_err(|_| ServiceError::BadRequestError(format!("Invalid offset: {}", raw)))?;
+                }
+                "cursor" => cursor = Some(raw.clone()),
+                "sort" => {
+                    for part in raw.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+                        let (field, direction) = match part.strip_prefix('-') {
+                            Some(field) => (field, SortDirection::Desc),
+                            None => (part.strip_prefix('+').unwrap_or(part), SortDirection::Asc),
+                        };
+                        if T::field_kind(field).is_none() {
+                            return Err(ServiceError::BadRequestError(format!("Cannot sort by {}", field)));
+                        }
+                        spec.sort.push(SortKey { field: field.to_string(), direction });
+                    }
+                }
+                _ => {
+                    let (field, op) = match key.split_once('[') {
+                        Some((field, rest)) => {
+                            let op = rest.strip_suffix(']')
+                                .ok_or_else(|| ServiceError::BadRequestError(format!("Malformed filter: {}", key)))?;
+                            (field, FilterOp::from_str(op)?)
+                        }
+                        None => (key.as_str(), FilterOp::Eq),
+                    };
+                    let kind = T::field_kind(field)
+                        .ok_or_else(|| ServiceError::BadRequestError(format!("Cannot filter by {}", field)))?;
+
+                    spec.filters.push(Filter {
+                        field: field.to_string(),
+                        op,
+                        value: kind.parse(field, raw)?,
+                    });
+                }
+            }
+        }
+
+        if spec.limit == 0 || spec.limit > Self::MAX_LIMIT {
+            return Err(ServiceError::BadRequestError(format!(
+                "limit must be between 1 and {}", Self::MAX_LIMIT
+            )));
+        }
+
+        if let Some(token) = cursor {
+            spec.offset = Self::decode_cursor(&token)?;
+        }
+
+        Ok(spec)
+    }
+
+    pub fn with_filter(mut self, field: &str, op: FilterOp, value: FilterValue) -> Self {
+        self.filters.push(Filter { field: field.to_string(), op, value });
+        self
+    }
+
+    pub fn encode_cursor(offset: u64) -> String {
+        let json = serde_json::to_vec(&Cursor { offset }).unwrap_or_default();
+        URL_SAFE_NO_PAD.encode(json)
+    }
+
+    pub fn decode_cursor(token: &str) -> ServiceResult<u64> {
+        URL_SAFE_NO_PAD.decode(token)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<Cursor>(&bytes).ok())
+            .map(|cursor| cursor.offset)
+            .ok_or_else(|| ServiceError::BadRequestError("Invalid cursor".to_string()))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: u64,
+    pub limit: u64,
+    pub offset: u64,
+}
+
+impl<T> Page<T> {
+    pub fn next_cursor(&self) -> Option<String> {
+        let next_offset = self.offset + self.items.len() as u64;
+        if self.items.is_empty() || next_offset >= self.total {
+            return None;
+        }
+
+        Some(QuerySpec::encode_cursor(next_offset))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Item;
+
+    impl Queryable for Item {
+        const FIELDS: &'static [(&'static str, FieldKind)] = &[
+            ("name", FieldKind::Text),
+            ("price", FieldKind::Number),
+            ("in_stock", FieldKind::Bool),
+            ("created_at", FieldKind::Timestamp),
+        ];
+
+        fn field_value(&self, _field: &str) -> Option<FilterValue> {
+            None
+        }
+    }
+
+    fn parse(params: &[(&str, &str)]) -> ServiceResult<QuerySpec> {
+        let params = params.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect();
+        QuerySpec::from_params::<Item>(&params)
+    }
+
+    fn rejected(params: &[(&str, &str)]) -> String {
+        match parse(params) {
+            Err(ServiceError::BadRequestError(message)) => message,
+            other => panic!("expected a bad request, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn filters_take_an_operator_in_brackets() {
+        let spec = parse(&[("price[gte]", "10"), ("name", "lamp")]).unwrap();
+
+        assert_eq!(spec.filters, [
+            Filter { field: "name".to_string(), op: FilterOp::Eq, value: FilterValue::Text("lamp".to_string()) },
+            Filter { field: "price".to_string(), op: FilterOp::Gte, value: FilterValue::Number(Decimal::from(10)) },
+        ]);
+    }
+
+    #[test]
+    fn unknown_operators_and_fields_are_rejected() {
+        assert_eq!(rejected(&[("price[between]", "1")]), "Unknown filter operator: between");
+        assert_eq!(rejected(&[("price[gte", "1")]), "Malformed filter: price[gte");
+        assert_eq!(rejected(&[("colour", "red")]), "Cannot filter by colour");
+    }
+
+    #[test]
+    fn malformed_values_are_rejected() {
+        assert!(rejected(&[("price[lt]", "cheap")]).starts_with("Invalid value for price"));
+        assert!(rejected(&[("in_stock", "yes")]).starts_with("Invalid value for in_stock"));
+        assert!(rejected(&[("created_at[gt]", "yesterday")]).starts_with("Invalid value for created_at"));
+
+        // Timestamps may be given as unix seconds too
+        let spec = parse(&[("created_at[gt]", "0")]).unwrap();
+        assert_eq!(spec.filters[0].value, FilterValue::Timestamp(DateTime::from_timestamp(0, 0).unwrap()));
+    }
+
+    #[test]
+    fn limit_defaults_and_is_bounded() {
+        assert_eq!(parse(&[]).unwrap().limit, QuerySpec::DEFAULT_LIMIT);
+        assert_eq!(parse(&[("limit", "100")]).unwrap().limit, QuerySpec::MAX_LIMIT);
+
+        assert_eq!(rejected(&[("limit", "0")]), "limit must be between 1 and 100");
+        assert_eq!(rejected(&[("limit", "101")]), "limit must be between 1 and 100");
+        assert_eq!(rejected(&[("limit", "ten")]), "Invalid limit: ten");
+    }
+
+    #[test]
+    fn negative_offsets_are_rejected() {
+        assert_eq!(rejected(&[("offset", "-1")]), "Invalid offset: -1");
+        assert_eq!(parse(&[("offset", "40")]).unwrap().offset, 40);
+    }
+
+    #[test]
+    fn a_cursor_overrides_the_offset() {
+        let cursor = QuerySpec::encode_cursor(60);
+        assert_eq!(parse(&[("offset", "20"), ("cursor", &cursor)]).unwrap().offset, 60);
+        assert_eq!(rejected(&[("cursor", "not-a-cursor")]), "Invalid cursor");
+    }
+
+    #[test]
+    fn sort_keys_take_an_optional_direction_prefix() {
+        let spec = parse(&[("sort", "-price, +name,created_at,")]).unwrap();
+
+        let keys: Vec<(&str, SortDirection)> = spec.sort.iter().map(|key| (key.field.as_str(), key.direction)).collect();
+        assert_eq!(keys, [
+            ("price", SortDirection::Desc),
+            ("name", SortDirection::Asc),
+            ("created_at", SortDirection::Asc),
+        ]);
+        assert_eq!(rejected(&[("sort", "-colour")]), "Cannot sort by colour");
+    }
+}
@@ -0,0 +1,82 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use async_trait::async_trait;
+use redis::aio::{ConnectionManager, ConnectionManagerConfig};
+use tokio::sync::{Mutex, OnceCell};
+use crate::config::RedisConfig;
+use crate::errors::{ServiceError, ServiceResult};
+use crate::repositories::HealthCheck;
+
+// How long one attempt to connect may take, and how long to wait after a failed attempt
+// before the next. Until then callers fail at once rather than queueing behind attempts.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+const RETRY_CONNECT_AFTER: Duration = Duration::from_secs(5);
+
+// A Redis connection, made on first use so that the service starts while Redis is down,
+// that reconnects by itself once made; clones share it
+#[derive(Clone)]
+pub struct RedisClient {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    client: redis::Client,
+    connection: OnceCell<ConnectionManager>,
+    last_failure: Mutex<Option<Instant>>,
+}
+
+impl RedisClient {
+    pub fn new(config: &RedisConfig) -> ServiceResult<Self> {
+        let client = redis::Client::open(config.url.as_str())
+            .map_err(|e| ServiceError::ConfigError(format!("Invalid Redis URL: {}", e)))?;
+            
+        Ok(Self {
+            shared: Arc::new(Shared {
+                client,
+                connection: OnceCell::new(),
+                last_failure: Mutex::new(None),
+            }),
+        })
+    }
+    
+    // The shared connection, connecting first if that has not succeeded yet
+    pub async fn connection(&self) -> ServiceResult<ConnectionManager> {
+        if let Some(connection) = self.shared.connection.get() {
+            return Ok(connection.clone());
+        }
+        
+        let mut last_failure = self.shared.last_failure.lock().await;
+        // Another caller may have connected while this one waited
+        if let Some(connection) = self.shared.connection.get() {
+            return Ok(connection.clone());
+        }
+        if last_failure.is_some_and(|failed_at| failed_at.elapsed() < RETRY_CONNECT_AFTER) {
+            return Err(ServiceError::DatabaseError("Redis is unreachable".to_string()));
+        }
+        
+        let config = ConnectionManagerConfig::new()
+            .set_connection_timeout(CONNECT_TIMEOUT)
+            .set_number_of_retries(1);
+        match ConnectionManager::new_with_config(self.shared.client.clone(), config).await {
+            Ok(connection) => {
+                *last_failure = None;
+                Ok(self.shared.connection.get_or_init(|| async { connection }).await.clone())
+            }
+            Err(e) => {
+                *last_failure = Some(Instant::now());
+                Err(e.into())
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl HealthCheck for RedisClient {
+    async fn health_check(&self) -> ServiceResult<()> {
+        redis::cmd("PING")
+            .query_async::<String>(&mut self.connection().await?)
+            .await
+            .map(|_| ())
+            .map_err(ServiceError::from)
+    }
+}
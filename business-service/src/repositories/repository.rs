@@ -1,11 +1,51 @@
 use async_trait::async_trait;
+use uuid::Uuid;
 use crate::errors::ServiceResult;
+use crate::models::facet::ProductFacets;
+use crate::models::search::{ScoredProduct, SearchTerms};
+use crate::models::stock::{StockChange, StockLevel};
+use crate::repositories::{Filter, Page, QuerySpec, UnitOfWork};
 
 #[async_trait]
-pub trait Repository<T, ID> {
+pub trait Repository<T, ID>: Send + Sync {
     async fn find_by_id(&self, id: ID) -> ServiceResult<Option<T>>;
-    async fn find_all(&self) -> ServiceResult<Vec<T>>;
+    async fn find_all(&self, query: &QuerySpec) -> ServiceResult<Page<T>>;
     async fn create(&self, item: T) -> ServiceResult<T>;
     async fn update(&self, id: ID, item: T) -> ServiceResult<T>;
     async fn delete(&self, id: ID) -> ServiceResult<()>;
+}
+
+// Repository operations that run inside a caller-owned unit of work, so several
+// writes (possibly across repositories) commit or roll back together
+#[async_trait]
+pub trait TransactionalRepository<T, ID>: Repository<T, ID> {
+    async fn find_by_id_in(&self, uow: &mut UnitOfWork, id: ID) -> ServiceResult<Option<T>>;
+    async fn create_in(&self, uow: &mut UnitOfWork, item: T) -> ServiceResult<T>;
+    async fn update_in(&self, uow: &mut UnitOfWork, id: ID, item: T) -> ServiceResult<T>;
+    async fn delete_in(&self, uow: &mut UnitOfWork, id: ID) -> ServiceResult<()>;
+}
+
+// Per-product stock levels. Each change is applied atomically and only if its condition
+// holds, so concurrent reservations can never oversell; a change that would leave the level
+// inconsistent fails with a conflict and leaves it untouched. Every write carries an
+// operation id, and repeating an operation that was already applied is a no-op. Operation
+// ids are remembered for a limited time, longer than any saga takes to finish.
+#[async_trait]
+pub trait StockRepository: Send + Sync {
+    async fn find_stock(&self, product_id: Uuid) -> ServiceResult<Option<StockLevel>>;
+    async fn change_stock(&self, product_id: Uuid, change: StockChange, operation: Uuid) -> ServiceResult<StockLevel>;
+    async fn set_on_hand(&self, product_id: Uuid, on_hand: i32, operation: Uuid) -> ServiceResult<StockLevel>;
+    // Whether the change with this operation id was applied to the product's stock
+    async fn has_applied(&self, product_id: Uuid, operation: Uuid) -> ServiceResult<bool>;
+}
+
+// Full-text search over products, most relevant first. The filters and paging of `query`
+// apply to the matches; its sort keys are not used.
+#[async_trait]
+pub trait ProductSearch: Send + Sync {
+    async fn search(&self, terms: &SearchTerms, query: &QuerySpec) -> ServiceResult<Page<ScoredProduct>>;
+
+    // Counts per category, price range and availability over all products matching
+    // `filters`, and `terms` when searching
+    async fn facets(&self, terms: Option<&SearchTerms>, filters: &[Filter]) -> ServiceResult<ProductFacets>;
 }
\ No newline at end of file
@@ -0,0 +1,136 @@
+use async_trait::async_trait;
+use std::str::FromStr;
+use sqlx::postgres::PgRow;
+use sqlx::Row;
+use chrono::{DateTime, Utc};
+use tracing::Instrument;
+use uuid::Uuid;
+use crate::errors::{ServiceError, ServiceResult};
+use crate::models::saga::{SagaRecord, SagaStatus};
+use crate::repositories::PostgresClient;
+use crate::telemetry::sql_span;
+
+// Durable saga progress. Each save overwrites the previous state of the saga, unless the
+// saga has been claimed by another owner since: that fails with a conflict.
+#[async_trait]
+pub trait SagaStore: Send + Sync {
+    async fn save(&self, saga: &SagaRecord) -> ServiceResult<()>;
+    // Take over the sagas still running or compensating that have made no progress since
+    // `updated_before`, marking them updated now. A saga is claimed by one caller only, even
+    // when several claim at once.
+    async fn claim_unfinished(&self, owner: Uuid, updated_before: DateTime<Utc>) -> ServiceResult<Vec<SagaRecord>>;
+}
+
+pub struct SagaRepository {
+    pg_client: PostgresClient,
+}
+
+impl SagaRepository {
+    const SAGA_COLUMNS: &'static str = "id, kind, payload, status, completed_steps, error, owner, created_at, updated_at";
+
+    pub fn new(pg_client: PostgresClient) -> Self {
+        Self { pg_client }
+    }
+
+    fn map_saga(row: &PgRow) -> ServiceResult<SagaRecord> {
+        let id: Uuid = row.try_get("id")
+            .map_err(ServiceError::from)?;
+        let kind: String = row.try_get("kind")
+            .map_err(ServiceError::from)?;
+        let payload: serde_json::Value = row.try_get("payload")
+            .map_err(ServiceError::from)?;
+        let status: String = row.try_get("status")
+            .map_err(ServiceError::from)?;
+        let completed_steps: i32 = row.try_get("completed_steps")
+            .map_err(ServiceError::from)?;
+        let error: Option<String> = row.try_get("error")
+            .map_err(ServiceError::from)?;
+        let owner: Option<Uuid> = row.try_get("owner")
+            .map_err(ServiceError::from)?;
+        let created_at: DateTime<Utc> = row.try_get("created_at")
+            .map_err(ServiceError::from)?;
+        let updated_at: DateTime<Utc> = row.try_get("updated_at")
+            .map_err(ServiceError::from)?;
+
+        Ok(SagaRecord {
+            id,
+            kind,
+            payload,
+            status: SagaStatus::from_str(&status)
+                .map_err(|e| ServiceError::DataError(e.to_string()))?,
+            completed_steps,
+            error,
+            owner,
+            created_at,
+            updated_at,
+        })
+    }
+}
+
+#[async_trait]
+impl SagaStore for SagaRepository {
+    async fn save(&self, saga: &SagaRecord) -> ServiceResult<()> {
+        let statement = r#"
+            INSERT INTO sagas (id, kind, payload, status, completed_steps, error, owner, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            ON CONFLICT (id) DO UPDATE
+            SET status = EXCLUDED.status,
+                completed_steps = EXCLUDED.completed_steps,
+                error = EXCLUDED.error,
+                updated_at = EXCLUDED.updated_at
+            WHERE sagas.owner IS NOT DISTINCT FROM EXCLUDED.owner
+            "#;
+        let saved = sqlx::query(statement)
+            .bind(saga.id)
+            .bind(&saga.kind)
+            .bind(&saga.payload)
+            .bind(saga.status.as_str())
+            .bind(saga.completed_steps)
+            .bind(&saga.error)
+            .bind(saga.owner)
+            .bind(saga.created_at)
+            .bind(saga.updated_at)
+            .execute(&self.pg_client.pool)
+            .instrument(sql_span(statement))
+            .await
+            .map_err(ServiceError::from)?;
+
+        if saved.rows_affected() == 0 {
+            return Err(ServiceError::ConflictError(format!("Saga {} was taken over by another coordinator", saga.id)));
+        }
+
+        Ok(())
+    }
+
+    // Rows another caller is claiming are skipped rather than waited for; once that caller
+    // commits they no longer look stale
+    async fn claim_unfinished(&self, owner: Uuid, updated_before: DateTime<Utc>) -> ServiceResult<Vec<SagaRecord>> {
+        let query = format!(
+            r#"
+            UPDATE sagas
+            SET owner = $1, updated_at = $2
+            WHERE id IN (
+                SELECT id
+                FROM sagas
+                WHERE status IN ('running', 'compensating') AND updated_at < $3
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING {}
+            "#,
+            Self::SAGA_COLUMNS
+        );
+        let rows = sqlx::query(&query)
+            .bind(owner)
+            .bind(Utc::now())
+            .bind(updated_before)
+            .fetch_all(&self.pg_client.pool)
+            .instrument(sql_span(&query))
+            .await
+            .map_err(ServiceError::from)?;
+
+        let mut sagas = rows.iter().map(Self::map_saga).collect::<ServiceResult<Vec<_>>>()?;
+        sagas.sort_by_key(|saga| saga.created_at);
+
+        Ok(sagas)
+    }
+}
@@ -0,0 +1,143 @@
+use async_trait::async_trait;
+use futures_util::future::BoxFuture;
+use mongodb::ClientSession;
+use sqlx::postgres::{PgConnection, PgPool};
+use sqlx::{Postgres, Transaction};
+use crate::errors::{ServiceError, ServiceResult};
+
+type UndoAction = Box<dyn FnOnce() + Send>;
+
+// A transaction shared by every repository call made through it.
+// Postgres repositories run their statements on the wrapped transaction and MongoDB
+// repositories on the wrapped session's transaction; in-memory repositories apply writes
+// immediately and register undo actions instead. Dropping a unit of work without
+// committing it rolls everything back.
+pub struct UnitOfWork {
+    transaction: Option<Transaction<'static, Postgres>>,
+    session: Option<ClientSession>,
+    undo: Vec<UndoAction>,
+    after_commit: Vec<BoxFuture<'static, ()>>,
+}
+
+impl UnitOfWork {
+    pub async fn begin(pool: &PgPool) -> ServiceResult<Self> {
+        let transaction = pool.begin().await
+            .map_err(ServiceError::from)?;
+
+        Ok(Self {
+            transaction: Some(transaction),
+            session: None,
+            undo: Vec::new(),
+            after_commit: Vec::new(),
+        })
+    }
+
+    // A unit of work over a MongoDB transaction, which needs a replica set
+    pub async fn begin_mongo(client: &mongodb::Client) -> ServiceResult<Self> {
+        let mut session = client.start_session(None).await
+            .map_err(ServiceError::from)?;
+        session.start_transaction(None).await
+            .map_err(ServiceError::from)?;
+
+        Ok(Self {
+            transaction: None,
+            session: Some(session),
+            undo: Vec::new(),
+            after_commit: Vec::new(),
+        })
+    }
+
+    // A unit of work with no database transaction, for in-memory repositories
+    pub fn in_memory() -> Self {
+        Self {
+            transaction: None,
+            session: None,
+            undo: Vec::new(),
+            after_commit: Vec::new(),
+        }
+    }
+
+    pub fn connection(&mut self) -> ServiceResult<&mut PgConnection> {
+        match self.transaction.as_mut() {
+            Some(transaction) => Ok(transaction),
+            None => Err(ServiceError::DataError(
+                "Unit of work is not backed by a Postgres transaction".to_string(),
+            )),
+        }
+    }
+
+    pub fn session(&mut self) -> ServiceResult<&mut ClientSession> {
+        match self.session.as_mut() {
+            Some(session) => Ok(session),
+            None => Err(ServiceError::DataError(
+                "Unit of work is not backed by a MongoDB transaction".to_string(),
+            )),
+        }
+    }
+
+    pub fn is_mongo(&self) -> bool {
+        self.session.is_some()
+    }
+
+    pub fn on_rollback(&mut self, undo: impl FnOnce() + Send + 'static) {
+        self.undo.push(Box::new(undo));
+    }
+
+    // Run `action` once the unit of work has committed; it is skipped if the commit fails or
+    // the unit of work is rolled back
+    pub fn after_commit(&mut self, action: impl std::future::Future<Output = ()> + Send + 'static) {
+        self.after_commit.push(Box::pin(action));
+    }
+
+    // Undo actions are kept until the commit is known to have succeeded, and run if it fails
+    pub async fn commit(mut self) -> ServiceResult<()> {
+        let result = match (self.transaction.take(), self.session.as_mut()) {
+            (Some(transaction), _) => transaction.commit().await
+                .map_err(ServiceError::from),
+            (None, Some(session)) => session.commit_transaction().await
+                .map_err(ServiceError::from),
+            (None, None) => Ok(()),
+        };
+        match result {
+            Ok(()) => {
+                self.undo.clear();
+                for action in std::mem::take(&mut self.after_commit) {
+                    action.await;
+                }
+            }
+            Err(_) => self.run_undo(),
+        }
+        result
+    }
+
+    pub async fn rollback(mut self) -> ServiceResult<()> {
+        self.run_undo();
+        self.after_commit.clear();
+
+        match (self.transaction.take(), self.session.as_mut()) {
+            (Some(transaction), _) => transaction.rollback().await
+                .map_err(ServiceError::from),
+            (None, Some(session)) => session.abort_transaction().await
+                .map_err(ServiceError::from),
+            (None, None) => Ok(()),
+        }
+    }
+
+    fn run_undo(&mut self) {
+        while let Some(undo) = self.undo.pop() {
+            undo();
+        }
+    }
+}
+
+impl Drop for UnitOfWork {
+    fn drop(&mut self) {
+        self.run_undo();
+    }
+}
+
+// Starts units of work, so services can group writes without knowing the storage backend
+#[async_trait]
+pub trait TransactionManager: Send + Sync {
+    async fn begin(&self) -> ServiceResult<UnitOfWork>;
+}
\ No newline at end of file
@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{Duration, Utc};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+use crate::errors::{ServiceError, ServiceResult};
+use crate::models::api_key::{ApiKey, CreateApiKeyDto, IssuedApiKey};
+use crate::models::auth::ApiKeyIdentity;
+use crate::repositories::ApiKeyStore;
+
+// Keys look like `bsk_<id>_<secret>`: the id finds the stored hash, the secret proves possession
+pub const API_KEY_PREFIX: &str = "bsk_";
+const SECRET_BYTES: usize = 32;
+
+// Stored hash a key was last verified against, and a digest of the key that passed
+type VerifiedKey = (String, [u8; 32]);
+
+pub struct ApiKeyService {
+    store: Arc<dyn ApiKeyStore>,
+    // Argon2 is deliberately slow, so a key that passed once is not checked again until
+    // its stored hash changes
+    verified: RwLock<HashMap<Uuid, VerifiedKey>>,
+}
+
+impl ApiKeyService {
+    // How stale `last_used_at` may get before a request updates it
+    const TOUCH_INTERVAL_SECS: i64 = 60;
+    
+    pub fn new(store: Arc<dyn ApiKeyStore>) -> Self {
+        Self {
+            store,
+            verified: RwLock::new(HashMap::new()),
+        }
+    }
+    
+    #[tracing::instrument(skip_all)]
+    pub async fn issue(&self, dto: CreateApiKeyDto) -> ServiceResult<IssuedApiKey> {
+        let name = dto.name.trim().to_string();
+        if name.is_empty() {
+            return Err(ServiceError::ValidationError("API key name must not be empty".to_string()));
+        }
+        let mut scopes = Vec::with_capacity(dto.scopes.len());
+        for scope in dto.scopes {
+            if !scopes.contains(&scope) {
+                scopes.push(scope);
+            }
+        }
+        if scopes.is_empty() {
+            return Err(ServiceError::ValidationError("API key needs at least one scope".to_string()));
+        }
+        
+        let id = Uuid::new_v4();
+        let (key, key_hash) = Self::generate(id).await?;
+        let api_key = ApiKey {
+            id,
+            name,
+            scopes,
+            key_hash,
+            created_at: Utc::now(),
+            rotated_at: None,
+            last_used_at: None,
+            revoked_at: None,
+        };
+        self.store.create(&api_key).await?;
+        
+        Ok(IssuedApiKey { api_key, key })
+    }
+    
+    #[tracing::instrument(skip_all)]
+    pub async fn get_all_keys(&self) -> ServiceResult<Vec<ApiKey>> {
+        self.store.find_all().await
+    }
+    
+    // Replace a key's secret. The previous secret stops working immediately.
+    #[tracing::instrument(skip_all, fields(api_key.id = %id))]
+    pub async fn rotate(&self, id: Uuid) -> ServiceResult<IssuedApiKey> {
+        let (key, key_hash) = Self::generate(id).await?;
+        let api_key = match self.store.rotate(id, &key_hash, Utc::now()).await? {
+            Some(api_key) => api_key,
+            None if self.store.find_by_id(id).await?.is_some() => {
+                return Err(ServiceError::ConflictError(format!("API key {} has been revoked", id)));
+            }
+            None => return Err(ServiceError::NotFoundError(format!("API key with id {} not found", id))),
+        };
+        self.verified.write().unwrap().remove(&id);
+        
+        Ok(IssuedApiKey { api_key, key })
+    }
+    
+    #[tracing::instrument(skip_all, fields(api_key.id = %id))]
+    pub async fn revoke(&self, id: Uuid) -> ServiceResult<ApiKey> {
+        let api_key = self.store.revoke(id, Utc::now()).await?
+            .ok_or_else(|| ServiceError::NotFoundError(format!("API key with id {} not found", id)))?;
+        self.verified.write().unwrap().remove(&id);
+        
+        Ok(api_key)
+    }
+    
+    #[tracing::instrument(skip_all)]
+    pub async fn authenticate(&self, presented: &str) -> ServiceResult<ApiKeyIdentity> {
+        let invalid = || ServiceError::AuthError("Invalid API key".to_string());
+        
+        let id = Self::parse_id(presented).ok_or_else(invalid)?;
+        let api_key = self.store.find_by_id(id).await?
+            .filter(|api_key| !api_key.is_revoked())
+            .ok_or_else(invalid)?;
+        
+        let digest: [u8; 32] = Sha256::digest(presented.as_bytes()).into();
+        let cached = self.verified.read().unwrap().get(&id) == Some(&(api_key.key_hash.clone(), digest));
+        if !cached {
+            if !Self::verify(presented, &api_key.key_hash).await? {
+                return Err(invalid());
+            }
+            self.verified.write().unwrap().insert(id, (api_key.key_hash.clone(), digest));
+        }
+        
+        let now = Utc::now();
+        let stale = api_key.last_used_at
+            .is_none_or(|used| now - used >= Duration::seconds(Self::TOUCH_INTERVAL_SECS));
+        if stale {
+            if let Err(e) = self.store.touch(id, now).await {
+                tracing::warn!("Failed to record use of API key {}: {}", id, e);
+            }
+        }
+        
+        Ok(ApiKeyIdentity {
+            id,
+            name: api_key.name,
+            scopes: api_key.scopes,
+        })
+    }
+    
+    fn parse_id(presented: &str) -> Option<Uuid> {
+        let (id, secret) = presented.strip_prefix(API_KEY_PREFIX)?.split_once('_')?;
+        if secret.is_empty() {
+            return None;
+        }
+        
+        Uuid::try_parse(id).ok()
+    }
+    
+    // A new key for the given id and its argon2 hash. Hashing runs off the async workers.
+    async fn generate(id: Uuid) -> ServiceResult<(String, String)> {
+        let mut secret = [0u8; SECRET_BYTES];
+        OsRng.fill_bytes(&mut secret);
+        let key = format!("{}{}_{}", API_KEY_PREFIX, id.simple(), URL_SAFE_NO_PAD.encode(secret));
+        
+        let hashed = key.clone();
+        let key_hash = tokio::task::spawn_blocking(move || {
+            let salt = SaltString::generate(&mut OsRng);
+            Argon2::default()
+                .hash_password(hashed.as_bytes(), &salt)
+                .map(|hash| hash.to_string())
+        })
+        .await
+        .map_err(|e| ServiceError::UnknownError(e.to_string()))?
+        .map_err(|e| ServiceError::UnknownError(format!("Failed to hash API key: {}", e)))?;
+        
+        Ok((key, key_hash))
+    }
+    
+    async fn verify(presented: &str, key_hash: &str) -> ServiceResult<bool> {
+        let presented = presented.to_string();
+        let key_hash = key_hash.to_string();
+        
+        tokio::task::spawn_blocking(move || {
+            let hash = PasswordHash::new(&key_hash)
+                .map_err(|e| ServiceError::DataError(format!("Invalid API key hash: {}", e)))?;
+            Ok(Argon2::default().verify_password(presented.as_bytes(), &hash).is_ok())
+        })
+        .await
+        .map_err(|e| ServiceError::UnknownError(e.to_string()))?
+    }
+}
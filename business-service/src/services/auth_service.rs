@@ -0,0 +1,29 @@
+use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+use crate::config::AuthConfig;
+use crate::errors::{ServiceError, ServiceResult};
+use crate::models::auth::{AuthenticatedUser, Claims};
+
+// Verifies the HS256 tokens auth-service signs with the shared JWT secret
+pub struct AuthService {
+    key: DecodingKey,
+    validation: Validation,
+}
+
+impl AuthService {
+    pub fn new(config: &AuthConfig) -> Self {
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.set_required_spec_claims(&["exp", "iat"]);
+        
+        Self {
+            key: DecodingKey::from_secret(config.jwt_secret.as_bytes()),
+            validation,
+        }
+    }
+    
+    pub fn authenticate(&self, token: &str) -> ServiceResult<AuthenticatedUser> {
+        let data = decode::<Claims>(token, &self.key, &self.validation)
+            .map_err(|e| ServiceError::AuthError(format!("Invalid token: {}", e)))?;
+        
+        Ok(data.claims.into())
+    }
+}
@@ -0,0 +1,67 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use futures_util::future::join_all;
+use crate::models::health::{DependencyHealth, HealthReport, HealthStatus};
+use crate::repositories::HealthCheck;
+
+struct Dependency {
+    name: String,
+    check: Arc<dyn HealthCheck>,
+    required: bool,
+}
+
+pub struct HealthService {
+    dependencies: Vec<Dependency>,
+    timeout: Duration,
+}
+
+impl HealthService {
+    // Each check that takes longer than `timeout` counts as down
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            dependencies: Vec::new(),
+            timeout,
+        }
+    }
+    
+    pub fn with_dependency(mut self, name: &str, check: Arc<dyn HealthCheck>, required: bool) -> Self {
+        self.dependencies.push(Dependency {
+            name: name.to_string(),
+            check,
+            required,
+        });
+        self
+    }
+    
+    // Check every dependency concurrently, so a readiness probe takes at most one timeout
+    pub async fn readiness(&self) -> HealthReport {
+        let checks = join_all(self.dependencies.iter().map(|dependency| self.check(dependency))).await;
+        HealthReport::new(checks)
+    }
+    
+    async fn check(&self, dependency: &Dependency) -> DependencyHealth {
+        let started = Instant::now();
+        let result = tokio::time::timeout(self.timeout, dependency.check.health_check()).await;
+        let latency_ms = started.elapsed().as_millis() as u64;
+        
+        let error = match result {
+            Ok(Ok(())) => None,
+            Ok(Err(e)) => {
+                tracing::warn!("Health check for {} failed: {}", dependency.name, e);
+                Some("unavailable".to_string())
+            }
+            Err(_) => {
+                tracing::warn!("Health check for {} timed out after {:?}", dependency.name, self.timeout);
+                Some(format!("timed out after {} ms", self.timeout.as_millis()))
+            }
+        };
+        
+        DependencyHealth {
+            name: dependency.name.clone(),
+            status: if error.is_none() { HealthStatus::Up } else { HealthStatus::Down },
+            required: dependency.required,
+            latency_ms,
+            error,
+        }
+    }
+}
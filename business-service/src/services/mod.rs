@@ -1,5 +1,15 @@
 pub mod product_service;
 pub mod order_service;
+pub mod saga;
+pub mod order_sagas;
+pub mod auth_service;
+pub mod api_key_service;
+pub mod health_service;
 
 pub use product_service::*;
-pub use order_service::*;
\ No newline at end of file
+pub use order_service::*;
+pub use saga::*;
+pub use order_sagas::*;
+pub use auth_service::*;
+pub use api_key_service::*;
+pub use health_service::*;
\ No newline at end of file
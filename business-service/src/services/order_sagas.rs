@@ -0,0 +1,340 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use crate::errors::{ServiceError, ServiceResult};
+use crate::models::event::{DomainEvent, EventType};
+use crate::models::order::{Order, OrderStatus, OrderStatusChange};
+use crate::models::saga::SagaRecord;
+use crate::models::stock::StockChange;
+use crate::repositories::{OrderHistoryStore, OutboxStore, StockRepository, TransactionManager, TransactionalRepository};
+use crate::services::saga::{operation_id, SagaDefinition, SagaStep};
+
+pub const PLACE_ORDER: &str = "place_order";
+pub const CHANGE_ORDER_STATUS: &str = "change_order_status";
+pub const DELETE_ORDER: &str = "delete_order";
+
+// Stores touched by the order sagas: orders, their history and their events in Postgres,
+// stock in MongoDB
+#[derive(Clone)]
+pub struct OrderSagaContext {
+    pub orders: Arc<dyn TransactionalRepository<Order, Uuid>>,
+    pub stock: Arc<dyn StockRepository>,
+    pub history: Arc<dyn OrderHistoryStore>,
+    pub outbox: Arc<dyn OutboxStore>,
+    pub transactions: Arc<dyn TransactionManager>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StatusChangePayload {
+    pub order: Order,
+    pub to: OrderStatus,
+    pub actor: Option<String>,
+    pub reason: Option<String>,
+}
+
+fn payload<T: for<'de> Deserialize<'de>>(saga: &SagaRecord) -> ServiceResult<T> {
+    serde_json::from_value(saga.payload.clone())
+        .map_err(|e| ServiceError::UnknownError(format!("Invalid payload for saga {}: {}", saga.id, e)))
+}
+
+// One stock step per order item, each applied under ids derived from the saga
+fn stock_steps(
+    context: &OrderSagaContext,
+    saga: &SagaRecord,
+    first_step: usize,
+    order: &Order,
+    change: fn(i32) -> StockChange,
+) -> Vec<Box<dyn SagaStep>> {
+    order.items
+        .iter()
+        .enumerate()
+        .map(|(offset, item)| {
+            let step = first_step + offset;
+            Box::new(StockStep {
+                stock: context.stock.clone(),
+                product_id: item.product_id,
+                change: change(item.quantity),
+                execute_id: operation_id(saga, step, "execute"),
+                compensate_id: operation_id(saga, step, "compensate"),
+            }) as Box<dyn SagaStep>
+        })
+        .collect()
+}
+
+// Reserve stock for every item, then write the order
+pub struct PlaceOrderSaga(pub OrderSagaContext);
+
+impl SagaDefinition for PlaceOrderSaga {
+    fn kind(&self) -> &'static str {
+        PLACE_ORDER
+    }
+    
+    fn steps(&self, saga: &SagaRecord) -> ServiceResult<Vec<Box<dyn SagaStep>>> {
+        let order: Order = payload(saga)?;
+        
+        let mut steps = stock_steps(&self.0, saga, 0, &order, StockChange::reserve);
+        steps.push(Box::new(CreateOrderStep { context: self.0.clone(), order }));
+        
+        Ok(steps)
+    }
+}
+
+// Change the order's status, then release its reservation (cancelled) or take it
+// out of stock (shipped)
+pub struct ChangeOrderStatusSaga(pub OrderSagaContext);
+
+impl SagaDefinition for ChangeOrderStatusSaga {
+    fn kind(&self) -> &'static str {
+        CHANGE_ORDER_STATUS
+    }
+    
+    fn steps(&self, saga: &SagaRecord) -> ServiceResult<Vec<Box<dyn SagaStep>>> {
+        let change: StatusChangePayload = payload(saga)?;
+        
+        let mut steps: Vec<Box<dyn SagaStep>> = vec![Box::new(ChangeStatusStep {
+            context: self.0.clone(),
+            order_id: change.order.id.unwrap_or_default(),
+            from: change.order.status,
+            to: change.to,
+            actor: change.actor.clone(),
+            reason: change.reason.clone(),
+            execute_id: operation_id(saga, 0, "execute"),
+            compensate_id: operation_id(saga, 0, "compensate"),
+        })];
+        match change.to {
+            OrderStatus::Cancelled => steps.extend(stock_steps(&self.0, saga, 1, &change.order, StockChange::release)),
+            OrderStatus::Shipped => steps.extend(stock_steps(&self.0, saga, 1, &change.order, StockChange::commit)),
+            _ => {}
+        }
+        
+        Ok(steps)
+    }
+}
+
+// Delete the order, then release its reservation if it still holds one
+pub struct DeleteOrderSaga(pub OrderSagaContext);
+
+impl SagaDefinition for DeleteOrderSaga {
+    fn kind(&self) -> &'static str {
+        DELETE_ORDER
+    }
+    
+    fn steps(&self, saga: &SagaRecord) -> ServiceResult<Vec<Box<dyn SagaStep>>> {
+        let order: Order = payload(saga)?;
+        let holds_stock = matches!(order.status, OrderStatus::Pending | OrderStatus::Processing);
+        
+        let mut steps: Vec<Box<dyn SagaStep>> = Vec::new();
+        if holds_stock {
+            steps.extend(stock_steps(&self.0, saga, 1, &order, StockChange::release));
+        }
+        steps.insert(0, Box::new(DeleteOrderStep { context: self.0.clone(), order }));
+        
+        Ok(steps)
+    }
+}
+
+struct StockStep {
+    stock: Arc<dyn StockRepository>,
+    product_id: Uuid,
+    change: StockChange,
+    execute_id: Uuid,
+    compensate_id: Uuid,
+}
+
+#[async_trait]
+impl SagaStep for StockStep {
+    fn name(&self) -> &'static str {
+        "stock"
+    }
+    
+    async fn execute(&self) -> ServiceResult<()> {
+        self.stock.change_stock(self.product_id, self.change, self.execute_id).await.map(|_| ())
+    }
+    
+    // Only a change that was applied is undone; a refused one left the stock untouched
+    async fn compensate(&self) -> ServiceResult<()> {
+        if !self.stock.has_applied(self.product_id, self.execute_id).await? {
+            return Ok(());
+        }
+        
+        self.stock.change_stock(self.product_id, self.change.inverse(), self.compensate_id).await.map(|_| ())
+    }
+}
+
+struct CreateOrderStep {
+    context: OrderSagaContext,
+    order: Order,
+}
+
+#[async_trait]
+impl SagaStep for CreateOrderStep {
+    fn name(&self) -> &'static str {
+        "create_order"
+    }
+    
+    async fn execute(&self) -> ServiceResult<()> {
+        let order_id = self.order.id.unwrap_or_default();
+        let mut uow = self.context.transactions.begin().await?;
+        if self.context.orders.find_by_id_in(&mut uow, order_id).await?.is_some() {
+            return Ok(());
+        }
+        
+        let created = self.context.orders.create_in(&mut uow, self.order.clone()).await?;
+        self.context.history.record_in(&mut uow, OrderStatusChange {
+            order_id,
+            from: None,
+            to: created.status,
+            changed_at: created.created_at,
+            actor: None,
+            reason: None,
+        }, None).await?;
+        let event = DomainEvent::new(EventType::OrderCreated, order_id, &created)?;
+        self.context.outbox.append_in(&mut uow, &[event]).await?;
+        uow.commit().await
+    }
+    
+    async fn compensate(&self) -> ServiceResult<()> {
+        let order_id = self.order.id.unwrap_or_default();
+        let mut uow = self.context.transactions.begin().await?;
+        if self.context.orders.find_by_id_in(&mut uow, order_id).await?.is_none() {
+            return Ok(());
+        }
+        
+        self.context.orders.delete_in(&mut uow, order_id).await?;
+        let event = DomainEvent::new(EventType::OrderDeleted, order_id, &self.order)?;
+        self.context.outbox.append_in(&mut uow, &[event]).await?;
+        uow.commit().await
+    }
+}
+
+struct ChangeStatusStep {
+    context: OrderSagaContext,
+    order_id: Uuid,
+    from: OrderStatus,
+    to: OrderStatus,
+    actor: Option<String>,
+    reason: Option<String>,
+    execute_id: Uuid,
+    compensate_id: Uuid,
+}
+
+impl ChangeStatusStep {
+    // Move the locked order from one status to another, recording the change under
+    // `operation`. A change already recorded under it is not repeated, and one that
+    // needs `after` is skipped unless that was recorded. Any other order not in `from`
+    // was changed by someone else, which is a conflict.
+    async fn transition(
+        &self,
+        from: OrderStatus,
+        to: OrderStatus,
+        actor: Option<String>,
+        reason: Option<String>,
+        operation: Uuid,
+        after: Option<Uuid>,
+    ) -> ServiceResult<()> {
+        let history = &self.context.history;
+        let mut uow = self.context.transactions.begin().await?;
+        let mut order = self.context.orders.find_by_id_in(&mut uow, self.order_id).await?
+            .ok_or_else(|| ServiceError::NotFoundError(format!("Order with id {} not found", self.order_id)))?;
+        
+        if history.has_recorded_in(&mut uow, self.order_id, operation).await? {
+            return Ok(());
+        }
+        if let Some(after) = after {
+            if !history.has_recorded_in(&mut uow, self.order_id, after).await? {
+                return Ok(());
+            }
+        }
+        if order.status != from {
+            return Err(ServiceError::ConflictError(format!(
+                "Order {} is {}, expected {}", self.order_id, order.status, from
+            )));
+        }
+        
+        order.status = to;
+        order.updated_at = chrono::Utc::now();
+        let updated = self.context.orders.update_in(&mut uow, self.order_id, order).await?;
+        let change = history.record_in(&mut uow, OrderStatusChange {
+            order_id: self.order_id,
+            from: Some(from),
+            to,
+            changed_at: updated.updated_at,
+            actor,
+            reason,
+        }, Some(operation)).await?;
+        let event = DomainEvent::new(EventType::OrderStatusChanged, self.order_id, &change)?;
+        self.context.outbox.append_in(&mut uow, &[event]).await?;
+        uow.commit().await
+    }
+}
+
+#[async_trait]
+impl SagaStep for ChangeStatusStep {
+    fn name(&self) -> &'static str {
+        "change_status"
+    }
+    
+    async fn execute(&self) -> ServiceResult<()> {
+        self.transition(self.from, self.to, self.actor.clone(), self.reason.clone(), self.execute_id, None).await
+    }
+    
+    // Reverting bypasses the transition table: it undoes a change that never fully happened.
+    // Only a change this saga made is reverted; an order that has since moved on, or is gone,
+    // is left alone.
+    async fn compensate(&self) -> ServiceResult<()> {
+        let reason = Some("reverted: status change could not be completed".to_string());
+        match self.transition(self.to, self.from, None, reason, self.compensate_id, Some(self.execute_id)).await {
+            Err(ServiceError::ConflictError(message)) | Err(ServiceError::NotFoundError(message)) => {
+                tracing::warn!("Not reverting status of order {}: {}", self.order_id, message);
+                Ok(())
+            }
+            result => result,
+        }
+    }
+}
+
+struct DeleteOrderStep {
+    context: OrderSagaContext,
+    order: Order,
+}
+
+#[async_trait]
+impl SagaStep for DeleteOrderStep {
+    fn name(&self) -> &'static str {
+        "delete_order"
+    }
+    
+    async fn execute(&self) -> ServiceResult<()> {
+        let order_id = self.order.id.unwrap_or_default();
+        let mut uow = self.context.transactions.begin().await?;
+        let Some(order) = self.context.orders.find_by_id_in(&mut uow, order_id).await? else {
+            return Ok(());
+        };
+        // The saga's stock steps were planned from the order's status when it started
+        if order.status != self.order.status {
+            return Err(ServiceError::ConflictError(format!(
+                "Order {} is {}, expected {}", order_id, order.status, self.order.status
+            )));
+        }
+        
+        self.context.orders.delete_in(&mut uow, order_id).await?;
+        let event = DomainEvent::new(EventType::OrderDeleted, order_id, &order)?;
+        self.context.outbox.append_in(&mut uow, &[event]).await?;
+        uow.commit().await
+    }
+    
+    // Restores the order itself; its status history was removed with it
+    async fn compensate(&self) -> ServiceResult<()> {
+        let order_id = self.order.id.unwrap_or_default();
+        let mut uow = self.context.transactions.begin().await?;
+        if self.context.orders.find_by_id_in(&mut uow, order_id).await?.is_some() {
+            return Ok(());
+        }
+        
+        let restored = self.context.orders.create_in(&mut uow, self.order.clone()).await?;
+        let event = DomainEvent::new(EventType::OrderRestored, order_id, &restored)?;
+        self.context.outbox.append_in(&mut uow, &[event]).await?;
+        uow.commit().await
+    }
+}
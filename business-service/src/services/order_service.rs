@@ -1,48 +1,158 @@
+use std::sync::Arc;
 use uuid::Uuid;
-use crate::errors::ServiceResult;
-use crate::models::order::{Order, CreateOrderDto, UpdateOrderStatusDto};
-use crate::repositories::{Repository, OrderRepository};
+use crate::errors::{ServiceError, ServiceResult};
+use crate::metrics::metrics;
+use crate::models::order::{Order, OrderItem, CreateOrderDto, CreateOrderItemDto, OrderStatusChange, UpdateOrderStatusDto};
+use crate::models::product::Product;
+use crate::repositories::{
+    OrderHistoryStore, OutboxStore, Page, QuerySpec, Repository, SagaStore, StockRepository, TransactionManager,
+    TransactionalRepository,
+};
+use crate::services::order_sagas::{
+    ChangeOrderStatusSaga, DeleteOrderSaga, OrderSagaContext, PlaceOrderSaga, StatusChangePayload,
+    CHANGE_ORDER_STATUS, DELETE_ORDER, PLACE_ORDER,
+};
+use crate::services::saga::SagaCoordinator;
 
 pub struct OrderService {
-    repository: OrderRepository,
+    repository: Arc<dyn TransactionalRepository<Order, Uuid>>,
+    products: Arc<dyn Repository<Product, Uuid>>,
+    history: Arc<dyn OrderHistoryStore>,
+    sagas: SagaCoordinator,
 }
 
 impl OrderService {
-    pub fn new(repository: OrderRepository) -> Self {
-        Self { repository }
+    pub fn new(
+        repository: Arc<dyn TransactionalRepository<Order, Uuid>>,
+        products: Arc<dyn Repository<Product, Uuid>>,
+        stock: Arc<dyn StockRepository>,
+        history: Arc<dyn OrderHistoryStore>,
+        outbox: Arc<dyn OutboxStore>,
+        transactions: Arc<dyn TransactionManager>,
+        saga_store: Arc<dyn SagaStore>,
+    ) -> Self {
+        // Writes that touch both orders (Postgres) and stock (MongoDB) run as sagas
+        let context = OrderSagaContext {
+            orders: repository.clone(),
+            stock,
+            history: history.clone(),
+            outbox,
+            transactions,
+        };
+        let mut sagas = SagaCoordinator::new(saga_store);
+        sagas.register(Arc::new(PlaceOrderSaga(context.clone())));
+        sagas.register(Arc::new(ChangeOrderStatusSaga(context.clone())));
+        sagas.register(Arc::new(DeleteOrderSaga(context)));
+        
+        Self { repository, products, history, sagas }
     }
     
+    #[tracing::instrument(skip_all, fields(order.id = %id))]
     pub async fn get_order(&self, id: Uuid) -> ServiceResult<Option<Order>> {
         self.repository.find_by_id(id).await
     }
     
-    pub async fn get_all_orders(&self) -> ServiceResult<Vec<Order>> {
-        self.repository.find_all().await
+    #[tracing::instrument(skip_all)]
+    pub async fn get_all_orders(&self, query: &QuerySpec) -> ServiceResult<Page<Order>> {
+        self.repository.find_all(query).await
     }
     
+    #[tracing::instrument(skip_all, fields(customer.id = %dto.customer_id))]
     pub async fn create_order(&self, dto: CreateOrderDto) -> ServiceResult<Order> {
+        let items = self.price_items(&dto.items).await?;
         let order = Order::new(
             dto.customer_id,
-            dto.items,
-        );
+            items,
+        )?;
+        
+        self.sagas.run(PLACE_ORDER, Self::to_payload(&order)?).await?;
+        metrics().record_order_created(&order);
         
-        self.repository.create(order).await
+        Ok(order)
     }
     
-    pub async fn update_order_status(&self, id: Uuid, dto: UpdateOrderStatusDto) -> ServiceResult<Order> {
-        // First, get the existing order
+    #[tracing::instrument(skip_all, fields(order.id = %id, order.status = %dto.status))]
+    pub async fn update_order_status(
+        &self,
+        id: Uuid,
+        dto: UpdateOrderStatusDto,
+        actor: Option<String>,
+    ) -> ServiceResult<Order> {
         let existing_order = self.repository.find_by_id(id).await?
-            .ok_or_else(|| crate::errors::ServiceError::NotFoundError(format!("Order with id {} not found", id)))?;
+            .ok_or_else(|| ServiceError::NotFoundError(format!("Order with id {} not found", id)))?;
+        
+        if !existing_order.status.can_transition_to(dto.status) {
+            return Err(ServiceError::ConflictError(format!(
+                "Order {} cannot move from {} to {}", id, existing_order.status, dto.status
+            )));
+        }
         
-        // Create updated order with new status
-        let mut updated_order = existing_order.clone();
-        updated_order.status = dto.status;
-        updated_order.updated_at = chrono::Utc::now();
+        let change = StatusChangePayload {
+            order: existing_order,
+            to: dto.status,
+            actor,
+            reason: dto.reason,
+        };
+        self.sagas.run(CHANGE_ORDER_STATUS, Self::to_payload(&change)?).await?;
+        metrics().record_order_status_change(dto.status);
         
-        self.repository.update(id, updated_order).await
+        self.repository.find_by_id(id).await?
+            .ok_or_else(|| ServiceError::NotFoundError(format!("Order with id {} not found", id)))
     }
     
+    #[tracing::instrument(skip_all, fields(order.id = %id))]
+    pub async fn get_order_history(&self, id: Uuid) -> ServiceResult<Vec<OrderStatusChange>> {
+        if self.repository.find_by_id(id).await?.is_none() {
+            return Err(ServiceError::NotFoundError(format!("Order with id {} not found", id)));
+        }
+        
+        self.history.find_by_order(id).await
+    }
+    
+    #[tracing::instrument(skip_all, fields(order.id = %id))]
     pub async fn delete_order(&self, id: Uuid) -> ServiceResult<()> {
-        self.repository.delete(id).await
+        let order = self.repository.find_by_id(id).await?
+            .ok_or_else(|| ServiceError::NotFoundError(format!("Order with ID {} not found", id)))?;
+        
+        self.sagas.run(DELETE_ORDER, Self::to_payload(&order)?).await?;
+        
+        Ok(())
     }
-}
\ No newline at end of file
+    
+    // Resume order sagas abandoned by a crashed or restarted process
+    #[tracing::instrument(skip_all)]
+    pub async fn recover_sagas(&self) -> ServiceResult<usize> {
+        self.sagas.recover().await
+    }
+    
+    fn to_payload<T: serde::Serialize>(value: &T) -> ServiceResult<serde_json::Value> {
+        serde_json::to_value(value)
+            .map_err(|e| ServiceError::UnknownError(e.to_string()))
+    }
+    
+    // Price each requested item from the catalog, rejecting unknown products
+    async fn price_items(&self, requested: &[CreateOrderItemDto]) -> ServiceResult<Vec<OrderItem>> {
+        let mut items = Vec::with_capacity(requested.len());
+        
+        for item in requested {
+            if item.quantity <= 0 {
+                return Err(ServiceError::ValidationError(format!(
+                    "Quantity for product {} must be positive", item.product_id
+                )));
+            }
+            
+            let product = self.products.find_by_id(item.product_id).await?
+                .ok_or_else(|| ServiceError::ValidationError(format!("Product {} does not exist", item.product_id)))?;
+            
+            items.push(OrderItem {
+                product_id: item.product_id,
+                name: product.name,
+                sku: product.sku,
+                quantity: item.quantity,
+                price: product.price,
+            });
+        }
+        
+        Ok(items)
+    }
+}
@@ -1,41 +1,109 @@
+use std::sync::Arc;
+use serde_json::json;
 use uuid::Uuid;
-use crate::errors::ServiceResult;
+use crate::errors::{ServiceError, ServiceResult};
+use crate::metrics::metrics;
+use crate::models::event::{DomainEvent, EventType};
+use crate::models::facet::ProductFacets;
 use crate::models::product::{Product, CreateProductDto, UpdateProductDto};
-use crate::repositories::{Repository, ProductRepository};
+use crate::models::search::{ProductSearchHit, SearchTerms};
+use crate::models::stock::{AdjustStockDto, ProductStock, StockChange, StockLevel};
+use crate::repositories::{
+    OutboxStore, Page, ProductSearch, QuerySpec, Repository, StockRepository, TransactionManager, TransactionalRepository,
+};
 
+// Catalog changes are written in a unit of work from `transactions` together with the events
+// describing them, so with MongoDB a change and its events commit or fail as one
 pub struct ProductService {
-    repository: ProductRepository,
+    repository: Arc<dyn TransactionalRepository<Product, Uuid>>,
+    // The products as stored, bypassing any cache in `repository`, for reads that a change is based on
+    current: Arc<dyn Repository<Product, Uuid>>,
+    stock: Arc<dyn StockRepository>,
+    search: Arc<dyn ProductSearch>,
+    outbox: Arc<dyn OutboxStore>,
+    transactions: Arc<dyn TransactionManager>,
 }
 
 impl ProductService {
-    pub fn new(repository: ProductRepository) -> Self {
-        Self { repository }
+    pub fn new(
+        repository: Arc<dyn TransactionalRepository<Product, Uuid>>,
+        current: Arc<dyn Repository<Product, Uuid>>,
+        stock: Arc<dyn StockRepository>,
+        search: Arc<dyn ProductSearch>,
+        outbox: Arc<dyn OutboxStore>,
+        transactions: Arc<dyn TransactionManager>,
+    ) -> Self {
+        Self { repository, current, stock, search, outbox, transactions }
     }
     
+    #[tracing::instrument(skip_all, fields(product.id = %id))]
     pub async fn get_product(&self, id: Uuid) -> ServiceResult<Option<Product>> {
         self.repository.find_by_id(id).await
     }
     
-    pub async fn get_all_products(&self) -> ServiceResult<Vec<Product>> {
-        self.repository.find_all().await
+    #[tracing::instrument(skip_all)]
+    pub async fn get_all_products(&self, query: &QuerySpec) -> ServiceResult<Page<Product>> {
+        self.repository.find_all(query).await
     }
     
+    // Products matching `text`, most relevant first, with the matched words highlighted
+    #[tracing::instrument(skip_all)]
+    pub async fn search_products(&self, text: &str, query: &QuerySpec) -> ServiceResult<Page<ProductSearchHit>> {
+        let terms = SearchTerms::parse(text)?;
+        let page = self.search.search(&terms, query).await?;
+        
+        Ok(Page {
+            items: page.items
+                .into_iter()
+                .map(|scored| ProductSearchHit {
+                    highlights: terms.highlight(&scored.product),
+                    product: scored.product,
+                    score: scored.score,
+                })
+                .collect(),
+            total: page.total,
+            limit: page.limit,
+            offset: page.offset,
+        })
+    }
+    
+    // Counts per category, price range and availability over everything the listing, or the
+    // search for `text`, matches
+    #[tracing::instrument(skip_all)]
+    pub async fn get_product_facets(&self, text: Option<&str>, query: &QuerySpec) -> ServiceResult<ProductFacets> {
+        let terms = text.map(SearchTerms::parse).transpose()?;
+        self.search.facets(terms.as_ref(), &query.filters).await
+    }
+    
+    #[tracing::instrument(skip_all)]
     pub async fn create_product(&self, dto: CreateProductDto) -> ServiceResult<Product> {
-        let product = Product::new(
+        if dto.on_hand < 0 {
+            return Err(ServiceError::ValidationError("on_hand cannot be negative".to_string()));
+        }
+        
+        let mut product = Product::new(
             dto.name,
             dto.description,
             dto.price,
             dto.sku,
             dto.category,
         );
+        product.set_stock(StockLevel::new(dto.on_hand, 0));
         
-        self.repository.create(product).await
+        let event = DomainEvent::new(EventType::ProductCreated, product.id.unwrap_or_default(), &product)?;
+        let mut uow = self.transactions.begin().await?;
+        let created = self.repository.create_in(&mut uow, product).await?;
+        self.outbox.append_in(&mut uow, &[event]).await?;
+        uow.commit().await?;
+        metrics().record_product_change("created");
+        Ok(created)
     }
     
+    #[tracing::instrument(skip_all, fields(product.id = %id))]
     pub async fn update_product(&self, id: Uuid, dto: UpdateProductDto) -> ServiceResult<Product> {
-        // First, get the existing product
-        let existing_product = self.repository.find_by_id(id).await?
-            .ok_or_else(|| crate::errors::ServiceError::NotFoundError(format!("Product with id {} not found", id)))?;
+        // First, get the existing product as stored, so that the update cannot write back stale cached fields
+        let existing_product = self.current.find_by_id(id).await?
+            .ok_or_else(|| ServiceError::NotFoundError(format!("Product with id {} not found", id)))?;
         
         // Create updated product with values from DTO or existing values
         let updated_product = Product {
@@ -45,15 +113,59 @@ impl ProductService {
             price: dto.price.unwrap_or(existing_product.price),
             sku: dto.sku.unwrap_or(existing_product.sku),
             category: dto.category.unwrap_or(existing_product.category),
-            in_stock: dto.in_stock.unwrap_or(existing_product.in_stock),
+            in_stock: existing_product.in_stock,
+            stock: existing_product.stock,
             created_at: existing_product.created_at,
             updated_at: chrono::Utc::now(),
         };
         
-        self.repository.update(id, updated_product).await
+        let mut events = vec![DomainEvent::new(EventType::ProductUpdated, id, &updated_product)?];
+        if updated_product.price != existing_product.price {
+            events.push(DomainEvent::new(EventType::ProductPriceChanged, id, &json!({
+                "product_id": id,
+                "old_price": existing_product.price,
+                "new_price": updated_product.price,
+            }))?);
+        }
+        
+        let mut uow = self.transactions.begin().await?;
+        let updated = self.repository.update_in(&mut uow, id, updated_product).await?;
+        self.outbox.append_in(&mut uow, &events).await?;
+        uow.commit().await?;
+        metrics().record_product_change("updated");
+        Ok(updated)
+    }
+    
+    #[tracing::instrument(skip_all, fields(product.id = %id))]
+    pub async fn get_stock(&self, id: Uuid) -> ServiceResult<Option<ProductStock>> {
+        Ok(self.stock.find_stock(id).await?.map(|level| ProductStock::new(id, &level)))
+    }
+    
+    #[tracing::instrument(skip_all, fields(product.id = %id))]
+    pub async fn adjust_stock(&self, id: Uuid, dto: AdjustStockDto) -> ServiceResult<ProductStock> {
+        let operation = Uuid::new_v4();
+        let level = match (dto.delta, dto.on_hand) {
+            (Some(delta), None) => self.stock.change_stock(id, StockChange::adjust(delta), operation).await?,
+            (None, Some(on_hand)) if on_hand >= 0 => self.stock.set_on_hand(id, on_hand, operation).await?,
+            (None, Some(_)) => {
+                return Err(ServiceError::ValidationError("on_hand cannot be negative".to_string()));
+            }
+            _ => {
+                return Err(ServiceError::ValidationError("Provide exactly one of delta or on_hand".to_string()));
+            }
+        };
+        
+        Ok(ProductStock::new(id, &level))
     }
     
+    #[tracing::instrument(skip_all, fields(product.id = %id))]
     pub async fn delete_product(&self, id: Uuid) -> ServiceResult<()> {
-        self.repository.delete(id).await
+        let event = DomainEvent::new(EventType::ProductDeleted, id, &json!({ "product_id": id }))?;
+        let mut uow = self.transactions.begin().await?;
+        self.repository.delete_in(&mut uow, id).await?;
+        self.outbox.append_in(&mut uow, &[event]).await?;
+        uow.commit().await?;
+        metrics().record_product_change("deleted");
+        Ok(())
     }
 }
\ No newline at end of file
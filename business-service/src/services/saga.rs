@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use tracing::Instrument;
+use uuid::Uuid;
+use crate::errors::{ServiceError, ServiceResult};
+use crate::models::saga::{SagaRecord, SagaStatus};
+use crate::repositories::SagaStore;
+
+// One step of a saga: a forward action and the compensation that undoes it. Each
+// action must be atomic on its own store and idempotent, because after a crash the
+// step that was in flight is executed (or compensated) again. A step that fails is
+// compensated too, as it may have written before failing, so compensating must also
+// be a no-op when the forward action never took effect.
+#[async_trait]
+pub trait SagaStep: Send + Sync {
+    fn name(&self) -> &'static str;
+    async fn execute(&self) -> ServiceResult<()>;
+    async fn compensate(&self) -> ServiceResult<()>;
+}
+
+// Rebuilds the steps of one kind of saga from its persisted record
+pub trait SagaDefinition: Send + Sync {
+    fn kind(&self) -> &'static str;
+    fn steps(&self, saga: &SagaRecord) -> ServiceResult<Vec<Box<dyn SagaStep>>>;
+}
+
+// A deterministic id for an action of a saga step, so a repeated action can be
+// recognised by the store it writes to
+pub fn operation_id(saga: &SagaRecord, step: usize, action: &str) -> Uuid {
+    Uuid::new_v3(&saga.id, format!("{}:{}", step, action).as_bytes())
+}
+
+// Runs sagas step by step, persisting progress after every step. When a step fails,
+// it and the steps before it are compensated in reverse order. Sagas interrupted by a crash
+// are picked up again by `recover`. Every save renews the coordinator's claim on the saga, so
+// only sagas it has stopped driving go stale and can be taken over.
+pub struct SagaCoordinator {
+    store: Arc<dyn SagaStore>,
+    definitions: HashMap<&'static str, Arc<dyn SagaDefinition>>,
+    owner: Uuid,
+}
+
+impl SagaCoordinator {
+    // Sagas that have not progressed for this long are considered abandoned
+    pub const STALE_AFTER_SECS: i64 = 60;
+    
+    pub fn new(store: Arc<dyn SagaStore>) -> Self {
+        Self {
+            store,
+            definitions: HashMap::new(),
+            owner: Uuid::new_v4(),
+        }
+    }
+    
+    pub fn register(&mut self, definition: Arc<dyn SagaDefinition>) {
+        self.definitions.insert(definition.kind(), definition);
+    }
+    
+    // Start a saga and drive it to the end. If it had to be compensated, the error of
+    // the failed step is returned.
+    pub async fn run(&self, kind: &str, payload: serde_json::Value) -> ServiceResult<SagaRecord> {
+        let mut saga = SagaRecord::new(kind, payload);
+        saga.owner = Some(self.owner);
+        self.store.save(&saga).await?;
+        
+        self.drive(saga).await
+    }
+    
+    // Claim and resume every abandoned saga: running ones continue forward, compensating
+    // ones continue backward. Returns how many sagas were resumed.
+    pub async fn recover(&self) -> ServiceResult<usize> {
+        let cutoff = Utc::now() - Duration::seconds(Self::STALE_AFTER_SECS);
+        let sagas = self.store.claim_unfinished(self.owner, cutoff).await?;
+        let count = sagas.len();
+        
+        for saga in sagas {
+            let id = saga.id;
+            match self.drive(saga).await {
+                Ok(saga) => tracing::info!("Recovered saga {} ({})", id, saga.status),
+                Err(e) => tracing::warn!("Saga {} did not complete during recovery: {}", id, e),
+            }
+        }
+        
+        Ok(count)
+    }
+    
+    #[tracing::instrument(name = "saga", skip_all, fields(saga.id = %saga.id, saga.kind = %saga.kind))]
+    async fn drive(&self, mut saga: SagaRecord) -> ServiceResult<SagaRecord> {
+        let definition = self.definitions.get(saga.kind.as_str())
+            .ok_or_else(|| ServiceError::UnknownError(format!("Unknown saga kind: {}", saga.kind)))?;
+        let steps = definition.steps(&saga)?;
+        
+        while saga.status == SagaStatus::Running {
+            let Some(step) = steps.get(saga.completed_steps as usize) else {
+                saga.status = SagaStatus::Completed;
+                self.save(&mut saga).await?;
+                break;
+            };
+            
+            match step.execute().instrument(Self::step_span(step.as_ref(), "execute")).await {
+                Ok(()) => {
+                    saga.completed_steps += 1;
+                    self.save(&mut saga).await?;
+                }
+                Err(e) => {
+                    tracing::warn!("Saga {} step {} failed, compensating: {}", saga.id, step.name(), e);
+                    // The failed step is undone along with the others, in case it got partway
+                    saga.completed_steps += 1;
+                    saga.status = SagaStatus::Compensating;
+                    saga.error = Some(e.to_string());
+                    self.save(&mut saga).await?;
+                    
+                    if let Err(compensation_error) = self.compensate(&mut saga, &steps).await {
+                        tracing::error!("Saga {} could not be compensated yet: {}", saga.id, compensation_error);
+                    }
+                    return Err(e);
+                }
+            }
+        }
+        
+        if saga.status == SagaStatus::Compensating {
+            self.compensate(&mut saga, &steps).await?;
+            let error = saga.error.clone().unwrap_or_default();
+            return Err(ServiceError::UnknownError(format!("Saga {} was compensated: {}", saga.id, error)));
+        }
+        
+        Ok(saga)
+    }
+    
+    // Undo the steps that ran, last first. A failed compensation leaves the saga
+    // compensating, so recovery retries it later.
+    async fn compensate(&self, saga: &mut SagaRecord, steps: &[Box<dyn SagaStep>]) -> ServiceResult<()> {
+        while saga.completed_steps > 0 {
+            let step = &steps[saga.completed_steps as usize - 1];
+            step.compensate().instrument(Self::step_span(step.as_ref(), "compensate")).await?;
+            saga.completed_steps -= 1;
+            self.save(saga).await?;
+        }
+        
+        saga.status = SagaStatus::Compensated;
+        self.save(saga).await
+    }
+    
+    fn step_span(step: &dyn SagaStep, action: &'static str) -> tracing::Span {
+        tracing::info_span!("saga_step", otel.name = %format_args!("{} {}", action, step.name()), step = step.name(), action)
+    }
+    
+    async fn save(&self, saga: &mut SagaRecord) -> ServiceResult<()> {
+        saga.updated_at = Utc::now();
+        self.store.save(saga).await
+    }
+}
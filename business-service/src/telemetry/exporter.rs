@@ -0,0 +1,102 @@
+use std::fmt;
+use std::io::Write;
+use std::sync::Mutex;
+use chrono::{DateTime, Utc};
+use opentelemetry::trace::{SpanKind, Status};
+use opentelemetry::{Key, Value};
+use opentelemetry_sdk::error::{OTelSdkError, OTelSdkResult};
+use opentelemetry_sdk::trace::{SpanData, SpanExporter};
+use opentelemetry_sdk::Resource;
+use serde_json::{json, Map};
+
+// Writes each finished span as one JSON object per line, for local development and for
+// deployments that ship files rather than run a collector
+pub struct JsonLinesExporter<W> {
+    writer: Mutex<W>,
+    service_name: Option<String>,
+}
+
+impl<W: Write + Send> JsonLinesExporter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: Mutex::new(writer),
+            service_name: None,
+        }
+    }
+    
+    fn to_json(&self, span: &SpanData) -> serde_json::Value {
+        let attributes: Map<String, serde_json::Value> = span.attributes
+            .iter()
+            .map(|attribute| (attribute.key.to_string(), Self::value_to_json(&attribute.value)))
+            .collect();
+        let kind = match span.span_kind {
+            SpanKind::Client => "client",
+            SpanKind::Server => "server",
+            SpanKind::Producer => "producer",
+            SpanKind::Consumer => "consumer",
+            SpanKind::Internal => "internal",
+        };
+        let (status, message) = match &span.status {
+            Status::Unset => ("unset", None),
+            Status::Ok => ("ok", None),
+            Status::Error { description } => ("error", Some(description.to_string())),
+        };
+        let duration = span.end_time.duration_since(span.start_time).unwrap_or_default();
+        let parent_span_id = (span.parent_span_id != opentelemetry::trace::SpanId::INVALID)
+            .then(|| span.parent_span_id.to_string());
+        
+        json!({
+            "service": self.service_name,
+            "trace_id": span.span_context.trace_id().to_string(),
+            "span_id": span.span_context.span_id().to_string(),
+            "parent_span_id": parent_span_id,
+            "name": span.name,
+            "kind": kind,
+            "start": DateTime::<Utc>::from(span.start_time).to_rfc3339(),
+            "duration_ms": duration.as_secs_f64() * 1000.0,
+            "status": status,
+            "status_message": message,
+            "attributes": attributes,
+        })
+    }
+    
+    fn value_to_json(value: &Value) -> serde_json::Value {
+        match value {
+            Value::Bool(flag) => json!(flag),
+            Value::I64(number) => json!(number),
+            Value::F64(number) => json!(number),
+            Value::String(text) => json!(text.as_str()),
+            other => json!(other.to_string()),
+        }
+    }
+}
+
+impl<W> fmt::Debug for JsonLinesExporter<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JsonLinesExporter").field("service_name", &self.service_name).finish()
+    }
+}
+
+impl<W: Write + Send + 'static> SpanExporter for JsonLinesExporter<W> {
+    async fn export(&self, batch: Vec<SpanData>) -> OTelSdkResult {
+        let mut lines = Vec::new();
+        for span in &batch {
+            serde_json::to_writer(&mut lines, &self.to_json(span))
+                .map_err(|e| OTelSdkError::InternalFailure(e.to_string()))?;
+            lines.push(b'\n');
+        }
+        
+        let mut writer = self.writer
+            .lock()
+            .map_err(|_| OTelSdkError::InternalFailure("Span writer is poisoned".to_string()))?;
+        writer.write_all(&lines)
+            .and_then(|_| writer.flush())
+            .map_err(|e| OTelSdkError::InternalFailure(e.to_string()))
+    }
+    
+    fn set_resource(&mut self, resource: &Resource) {
+        self.service_name = resource
+            .get(&Key::from_static_str("service.name"))
+            .map(|name| name.to_string());
+    }
+}
@@ -0,0 +1,64 @@
+pub mod exporter;
+pub mod spans;
+
+pub use exporter::*;
+pub use spans::*;
+
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use opentelemetry::global;
+use opentelemetry::propagation::{Extractor, Injector};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use crate::config::{TelemetryConfig, TraceExporter};
+use crate::errors::{ServiceError, ServiceResult};
+
+// Installs the W3C trace-context propagator and builds the tracer provider for the configured
+// exporter. Returns None when tracing is disabled; otherwise the provider must be shut down
+// on exit so buffered spans are flushed.
+pub fn init_tracer_provider(config: &TelemetryConfig) -> ServiceResult<Option<SdkTracerProvider>> {
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    
+    let builder = SdkTracerProvider::builder()
+        .with_resource(Resource::builder().with_service_name(config.service_name.clone()).build());
+    let provider = match &config.exporter {
+        TraceExporter::Disabled => return Ok(None),
+        TraceExporter::Otlp { endpoint } => {
+            let exporter = opentelemetry_otlp::SpanExporter::builder()
+                .with_http()
+                .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+                .build()
+                .map_err(|e| ServiceError::ConfigError(format!("Invalid OTLP exporter: {}", e)))?;
+            builder.with_batch_exporter(exporter)
+        }
+        TraceExporter::File { path } => {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map_err(|e| ServiceError::ConfigError(format!("Cannot open traces file {}: {}", path, e)))?;
+            builder.with_batch_exporter(JsonLinesExporter::new(file))
+        }
+        TraceExporter::Stdout => builder.with_batch_exporter(JsonLinesExporter::new(std::io::stdout())),
+    };
+    
+    Ok(Some(provider.build()))
+}
+
+// The trace context of the current span as W3C headers (`traceparent`, `tracestate`), for
+// outbound calls and messages so the receiving side can continue the trace
+pub fn trace_context() -> HashMap<String, String> {
+    let context = tracing::Span::current().context();
+    let mut carrier = HashMap::new();
+    global::get_text_map_propagator(|propagator| propagator.inject_context(&context, &mut carrier as &mut dyn Injector));
+    
+    carrier
+}
+
+// Continues the trace described by incoming W3C headers, if any
+pub fn extract_trace_context(carrier: &dyn Extractor) -> opentelemetry::Context {
+    global::get_text_map_propagator(|propagator| propagator.extract(carrier))
+}
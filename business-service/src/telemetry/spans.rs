@@ -0,0 +1,64 @@
+use mongodb::bson::Document;
+use tracing::Span;
+
+// A client span for one SQL statement, named `<operation> <table>` like `SELECT orders`.
+// The statement is recorded with its placeholders, never with bound values.
+pub fn sql_span(statement: &str) -> Span {
+    let statement = statement.split_whitespace().collect::<Vec<_>>().join(" ");
+    let operation = statement.split(' ').next().unwrap_or_default().to_uppercase();
+    let name = match sql_table(&statement) {
+        Some(table) => format!("{} {}", operation, table),
+        None => operation.clone(),
+    };
+    
+    tracing::info_span!(
+        "db.query",
+        otel.name = %name,
+        otel.kind = "client",
+        db.system = "postgresql",
+        db.operation = %operation,
+        db.statement = %statement,
+    )
+}
+
+// A client span for one MongoDB command, named `<operation> <collection>` like `find products`.
+// Only the filter is recorded as the statement, never the documents being written.
+pub fn mongo_span(operation: &str, collection: &str, filter: Option<&Document>) -> Span {
+    let span = tracing::info_span!(
+        "db.query",
+        otel.name = %format_args!("{} {}", operation, collection),
+        otel.kind = "client",
+        db.system = "mongodb",
+        db.operation = operation,
+        db.mongodb.collection = collection,
+        db.statement = tracing::field::Empty,
+    );
+    if let Some(filter) = filter {
+        span.record("db.statement", tracing::field::display(filter));
+    }
+    
+    span
+}
+
+// A producer span for publishing one message, named `<destination> publish`
+pub fn publish_span(system: &'static str, destination: &str, message_id: &str) -> Span {
+    tracing::info_span!(
+        "messaging.publish",
+        otel.name = %format_args!("{} publish", destination),
+        otel.kind = "producer",
+        otel.status_code = tracing::field::Empty,
+        messaging.system = system,
+        messaging.operation = "publish",
+        messaging.destination.name = destination,
+        messaging.message.id = message_id,
+    )
+}
+
+// The table a statement reads or writes: the first name after FROM, INTO or UPDATE
+fn sql_table(statement: &str) -> Option<&str> {
+    let mut words = statement.split(' ');
+    words.find(|word| ["FROM", "INTO", "UPDATE"].iter().any(|keyword| word.eq_ignore_ascii_case(keyword)))?;
+    words.next()
+        .map(|table| table.trim_end_matches([',', ';', '(']))
+        .filter(|table| !table.is_empty())
+}
@@ -0,0 +1,864 @@
+// End-to-end tests of the HTTP API running in-process against the in-memory repositories
+use std::sync::Arc;
+use actix_web::{http::header, http::StatusCode, test};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use business_service::errors::{ServiceError, ServiceResult};
+use business_service::models::{IdempotencyClaim, IdempotencyRecord, Product, StoredResponse};
+use business_service::repositories::{
+    IdempotencyStore, InMemoryIdempotencyStore, InMemoryRepository, InMemoryTransactionManager, Repository,
+    TransactionManager, TransactionalRepository,
+};
+
+mod common;
+use common::{bearer_as, test_app, token, TestServices, JWT_SECRET};
+
+fn bearer(user_id: i64) -> (header::HeaderName, String) {
+    bearer_as(user_id, &["admin"])
+}
+
+// The customer id orders use for a customer with the given user id
+fn customer_id(user_id: i64) -> String {
+    format!("00000000-0000-0000-0000-{:012x}", user_id)
+}
+
+// Requests authenticated as admin user 1
+fn get() -> test::TestRequest {
+    test::TestRequest::get().insert_header(bearer(1))
+}
+
+fn post() -> test::TestRequest {
+    test::TestRequest::post().insert_header(bearer(1))
+}
+
+fn put() -> test::TestRequest {
+    test::TestRequest::put().insert_header(bearer(1))
+}
+
+fn patch() -> test::TestRequest {
+    test::TestRequest::patch().insert_header(bearer(1))
+}
+
+fn delete() -> test::TestRequest {
+    test::TestRequest::delete().insert_header(bearer(1))
+}
+
+fn product_body(name: &str, category: &str, amount: &str) -> Value {
+    json!({
+        "name": name,
+        "description": format!("{} description", name),
+        "price": { "amount": amount, "currency": "USD" },
+        "sku": format!("SKU-{}", name.to_uppercase()),
+        "category": category,
+        "on_hand": 10,
+    })
+}
+
+macro_rules! create_product {
+    ($app:expr, $name:expr, $category:expr, $amount:expr) => {{
+        let req = post()
+            .uri("/api/products")
+            .set_json(product_body($name, $category, $amount))
+            .to_request();
+        let created: Value = test::call_and_read_body_json($app, req).await;
+        created["_id"].as_str().unwrap().to_string()
+    }};
+}
+
+#[actix_web::test]
+async fn product_crud_round_trip() {
+    let app = test_app!();
+    
+    let req = post()
+        .uri("/api/products")
+        .set_json(product_body("lamp", "home", "19.99"))
+        .to_request();
+    let created: Value = test::call_and_read_body_json(&app, req).await;
+    let id = created["_id"].as_str().unwrap().to_string();
+    assert_eq!(created["price"], json!({ "amount": "19.99", "currency": "USD" }));
+    
+    let req = put()
+        .uri(&format!("/api/products/{}", id))
+        .set_json(json!({ "name": "desk lamp" }))
+        .to_request();
+    let updated: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(updated["name"], "desk lamp");
+    assert_eq!(updated["sku"], "SKU-LAMP");
+    
+    let req = delete().uri(&format!("/api/products/{}", id)).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NO_CONTENT);
+    
+    let req = get().uri(&format!("/api/products/{}", id)).to_request();
+    let response = test::call_service(&app, req).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let problem: Value = test::read_body_json(response).await;
+    assert_eq!(problem["code"], "not_found");
+    assert_eq!(problem["detail"], format!("Product with id {} not found", id));
+}
+
+#[actix_web::test]
+async fn product_list_filters_sorts_and_pages() {
+    let app = test_app!();
+    
+    for (name, category, amount) in [("a", "books", "5.00"), ("b", "books", "15.00"), ("c", "books", "25.00"), ("d", "toys", "9.00")] {
+        create_product!(&app, name, category, amount);
+    }
+    
+    let req = get()
+        .uri("/api/products?category=books&price%5Bgte%5D=10&sort=-price&limit=1")
+        .to_request();
+    let page: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(page["total"], 2);
+    assert_eq!(page["items"][0]["name"], "c");
+    
+    let next = page["links"]["next"].as_str().unwrap().to_string();
+    let req = get().uri(&next).to_request();
+    let page: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(page["items"][0]["name"], "b");
+    assert!(page["links"]["next"].is_null());
+    
+    let req = get().uri("/api/products?colour=red").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn order_lifecycle() {
+    let app = test_app!();
+    let lamp = create_product!(&app, "lamp", "home", "19.99");
+    let bulb = create_product!(&app, "bulb", "home", "0.05");
+    
+    // Client-supplied prices are ignored in favour of the catalog
+    let req = post()
+        .uri("/api/orders")
+        .set_json(json!({
+            "customer_id": "8a1f7a52-6f4e-4a57-9a55-0f5b6b4f2f11",
+            "items": [
+                { "product_id": lamp, "quantity": 3, "price": { "amount": "0.01", "currency": "USD" } },
+                { "product_id": bulb, "quantity": 1 }
+            ]
+        }))
+        .to_request();
+    let created: Value = test::call_and_read_body_json(&app, req).await;
+    let id = created["_id"].as_str().unwrap().to_string();
+    assert_eq!(created["total"], json!({ "amount": "60.02", "currency": "USD" }));
+    assert_eq!(created["items"][0]["sku"], "SKU-LAMP");
+    assert_eq!(created["status"], "pending");
+    
+    let req = patch()
+        .uri(&format!("/api/orders/{}/status", id))
+        .set_json(json!({ "status": "processing" }))
+        .to_request();
+    let updated: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(updated["status"], "processing");
+    
+    let req = get().uri("/api/orders?status=processing").to_request();
+    let page: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(page["total"], 1);
+    assert_eq!(page["items"][0]["_id"], id.as_str());
+}
+
+#[actix_web::test]
+async fn order_status_follows_transition_table_and_records_history() {
+    let app = test_app!();
+    let lamp = create_product!(&app, "lamp", "home", "19.99");
+    
+    let req = post()
+        .uri("/api/orders")
+        .set_json(json!({
+            "customer_id": "8a1f7a52-6f4e-4a57-9a55-0f5b6b4f2f11",
+            "items": [{ "product_id": lamp, "quantity": 1 }]
+        }))
+        .to_request();
+    let created: Value = test::call_and_read_body_json(&app, req).await;
+    let id = created["_id"].as_str().unwrap().to_string();
+    
+    // Pending orders cannot skip straight to delivered
+    let req = patch()
+        .uri(&format!("/api/orders/{}/status", id))
+        .set_json(json!({ "status": "delivered" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::CONFLICT);
+    
+    let req = patch()
+        .uri(&format!("/api/orders/{}/status", id))
+        .set_json(json!({ "status": "cancelled", "reason": "customer request" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    
+    // Cancelled is final
+    let req = patch()
+        .uri(&format!("/api/orders/{}/status", id))
+        .set_json(json!({ "status": "pending" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::CONFLICT);
+    
+    let req = get().uri(&format!("/api/orders/{}/history", id)).to_request();
+    let history: Value = test::call_and_read_body_json(&app, req).await;
+    let history = history.as_array().unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0]["from"], Value::Null);
+    assert_eq!(history[0]["to"], "pending");
+    assert_eq!(history[1]["from"], "pending");
+    assert_eq!(history[1]["to"], "cancelled");
+    assert_eq!(history[1]["reason"], "customer request");
+    assert_eq!(history[1]["actor"], "user:1");
+    
+    let req = get()
+        .uri("/api/orders/1a1f7a52-6f4e-4a57-9a55-0f5b6b4f2f11/history")
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn order_rejects_unknown_products_and_insufficient_stock() {
+    let app = test_app!();
+    let lamp = create_product!(&app, "lamp", "home", "19.99");
+    
+    let req = post()
+        .uri("/api/orders")
+        .set_json(json!({
+            "customer_id": "8a1f7a52-6f4e-4a57-9a55-0f5b6b4f2f11",
+            "items": [{ "product_id": "1a1f7a52-6f4e-4a57-9a55-0f5b6b4f2f11", "quantity": 1 }]
+        }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    
+    // The lamp is reserved before the second line fails, and must be released again
+    let req = post()
+        .uri("/api/orders")
+        .set_json(json!({
+            "customer_id": "8a1f7a52-6f4e-4a57-9a55-0f5b6b4f2f11",
+            "items": [{ "product_id": lamp, "quantity": 4 }, { "product_id": lamp, "quantity": 7 }]
+        }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::CONFLICT);
+    
+    let req = get().uri(&format!("/api/products/{}/stock", lamp)).to_request();
+    let stock: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(stock["reserved"], 0);
+    assert_eq!(stock["available"], 10);
+}
+
+#[actix_web::test]
+async fn stock_is_reserved_released_and_committed_by_orders() {
+    let app = test_app!();
+    let lamp = create_product!(&app, "lamp", "home", "19.99");
+    
+    macro_rules! place_order {
+        ($quantity:expr) => {{
+            let req = post()
+                .uri("/api/orders")
+                .set_json(json!({
+                    "customer_id": "8a1f7a52-6f4e-4a57-9a55-0f5b6b4f2f11",
+                    "items": [{ "product_id": lamp, "quantity": $quantity }]
+                }))
+                .to_request();
+            let created: Value = test::call_and_read_body_json(&app, req).await;
+            created["_id"].as_str().unwrap().to_string()
+        }};
+    }
+    macro_rules! set_status {
+        ($id:expr, $status:expr) => {{
+            let req = patch()
+                .uri(&format!("/api/orders/{}/status", $id))
+                .set_json(json!({ "status": $status }))
+                .to_request();
+            assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+        }};
+    }
+    macro_rules! stock {
+        () => {{
+            let req = get().uri(&format!("/api/products/{}/stock", lamp)).to_request();
+            let stock: Value = test::call_and_read_body_json(&app, req).await;
+            (stock["on_hand"].as_i64().unwrap(), stock["reserved"].as_i64().unwrap())
+        }};
+    }
+    
+    let cancelled = place_order!(6);
+    assert_eq!(stock!(), (10, 6));
+    set_status!(cancelled, "cancelled");
+    assert_eq!(stock!(), (10, 0));
+    
+    let shipped = place_order!(10);
+    assert_eq!(stock!(), (10, 10));
+    let req = get().uri(&format!("/api/products/{}", lamp)).to_request();
+    let product: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(product["in_stock"], false);
+    
+    set_status!(shipped, "processing");
+    set_status!(shipped, "shipped");
+    assert_eq!(stock!(), (0, 0));
+    
+    // Adjust by a delta or to an absolute count, never below what is reserved
+    let req = patch()
+        .uri(&format!("/api/products/{}/stock", lamp))
+        .set_json(json!({ "delta": 5 }))
+        .to_request();
+    let adjusted: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(adjusted["available"], 5);
+    
+    place_order!(3);
+    let req = patch()
+        .uri(&format!("/api/products/{}/stock", lamp))
+        .set_json(json!({ "on_hand": 2 }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::CONFLICT);
+    
+    let req = patch()
+        .uri(&format!("/api/products/{}/stock", lamp))
+        .set_json(json!({ "delta": 1, "on_hand": 2 }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[actix_web::test]
+async fn unit_of_work_rolls_back_in_memory_writes() {
+    let repository = InMemoryRepository::<Product>::new();
+    let product = Product::new(
+        "lamp".to_string(),
+        "a lamp".to_string(),
+        serde_json::from_value(json!({ "amount": "19.99", "currency": "USD" })).unwrap(),
+        "SKU-LAMP".to_string(),
+        "home".to_string(),
+    );
+    let id = product.id.unwrap();
+    
+    let mut uow = InMemoryTransactionManager.begin().await.unwrap();
+    repository.create_in(&mut uow, product.clone()).await.unwrap();
+    uow.rollback().await.unwrap();
+    assert!(repository.find_by_id(id).await.unwrap().is_none());
+    
+    let mut uow = InMemoryTransactionManager.begin().await.unwrap();
+    repository.create_in(&mut uow, product).await.unwrap();
+    uow.commit().await.unwrap();
+    assert!(repository.find_by_id(id).await.unwrap().is_some());
+}
+
+#[actix_web::test]
+async fn idempotency_key_replays_first_response() {
+    let app = test_app!();
+    let lamp = create_product!(&app, "lamp", "home", "19.99");
+    let order = json!({
+        "customer_id": "8a1f7a52-6f4e-4a57-9a55-0f5b6b4f2f11",
+        "items": [{ "product_id": lamp, "quantity": 1 }]
+    });
+    
+    let req = post()
+        .uri("/api/orders")
+        .insert_header(("Idempotency-Key", "order-1"))
+        .set_json(&order)
+        .to_request();
+    let first = test::call_service(&app, req).await;
+    assert_eq!(first.status(), StatusCode::CREATED);
+    let first: Value = test::read_body_json(first).await;
+    
+    // A retry gets the stored response instead of placing a second order
+    let req = post()
+        .uri("/api/orders")
+        .insert_header(("Idempotency-Key", "order-1"))
+        .set_json(&order)
+        .to_request();
+    let retry = test::call_service(&app, req).await;
+    assert_eq!(retry.status(), StatusCode::CREATED);
+    assert_eq!(retry.headers().get("Idempotent-Replayed").unwrap(), "true");
+    let retry: Value = test::read_body_json(retry).await;
+    assert_eq!(retry, first);
+    
+    let req = get().uri("/api/orders").to_request();
+    let page: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(page["total"], 1);
+    
+    // Reusing the key for a different payload is rejected
+    let req = post()
+        .uri("/api/orders")
+        .insert_header(("Idempotency-Key", "order-1"))
+        .set_json(json!({
+            "customer_id": "8a1f7a52-6f4e-4a57-9a55-0f5b6b4f2f11",
+            "items": [{ "product_id": lamp, "quantity": 2 }]
+        }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    
+    // Keys are per caller, so another user's identical request is not a replay
+    let req = test::TestRequest::post()
+        .uri("/api/orders")
+        .insert_header(bearer(2))
+        .insert_header(("Idempotency-Key", "order-1"))
+        .set_json(&order)
+        .to_request();
+    let other = test::call_service(&app, req).await;
+    assert_eq!(other.status(), StatusCode::CREATED);
+    assert!(other.headers().get("Idempotent-Replayed").is_none());
+}
+
+// A claim whose request died is taken over once its lease lapses, instead of answering 409
+// until the key expires
+#[actix_web::test]
+async fn lapsed_idempotency_claims_are_taken_over() {
+    let held = InMemoryIdempotencyStore::new(60, 60);
+    assert!(matches!(held.claim("order-1", "fingerprint").await.unwrap(), IdempotencyClaim::Claimed(_)));
+    let IdempotencyClaim::Taken(record) = held.claim("order-1", "fingerprint").await.unwrap() else {
+        panic!("a held claim was taken over");
+    };
+    assert!(record.response.is_none());
+    
+    let lapsed = InMemoryIdempotencyStore::new(60, 0);
+    assert!(matches!(lapsed.claim("order-1", "fingerprint").await.unwrap(), IdempotencyClaim::Claimed(_)));
+    let IdempotencyClaim::Claimed(token) = lapsed.claim("order-1", "fingerprint").await.unwrap() else {
+        panic!("a lapsed claim was not taken over");
+    };
+    
+    // A completed key is kept for its TTL however long ago it was claimed
+    let response = StoredResponse { status: 201, content_type: None, body: b"{}".to_vec() };
+    lapsed.complete("order-1", token, &response).await.unwrap();
+    assert_eq!(lapsed.claim("order-1", "fingerprint").await.unwrap(), IdempotencyClaim::Taken(IdempotencyRecord {
+        fingerprint: "fingerprint".to_string(),
+        response: Some(response),
+    }));
+}
+
+// The request whose claim lapsed may still finish after another took the key over. Its late
+// response or release must not replace the new owner's, or a third duplicate could run.
+#[actix_web::test]
+async fn a_stale_claim_cannot_complete_or_release_a_key_taken_over() {
+    let store = InMemoryIdempotencyStore::new(60, 0);
+    let IdempotencyClaim::Claimed(stale) = store.claim("order-1", "fingerprint").await.unwrap() else {
+        panic!("a free key was not claimed");
+    };
+    let IdempotencyClaim::Claimed(owner) = store.claim("order-1", "fingerprint").await.unwrap() else {
+        panic!("a lapsed claim was not taken over");
+    };
+    
+    store.release("order-1", stale).await.unwrap();
+    let response = StoredResponse { status: 201, content_type: None, body: b"{\"owner\":true}".to_vec() };
+    store.complete("order-1", owner, &response).await.unwrap();
+    let late = StoredResponse { status: 201, content_type: None, body: b"{\"owner\":false}".to_vec() };
+    store.complete("order-1", stale, &late).await.unwrap();
+    store.release("order-1", stale).await.unwrap();
+    
+    let IdempotencyClaim::Taken(record) = store.claim("order-1", "fingerprint").await.unwrap() else {
+        panic!("a completed key was claimed again");
+    };
+    assert_eq!(record.response, Some(response));
+}
+
+// Fails to store responses, as when the database goes away after the request ran
+struct ForgetfulIdempotencyStore(InMemoryIdempotencyStore);
+
+#[async_trait]
+impl IdempotencyStore for ForgetfulIdempotencyStore {
+    async fn claim(&self, key: &str, fingerprint: &str) -> ServiceResult<IdempotencyClaim> {
+        self.0.claim(key, fingerprint).await
+    }
+    
+    async fn complete(&self, _key: &str, _token: Uuid, _response: &StoredResponse) -> ServiceResult<()> {
+        Err(ServiceError::DatabaseError("connection reset".to_string()))
+    }
+    
+    async fn release(&self, key: &str, token: Uuid) -> ServiceResult<()> {
+        self.0.release(key, token).await
+    }
+    
+    async fn purge_expired(&self) -> ServiceResult<u64> {
+        self.0.purge_expired().await
+    }
+}
+
+#[actix_web::test]
+async fn idempotency_keys_are_released_when_the_response_cannot_be_stored() {
+    let store = Arc::new(ForgetfulIdempotencyStore(InMemoryIdempotencyStore::new(60, 60)));
+    let app = test_app!(TestServices::new().with_idempotency(store));
+    let lamp = create_product!(&app, "lamp", "home", "19.99");
+    let order = json!({
+        "customer_id": "8a1f7a52-6f4e-4a57-9a55-0f5b6b4f2f11",
+        "items": [{ "product_id": lamp, "quantity": 1 }]
+    });
+    
+    let place = || {
+        post()
+            .uri("/api/orders")
+            .insert_header(("Idempotency-Key", "order-1"))
+            .set_json(&order)
+            .to_request()
+    };
+    assert_eq!(test::call_service(&app, place()).await.status(), StatusCode::CREATED);
+    
+    // Nothing was stored to replay, so the retry runs rather than waiting on the claim
+    let retry = test::call_service(&app, place()).await;
+    assert_eq!(retry.status(), StatusCode::CREATED);
+    assert!(retry.headers().get("Idempotent-Replayed").is_none());
+}
+
+#[actix_web::test]
+async fn api_requires_a_valid_bearer_token() {
+    let app = test_app!();
+    
+    let req = test::TestRequest::get().uri("/api/products").to_request();
+    let response = test::call_service(&app, req).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(response.headers().get(header::WWW_AUTHENTICATE).unwrap(), "Bearer");
+    assert_eq!(response.headers().get(header::CONTENT_TYPE).unwrap(), "application/problem+json");
+    let problem: Value = test::read_body_json(response).await;
+    assert_eq!(problem["status"], 401);
+    assert_eq!(problem["code"], "unauthenticated");
+    assert_eq!(problem["detail"], "Missing bearer token or API key");
+    
+    for token in [
+        "not-a-token".to_string(),
+        token(1, &["admin"], "some-other-secret", 3600),
+        token(1, &["admin"], JWT_SECRET, -3600),
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/api/products")
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .set_json(product_body("lamp", "home", "19.99"))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNAUTHORIZED);
+    }
+    
+    let req = get().uri("/api/products").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    
+    // The health probes stay public
+    for uri in ["/health", "/health/live", "/health/ready"] {
+        let req = test::TestRequest::get().uri(uri).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK, "{}", uri);
+    }
+}
+
+#[actix_web::test]
+async fn catalog_is_managed_by_admins_and_catalog_managers() {
+    let app = test_app!();
+    
+    for (roles, expected) in [
+        (vec!["catalog_manager"], StatusCode::CREATED),
+        (vec!["support"], StatusCode::FORBIDDEN),
+        (vec!["customer"], StatusCode::FORBIDDEN),
+        (vec![], StatusCode::FORBIDDEN),
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/api/products")
+            .insert_header(bearer_as(5, &roles))
+            .set_json(product_body(&format!("lamp-{}", roles.join("-")), "home", "19.99"))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), expected, "roles {:?}", roles);
+    }
+    
+    // Everyone signed in can browse the catalog
+    let lamp = create_product!(&app, "lamp", "home", "19.99");
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/products/{}", lamp))
+        .insert_header(bearer_as(5, &["customer"]))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    
+    for req in [
+        test::TestRequest::put().uri(&format!("/api/products/{}", lamp)).set_json(json!({ "name": "desk lamp" })),
+        test::TestRequest::patch().uri(&format!("/api/products/{}/stock", lamp)).set_json(json!({ "delta": 5 })),
+        test::TestRequest::delete().uri(&format!("/api/products/{}", lamp)),
+    ] {
+        let req = req.insert_header(bearer_as(5, &["customer"])).to_request();
+        let response = test::call_service(&app, req).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let problem: Value = test::read_body_json(response).await;
+        assert_eq!(problem["code"], "forbidden");
+        assert_eq!(problem["detail"], "Missing permission manage_catalog");
+    }
+    
+    let req = test::TestRequest::delete()
+        .uri(&format!("/api/products/{}", lamp))
+        .insert_header(bearer_as(5, &["catalog_manager"]))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NO_CONTENT);
+}
+
+#[actix_web::test]
+async fn customers_only_place_and_see_their_own_orders() {
+    let app = test_app!();
+    let lamp = create_product!(&app, "lamp", "home", "19.99");
+    
+    macro_rules! place_order {
+        ($user_id:expr, $customer_id:expr) => {{
+            let req = test::TestRequest::post()
+                .uri("/api/orders")
+                .insert_header(bearer_as($user_id, &["customer"]))
+                .set_json(json!({ "customer_id": $customer_id, "items": [{ "product_id": lamp, "quantity": 1 }] }))
+                .to_request();
+            test::call_service(&app, req).await
+        }};
+    }
+    
+    let response = place_order!(7, customer_id(7));
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let own: Value = test::read_body_json(response).await;
+    let own = own["_id"].as_str().unwrap().to_string();
+    assert_eq!(place_order!(7, customer_id(8)).status(), StatusCode::FORBIDDEN);
+    assert_eq!(place_order!(8, customer_id(8)).status(), StatusCode::CREATED);
+    
+    // Listings only include the caller's orders, even when filtering for someone else's
+    for uri in ["/api/orders".to_string(), format!("/api/orders?customer_id={}", customer_id(8))] {
+        let req = test::TestRequest::get().uri(&uri).insert_header(bearer_as(7, &["customer"])).to_request();
+        let page: Value = test::call_and_read_body_json(&app, req).await;
+        let expected = if uri.contains("customer_id") { 0 } else { 1 };
+        assert_eq!(page["total"], expected, "{}", uri);
+    }
+    
+    for (user_id, expected) in [(7, StatusCode::OK), (8, StatusCode::NOT_FOUND)] {
+        for uri in [format!("/api/orders/{}", own), format!("/api/orders/{}/history", own)] {
+            let req = test::TestRequest::get().uri(&uri).insert_header(bearer_as(user_id, &["customer"])).to_request();
+            assert_eq!(test::call_service(&app, req).await.status(), expected, "user {} {}", user_id, uri);
+        }
+    }
+    
+    // Customers can neither change status nor delete orders, even their own
+    let req = test::TestRequest::patch()
+        .uri(&format!("/api/orders/{}/status", own))
+        .insert_header(bearer_as(7, &["customer"]))
+        .set_json(json!({ "status": "cancelled" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+    
+    let req = test::TestRequest::delete()
+        .uri(&format!("/api/orders/{}", own))
+        .insert_header(bearer_as(7, &["customer"]))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+}
+
+#[actix_web::test]
+async fn support_reads_any_order_and_changes_status() {
+    let app = test_app!();
+    let lamp = create_product!(&app, "lamp", "home", "19.99");
+    
+    let req = test::TestRequest::post()
+        .uri("/api/orders")
+        .insert_header(bearer_as(7, &["customer"]))
+        .set_json(json!({ "customer_id": customer_id(7), "items": [{ "product_id": lamp, "quantity": 1 }] }))
+        .to_request();
+    let created: Value = test::call_and_read_body_json(&app, req).await;
+    let id = created["_id"].as_str().unwrap().to_string();
+    
+    let req = test::TestRequest::get().uri("/api/orders").insert_header(bearer_as(9, &["support"])).to_request();
+    let page: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(page["total"], 1);
+    
+    let req = test::TestRequest::patch()
+        .uri(&format!("/api/orders/{}/status", id))
+        .insert_header(bearer_as(9, &["support"]))
+        .set_json(json!({ "status": "processing" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    
+    // Support staff do not place or delete orders, and catalog managers do not handle orders at all
+    let req = test::TestRequest::post()
+        .uri("/api/orders")
+        .insert_header(bearer_as(9, &["support"]))
+        .set_json(json!({ "customer_id": customer_id(7), "items": [{ "product_id": lamp, "quantity": 1 }] }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+    
+    let req = test::TestRequest::delete()
+        .uri(&format!("/api/orders/{}", id))
+        .insert_header(bearer_as(9, &["support"]))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+    
+    let req = test::TestRequest::get().uri("/api/orders").insert_header(bearer_as(9, &["catalog_manager"])).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+    
+    let req = test::TestRequest::delete().uri(&format!("/api/orders/{}", id)).insert_header(bearer(1)).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NO_CONTENT);
+}
+
+#[actix_web::test]
+async fn api_keys_are_issued_rotated_and_revoked() {
+    let app = test_app!();
+    let lamp = create_product!(&app, "lamp", "home", "19.99");
+    
+    let req = post()
+        .uri("/api/keys")
+        .set_json(json!({ "name": "analytics", "scopes": ["products:read", "orders:read"] }))
+        .to_request();
+    let response = test::call_service(&app, req).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let issued: Value = test::read_body_json(response).await;
+    let id = issued["id"].as_str().unwrap().to_string();
+    let key = issued["key"].as_str().unwrap().to_string();
+    assert!(key.starts_with("bsk_"));
+    assert!(issued.get("key_hash").is_none());
+    
+    macro_rules! with_key {
+        ($req:expr, $key:expr) => {
+            test::call_service(&app, $req.insert_header(("X-Api-Key", $key.as_str())).to_request()).await.status()
+        };
+    }
+    
+    assert_eq!(with_key!(test::TestRequest::get().uri(&format!("/api/products/{}", lamp)), key), StatusCode::OK);
+    assert_eq!(with_key!(test::TestRequest::get().uri("/api/orders"), key), StatusCode::OK);
+    assert_eq!(
+        with_key!(test::TestRequest::post().uri("/api/products").set_json(product_body("bulb", "home", "0.05")), key),
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(with_key!(test::TestRequest::get().uri("/api/keys"), key), StatusCode::FORBIDDEN);
+    assert_eq!(with_key!(test::TestRequest::get().uri("/api/orders"), format!("{}x", key)), StatusCode::UNAUTHORIZED);
+    
+    // Listings never include the key itself, but do show when it was last used
+    let req = get().uri("/api/keys").to_request();
+    let keys: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(keys[0]["scopes"], json!(["products:read", "orders:read"]));
+    assert!(keys[0]["last_used_at"].is_number());
+    assert!(keys[0].get("key").is_none());
+    
+    // Rotation replaces the secret; the old one stops working straight away
+    let req = post().uri(&format!("/api/keys/{}/rotate", id)).to_request();
+    let rotated: Value = test::call_and_read_body_json(&app, req).await;
+    let rotated = rotated["key"].as_str().unwrap().to_string();
+    assert_ne!(rotated, key);
+    assert_eq!(with_key!(test::TestRequest::get().uri("/api/orders"), key), StatusCode::UNAUTHORIZED);
+    assert_eq!(with_key!(test::TestRequest::get().uri("/api/orders"), rotated), StatusCode::OK);
+    
+    let req = delete().uri(&format!("/api/keys/{}", id)).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NO_CONTENT);
+    assert_eq!(with_key!(test::TestRequest::get().uri("/api/orders"), rotated), StatusCode::UNAUTHORIZED);
+    
+    let req = post().uri(&format!("/api/keys/{}/rotate", id)).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::CONFLICT);
+    
+    // Only admins manage keys, and keys need at least one known scope
+    let req = test::TestRequest::post()
+        .uri("/api/keys")
+        .insert_header(bearer_as(5, &["support"]))
+        .set_json(json!({ "name": "batch", "scopes": ["orders:write"] }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+    
+    let req = post().uri("/api/keys").set_json(json!({ "name": "batch", "scopes": [] })).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    
+    let req = post().uri("/api/keys").set_json(json!({ "name": "batch", "scopes": ["orders:delete"] })).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn invalid_bodies_report_every_field_error() {
+    let app = test_app!();
+    
+    let req = post()
+        .uri("/api/products")
+        .set_json(json!({
+            "name": "  ",
+            "description": "",
+            "price": { "amount": "-1.00", "currency": "USD" },
+            "sku": "",
+            "category": "home",
+            "on_hand": -3,
+        }))
+        .to_request();
+    let response = test::call_service(&app, req).await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let problem: Value = test::read_body_json(response).await;
+    assert_eq!(problem["code"], "validation_failed");
+    assert_eq!(problem["detail"], "4 fields are invalid");
+    let pointers: Vec<&str> = problem["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|error| error["pointer"].as_str().unwrap())
+        .collect();
+    assert_eq!(pointers, ["/name", "/price/amount", "/sku", "/on_hand"]);
+    
+    let lamp = create_product!(&app, "lamp", "home", "19.99");
+    let req = put()
+        .uri(&format!("/api/products/{}", lamp))
+        .set_json(json!({ "category": "" }))
+        .to_request();
+    let problem: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(problem["errors"], json!([{ "pointer": "/category", "detail": "must not be blank" }]));
+    
+    let req = patch()
+        .uri(&format!("/api/products/{}/stock", lamp))
+        .set_json(json!({ "on_hand": -1 }))
+        .to_request();
+    let problem: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(problem["errors"], json!([{ "pointer": "/on_hand", "detail": "must not be negative" }]));
+    
+    let req = post()
+        .uri("/api/orders")
+        .set_json(json!({
+            "customer_id": "8a1f7a52-6f4e-4a57-9a55-0f5b6b4f2f11",
+            "items": [{ "product_id": lamp, "quantity": 1 }, { "product_id": lamp, "quantity": 0 }]
+        }))
+        .to_request();
+    let problem: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(problem["errors"], json!([{ "pointer": "/items/1/quantity", "detail": "must be greater than 0" }]));
+    
+    let req = post()
+        .uri("/api/orders")
+        .set_json(json!({ "customer_id": "8a1f7a52-6f4e-4a57-9a55-0f5b6b4f2f11", "items": [] }))
+        .to_request();
+    let problem: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(problem["errors"][0]["pointer"], "/items");
+    
+    // Fields serde cannot read are reported alongside the rules they break
+    let req = post()
+        .uri("/api/products")
+        .set_json(json!({ "description": "", "price": { "amount": "cheap" }, "sku": "", "category": "home" }))
+        .to_request();
+    let response = test::call_service(&app, req).await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let problem: Value = test::read_body_json(response).await;
+    let pointers: Vec<&str> = problem["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|error| error["pointer"].as_str().unwrap())
+        .collect();
+    assert_eq!(pointers, ["/name", "/price/amount", "/sku"]);
+    assert_eq!(problem["errors"][0]["detail"], "is required");
+    
+    let req = patch()
+        .uri(&format!("/api/orders/{}/status", uuid::Uuid::new_v4()))
+        .set_json(json!({ "status": "lost" }))
+        .to_request();
+    let problem: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(problem["errors"][0]["pointer"], "/status");
+    
+    // Nothing was reserved by the rejected orders
+    let req = get().uri(&format!("/api/products/{}/stock", lamp)).to_request();
+    let stock: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(stock["reserved"], 0);
+}
+
+#[actix_web::test]
+async fn unparseable_requests_get_bad_request_problems() {
+    let app = test_app!();
+    
+    let req = post()
+        .uri("/api/products")
+        .insert_header((header::CONTENT_TYPE, "application/json"))
+        .set_payload("{ \"name\": ")
+        .to_request();
+    let response = test::call_service(&app, req).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(response.headers().get(header::CONTENT_TYPE).unwrap(), "application/problem+json");
+    let problem: Value = test::read_body_json(response).await;
+    assert_eq!(problem["code"], "bad_request");
+    assert!(problem["detail"].as_str().unwrap().starts_with("Malformed JSON body"));
+    
+    let req = get().uri("/api/orders/not-a-uuid").to_request();
+    let response = test::call_service(&app, req).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let problem: Value = test::read_body_json(response).await;
+    assert_eq!(problem["code"], "bad_request");
+    assert!(problem["detail"].as_str().unwrap().starts_with("Invalid path segment in /api/orders/not-a-uuid"));
+    
+    let req = post()
+        .uri("/api/products")
+        .insert_header((header::CONTENT_TYPE, "application/json"))
+        .set_payload("[]")
+        .to_request();
+    let problem: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(problem["errors"], json!([{ "pointer": "", "detail": "must be a JSON object" }]));
+}
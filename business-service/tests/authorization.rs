@@ -0,0 +1,113 @@
+// Role to permission mapping of tokens issued by auth-service
+use business_service::models::{ApiKeyIdentity, ApiScope, AuthenticatedUser, Caller, Claims, Permission, Role};
+use uuid::Uuid;
+
+fn user(roles: &[&str]) -> Caller {
+    Caller::User(AuthenticatedUser::from(Claims {
+        id: 42,
+        email: "user42@example.com".to_string(),
+        roles: roles.iter().map(|role| role.to_string()).collect(),
+        iat: 0,
+        exp: 0,
+    }))
+}
+
+fn api_key(scopes: &[ApiScope]) -> Caller {
+    Caller::ApiKey(ApiKeyIdentity {
+        id: Uuid::new_v4(),
+        name: "analytics".to_string(),
+        scopes: scopes.to_vec(),
+    })
+}
+
+#[test]
+fn admin_can_do_everything() {
+    let admin = user(&["admin"]);
+    for permission in [
+        Permission::ManageCatalog,
+        Permission::PlaceOrders,
+        Permission::ReadOrders,
+        Permission::ChangeOrderStatus,
+        Permission::DeleteOrders,
+        Permission::AllCustomers,
+        Permission::ManageApiKeys,
+        Permission::ReplayEvents,
+    ] {
+        assert!(admin.can(permission), "{:?}", permission);
+    }
+    assert!(admin.acts_for(Uuid::new_v4()));
+}
+
+#[test]
+fn catalog_manager_only_manages_the_catalog() {
+    let manager = user(&["catalog_manager"]);
+    assert!(manager.can(Permission::ManageCatalog));
+    assert!(!manager.can(Permission::ReadOrders));
+    assert!(!manager.can(Permission::PlaceOrders));
+}
+
+#[test]
+fn support_handles_every_customers_orders() {
+    let support = user(&["support"]);
+    assert!(support.can(Permission::ReadOrders));
+    assert!(support.can(Permission::ChangeOrderStatus));
+    assert!(support.acts_for(Uuid::new_v4()));
+    assert!(!support.can(Permission::PlaceOrders));
+    assert!(!support.can(Permission::DeleteOrders));
+    assert!(!support.can(Permission::ManageCatalog));
+}
+
+#[test]
+fn customer_acts_only_for_themselves() {
+    let customer = user(&["customer"]);
+    assert!(customer.can(Permission::PlaceOrders));
+    assert!(customer.can(Permission::ReadOrders));
+    assert!(!customer.can(Permission::ChangeOrderStatus));
+    assert!(!customer.can(Permission::ManageCatalog));
+    let own = customer.customer_scope().unwrap();
+    assert_eq!(own.to_string(), "00000000-0000-0000-0000-00000000002a");
+    assert!(customer.acts_for(own));
+    assert!(!customer.acts_for(Uuid::new_v4()));
+}
+
+#[test]
+fn tokens_without_known_roles() {
+    // No roles claim means a customer; unknown role names grant nothing
+    let claims = |roles: &[&str]| Claims {
+        id: 1,
+        email: "user1@example.com".to_string(),
+        roles: roles.iter().map(|role| role.to_string()).collect(),
+        iat: 0,
+        exp: 0,
+    };
+    assert_eq!(AuthenticatedUser::from(claims(&[])).roles, vec![Role::Customer]);
+    let unknown = AuthenticatedUser::from(claims(&["superuser"]));
+    assert!(unknown.roles.is_empty());
+    assert!(!unknown.can(Permission::ReadCatalog));
+}
+
+#[test]
+fn roles_combine() {
+    let user = user(&["catalog_manager", "support"]);
+    assert!(user.can(Permission::ManageCatalog));
+    assert!(user.can(Permission::ChangeOrderStatus));
+    assert!(!user.can(Permission::DeleteOrders));
+}
+
+#[test]
+fn api_keys_are_limited_to_their_scopes() {
+    let reader = api_key(&[ApiScope::ProductsRead, ApiScope::OrdersRead]);
+    assert!(reader.can(Permission::ReadCatalog));
+    assert!(reader.can(Permission::ReadOrders));
+    assert!(!reader.can(Permission::ManageCatalog));
+    assert!(!reader.can(Permission::PlaceOrders));
+    assert!(reader.customer_scope().is_none());
+    assert!(reader.subject().starts_with("api_key:"));
+    
+    let writer = api_key(&[ApiScope::ProductsWrite, ApiScope::OrdersWrite]);
+    assert!(writer.can(Permission::ReadCatalog));
+    assert!(writer.can(Permission::ManageCatalog));
+    assert!(writer.can(Permission::ChangeOrderStatus));
+    assert!(!writer.can(Permission::DeleteOrders));
+    assert!(!writer.can(Permission::ManageApiKeys));
+}
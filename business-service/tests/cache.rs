@@ -0,0 +1,401 @@
+// Product reads cached by `Cached` in front of a repository that counts its reads, through
+// `ProductService` and directly
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use async_trait::async_trait;
+use serde_json::json;
+use uuid::Uuid;
+
+use business_service::errors::{ServiceError, ServiceResult};
+use business_service::metrics::metrics;
+use business_service::models::{
+    AdjustStockDto, CreateProductDto, Product, ProductFacets, ScoredProduct, SearchTerms, StockChange, StockLevel, UpdateProductDto,
+};
+use business_service::repositories::{
+    CacheStore, Cached, Filter, InMemoryOutbox, InMemoryRepository, InMemoryTransactionManager, LocalCache, Page, ProductSearch,
+    QuerySpec, Repository, StockRepository, TransactionManager, TransactionalRepository, UnitOfWork,
+};
+use business_service::services::ProductService;
+
+// The in-memory repository, counting reads and optionally slowing down their answers
+#[derive(Clone, Default)]
+struct CountingRepository {
+    inner: InMemoryRepository<Product>,
+    reads: Arc<AtomicUsize>,
+    delay: Option<Duration>,
+}
+
+impl CountingRepository {
+    fn slow(delay: Duration) -> Self {
+        Self { delay: Some(delay), ..Self::default() }
+    }
+    
+    async fn read(&self) {
+        self.reads.fetch_add(1, Ordering::SeqCst);
+        if let Some(delay) = self.delay {
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+#[async_trait]
+impl Repository<Product, Uuid> for CountingRepository {
+    async fn find_by_id(&self, id: Uuid) -> ServiceResult<Option<Product>> {
+        let found = self.inner.find_by_id(id).await;
+        self.read().await;
+        found
+    }
+    
+    async fn find_all(&self, query: &QuerySpec) -> ServiceResult<Page<Product>> {
+        let page = self.inner.find_all(query).await;
+        self.read().await;
+        page
+    }
+    
+    async fn create(&self, item: Product) -> ServiceResult<Product> {
+        self.inner.create(item).await
+    }
+    
+    async fn update(&self, id: Uuid, item: Product) -> ServiceResult<Product> {
+        self.inner.update(id, item).await
+    }
+    
+    async fn delete(&self, id: Uuid) -> ServiceResult<()> {
+        self.inner.delete(id).await
+    }
+}
+
+#[async_trait]
+impl TransactionalRepository<Product, Uuid> for CountingRepository {
+    async fn find_by_id_in(&self, uow: &mut UnitOfWork, id: Uuid) -> ServiceResult<Option<Product>> {
+        let found = self.inner.find_by_id_in(uow, id).await;
+        self.read().await;
+        found
+    }
+    
+    async fn create_in(&self, uow: &mut UnitOfWork, item: Product) -> ServiceResult<Product> {
+        self.inner.create_in(uow, item).await
+    }
+    
+    async fn update_in(&self, uow: &mut UnitOfWork, id: Uuid, item: Product) -> ServiceResult<Product> {
+        self.inner.update_in(uow, id, item).await
+    }
+    
+    async fn delete_in(&self, uow: &mut UnitOfWork, id: Uuid) -> ServiceResult<()> {
+        self.inner.delete_in(uow, id).await
+    }
+}
+
+#[async_trait]
+impl StockRepository for CountingRepository {
+    async fn find_stock(&self, product_id: Uuid) -> ServiceResult<Option<StockLevel>> {
+        self.inner.find_stock(product_id).await
+    }
+    
+    async fn change_stock(&self, product_id: Uuid, change: StockChange, operation: Uuid) -> ServiceResult<StockLevel> {
+        self.inner.change_stock(product_id, change, operation).await
+    }
+    
+    async fn set_on_hand(&self, product_id: Uuid, on_hand: i32, operation: Uuid) -> ServiceResult<StockLevel> {
+        self.inner.set_on_hand(product_id, on_hand, operation).await
+    }
+    
+    async fn has_applied(&self, product_id: Uuid, operation: Uuid) -> ServiceResult<bool> {
+        self.inner.has_applied(product_id, operation).await
+    }
+}
+
+#[async_trait]
+impl ProductSearch for CountingRepository {
+    async fn search(&self, terms: &SearchTerms, query: &QuerySpec) -> ServiceResult<Page<ScoredProduct>> {
+        let found = self.inner.search(terms, query).await;
+        self.read().await;
+        found
+    }
+    
+    async fn facets(&self, terms: Option<&SearchTerms>, filters: &[Filter]) -> ServiceResult<ProductFacets> {
+        let facets = self.inner.facets(terms, filters).await;
+        self.read().await;
+        facets
+    }
+}
+
+// A cache whose backend is down
+struct UnavailableCache;
+
+#[async_trait]
+impl CacheStore for UnavailableCache {
+    async fn get(&self, _key: &str) -> ServiceResult<Option<String>> {
+        Err(ServiceError::DatabaseError("connection refused".to_string()))
+    }
+    
+    async fn set(&self, _key: &str, _value: &str, _ttl: Duration) -> ServiceResult<()> {
+        Err(ServiceError::DatabaseError("connection refused".to_string()))
+    }
+    
+    async fn delete(&self, _key: &str) -> ServiceResult<()> {
+        Err(ServiceError::DatabaseError("connection refused".to_string()))
+    }
+    
+    async fn counter(&self, _key: &str) -> ServiceResult<i64> {
+        Err(ServiceError::DatabaseError("connection refused".to_string()))
+    }
+    
+    async fn increment(&self, _key: &str) -> ServiceResult<i64> {
+        Err(ServiceError::DatabaseError("connection refused".to_string()))
+    }
+}
+
+fn local_cache() -> Arc<dyn CacheStore> {
+    Arc::new(LocalCache::new(NonZeroUsize::new(100).unwrap()))
+}
+
+fn cached(repository: CountingRepository, cache: Arc<dyn CacheStore>, name: &'static str) -> Arc<Cached<CountingRepository>> {
+    Arc::new(Cached::new(repository, cache, name, Duration::from_secs(60)))
+}
+
+// Products read through a cache, and read from `repository` itself before they are changed
+fn product_service(repository: &CountingRepository, cache: Arc<dyn CacheStore>, name: &'static str) -> ProductService {
+    let products = cached(repository.clone(), cache, name);
+    ProductService::new(
+        products.clone(),
+        Arc::new(repository.clone()),
+        products.clone(),
+        products,
+        Arc::new(InMemoryOutbox::new()),
+        Arc::new(InMemoryTransactionManager),
+    )
+}
+
+fn lamp() -> Product {
+    Product::new(
+        "Lamp".to_string(),
+        "A lamp".to_string(),
+        serde_json::from_value(json!({ "amount": "19.99", "currency": "USD" })).unwrap(),
+        "LAMP-1".to_string(),
+        "home".to_string(),
+    )
+}
+
+fn product_dto(sku: &str) -> CreateProductDto {
+    serde_json::from_value(json!({
+        "name": "Lamp",
+        "description": "A lamp",
+        "price": { "amount": "19.99", "currency": "USD" },
+        "sku": sku,
+        "category": "home",
+        "on_hand": 10
+    }))
+    .unwrap()
+}
+
+#[actix_web::test]
+async fn products_are_read_from_the_cache_until_they_change() {
+    let repository = CountingRepository::default();
+    let reads = || repository.reads.load(Ordering::SeqCst);
+    let service = product_service(&repository, local_cache(), "products");
+    
+    let product_id = service.create_product(product_dto("LAMP-1")).await.unwrap().id.unwrap();
+    assert_eq!(service.get_product(product_id).await.unwrap().unwrap().name, "Lamp");
+    assert_eq!(service.get_product(product_id).await.unwrap().unwrap().name, "Lamp");
+    assert_eq!(reads(), 1);
+    
+    // The update reads the product from the repository, then drops it from the cache
+    let rename: UpdateProductDto = serde_json::from_value(json!({ "name": "Desk lamp" })).unwrap();
+    service.update_product(product_id, rename).await.unwrap();
+    assert_eq!(service.get_product(product_id).await.unwrap().unwrap().name, "Desk lamp");
+    assert_eq!(reads(), 3);
+    
+    service.adjust_stock(product_id, AdjustStockDto { delta: Some(-4), on_hand: None }).await.unwrap();
+    assert_eq!(service.get_product(product_id).await.unwrap().unwrap().stock.on_hand, 6);
+    assert_eq!(reads(), 4);
+    
+    service.delete_product(product_id).await.unwrap();
+    assert!(service.get_product(product_id).await.unwrap().is_none());
+    assert_eq!(reads(), 5);
+}
+
+#[actix_web::test]
+async fn updates_start_from_the_stored_product_rather_than_a_cached_copy() {
+    let repository = CountingRepository::default();
+    let service = product_service(&repository, local_cache(), "products");
+    let product_id = service.create_product(product_dto("LAMP-1")).await.unwrap().id.unwrap();
+    let mut product = service.get_product(product_id).await.unwrap().unwrap();
+    
+    // Another instance renames the product; this one still has the old name cached
+    product.name = "Desk lamp".to_string();
+    repository.update(product_id, product).await.unwrap();
+    assert_eq!(service.get_product(product_id).await.unwrap().unwrap().name, "Lamp");
+    
+    let reprice: UpdateProductDto = serde_json::from_value(json!({ "price": { "amount": "24.99", "currency": "USD" } })).unwrap();
+    let updated = service.update_product(product_id, reprice).await.unwrap();
+    assert_eq!(updated.name, "Desk lamp");
+    assert_eq!(service.get_product(product_id).await.unwrap().unwrap().name, "Desk lamp");
+}
+
+#[actix_web::test]
+async fn loads_overtaken_by_a_write_are_not_cached() {
+    let repository = CountingRepository::slow(Duration::from_millis(50));
+    let products = cached(repository.clone(), local_cache(), "products");
+    let lamp = products.create(lamp()).await.unwrap();
+    let product_id = lamp.id.unwrap();
+    
+    // The rename lands while the first read is still loading the old name
+    let rename = async {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        products.update(product_id, Product { name: "Desk lamp".to_string(), ..lamp.clone() }).await.unwrap();
+    };
+    let (loaded, _) = tokio::join!(products.find_by_id(product_id), rename);
+    assert_eq!(loaded.unwrap().unwrap().name, "Lamp");
+    
+    assert_eq!(products.find_by_id(product_id).await.unwrap().unwrap().name, "Desk lamp");
+}
+
+#[actix_web::test]
+async fn listings_are_cached_per_query_and_dropped_by_any_write() {
+    let repository = CountingRepository::default();
+    let reads = || repository.reads.load(Ordering::SeqCst);
+    let service = product_service(&repository, local_cache(), "products");
+    let first_page = QuerySpec { limit: 1, ..QuerySpec::default() };
+    
+    service.create_product(product_dto("LAMP-1")).await.unwrap();
+    assert_eq!(service.get_all_products(&QuerySpec::default()).await.unwrap().total, 1);
+    assert_eq!(service.get_all_products(&QuerySpec::default()).await.unwrap().total, 1);
+    assert_eq!(service.get_all_products(&first_page).await.unwrap().items.len(), 1);
+    assert_eq!(reads(), 2);
+    
+    let chair = service.create_product(product_dto("CHAIR-1")).await.unwrap();
+    assert_eq!(service.get_all_products(&QuerySpec::default()).await.unwrap().total, 2);
+    assert_eq!(reads(), 3);
+    
+    // Stock is part of listed products, so stock changes drop listings too
+    service.adjust_stock(chair.id.unwrap(), AdjustStockDto { delta: None, on_hand: Some(0) }).await.unwrap();
+    let listed = service.get_all_products(&QuerySpec::default()).await.unwrap();
+    assert!(listed.items.iter().any(|product| product.sku == "CHAIR-1" && !product.in_stock));
+    assert_eq!(reads(), 4);
+}
+
+#[actix_web::test]
+async fn searches_are_cached_like_listings() {
+    let repository = CountingRepository::default();
+    let reads = || repository.reads.load(Ordering::SeqCst);
+    let service = product_service(&repository, local_cache(), "products");
+    
+    let lamp_id = service.create_product(product_dto("LAMP-1")).await.unwrap().id.unwrap();
+    assert_eq!(service.search_products("lamp", &QuerySpec::default()).await.unwrap().total, 1);
+    assert_eq!(service.search_products("lamp", &QuerySpec::default()).await.unwrap().total, 1);
+    assert_eq!(service.search_products("lamps", &QuerySpec::default()).await.unwrap().total, 1);
+    assert_eq!(reads(), 2);
+    
+    service.delete_product(lamp_id).await.unwrap();
+    assert_eq!(service.search_products("lamp", &QuerySpec::default()).await.unwrap().total, 0);
+    assert_eq!(reads(), 3);
+}
+
+#[actix_web::test]
+async fn facets_are_cached_like_listings() {
+    let repository = CountingRepository::default();
+    let reads = || repository.reads.load(Ordering::SeqCst);
+    let service = product_service(&repository, local_cache(), "products");
+    
+    let lamp_id = service.create_product(product_dto("LAMP-1")).await.unwrap().id.unwrap();
+    assert_eq!(service.get_product_facets(None, &QuerySpec::default()).await.unwrap().categories[0].count, 1);
+    assert_eq!(service.get_product_facets(None, &QuerySpec::default()).await.unwrap().categories[0].count, 1);
+    // Paging does not change the counts, so it does not change the cache key either
+    let second_page = QuerySpec { offset: 20, ..QuerySpec::default() };
+    assert_eq!(service.get_product_facets(None, &second_page).await.unwrap().categories[0].count, 1);
+    assert_eq!(service.get_product_facets(Some("lamp"), &QuerySpec::default()).await.unwrap().categories[0].count, 1);
+    assert_eq!(reads(), 2);
+    
+    service.adjust_stock(lamp_id, AdjustStockDto { delta: None, on_hand: Some(0) }).await.unwrap();
+    let facets = service.get_product_facets(None, &QuerySpec::default()).await.unwrap();
+    assert_eq!(facets.in_stock.iter().find(|counted| !counted.in_stock).unwrap().count, 1);
+    assert_eq!(reads(), 3);
+}
+
+#[actix_web::test]
+async fn concurrent_misses_read_the_repository_once() {
+    let repository = CountingRepository::slow(Duration::from_millis(50));
+    let products = cached(repository.clone(), local_cache(), "products");
+    let product_id = products.create(lamp()).await.unwrap()
+    .id
+    .unwrap();
+    
+    let lookups = (0..20).map(|_| products.find_by_id(product_id));
+    let found = futures_util::future::join_all(lookups).await;
+    
+    assert!(found.iter().all(|product| product.as_ref().unwrap().as_ref().unwrap().sku == "LAMP-1"));
+    assert_eq!(repository.reads.load(Ordering::SeqCst), 1);
+}
+
+#[actix_web::test]
+async fn writes_in_a_unit_of_work_drop_cached_values_once_it_commits() {
+    let repository = CountingRepository::default();
+    let products = cached(repository.clone(), local_cache(), "products");
+    let lamp = products.create(lamp()).await.unwrap();
+    let product_id = lamp.id.unwrap();
+    let renamed = Product { name: "Desk lamp".to_string(), ..lamp };
+    assert_eq!(products.find_by_id(product_id).await.unwrap().unwrap().name, "Lamp");
+    
+    let mut uow = InMemoryTransactionManager.begin().await.unwrap();
+    products.update_in(&mut uow, product_id, renamed.clone()).await.unwrap();
+    uow.rollback().await.unwrap();
+    assert_eq!(products.find_by_id(product_id).await.unwrap().unwrap().name, "Lamp");
+    assert_eq!(repository.reads.load(Ordering::SeqCst), 1);
+    
+    let mut uow = InMemoryTransactionManager.begin().await.unwrap();
+    products.update_in(&mut uow, product_id, renamed).await.unwrap();
+    uow.commit().await.unwrap();
+    assert_eq!(products.find_by_id(product_id).await.unwrap().unwrap().name, "Desk lamp");
+    assert_eq!(repository.reads.load(Ordering::SeqCst), 2);
+}
+
+#[actix_web::test]
+async fn reads_fall_back_to_the_repository_while_the_cache_is_down() {
+    let repository = CountingRepository::default();
+    let service = product_service(&repository, Arc::new(UnavailableCache), "products");
+    
+    let product_id = service.create_product(product_dto("LAMP-1")).await.unwrap().id.unwrap();
+    assert!(service.get_product(product_id).await.unwrap().is_some());
+    assert!(service.get_product(product_id).await.unwrap().is_some());
+    assert_eq!(service.get_all_products(&QuerySpec::default()).await.unwrap().total, 1);
+    assert_eq!(repository.reads.load(Ordering::SeqCst), 3);
+}
+
+#[actix_web::test]
+async fn cache_hits_and_misses_are_counted() {
+    let service = product_service(&CountingRepository::default(), local_cache(), "counted_products");
+    let product_id = service.create_product(product_dto("LAMP-1")).await.unwrap().id.unwrap();
+    
+    for _ in 0..3 {
+        service.get_product(product_id).await.unwrap();
+    }
+    
+    let rendered = metrics().render();
+    assert!(rendered.contains(r#"cache_lookups_total{cache="counted_products",method="find_by_id",result="hit"} 2"#));
+    assert!(rendered.contains(r#"cache_lookups_total{cache="counted_products",method="find_by_id",result="miss"} 1"#));
+}
+
+#[actix_web::test]
+async fn local_cache_expires_and_evicts_the_least_recently_used() {
+    let cache = LocalCache::new(NonZeroUsize::new(2).unwrap());
+    
+    cache.set("short", "1", Duration::from_millis(20)).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(40)).await;
+    assert_eq!(cache.get("short").await.unwrap(), None);
+    
+    cache.set("a", "1", Duration::from_secs(60)).await.unwrap();
+    cache.set("b", "2", Duration::from_secs(60)).await.unwrap();
+    cache.get("a").await.unwrap();
+    cache.set("c", "3", Duration::from_secs(60)).await.unwrap();
+    assert_eq!(cache.get("a").await.unwrap().as_deref(), Some("1"));
+    assert_eq!(cache.get("b").await.unwrap(), None);
+    
+    // Counters survive eviction
+    assert_eq!(cache.increment("version").await.unwrap(), 1);
+    cache.set("d", "4", Duration::from_secs(60)).await.unwrap();
+    cache.set("e", "5", Duration::from_secs(60)).await.unwrap();
+    assert_eq!(cache.counter("version").await.unwrap(), 1);
+}
@@ -0,0 +1,163 @@
+// Helpers shared by the tests that drive the HTTP API in-process. Each test binary uses a
+// different subset of them.
+#![allow(dead_code, unused_macros)]
+
+use std::sync::Arc;
+use actix_web::{http::header, web};
+use chrono::Utc;
+use jsonwebtoken::{encode, EncodingKey, Header};
+use uuid::Uuid;
+
+use business_service::config::AuthConfig;
+use business_service::events::{LogPublisher, OutboxRelay};
+use business_service::models::{Claims, Order, Product};
+use business_service::repositories::{
+    IdempotencyStore, InMemoryApiKeyStore, InMemoryIdempotencyStore, InMemoryOrderHistory, InMemoryOutbox, InMemoryRepository,
+    InMemorySagaStore, InMemoryTransactionManager, ProductSearch, StockRepository, TransactionalRepository,
+};
+use business_service::services::{ApiKeyService, AuthService, HealthService, OrderService, ProductService};
+
+pub const JWT_SECRET: &str = "test-secret";
+
+// The services `test_app!` registers, all backed by in-memory stores
+pub struct TestServices {
+    products: web::Data<ProductService>,
+    orders: web::Data<OrderService>,
+    idempotency: Arc<dyn IdempotencyStore>,
+    auth: web::Data<AuthService>,
+    api_keys: web::Data<ApiKeyService>,
+    health: web::Data<HealthService>,
+    relay: web::Data<OutboxRelay>,
+}
+
+impl TestServices {
+    pub fn new() -> Self {
+        Self::with_products(Arc::new(InMemoryRepository::<Product>::new()))
+    }
+    
+    // Services that read and write products, stock and searches through `products`
+    pub fn with_products<R>(products: Arc<R>) -> Self
+    where
+        R: TransactionalRepository<Product, Uuid> + StockRepository + ProductSearch + 'static,
+    {
+        let outbox = Arc::new(InMemoryOutbox::new());
+        Self {
+            products: web::Data::new(ProductService::new(
+                products.clone(),
+                products.clone(),
+                products.clone(),
+                products.clone(),
+                outbox.clone(),
+                Arc::new(InMemoryTransactionManager),
+            )),
+            orders: web::Data::new(OrderService::new(
+                Arc::new(InMemoryRepository::<Order>::new()),
+                products.clone(),
+                products,
+                Arc::new(InMemoryOrderHistory::new()),
+                outbox.clone(),
+                Arc::new(InMemoryTransactionManager),
+                Arc::new(InMemorySagaStore::new()),
+            )),
+            idempotency: Arc::new(InMemoryIdempotencyStore::new(60, 60)),
+            auth: web::Data::new(AuthService::new(&AuthConfig { jwt_secret: JWT_SECRET.to_string() })),
+            api_keys: web::Data::new(ApiKeyService::new(Arc::new(InMemoryApiKeyStore::new()))),
+            health: web::Data::new(HealthService::new(std::time::Duration::from_secs(1))),
+            relay: web::Data::new(OutboxRelay::new(
+                outbox,
+                Arc::new(InMemoryTransactionManager),
+                Arc::new(LogPublisher),
+                100,
+            )),
+        }
+    }
+    
+    pub fn with_idempotency(mut self, idempotency: Arc<dyn IdempotencyStore>) -> Self {
+        self.idempotency = idempotency;
+        self
+    }
+    
+    pub fn configure(self, cfg: &mut web::ServiceConfig) {
+        cfg.app_data(self.products)
+            .app_data(self.orders)
+            .app_data(web::Data::from(self.idempotency))
+            .app_data(self.auth)
+            .app_data(self.api_keys)
+            .app_data(self.health)
+            .app_data(self.relay);
+    }
+}
+
+// The whole API over `TestServices::new()`, or over the given services, optionally wrapped
+// in a middleware such as request tracing
+macro_rules! test_app {
+    () => {
+        test_app!($crate::common::TestServices::new())
+    };
+    ($services:expr) => {{
+        let services = $services;
+        actix_web::test::init_service(
+            actix_web::App::new()
+                .configure(|cfg| services.configure(cfg))
+                .configure(business_service::api::configure_routes),
+        )
+        .await
+    }};
+    ($services:expr, $middleware:expr) => {{
+        let services = $services;
+        actix_web::test::init_service(
+            actix_web::App::new()
+                .wrap($middleware)
+                .configure(|cfg| services.configure(cfg))
+                .configure(business_service::api::configure_routes),
+        )
+        .await
+    }};
+}
+#[allow(unused_imports)]
+pub(crate) use test_app;
+
+// A token as auth-service would issue it, valid for `expires_in` seconds
+pub fn token(user_id: i64, roles: &[&str], secret: &str, expires_in: i64) -> String {
+    let now = Utc::now().timestamp();
+    let claims = Claims {
+        id: user_id,
+        email: format!("user{}@example.com", user_id),
+        roles: roles.iter().map(|role| role.to_string()).collect(),
+        iat: now,
+        exp: now + expires_in,
+    };
+    encode(&Header::default(), &claims, &EncodingKey::from_secret(secret.as_bytes())).unwrap()
+}
+
+pub fn bearer_as(user_id: i64, roles: &[&str]) -> (header::HeaderName, String) {
+    (header::AUTHORIZATION, format!("Bearer {}", token(user_id, roles, JWT_SECRET, 3600)))
+}
+
+pub fn admin_bearer() -> (header::HeaderName, String) {
+    bearer_as(1, &["admin"])
+}
+
+// Create a product as an admin and return its id
+macro_rules! create_product {
+    ($app:expr, $name:expr, $description:expr, $category:expr, $price:expr, $on_hand:expr) => {{
+        let req = actix_web::test::TestRequest::post()
+            .uri("/api/products")
+            .insert_header($crate::common::admin_bearer())
+            .set_json(serde_json::json!({
+                "name": $name,
+                "description": $description,
+                "price": { "amount": $price, "currency": "USD" },
+                "sku": format!("SKU-{}", uuid::Uuid::new_v4().simple()),
+                "category": $category,
+                "on_hand": $on_hand,
+            }))
+            .to_request();
+        let response = actix_web::test::call_service($app, req).await;
+        assert_eq!(response.status(), actix_web::http::StatusCode::CREATED);
+        let product: serde_json::Value = actix_web::test::read_body_json(response).await;
+        product["_id"].as_str().unwrap().to_string()
+    }};
+}
+#[allow(unused_imports)]
+pub(crate) use create_product;
@@ -0,0 +1,74 @@
+// HTTP mapping of service errors to problem+json responses
+use actix_web::body::to_bytes;
+use actix_web::http::{header, StatusCode};
+use actix_web::ResponseError;
+use serde_json::Value;
+
+use business_service::errors::ServiceError;
+
+async fn problem(e: ServiceError) -> (StatusCode, Value) {
+    let response = e.error_response();
+    assert_eq!(response.headers().get(header::CONTENT_TYPE).unwrap(), "application/problem+json");
+    let status = response.status();
+    let body = to_bytes(response.into_body()).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[actix_web::test]
+async fn errors_map_to_statuses_and_stable_codes() {
+    for (e, status, code) in [
+        (ServiceError::BadRequestError("x".into()), StatusCode::BAD_REQUEST, "bad_request"),
+        (ServiceError::AuthError("x".into()), StatusCode::UNAUTHORIZED, "unauthenticated"),
+        (ServiceError::ForbiddenError("x".into()), StatusCode::FORBIDDEN, "forbidden"),
+        (ServiceError::NotFoundError("x".into()), StatusCode::NOT_FOUND, "not_found"),
+        (ServiceError::ConflictError("x".into()), StatusCode::CONFLICT, "conflict"),
+        (ServiceError::ValidationError("x".into()), StatusCode::UNPROCESSABLE_ENTITY, "validation_failed"),
+        (ServiceError::DatabaseError("x".into()), StatusCode::SERVICE_UNAVAILABLE, "database_unavailable"),
+        (ServiceError::DataError("x".into()), StatusCode::INTERNAL_SERVER_ERROR, "internal_error"),
+        (ServiceError::UnknownError("x".into()), StatusCode::INTERNAL_SERVER_ERROR, "internal_error"),
+    ] {
+        let (actual, body) = problem(e).await;
+        assert_eq!(actual, status);
+        assert_eq!(body["status"], status.as_u16());
+        assert_eq!(body["code"], code);
+        assert_eq!(body["type"], format!("urn:problem-type:business-service:{}", code));
+        assert!(body["title"].is_string());
+    }
+}
+
+#[actix_web::test]
+async fn client_errors_keep_their_message() {
+    let (_, body) = problem(ServiceError::NotFoundError("Order with id 42 not found".into())).await;
+    assert_eq!(body["detail"], "Order with id 42 not found");
+}
+
+#[actix_web::test]
+async fn server_errors_do_not_leak_internals() {
+    let leaky = "error returned from database: relation \"orders\" does not exist at 10.0.0.5:5432";
+    for e in [
+        ServiceError::DatabaseError(leaky.into()),
+        ServiceError::DataError(leaky.into()),
+        ServiceError::ConfigError(leaky.into()),
+        ServiceError::UnknownError(leaky.into()),
+    ] {
+        let (_, body) = problem(e).await;
+        let text = body.to_string();
+        assert!(!text.contains("relation"), "{}", text);
+        assert!(!text.contains("10.0.0.5"), "{}", text);
+    }
+}
+
+#[actix_web::test]
+async fn only_transient_database_failures_are_unavailable() {
+    let unavailable = |e: ServiceError| matches!(e, ServiceError::DatabaseError(_));
+    
+    assert!(unavailable(sqlx::Error::PoolTimedOut.into()));
+    assert!(unavailable(sqlx::Error::Io(std::io::ErrorKind::ConnectionReset.into()).into()));
+    assert!(unavailable(mongodb::error::Error::from(std::io::ErrorKind::ConnectionRefused).into()));
+    
+    assert!(!unavailable(sqlx::Error::RowNotFound.into()));
+    assert!(!unavailable(sqlx::Error::ColumnNotFound("price".to_string()).into()));
+    assert!(!unavailable(sqlx::Error::Protocol("unexpected message".to_string()).into()));
+    assert!(!unavailable(mongodb::error::Error::custom("unexpected reply").into()));
+    assert!(!unavailable(mongodb::bson::from_document::<u32>(mongodb::bson::doc! {}).unwrap_err().into()));
+}
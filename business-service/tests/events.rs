@@ -0,0 +1,139 @@
+// The envelope events are published in, and the events `OrderService` and `ProductService` publish
+use std::sync::Arc;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use business_service::config::{EventStreamConfig, RedisConfig};
+use business_service::events::{EventPublisher, InMemoryPublisher, OutboxRelay, RedisStreamPublisher};
+use business_service::models::{
+    CreateOrderDto, CreateOrderItemDto, DomainEvent, EventEnvelope, EventType, Order, Product, UpdateProductDto,
+};
+use business_service::repositories::{
+    InMemoryOrderHistory, InMemoryOutbox, InMemoryRepository, InMemorySagaStore, InMemoryTransactionManager, RedisClient,
+};
+use business_service::services::{OrderService, ProductService};
+
+#[test]
+fn envelope_carries_type_aggregate_and_schema_version() {
+    let order_id = Uuid::new_v4();
+    let event = DomainEvent::new(EventType::OrderStatusChanged, order_id, &json!({ "to": "shipped" })).unwrap();
+    
+    let envelope = serde_json::to_value(EventEnvelope::from(&event)).unwrap();
+    assert_eq!(
+        envelope,
+        json!({
+            "id": event.id,
+            "type": "OrderStatusChanged",
+            "aggregate_type": "order",
+            "aggregate_id": order_id,
+            "occurred_at": event.occurred_at,
+            "schema_version": 1,
+            "payload": { "to": "shipped" },
+        })
+    );
+    
+    let parsed: EventEnvelope = serde_json::from_value(envelope).unwrap();
+    assert_eq!(parsed, EventEnvelope::from(&event));
+}
+
+#[actix_web::test]
+async fn order_and_product_events_reach_the_publisher_in_envelopes() {
+    let product_repository = Arc::new(InMemoryRepository::<Product>::new());
+    let outbox = Arc::new(InMemoryOutbox::new());
+    let publisher = InMemoryPublisher::new();
+    let relay = OutboxRelay::new(outbox.clone(), Arc::new(InMemoryTransactionManager), Arc::new(publisher.clone()), 10);
+    let products = ProductService::new(
+        product_repository.clone(),
+        product_repository.clone(),
+        product_repository.clone(),
+        product_repository.clone(),
+        outbox.clone(),
+        Arc::new(InMemoryTransactionManager),
+    );
+    let orders = OrderService::new(
+        Arc::new(InMemoryRepository::<Order>::new()),
+        product_repository.clone(),
+        product_repository,
+        Arc::new(InMemoryOrderHistory::new()),
+        outbox,
+        Arc::new(InMemoryTransactionManager),
+        Arc::new(InMemorySagaStore::new()),
+    );
+    
+    let product = products
+        .create_product(serde_json::from_value(json!({
+            "name": "Lamp",
+            "description": "A lamp",
+            "price": { "amount": "19.99", "currency": "USD" },
+            "sku": "LAMP-1",
+            "category": "home",
+            "on_hand": 3
+        })).unwrap())
+        .await
+        .unwrap();
+    let product_id = product.id.unwrap();
+    let reprice: UpdateProductDto = serde_json::from_value(json!({ "price": { "amount": "17.50", "currency": "USD" } })).unwrap();
+    products.update_product(product_id, reprice).await.unwrap();
+    let order = orders
+        .create_order(CreateOrderDto {
+            customer_id: Uuid::new_v4(),
+            items: vec![CreateOrderItemDto { product_id, quantity: 1 }],
+        })
+        .await
+        .unwrap();
+    relay.relay_pending().await.unwrap();
+    
+    let published = publisher.published();
+    let types: Vec<EventType> = published.iter().map(|envelope| envelope.event_type).collect();
+    assert_eq!(
+        types,
+        vec![
+            EventType::ProductCreated,
+            EventType::ProductUpdated,
+            EventType::ProductPriceChanged,
+            EventType::OrderCreated,
+        ]
+    );
+    assert!(published.iter().all(|envelope| envelope.schema_version == EventEnvelope::SCHEMA_VERSION));
+    
+    let placed = publisher.published_for(order.id.unwrap());
+    assert_eq!(placed[0].aggregate_type, "order");
+    assert_eq!(placed[0].payload["items"][0]["price"], json!({ "amount": "17.50", "currency": "USD" }));
+    assert_eq!(publisher.published_for(product_id)[2].payload["new_price"]["amount"], "17.50");
+}
+
+// Opt in with `cargo test -- --ignored`, against the Redis at `TEST_REDIS_URL`
+#[actix_web::test]
+#[ignore = "needs a Redis server at TEST_REDIS_URL (default redis://127.0.0.1:6379)"]
+async fn redis_stream_entries_carry_type_aggregate_and_envelope() {
+    let url = std::env::var("TEST_REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1:6379".to_string());
+    let redis = RedisClient::new(&RedisConfig { url }).unwrap();
+    let config = EventStreamConfig { prefix: format!("test-events-{}", Uuid::new_v4().simple()), max_len: 100 };
+    let publisher = RedisStreamPublisher::new(redis.clone(), &config);
+    let order_id = Uuid::new_v4();
+    let event = DomainEvent::new(EventType::OrderCreated, order_id, &json!({ "total": "19.99" })).unwrap();
+    let envelope = EventEnvelope::from(&event);
+    
+    publisher.publish(&envelope).await.unwrap();
+    
+    let stream = publisher.stream_key(&envelope);
+    assert_eq!(stream, format!("{}:order", config.prefix));
+    let mut connection = redis.connection().await.unwrap();
+    let entries: Vec<(String, Vec<String>)> = redis::cmd("XRANGE")
+        .arg(&stream)
+        .arg("-")
+        .arg("+")
+        .query_async(&mut connection)
+        .await
+        .unwrap();
+    redis::cmd("DEL").arg(&stream).query_async::<()>(&mut connection).await.unwrap();
+    
+    assert_eq!(entries.len(), 1);
+    let fields = &entries[0].1;
+    assert_eq!(fields[..5], ["type", "OrderCreated", "aggregate_id", order_id.to_string().as_str(), "envelope"]);
+    assert_eq!(serde_json::from_str::<Value>(&fields[5]).unwrap(), serde_json::to_value(&envelope).unwrap());
+    // Anything after the envelope is W3C trace context
+    for header in fields[6..].chunks(2).map(|pair| pair[0].as_str()) {
+        assert!(["traceparent", "tracestate"].contains(&header), "{}", header);
+    }
+}
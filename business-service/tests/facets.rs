@@ -0,0 +1,145 @@
+// Facet counts returned with product listings and searches, against the in-memory repository
+use std::str::FromStr;
+use actix_web::{http::StatusCode, test};
+use mongodb::bson::{doc, Bson};
+use rust_decimal::Decimal;
+use serde_json::{json, Value};
+
+use business_service::models::ProductFacets;
+use business_service::repositories::ProductRepository;
+
+mod common;
+use common::{bearer_as, create_product, test_app};
+
+macro_rules! get_facets {
+    ($app:expr, $uri:expr) => {{
+        let req = test::TestRequest::get()
+            .uri($uri)
+            .insert_header(bearer_as(1, &["customer"]))
+            .to_request();
+        let response = test::call_service($app, req).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: Value = test::read_body_json(response).await;
+        body["facets"].clone()
+    }};
+}
+
+// Counts of the price ranges, in order
+fn price_counts(facets: &Value) -> Vec<u64> {
+    facets["price_ranges"].as_array().unwrap().iter().map(|range| range["count"].as_u64().unwrap()).collect()
+}
+
+#[actix_web::test]
+async fn listings_count_every_matching_product_not_just_the_page() {
+    let app = test_app!();
+    create_product!(&app, "Desk lamp", "", "lighting", "24.99", 5);
+    create_product!(&app, "Floor lamp", "", "lighting", "25.00", 0);
+    create_product!(&app, "Bookshelf", "", "furniture", "120.00", 1);
+    create_product!(&app, "Sofa", "", "furniture", "899.00", 1);
+    create_product!(&app, "Armchair", "", "furniture", "499.99", 2);
+    
+    let facets = get_facets!(&app, "/api/products?limit=1");
+    
+    assert_eq!(facets["categories"], json!([
+        { "category": "furniture", "count": 3 },
+        { "category": "lighting", "count": 2 },
+    ]));
+    assert_eq!(facets["price_ranges"][0], json!({ "min": 0, "max": 25, "count": 1 }));
+    assert_eq!(facets["price_ranges"][5], json!({ "min": 500, "max": null, "count": 1 }));
+    assert_eq!(price_counts(&facets), vec![1, 1, 0, 1, 1, 1]);
+    assert_eq!(facets["in_stock"], json!([
+        { "in_stock": true, "count": 4 },
+        { "in_stock": false, "count": 1 },
+    ]));
+}
+
+#[actix_web::test]
+async fn facets_follow_the_filters() {
+    let app = test_app!();
+    create_product!(&app, "Desk lamp", "", "lighting", "35.00", 5);
+    create_product!(&app, "Floor lamp", "", "lighting", "95.00", 0);
+    create_product!(&app, "Bookshelf", "", "furniture", "80.00", 1);
+    
+    let facets = get_facets!(&app, "/api/products?price[gte]=50&in_stock=true");
+    
+    assert_eq!(facets["categories"], json!([{ "category": "furniture", "count": 1 }]));
+    assert_eq!(price_counts(&facets), vec![0, 0, 1, 0, 0, 0]);
+    assert_eq!(facets["in_stock"], json!([
+        { "in_stock": true, "count": 1 },
+        { "in_stock": false, "count": 0 },
+    ]));
+}
+
+#[actix_web::test]
+async fn searches_count_their_matches() {
+    let app = test_app!();
+    create_product!(&app, "Desk lamp", "", "lighting", "35.00", 5);
+    create_product!(&app, "Lamp oil", "", "supplies", "8.00", 0);
+    create_product!(&app, "Bookshelf", "", "furniture", "80.00", 1);
+    
+    let facets = get_facets!(&app, "/api/products/search?q=lamp&limit=1");
+    
+    assert_eq!(facets["categories"], json!([
+        { "category": "lighting", "count": 1 },
+        { "category": "supplies", "count": 1 },
+    ]));
+    assert_eq!(price_counts(&facets), vec![1, 1, 0, 0, 0, 0]);
+}
+
+#[actix_web::test]
+async fn empty_listings_still_list_every_price_range_and_availability() {
+    let app = test_app!();
+    
+    let facets = get_facets!(&app, "/api/products?category=lighting");
+    
+    assert_eq!(facets["categories"], json!([]));
+    assert_eq!(price_counts(&facets), vec![0; 6]);
+    assert_eq!(facets["in_stock"].as_array().unwrap().len(), 2);
+}
+
+#[actix_web::test]
+async fn price_ranges_include_their_lower_bound() {
+    let range = |amount: &str| ProductFacets::price_range(Decimal::from_str(amount).unwrap());
+    
+    assert_eq!(range("0"), 0);
+    assert_eq!(range("24.99"), 0);
+    assert_eq!(range("25"), 1);
+    assert_eq!(range("499.99"), 4);
+    assert_eq!(range("500"), 5);
+    assert_eq!(range("10000"), 5);
+}
+
+#[actix_web::test]
+async fn mongo_facet_output_is_read_into_buckets() {
+    let output = doc! {
+        "categories": [
+            { "_id": "lighting", "count": 2_i32 },
+            { "_id": "furniture", "count": 3_i64 },
+        ],
+        "price_ranges": [
+            { "_id": 0_i64, "count": 1_i32 },
+            { "_id": 100_i64, "count": 2_i32 },
+            { "_id": "above", "count": 4_i32 },
+        ],
+        "in_stock": [{ "_id": false, "count": 5_i32 }],
+    };
+    
+    let facets = ProductRepository::facets_from_document(&output).unwrap();
+    
+    assert_eq!(facets.categories.iter().map(|counted| counted.category.as_str()).collect::<Vec<_>>(), vec!["furniture", "lighting"]);
+    assert_eq!(facets.price_ranges.iter().map(|range| range.count).collect::<Vec<_>>(), vec![1, 0, 0, 2, 0, 4]);
+    assert_eq!(facets.in_stock.iter().map(|counted| (counted.in_stock, counted.count)).collect::<Vec<_>>(), vec![(true, 0), (false, 5)]);
+}
+
+#[actix_web::test]
+async fn unknown_mongo_price_buckets_are_rejected() {
+    for bucket in [Bson::Int64(30), Bson::String("other".to_string()), Bson::Null] {
+        let output = doc! {
+            "categories": [],
+            "price_ranges": [{ "_id": bucket.clone(), "count": 1_i32 }],
+            "in_stock": [],
+        };
+        
+        assert!(ProductRepository::facets_from_document(&output).is_err(), "{:?}", bucket);
+    }
+}
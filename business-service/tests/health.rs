@@ -0,0 +1,78 @@
+// Readiness checks against stand-in stores that succeed, fail or hang
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use actix_web::{http::StatusCode, test, web, App};
+use async_trait::async_trait;
+use serde_json::Value;
+
+use business_service::api::health_controller;
+use business_service::errors::{ServiceError, ServiceResult};
+use business_service::models::HealthStatus;
+use business_service::repositories::HealthCheck;
+use business_service::services::HealthService;
+
+enum FakeStore {
+    Up(Duration),
+    Failing,
+    Hanging,
+}
+
+#[async_trait]
+impl HealthCheck for FakeStore {
+    async fn health_check(&self) -> ServiceResult<()> {
+        match self {
+            FakeStore::Up(delay) => {
+                tokio::time::sleep(*delay).await;
+                Ok(())
+            }
+            FakeStore::Failing => Err(ServiceError::DatabaseError("connection refused by 10.0.0.5".to_string())),
+            FakeStore::Hanging => std::future::pending().await,
+        }
+    }
+}
+
+#[actix_web::test]
+async fn checks_run_concurrently_and_time_out() {
+    let service = HealthService::new(Duration::from_millis(300))
+        .with_dependency("postgres", Arc::new(FakeStore::Up(Duration::from_millis(150))), true)
+        .with_dependency("mongodb", Arc::new(FakeStore::Up(Duration::from_millis(150))), true)
+        .with_dependency("cache", Arc::new(FakeStore::Hanging), false);
+    
+    let started = Instant::now();
+    let report = service.readiness().await;
+    assert!(started.elapsed() < Duration::from_millis(450), "checks ran one after another");
+    
+    assert_eq!(report.status, HealthStatus::Up);
+    let statuses: Vec<_> = report.checks.iter().map(|check| (check.name.as_str(), check.status)).collect();
+    assert_eq!(statuses, [("postgres", HealthStatus::Up), ("mongodb", HealthStatus::Up), ("cache", HealthStatus::Down)]);
+    assert!(report.checks[0].latency_ms >= 150);
+    assert_eq!(report.checks[2].error.as_deref(), Some("timed out after 300 ms"));
+}
+
+#[actix_web::test]
+async fn ready_is_unavailable_while_a_required_store_is_down() {
+    let service = HealthService::new(Duration::from_millis(300))
+        .with_dependency("postgres", Arc::new(FakeStore::Up(Duration::ZERO)), true)
+        .with_dependency("mongodb", Arc::new(FakeStore::Failing), true);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(service))
+            .route("/health/live", web::get().to(health_controller::live))
+            .route("/health/ready", web::get().to(health_controller::ready)),
+    )
+    .await;
+    
+    let req = test::TestRequest::get().uri("/health/ready").to_request();
+    let response = test::call_service(&app, req).await;
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let report: Value = test::read_body_json(response).await;
+    assert_eq!(report["status"], "down");
+    assert_eq!(report["checks"][0]["status"], "up");
+    assert_eq!(report["checks"][1]["status"], "down");
+    // The database error itself is only logged
+    assert_eq!(report["checks"][1]["error"], "unavailable");
+    
+    // Liveness does not depend on the stores
+    let req = test::TestRequest::get().uri("/health/live").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+}
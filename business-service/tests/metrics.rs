@@ -0,0 +1,123 @@
+// Metrics recorded while serving requests, read back from `/metrics`
+use std::sync::Arc;
+use actix_web::middleware::from_fn;
+use actix_web::{http::header, http::StatusCode, test};
+use serde_json::{json, Value};
+
+use business_service::api::metrics::record_request_metrics;
+use business_service::models::Product;
+use business_service::repositories::{InMemoryRepository, Instrumented};
+
+mod common;
+use common::{admin_bearer, test_app, TestServices};
+
+// An app whose product repository records its calls, counting requests as `main` does
+macro_rules! metered_app {
+    () => {
+        test_app!(
+            TestServices::with_products(Arc::new(Instrumented::new(InMemoryRepository::<Product>::new(), "memory", "products"))),
+            from_fn(record_request_metrics)
+        )
+    };
+}
+
+// The value of one sample, e.g. `http_requests_total{method="GET",route="/metrics",status="200"}`
+fn sample(metrics: &str, series: &str) -> f64 {
+    metrics
+        .lines()
+        .find_map(|line| line.strip_prefix(series)?.strip_prefix(' '))
+        .map(|value| value.parse().unwrap())
+        .unwrap_or_default()
+}
+
+macro_rules! scrape {
+    ($app:expr) => {{
+        let req = test::TestRequest::get().uri("/metrics").to_request();
+        let response = test::call_service($app, req).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().get(header::CONTENT_TYPE).unwrap().to_str().unwrap().starts_with("text/plain"));
+        String::from_utf8(test::read_body(response).await.to_vec()).unwrap()
+    }};
+}
+
+// Metrics are process-wide and other tests in this file run concurrently, so
+// assertions compare counts before and after
+#[actix_web::test]
+async fn requests_are_counted_by_route_pattern() {
+    let app = metered_app!();
+    let before = scrape!(&app);
+    
+    for _ in 0..2 {
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/products/{}", uuid::Uuid::new_v4()))
+            .insert_header(admin_bearer())
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+    }
+    let req = test::TestRequest::get().uri("/no/such/route").to_request();
+    test::call_service(&app, req).await;
+    
+    let after = scrape!(&app);
+    let by_id = r#"http_requests_total{method="GET",route="/api/products/{id}",status="404"}"#;
+    assert_eq!(sample(&after, by_id) - sample(&before, by_id), 2.0);
+    let unmatched = r#"http_requests_total{method="GET",route="unmatched",status="404"}"#;
+    assert_eq!(sample(&after, unmatched) - sample(&before, unmatched), 1.0);
+    assert!(after.contains(r#"http_request_duration_seconds_bucket{method="GET",route="/api/products/{id}",status="404",le="0.005"}"#));
+}
+
+#[actix_web::test]
+async fn business_events_and_repository_calls_are_recorded() {
+    let app = metered_app!();
+    let before = scrape!(&app);
+    
+    let req = test::TestRequest::post()
+        .uri("/api/products")
+        .insert_header(admin_bearer())
+        .set_json(json!({
+            "name": "lamp",
+            "description": "",
+            "price": { "amount": "19.99", "currency": "GBP" },
+            "sku": "LAMP",
+            "category": "home",
+            "on_hand": 10,
+        }))
+        .to_request();
+    let product: Value = test::call_and_read_body_json(&app, req).await;
+    let product_id = product["_id"].as_str().unwrap().to_string();
+    
+    let req = test::TestRequest::post()
+        .uri("/api/orders")
+        .insert_header(admin_bearer())
+        .set_json(json!({
+            "customer_id": "8a1f7a52-6f4e-4a57-9a55-0f5b6b4f2f11",
+            "items": [{ "product_id": product_id, "quantity": 2 }]
+        }))
+        .to_request();
+    let order: Value = test::call_and_read_body_json(&app, req).await;
+    
+    let req = test::TestRequest::patch()
+        .uri(&format!("/api/orders/{}/status", order["_id"].as_str().unwrap()))
+        .insert_header(admin_bearer())
+        .set_json(json!({ "status": "cancelled" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    
+    let req = test::TestRequest::delete()
+        .uri(&format!("/api/products/{}", product_id))
+        .insert_header(admin_bearer())
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NO_CONTENT);
+    
+    let after = scrape!(&app);
+    let delta = |series: &str| sample(&after, series) - sample(&before, series);
+    assert_eq!(delta(r#"business_product_changes_total{action="created"}"#), 1.0);
+    assert_eq!(delta(r#"business_product_changes_total{action="deleted"}"#), 1.0);
+    assert_eq!(delta(r#"business_orders_created_total{status="pending"}"#), 1.0);
+    assert_eq!(delta(r#"business_order_status_changes_total{status="cancelled"}"#), 1.0);
+    assert_eq!(delta(r#"business_order_value_count{currency="GBP"}"#), 1.0);
+    assert!((delta(r#"business_order_value_sum{currency="GBP"}"#) - 39.98).abs() < 1e-9);
+    
+    // Reserving and releasing the lamp went through the timed repository
+    let change_stock = r#"db_operation_duration_seconds_count{method="change_stock",outcome="ok",repository="products",store="memory"}"#;
+    assert_eq!(delta(change_stock), 2.0);
+}
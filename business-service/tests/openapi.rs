@@ -0,0 +1,107 @@
+// Checks that the served OpenAPI document and the routes registered in `api::routes` agree
+use std::collections::BTreeSet;
+use actix_web::{http::header, http::Method, http::StatusCode, test};
+use serde_json::Value;
+
+use business_service::api::routes::ROUTES;
+use business_service::errors::PROBLEM_CONTENT_TYPE;
+
+mod common;
+use common::{admin_bearer, test_app};
+
+// `(METHOD, path)` for every operation in the document
+fn documented_operations(spec: &Value) -> BTreeSet<(String, String)> {
+    let mut operations = BTreeSet::new();
+    for (path, item) in spec["paths"].as_object().unwrap() {
+        for method in item.as_object().unwrap().keys() {
+            operations.insert((method.to_uppercase(), path.clone()));
+        }
+    }
+    operations
+}
+
+// `(METHOD, path)` for every route `configure_routes` registers
+fn registered_routes() -> BTreeSet<(String, String)> {
+    ROUTES.iter().map(|route| (route.method.to_string(), route.full_path())).collect()
+}
+
+#[actix_web::test]
+async fn served_spec_matches_registered_routes() {
+    let app = test_app!();
+    
+    let req = test::TestRequest::get().uri("/api/openapi.json").to_request();
+    let response = test::call_service(&app, req).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let spec: Value = test::read_body_json(response).await;
+    assert_eq!(spec["openapi"], "3.1.0");
+    
+    let documented = documented_operations(&spec);
+    let registered = registered_routes();
+    assert_eq!(
+        documented.difference(&registered).collect::<Vec<_>>(),
+        Vec::<&(String, String)>::new(),
+        "documented operations that are not registered"
+    );
+    assert_eq!(
+        registered.difference(&documented).collect::<Vec<_>>(),
+        Vec::<&(String, String)>::new(),
+        "registered routes missing from the OpenAPI document"
+    );
+    
+    // Every documented operation reaches a handler: unrouted requests get an empty 404 or a 405,
+    // while handlers answer with a problem document at worst
+    for (method, path) in &documented {
+        let uri = path.replace("{id}", "8a1f7a52-6f4e-4a57-9a55-0f5b6b4f2f11");
+        let req = test::TestRequest::default()
+            .method(Method::from_bytes(method.as_bytes()).unwrap())
+            .uri(&uri)
+            .insert_header(admin_bearer())
+            .to_request();
+        let response = test::call_service(&app, req).await;
+        
+        let status = response.status();
+        let content_type = response.headers().get(header::CONTENT_TYPE).map(|value| value.to_str().unwrap().to_string());
+        let unrouted = status == StatusCode::METHOD_NOT_ALLOWED
+            || (status == StatusCode::NOT_FOUND && content_type.as_deref() != Some(PROBLEM_CONTENT_TYPE));
+        assert!(!unrouted, "{} {} is documented but not routed ({})", method, path, status);
+    }
+}
+
+#[actix_web::test]
+async fn documented_routes_declare_their_security() {
+    let app = test_app!();
+    
+    let req = test::TestRequest::get().uri("/api/openapi.json").to_request();
+    let spec: Value = test::call_and_read_body_json(&app, req).await;
+    
+    let schemes = spec["components"]["securitySchemes"].as_object().unwrap();
+    assert_eq!(schemes["bearer"]["scheme"], "bearer");
+    assert_eq!(schemes["api_key"]["name"], "X-Api-Key");
+    
+    for (path, item) in spec["paths"].as_object().unwrap() {
+        for (method, operation) in item.as_object().unwrap() {
+            let secured = operation.get("security").is_some();
+            assert_eq!(secured, path.starts_with("/api/"), "{} {}", method, path);
+            if secured {
+                assert!(operation["responses"]["401"].is_object(), "{} {} has no 401 response", method, path);
+            }
+        }
+    }
+    
+    // Routes that need a permission are the ones documented as secured
+    for route in ROUTES {
+        let operation = &spec["paths"][route.full_path()][route.method.as_str().to_lowercase()];
+        assert_eq!(operation.get("security").is_some(), route.permission.is_some(), "{} {}", route.method, route.full_path());
+    }
+}
+
+#[actix_web::test]
+async fn swagger_ui_is_served() {
+    let app = test_app!();
+    
+    let req = test::TestRequest::get().uri("/api/docs/").to_request();
+    let response = test::call_service(&app, req).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = test::read_body(response).await;
+    assert!(String::from_utf8_lossy(&body).contains("swagger-ui"));
+}
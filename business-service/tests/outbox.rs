@@ -0,0 +1,418 @@
+// Domain events recorded in the outbox by the order and product services, and relayed by
+// `OutboxRelay`, using the in-memory repositories
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use actix_web::{http::StatusCode, test, web, App};
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use business_service::api::configure_routes;
+use business_service::config::AuthConfig;
+use business_service::errors::{ServiceError, ServiceResult};
+use business_service::events::{EventPublisher, InMemoryPublisher, OutboxRelay};
+use business_service::models::{
+    CreateOrderDto, CreateOrderItemDto, CreateProductDto, DomainEvent, EventEnvelope, EventType, Order,
+    OrderStatus, Product, ReplayEventsDto, UpdateOrderStatusDto, UpdateProductDto,
+};
+use business_service::repositories::{
+    InMemoryOrderHistory, InMemoryOutbox, InMemoryRepository, InMemorySagaStore, InMemoryTransactionManager,
+    OutboxStore, TransactionManager,
+};
+use business_service::services::{AuthService, OrderService, ProductService};
+
+mod common;
+use common::{bearer_as, JWT_SECRET};
+
+// Publishes in memory, except for the aggregates it is told to fail and while it is unreachable
+#[derive(Default)]
+struct FlakyPublisher {
+    inner: InMemoryPublisher,
+    failing: Mutex<HashSet<Uuid>>,
+    unreachable: Mutex<bool>,
+}
+
+impl FlakyPublisher {
+    fn published(&self) -> Vec<(EventType, Uuid)> {
+        self.inner
+            .published()
+            .iter()
+            .map(|envelope| (envelope.event_type, envelope.aggregate_id))
+            .collect()
+    }
+    
+    fn fail_for(&self, aggregate_id: Uuid, failing: bool) {
+        let mut aggregates = self.failing.lock().unwrap();
+        if failing {
+            aggregates.insert(aggregate_id);
+        } else {
+            aggregates.remove(&aggregate_id);
+        }
+    }
+    
+    fn set_unreachable(&self, unreachable: bool) {
+        *self.unreachable.lock().unwrap() = unreachable;
+    }
+}
+
+#[async_trait]
+impl EventPublisher for FlakyPublisher {
+    async fn publish(&self, envelope: &EventEnvelope) -> ServiceResult<()> {
+        if *self.unreachable.lock().unwrap() {
+            return Err(ServiceError::DatabaseError("connection refused".to_string()));
+        }
+        if self.failing.lock().unwrap().contains(&envelope.aggregate_id) {
+            return Err(ServiceError::UnknownError("broker unavailable".to_string()));
+        }
+        
+        self.inner.publish(envelope).await
+    }
+}
+
+struct Fixture {
+    outbox: Arc<InMemoryOutbox>,
+    publisher: Arc<FlakyPublisher>,
+    relay: OutboxRelay,
+    products: ProductService,
+    orders: OrderService,
+}
+
+fn fixture() -> Fixture {
+    let product_repository = Arc::new(InMemoryRepository::<Product>::new());
+    let outbox = Arc::new(InMemoryOutbox::new());
+    let publisher = Arc::new(FlakyPublisher::default());
+    
+    Fixture {
+        relay: relay(&outbox, &publisher, 100).with_retries(OutboxRelay::DEFAULT_MAX_ATTEMPTS, Duration::zero()),
+        products: ProductService::new(
+            product_repository.clone(),
+            product_repository.clone(),
+            product_repository.clone(),
+            product_repository.clone(),
+            outbox.clone(),
+            Arc::new(InMemoryTransactionManager),
+        ),
+        orders: OrderService::new(
+            Arc::new(InMemoryRepository::<Order>::new()),
+            product_repository.clone(),
+            product_repository,
+            Arc::new(InMemoryOrderHistory::new()),
+            outbox.clone(),
+            Arc::new(InMemoryTransactionManager),
+            Arc::new(InMemorySagaStore::new()),
+        ),
+        outbox,
+        publisher,
+    }
+}
+
+fn relay(outbox: &Arc<InMemoryOutbox>, publisher: &Arc<FlakyPublisher>, batch_size: i64) -> OutboxRelay {
+    OutboxRelay::new(outbox.clone(), Arc::new(InMemoryTransactionManager), publisher.clone(), batch_size)
+}
+
+fn product_dto(sku: &str, price: &str) -> CreateProductDto {
+    serde_json::from_value(json!({
+        "name": "Lamp",
+        "description": "A lamp",
+        "price": { "amount": price, "currency": "USD" },
+        "sku": sku,
+        "category": "home",
+        "on_hand": 10
+    }))
+    .unwrap()
+}
+
+fn order_dto(product: &Product) -> CreateOrderDto {
+    CreateOrderDto {
+        customer_id: Uuid::new_v4(),
+        items: vec![CreateOrderItemDto { product_id: product.id.unwrap(), quantity: 2 }],
+    }
+}
+
+fn recorded(outbox: &InMemoryOutbox) -> Vec<(EventType, Uuid)> {
+    outbox.entries().iter().map(|entry| (entry.event.event_type, entry.event.aggregate_id)).collect()
+}
+
+#[actix_web::test]
+async fn order_changes_record_events_in_the_outbox() {
+    let fixture = fixture();
+    let product = fixture.products.create_product(product_dto("LAMP-1", "19.99")).await.unwrap();
+    let product_id = product.id.unwrap();
+    
+    let order = fixture.orders.create_order(order_dto(&product)).await.unwrap();
+    let order_id = order.id.unwrap();
+    fixture.orders
+        .update_order_status(order_id, UpdateOrderStatusDto { status: OrderStatus::Cancelled, reason: None }, None)
+        .await
+        .unwrap();
+    fixture.orders.delete_order(order_id).await.unwrap();
+    
+    assert_eq!(
+        recorded(&fixture.outbox),
+        vec![
+            (EventType::ProductCreated, product_id),
+            (EventType::OrderCreated, order_id),
+            (EventType::OrderStatusChanged, order_id),
+            (EventType::OrderDeleted, order_id),
+        ]
+    );
+    
+    let entries = fixture.outbox.entries();
+    assert_eq!(entries[1].event.payload["total"], json!({ "amount": "39.98", "currency": "USD" }));
+    assert_eq!(entries[2].event.payload["from"], "pending");
+    assert_eq!(entries[2].event.payload["to"], "cancelled");
+}
+
+#[actix_web::test]
+async fn price_changes_are_recorded_as_their_own_event() {
+    let fixture = fixture();
+    let product = fixture.products.create_product(product_dto("LAMP-1", "19.99")).await.unwrap();
+    let product_id = product.id.unwrap();
+    
+    let rename: UpdateProductDto = serde_json::from_value(json!({ "name": "Desk lamp" })).unwrap();
+    fixture.products.update_product(product_id, rename).await.unwrap();
+    let reprice: UpdateProductDto = serde_json::from_value(json!({ "price": { "amount": "24.99", "currency": "USD" } })).unwrap();
+    fixture.products.update_product(product_id, reprice).await.unwrap();
+    fixture.products.delete_product(product_id).await.unwrap();
+    
+    assert_eq!(
+        recorded(&fixture.outbox),
+        vec![
+            (EventType::ProductCreated, product_id),
+            (EventType::ProductUpdated, product_id),
+            (EventType::ProductUpdated, product_id),
+            (EventType::ProductPriceChanged, product_id),
+            (EventType::ProductDeleted, product_id),
+        ]
+    );
+    let price_change = &fixture.outbox.entries()[3].event.payload;
+    assert_eq!(price_change["old_price"]["amount"], "19.99");
+    assert_eq!(price_change["new_price"]["amount"], "24.99");
+}
+
+#[actix_web::test]
+async fn failed_changes_record_no_events() {
+    let fixture = fixture();
+    let product = fixture.products.create_product(product_dto("LAMP-1", "19.99")).await.unwrap();
+    
+    // More than is in stock: the order is never written
+    let mut dto = order_dto(&product);
+    dto.items[0].quantity = 50;
+    assert!(matches!(fixture.orders.create_order(dto).await, Err(ServiceError::ConflictError(_))));
+    assert!(fixture.products.delete_product(Uuid::new_v4()).await.is_err());
+    
+    assert_eq!(recorded(&fixture.outbox), vec![(EventType::ProductCreated, product.id.unwrap())]);
+}
+
+#[actix_web::test]
+async fn events_appended_in_a_rolled_back_unit_of_work_are_dropped() {
+    let outbox = InMemoryOutbox::new();
+    let transactions = InMemoryTransactionManager;
+    let event = DomainEvent::new(EventType::OrderDeleted, Uuid::new_v4(), &json!({})).unwrap();
+    
+    let mut uow = transactions.begin().await.unwrap();
+    outbox.append_in(&mut uow, std::slice::from_ref(&event)).await.unwrap();
+    uow.rollback().await.unwrap();
+    assert!(outbox.entries().is_empty());
+    
+    let mut uow = transactions.begin().await.unwrap();
+    outbox.append_in(&mut uow, &[event]).await.unwrap();
+    uow.commit().await.unwrap();
+    assert_eq!(outbox.entries().len(), 1);
+}
+
+// Events moved from MongoDB are appended again if dropping them there failed
+#[actix_web::test]
+async fn events_already_in_the_outbox_are_not_appended_again() {
+    let outbox = InMemoryOutbox::new();
+    let transactions = InMemoryTransactionManager;
+    let event = DomainEvent::new(EventType::ProductDeleted, Uuid::new_v4(), &json!({})).unwrap();
+    
+    for _ in 0..2 {
+        let mut uow = transactions.begin().await.unwrap();
+        outbox.append_in(&mut uow, std::slice::from_ref(&event)).await.unwrap();
+        uow.commit().await.unwrap();
+    }
+    assert_eq!(outbox.entries().len(), 1);
+}
+
+#[actix_web::test]
+async fn relay_publishes_each_event_once_in_sequence_order() {
+    let fixture = fixture();
+    let product = fixture.products.create_product(product_dto("LAMP-1", "19.99")).await.unwrap();
+    let order = fixture.orders.create_order(order_dto(&product)).await.unwrap();
+    
+    assert_eq!(fixture.relay.relay_pending().await.unwrap(), 2);
+    assert_eq!(
+        fixture.publisher.published(),
+        vec![(EventType::ProductCreated, product.id.unwrap()), (EventType::OrderCreated, order.id.unwrap())]
+    );
+    
+    // Published events stay in the outbox but are not published again
+    assert_eq!(fixture.relay.relay_pending().await.unwrap(), 0);
+    assert_eq!(fixture.publisher.published().len(), 2);
+    assert_eq!(fixture.outbox.entries().len(), 2);
+}
+
+#[actix_web::test]
+async fn relay_holds_back_an_aggregate_until_its_failed_event_is_published() {
+    let fixture = fixture();
+    let lamp = fixture.products.create_product(product_dto("LAMP-1", "19.99")).await.unwrap();
+    let lamp_id = lamp.id.unwrap();
+    let chair = fixture.products.create_product(product_dto("CHAIR-1", "49.00")).await.unwrap();
+    let chair_id = chair.id.unwrap();
+    let reprice: UpdateProductDto = serde_json::from_value(json!({ "price": { "amount": "29.99", "currency": "USD" } })).unwrap();
+    fixture.products.update_product(lamp_id, reprice).await.unwrap();
+    
+    // The lamp's first event fails, so its later ones wait; the chair is unaffected
+    fixture.publisher.fail_for(lamp_id, true);
+    assert_eq!(fixture.relay.relay_pending().await.unwrap(), 1);
+    assert_eq!(fixture.publisher.published(), vec![(EventType::ProductCreated, chair_id)]);
+    
+    fixture.publisher.fail_for(lamp_id, false);
+    assert_eq!(fixture.relay.relay_pending().await.unwrap(), 3);
+    assert_eq!(
+        fixture.publisher.published(),
+        vec![
+            (EventType::ProductCreated, chair_id),
+            (EventType::ProductCreated, lamp_id),
+            (EventType::ProductUpdated, lamp_id),
+            (EventType::ProductPriceChanged, lamp_id),
+        ]
+    );
+}
+
+// Without skipping them, a batch full of a failing aggregate's events would stall every other one
+#[actix_web::test]
+async fn claims_skip_aggregates_waiting_to_retry() {
+    let fixture = fixture();
+    let relay = relay(&fixture.outbox, &fixture.publisher, 2).with_retries(3, Duration::hours(1));
+    let lamp = fixture.products.create_product(product_dto("LAMP-1", "19.99")).await.unwrap();
+    let lamp_id = lamp.id.unwrap();
+    fixture.products.delete_product(lamp_id).await.unwrap();
+    let chair = fixture.products.create_product(product_dto("CHAIR-1", "49.00")).await.unwrap();
+    
+    fixture.publisher.fail_for(lamp_id, true);
+    assert_eq!(relay.relay_batch().await.unwrap(), 0);
+    assert_eq!(relay.relay_batch().await.unwrap(), 1);
+    assert_eq!(fixture.publisher.published(), vec![(EventType::ProductCreated, chair.id.unwrap())]);
+    
+    // The lamp waits out its retry delay even once the publisher would take it
+    fixture.publisher.fail_for(lamp_id, false);
+    assert_eq!(relay.relay_pending().await.unwrap(), 0);
+    assert_eq!(fixture.outbox.entries()[0].attempts, 1);
+}
+
+#[actix_web::test]
+async fn events_that_keep_failing_are_dead_lettered_until_replayed() {
+    let fixture = fixture();
+    let relay = relay(&fixture.outbox, &fixture.publisher, 100).with_retries(2, Duration::zero());
+    let lamp = fixture.products.create_product(product_dto("LAMP-1", "19.99")).await.unwrap();
+    let lamp_id = lamp.id.unwrap();
+    
+    fixture.publisher.fail_for(lamp_id, true);
+    relay.relay_pending().await.unwrap();
+    assert!(fixture.outbox.dead_letters().is_empty());
+    relay.relay_pending().await.unwrap();
+    assert_eq!(fixture.outbox.dead_letters().len(), 1);
+    
+    // The lamp's later events no longer wait for it
+    fixture.publisher.fail_for(lamp_id, false);
+    fixture.products.delete_product(lamp_id).await.unwrap();
+    assert_eq!(relay.relay_pending().await.unwrap(), 1);
+    assert_eq!(fixture.publisher.published(), vec![(EventType::ProductDeleted, lamp_id)]);
+    
+    let replay = ReplayEventsDto { from_sequence: 1, aggregate_id: Some(lamp_id) };
+    assert_eq!(relay.replay(replay).await.unwrap(), 2);
+    assert!(fixture.outbox.dead_letters().is_empty());
+    assert_eq!(relay.relay_pending().await.unwrap(), 2);
+    assert_eq!(
+        fixture.publisher.published()[1..],
+        [(EventType::ProductCreated, lamp_id), (EventType::ProductDeleted, lamp_id)]
+    );
+}
+
+#[actix_web::test]
+async fn an_unreachable_publisher_does_not_use_up_attempts() {
+    let fixture = fixture();
+    let relay = relay(&fixture.outbox, &fixture.publisher, 100).with_retries(1, Duration::hours(1));
+    fixture.products.create_product(product_dto("LAMP-1", "19.99")).await.unwrap();
+    fixture.products.create_product(product_dto("CHAIR-1", "49.00")).await.unwrap();
+    
+    fixture.publisher.set_unreachable(true);
+    assert_eq!(relay.relay_pending().await.unwrap(), 0);
+    assert!(fixture.outbox.entries().iter().all(|entry| entry.attempts == 0));
+    
+    fixture.publisher.set_unreachable(false);
+    assert_eq!(relay.relay_pending().await.unwrap(), 2);
+    assert!(fixture.outbox.dead_letters().is_empty());
+}
+
+#[actix_web::test]
+async fn replay_publishes_events_again_in_order() {
+    let fixture = fixture();
+    let lamp = fixture.products.create_product(product_dto("LAMP-1", "19.99")).await.unwrap();
+    let lamp_id = lamp.id.unwrap();
+    let chair = fixture.products.create_product(product_dto("CHAIR-1", "49.00")).await.unwrap();
+    fixture.products.delete_product(lamp_id).await.unwrap();
+    fixture.relay.relay_pending().await.unwrap();
+    
+    let replay = ReplayEventsDto { from_sequence: 1, aggregate_id: Some(lamp_id) };
+    assert_eq!(fixture.relay.replay(replay).await.unwrap(), 2);
+    fixture.relay.relay_pending().await.unwrap();
+    assert_eq!(
+        fixture.publisher.published()[3..],
+        [(EventType::ProductCreated, lamp_id), (EventType::ProductDeleted, lamp_id)]
+    );
+    
+    let replay = ReplayEventsDto { from_sequence: 2, aggregate_id: None };
+    assert_eq!(fixture.relay.replay(replay).await.unwrap(), 2);
+    fixture.relay.relay_pending().await.unwrap();
+    assert_eq!(
+        fixture.publisher.published()[5..],
+        [(EventType::ProductCreated, chair.id.unwrap()), (EventType::ProductDeleted, lamp_id)]
+    );
+}
+
+#[actix_web::test]
+async fn purge_drops_only_events_published_before_the_retention_period() {
+    let fixture = fixture();
+    fixture.products.create_product(product_dto("LAMP-1", "19.99")).await.unwrap();
+    fixture.relay.relay_pending().await.unwrap();
+    fixture.products.create_product(product_dto("CHAIR-1", "49.00")).await.unwrap();
+    
+    assert_eq!(fixture.relay.purge(1).await.unwrap(), 0);
+    assert_eq!(fixture.outbox.purge_published(Utc::now()).await.unwrap(), 1);
+    assert_eq!(fixture.outbox.entries().len(), 1);
+}
+
+#[actix_web::test]
+async fn replay_endpoint_requires_admin_and_a_valid_sequence() {
+    let fixture = fixture();
+    fixture.products.create_product(product_dto("LAMP-1", "19.99")).await.unwrap();
+    fixture.relay.relay_pending().await.unwrap();
+    
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(AuthService::new(&AuthConfig { jwt_secret: JWT_SECRET.to_string() })))
+            .app_data(web::Data::new(fixture.relay))
+            .configure(configure_routes),
+    )
+    .await;
+    
+    let replay = |roles: &[&str], body: Value| {
+        test::TestRequest::post().uri("/api/events/replay").insert_header(bearer_as(1, roles)).set_json(body).to_request()
+    };
+    
+    let response = test::call_service(&app, replay(&["catalog_manager"], json!({ "from_sequence": 1 }))).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    
+    let response = test::call_service(&app, replay(&["admin"], json!({ "from_sequence": 0 }))).await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    
+    let response = test::call_service(&app, replay(&["admin"], json!({ "from_sequence": 1 }))).await;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body, json!({ "replayed": 1 }));
+}
@@ -0,0 +1,316 @@
+// Recovery of order sagas interrupted part-way, using the in-memory repositories
+use std::sync::{Arc, Mutex};
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use serde_json::json;
+
+use business_service::errors::{ServiceError, ServiceResult};
+use business_service::models::{
+    CreateOrderDto, CreateOrderItemDto, Order, OrderItem, OrderStatus, Product, SagaRecord, SagaStatus, StockChange,
+    StockLevel, UpdateOrderStatusDto,
+};
+use business_service::repositories::{
+    InMemoryOrderHistory, InMemoryOutbox, InMemoryRepository, InMemorySagaStore, InMemoryTransactionManager, Repository,
+    SagaStore, StockRepository,
+};
+use business_service::services::{
+    operation_id, OrderService, SagaCoordinator, SagaDefinition, SagaStep, StatusChangePayload, CHANGE_ORDER_STATUS,
+    DELETE_ORDER, PLACE_ORDER,
+};
+
+struct Fixture {
+    products: Arc<InMemoryRepository<Product>>,
+    orders: Arc<InMemoryRepository<Order>>,
+    sagas: Arc<InMemorySagaStore>,
+    service: OrderService,
+}
+
+fn product(name: &str, on_hand: i32) -> Product {
+    let mut product = Product::new(
+        name.to_string(),
+        format!("a {}", name),
+        serde_json::from_value(json!({ "amount": "19.99", "currency": "USD" })).unwrap(),
+        format!("SKU-{}", name.to_uppercase()),
+        "home".to_string(),
+    );
+    product.set_stock(StockLevel::new(on_hand, 0));
+    product
+}
+
+async fn fixture() -> (Fixture, Product) {
+    let products = Arc::new(InMemoryRepository::<Product>::new());
+    let orders = Arc::new(InMemoryRepository::<Order>::new());
+    let sagas = Arc::new(InMemorySagaStore::new());
+    let service = OrderService::new(
+        orders.clone(),
+        products.clone(),
+        products.clone(),
+        Arc::new(InMemoryOrderHistory::new()),
+        Arc::new(InMemoryOutbox::new()),
+        Arc::new(InMemoryTransactionManager),
+        sagas.clone(),
+    );
+    
+    let product = products.create(product("lamp", 5)).await.unwrap();
+    
+    (Fixture { products, orders, sagas, service }, product)
+}
+
+// A place-order saga whose first step (reserving stock) ran before the process died
+async fn interrupted_saga(fixture: &Fixture, product: &Product, status: SagaStatus, completed_steps: i32) -> (SagaRecord, Order) {
+    let order = Order::new(
+        uuid::Uuid::new_v4(),
+        vec![OrderItem {
+            product_id: product.id.unwrap(),
+            name: product.name.clone(),
+            sku: product.sku.clone(),
+            quantity: 2,
+            price: product.price,
+        }],
+    )
+    .unwrap();
+    
+    let mut saga = SagaRecord::new(PLACE_ORDER, serde_json::to_value(&order).unwrap());
+    saga.status = status;
+    saga.completed_steps = completed_steps;
+    saga.updated_at = Utc::now() - Duration::minutes(5);
+    fixture.sagas.save(&saga).await.unwrap();
+    
+    fixture.products
+        .change_stock(product.id.unwrap(), StockChange::reserve(2), operation_id(&saga, 0, "execute"))
+        .await
+        .unwrap();
+    
+    (saga, order)
+}
+
+#[actix_web::test]
+async fn recovery_resumes_running_saga_without_repeating_steps() {
+    let (fixture, product) = fixture().await;
+    
+    // The reservation happened but the saga never recorded it
+    let (_, order) = interrupted_saga(&fixture, &product, SagaStatus::Running, 0).await;
+    
+    assert_eq!(fixture.service.recover_sagas().await.unwrap(), 1);
+    
+    assert!(fixture.orders.find_by_id(order.id.unwrap()).await.unwrap().is_some());
+    let stock = fixture.products.find_stock(product.id.unwrap()).await.unwrap().unwrap();
+    assert_eq!((stock.on_hand, stock.reserved), (5, 2));
+    
+    // Nothing is left to recover
+    assert_eq!(fixture.service.recover_sagas().await.unwrap(), 0);
+}
+
+#[actix_web::test]
+async fn recovery_finishes_compensating_saga() {
+    let (fixture, product) = fixture().await;
+    let (_, order) = interrupted_saga(&fixture, &product, SagaStatus::Compensating, 1).await;
+    
+    assert_eq!(fixture.service.recover_sagas().await.unwrap(), 1);
+    
+    assert!(fixture.orders.find_by_id(order.id.unwrap()).await.unwrap().is_none());
+    let stock = fixture.products.find_stock(product.id.unwrap()).await.unwrap().unwrap();
+    assert_eq!((stock.on_hand, stock.reserved), (5, 0));
+}
+
+#[actix_web::test]
+async fn recent_sagas_are_left_to_their_owner() {
+    let (fixture, product) = fixture().await;
+    let (mut saga, _) = interrupted_saga(&fixture, &product, SagaStatus::Running, 1).await;
+    saga.updated_at = Utc::now();
+    fixture.sagas.save(&saga).await.unwrap();
+    
+    assert_eq!(fixture.service.recover_sagas().await.unwrap(), 0);
+}
+
+#[actix_web::test]
+async fn an_abandoned_saga_is_claimed_by_one_coordinator_only() {
+    let (fixture, product) = fixture().await;
+    let (saga, _) = interrupted_saga(&fixture, &product, SagaStatus::Running, 1).await;
+    let cutoff = Utc::now() - Duration::minutes(1);
+    
+    let claimed = fixture.sagas.claim_unfinished(uuid::Uuid::new_v4(), cutoff).await.unwrap();
+    
+    assert_eq!(claimed.len(), 1);
+    assert_eq!(claimed[0].id, saga.id);
+    assert!(fixture.sagas.claim_unfinished(uuid::Uuid::new_v4(), cutoff).await.unwrap().is_empty());
+}
+
+// A coordinator that stalled and lost its saga must not overwrite the progress of the one
+// that took it over
+#[actix_web::test]
+async fn a_saga_taken_over_refuses_saves_from_its_previous_owner() {
+    let (fixture, product) = fixture().await;
+    let (mut saga, _) = interrupted_saga(&fixture, &product, SagaStatus::Running, 1).await;
+    
+    let claimed = fixture.sagas.claim_unfinished(uuid::Uuid::new_v4(), Utc::now()).await.unwrap();
+    assert_eq!(claimed.len(), 1);
+    
+    saga.completed_steps = 2;
+    saga.updated_at = Utc::now();
+    let result = fixture.sagas.save(&saga).await;
+    
+    assert!(matches!(result, Err(ServiceError::ConflictError(_))));
+    fixture.sagas.save(&claimed[0]).await.unwrap();
+}
+
+// Operation ids used to be kept in a list of the last hundred per product, so a busy
+// product forgot them while a saga could still repeat its step
+#[actix_web::test]
+async fn stock_operations_are_remembered_however_many_follow() {
+    let (fixture, product) = fixture().await;
+    let product_id = product.id.unwrap();
+    let first = uuid::Uuid::new_v4();
+    fixture.products.change_stock(product_id, StockChange::adjust(1), first).await.unwrap();
+    for _ in 0..150 {
+        fixture.products.change_stock(product_id, StockChange::adjust(1), uuid::Uuid::new_v4()).await.unwrap();
+    }
+    
+    assert!(fixture.products.has_applied(product_id, first).await.unwrap());
+    let stock = fixture.products.change_stock(product_id, StockChange::adjust(1), first).await.unwrap();
+    assert_eq!(stock.on_hand, 5 + 151);
+    assert!(!fixture.products.has_applied(product_id, uuid::Uuid::new_v4()).await.unwrap());
+}
+
+// Appends to a shared log, and removes its entry again when compensated
+struct LoggingStep {
+    name: &'static str,
+    log: Arc<Mutex<Vec<&'static str>>>,
+    fails: bool,
+}
+
+#[async_trait]
+impl SagaStep for LoggingStep {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+    
+    // Writes before failing, like a step whose last write is refused
+    async fn execute(&self) -> ServiceResult<()> {
+        self.log.lock().unwrap().push(self.name);
+        if self.fails {
+            return Err(ServiceError::ConflictError(format!("{} failed", self.name)));
+        }
+        Ok(())
+    }
+    
+    async fn compensate(&self) -> ServiceResult<()> {
+        self.log.lock().unwrap().retain(|entry| *entry != self.name);
+        Ok(())
+    }
+}
+
+struct LoggingSaga(Arc<Mutex<Vec<&'static str>>>);
+
+impl SagaDefinition for LoggingSaga {
+    fn kind(&self) -> &'static str {
+        "logging"
+    }
+    
+    fn steps(&self, _saga: &SagaRecord) -> ServiceResult<Vec<Box<dyn SagaStep>>> {
+        Ok(vec![
+            Box::new(LoggingStep { name: "first", log: self.0.clone(), fails: false }),
+            Box::new(LoggingStep { name: "second", log: self.0.clone(), fails: true }),
+        ])
+    }
+}
+
+#[actix_web::test]
+async fn a_failed_step_is_compensated_with_the_steps_before_it() {
+    let sagas = Arc::new(InMemorySagaStore::new());
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let mut coordinator = SagaCoordinator::new(sagas.clone());
+    coordinator.register(Arc::new(LoggingSaga(log.clone())));
+    
+    let result = coordinator.run("logging", json!({})).await;
+    
+    assert!(matches!(result, Err(ServiceError::ConflictError(_))));
+    assert!(log.lock().unwrap().is_empty());
+    assert!(sagas.claim_unfinished(uuid::Uuid::new_v4(), Utc::now() + Duration::minutes(1)).await.unwrap().is_empty());
+}
+
+// The refused reservation is not released, or it would take back units the first one holds
+#[actix_web::test]
+async fn a_refused_reservation_is_not_undone() {
+    let (fixture, lamp) = fixture().await;
+    let chair = fixture.products.create(product("chair", 1)).await.unwrap();
+    let dto = CreateOrderDto {
+        customer_id: uuid::Uuid::new_v4(),
+        items: vec![
+            CreateOrderItemDto { product_id: lamp.id.unwrap(), quantity: 2 },
+            CreateOrderItemDto { product_id: chair.id.unwrap(), quantity: 3 },
+        ],
+    };
+    
+    let result = fixture.service.create_order(dto).await;
+    
+    assert!(matches!(result, Err(ServiceError::ConflictError(_))));
+    for (product, on_hand) in [(lamp, 5), (chair, 1)] {
+        let stock = fixture.products.find_stock(product.id.unwrap()).await.unwrap().unwrap();
+        assert_eq!((stock.on_hand, stock.reserved), (on_hand, 0));
+    }
+    assert!(fixture.sagas.claim_unfinished(uuid::Uuid::new_v4(), Utc::now() + Duration::minutes(1)).await.unwrap().is_empty());
+}
+
+// Two cancellations of one pending order both pass the unlocked transition check. The
+// second must not take the first one's change for its own, release the reservation again
+// or, failing that, revert the cancellation.
+#[actix_web::test]
+async fn concurrent_changes_to_the_same_status_apply_once() {
+    let (fixture, product) = fixture().await;
+    let dto = CreateOrderDto {
+        customer_id: uuid::Uuid::new_v4(),
+        items: vec![CreateOrderItemDto { product_id: product.id.unwrap(), quantity: 2 }],
+    };
+    let order = fixture.service.create_order(dto).await.unwrap();
+    let order_id = order.id.unwrap();
+    
+    for _ in 0..2 {
+        let change = StatusChangePayload {
+            order: order.clone(),
+            to: OrderStatus::Cancelled,
+            actor: None,
+            reason: None,
+        };
+        let mut saga = SagaRecord::new(CHANGE_ORDER_STATUS, serde_json::to_value(&change).unwrap());
+        saga.updated_at = Utc::now() - Duration::minutes(5);
+        fixture.sagas.save(&saga).await.unwrap();
+    }
+    
+    assert_eq!(fixture.service.recover_sagas().await.unwrap(), 2);
+    
+    let cancelled = fixture.orders.find_by_id(order_id).await.unwrap().unwrap();
+    assert_eq!(cancelled.status, OrderStatus::Cancelled);
+    let history = fixture.service.get_order_history(order_id).await.unwrap();
+    assert_eq!(history.iter().map(|change| change.to).collect::<Vec<_>>(), [OrderStatus::Pending, OrderStatus::Cancelled]);
+    let stock = fixture.products.find_stock(product.id.unwrap()).await.unwrap().unwrap();
+    assert_eq!((stock.on_hand, stock.reserved), (5, 0));
+}
+
+// A delete planned while the order was pending releases its reservation. If the order was
+// cancelled before the delete ran, that reservation is gone and releasing it again would free
+// stock held by other orders.
+#[actix_web::test]
+async fn a_delete_planned_before_a_status_change_is_refused() {
+    let (fixture, product) = fixture().await;
+    let place = || CreateOrderDto {
+        customer_id: uuid::Uuid::new_v4(),
+        items: vec![CreateOrderItemDto { product_id: product.id.unwrap(), quantity: 2 }],
+    };
+    let order = fixture.service.create_order(place()).await.unwrap();
+    fixture.service.create_order(place()).await.unwrap();
+    let order_id = order.id.unwrap();
+    
+    let mut saga = SagaRecord::new(DELETE_ORDER, serde_json::to_value(&order).unwrap());
+    saga.updated_at = Utc::now() - Duration::minutes(5);
+    fixture.sagas.save(&saga).await.unwrap();
+    let cancel = UpdateOrderStatusDto { status: OrderStatus::Cancelled, reason: None };
+    fixture.service.update_order_status(order_id, cancel, None).await.unwrap();
+    
+    assert_eq!(fixture.service.recover_sagas().await.unwrap(), 1);
+    
+    let cancelled = fixture.orders.find_by_id(order_id).await.unwrap().unwrap();
+    assert_eq!(cancelled.status, OrderStatus::Cancelled);
+    let stock = fixture.products.find_stock(product.id.unwrap()).await.unwrap().unwrap();
+    assert_eq!((stock.on_hand, stock.reserved), (5, 2));
+}
@@ -0,0 +1,141 @@
+// Full-text product search through `GET /api/products/search`, against the in-memory repository
+use actix_web::{http::StatusCode, test};
+use rust_decimal::Decimal;
+use serde_json::Value;
+
+use business_service::models::{Currency, Money, Product, SearchTerms};
+
+mod common;
+use common::{bearer_as, create_product, test_app};
+
+macro_rules! search {
+    ($app:expr, $query:expr) => {{
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/products/search?{}", $query))
+            .insert_header(bearer_as(1, &["customer"]))
+            .to_request();
+        test::call_service($app, req).await
+    }};
+}
+
+fn names(body: &Value) -> Vec<&str> {
+    body["items"].as_array().unwrap().iter().map(|hit| hit["product"]["name"].as_str().unwrap()).collect()
+}
+
+#[actix_web::test]
+async fn matches_in_heavier_fields_rank_first() {
+    let app = test_app!();
+    create_product!(&app, "Reading chair", "Pairs well with a desk lamp", "furniture", "120.00", 2);
+    create_product!(&app, "Desk lamp", "An adjustable lamp", "lighting", "35.00", 5);
+    create_product!(&app, "Bookshelf", "Five shelves", "furniture", "80.00", 1);
+    
+    let response = search!(&app, "q=lamps");
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+    
+    assert_eq!(body["total"], 2);
+    assert_eq!(names(&body), vec!["Desk lamp", "Reading chair"]);
+    let scores: Vec<f64> = body["items"].as_array().unwrap().iter().map(|hit| hit["score"].as_f64().unwrap()).collect();
+    assert!(scores[0] > scores[1]);
+}
+
+#[actix_web::test]
+async fn matched_words_are_highlighted() {
+    let app = test_app!();
+    create_product!(&app, "Desk lamp", "A <bright> lamp & shade", "lighting", "35.00", 5);
+    
+    let body: Value = test::read_body_json(search!(&app, "q=LAMP")).await;
+    
+    let highlights = &body["items"][0]["highlights"];
+    assert_eq!(highlights["name"], "Desk <mark>lamp</mark>");
+    assert_eq!(highlights["description"], "A &lt;bright&gt; <mark>lamp</mark> &amp; shade");
+    assert!(highlights.get("category").is_none());
+}
+
+#[actix_web::test]
+async fn other_forms_of_a_word_are_highlighted() {
+    let app = test_app!();
+    create_product!(&app, "Running shoes", "Stopped by the door", "sport", "60.00", 3);
+    
+    let body: Value = test::read_body_json(search!(&app, "q=runs+stopping")).await;
+    
+    let highlights = &body["items"][0]["highlights"];
+    assert_eq!(highlights["name"], "<mark>Running</mark> shoes");
+    assert_eq!(highlights["description"], "<mark>Stopped</mark> by the door");
+}
+
+#[actix_web::test]
+async fn words_starting_with_a_term_are_highlighted_when_none_match_exactly() {
+    let price = Money::new(Decimal::from(40), Currency::Usd);
+    let product = Product::new("Generously sized lamp".to_string(), "Bright".to_string(), price, "SKU-1".to_string(), "lighting".to_string());
+    
+    // As for a product MongoDB's stemmer matched but ours does not
+    let highlights = SearchTerms::parse("generous").unwrap().highlight(&product);
+    
+    assert_eq!(highlights.get("name").map(String::as_str), Some("<mark>Generously</mark> sized lamp"));
+    assert_eq!(highlights.len(), 1);
+    // Not used when a word matches
+    let highlights = SearchTerms::parse("generous lamp").unwrap().highlight(&product);
+    assert_eq!(highlights.get("name").map(String::as_str), Some("Generously sized <mark>lamp</mark>"));
+}
+
+#[actix_web::test]
+async fn search_combines_with_category_price_and_stock_filters() {
+    let app = test_app!();
+    create_product!(&app, "Desk lamp", "", "lighting", "35.00", 5);
+    create_product!(&app, "Floor lamp", "", "lighting", "95.00", 5);
+    create_product!(&app, "Lamp oil", "", "supplies", "8.00", 5);
+    create_product!(&app, "Wall lamp", "", "lighting", "40.00", 0);
+    
+    let body: Value = test::read_body_json(search!(&app, "q=lamp&category=lighting&price[gte]=30&price[lte]=50&in_stock=true")).await;
+    
+    assert_eq!(names(&body), vec!["Desk lamp"]);
+}
+
+#[actix_web::test]
+async fn phrases_must_appear_and_excluded_words_must_not() {
+    let app = test_app!();
+    create_product!(&app, "Desk lamp", "", "lighting", "35.00", 5);
+    create_product!(&app, "Lamp for a desk", "", "lighting", "35.00", 5);
+    create_product!(&app, "Desk lamp shade", "", "lighting", "15.00", 5);
+    
+    let body: Value = test::read_body_json(search!(&app, "q=%22desk%20lamp%22%20-shade")).await;
+    
+    assert_eq!(names(&body), vec!["Desk lamp"]);
+}
+
+#[actix_web::test]
+async fn results_are_paged() {
+    let app = test_app!();
+    for i in 0..3 {
+        create_product!(&app, format!("Lamp {}", i), "", "lighting", "10.00", 1);
+    }
+    
+    let body: Value = test::read_body_json(search!(&app, "q=lamp&limit=2")).await;
+    
+    assert_eq!(body["total"], 3);
+    assert_eq!(body["items"].as_array().unwrap().len(), 2);
+    assert!(body["links"]["next"].as_str().unwrap().starts_with("/api/products/search?"));
+    assert!(body["links"]["next"].as_str().unwrap().contains("q=lamp"));
+}
+
+#[actix_web::test]
+async fn bad_searches_are_rejected() {
+    let app = test_app!();
+    
+    for query in ["", "q=", "q=%20-lamp", "q=lamp&sort=price", "q=lamp&colour=red"] {
+        let response = search!(&app, query);
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", query);
+    }
+}
+
+#[actix_web::test]
+async fn search_terms_follow_text_search_syntax() {
+    let terms = SearchTerms::parse(r#"  Boxes "Desk  LAMP" -shades "#).unwrap();
+    
+    assert_eq!(terms.text, r#"Boxes "Desk  LAMP" -shades"#);
+    assert_eq!(terms.words, vec!["box", "desk", "lamp"]);
+    assert_eq!(terms.phrases, vec!["desk lamp"]);
+    assert_eq!(terms.excluded, vec!["shade"]);
+    assert!(SearchTerms::parse(&"a".repeat(201)).is_err());
+}
@@ -0,0 +1,182 @@
+// Spans exported for requests, services and repositories, and W3C trace-context propagation
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use actix_web::middleware::from_fn;
+use actix_web::{http::StatusCode, test};
+use opentelemetry::global;
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use serde_json::{json, Value};
+use tracing::subscriber::DefaultGuard;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::layer::SubscriberExt;
+
+use business_service::api::telemetry::trace_requests;
+use business_service::models::Product;
+use business_service::repositories::{InMemoryRepository, Instrumented};
+use business_service::telemetry::{extract_trace_context, mongo_span, sql_span, trace_context, JsonLinesExporter};
+
+mod common;
+use common::{admin_bearer, test_app, TestServices};
+
+// An app whose product repository records spans, tracing requests as `main` does
+macro_rules! traced_app {
+    () => {
+        test_app!(
+            TestServices::with_products(Arc::new(Instrumented::new(InMemoryRepository::<Product>::new(), "memory", "products"))),
+            from_fn(trace_requests)
+        )
+    };
+}
+const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+const PARENT_SPAN_ID: &str = "00f067aa0ba902b7";
+
+// Collects the JSON lines the exporter writes
+#[derive(Clone, Default)]
+struct ExportedSpans(Arc<Mutex<Vec<u8>>>);
+
+impl Write for ExportedSpans {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+    
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl ExportedSpans {
+    fn all(&self) -> Vec<Value> {
+        String::from_utf8(self.0.lock().unwrap().clone())
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+    
+    fn named(&self, name: &str) -> Value {
+        self.all()
+            .into_iter()
+            .find(|span| span["name"] == name)
+            .unwrap_or_else(|| panic!("no span named {} in {:?}", name, self.all()))
+    }
+}
+
+// Exports every span of this thread as soon as it ends. Tests run on their own threads,
+// so each one only sees its own spans.
+fn trace_into(spans: &ExportedSpans) -> (SdkTracerProvider, DefaultGuard) {
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    let provider = SdkTracerProvider::builder()
+        .with_simple_exporter(JsonLinesExporter::new(spans.clone()))
+        .build();
+    let subscriber = tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("tests")));
+    
+    (provider, tracing::subscriber::set_default(subscriber))
+}
+
+#[actix_web::test]
+async fn incoming_traceparent_continues_the_trace_through_service_and_repository() {
+    let spans = ExportedSpans::default();
+    let (_provider, _guard) = trace_into(&spans);
+    let app = traced_app!();
+    
+    let req = test::TestRequest::post()
+        .uri("/api/products")
+        .insert_header(admin_bearer())
+        .insert_header(("traceparent", format!("00-{}-{}-01", TRACE_ID, PARENT_SPAN_ID)))
+        .set_json(json!({
+            "name": "Widget",
+            "description": "A widget",
+            "price": { "amount": "9.99", "currency": "USD" },
+            "sku": "WID-1",
+            "category": "widgets",
+            "on_hand": 5
+        }))
+        .to_request();
+    let response = test::call_service(&app, req).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    
+    let server = spans.named("POST /api/products");
+    assert_eq!(server["kind"], "server");
+    assert_eq!(server["trace_id"], TRACE_ID);
+    assert_eq!(server["parent_span_id"], PARENT_SPAN_ID);
+    assert_eq!(server["attributes"]["http.route"], "/api/products");
+    assert_eq!(server["attributes"]["http.response.status_code"], 201);
+    
+    let service = spans.named("create_product");
+    assert_eq!(service["trace_id"], TRACE_ID);
+    assert_eq!(service["parent_span_id"], server["span_id"]);
+    
+    let repository = spans.named("products.create_in");
+    assert_eq!(repository["trace_id"], TRACE_ID);
+    assert_eq!(repository["parent_span_id"], service["span_id"]);
+    assert_eq!(repository["attributes"]["store"], "memory");
+}
+
+#[actix_web::test]
+async fn requests_without_traceparent_start_a_new_trace() {
+    let spans = ExportedSpans::default();
+    let (_provider, _guard) = trace_into(&spans);
+    let app = traced_app!();
+    
+    let req = test::TestRequest::get().uri("/api/orders/not-a-uuid").insert_header(admin_bearer()).to_request();
+    let response = test::call_service(&app, req).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    
+    let server = spans.named("GET /api/orders/{id}");
+    assert_eq!(server["parent_span_id"], Value::Null);
+    assert_ne!(server["trace_id"], TRACE_ID);
+    assert_eq!(server["attributes"]["http.response.status_code"], 400);
+    assert_eq!(server["status"], "unset");
+}
+
+#[actix_web::test]
+async fn outbound_trace_context_continues_the_current_span() {
+    let spans = ExportedSpans::default();
+    let (_provider, _guard) = trace_into(&spans);
+    
+    let incoming: HashMap<String, String> = [("traceparent".to_string(), format!("00-{}-{}-01", TRACE_ID, PARENT_SPAN_ID))].into();
+    let span = tracing::info_span!("handler");
+    span.set_parent(extract_trace_context(&incoming)).unwrap();
+    let outgoing = span.in_scope(trace_context);
+    drop(span);
+    
+    let handler = spans.named("handler");
+    assert_eq!(handler["parent_span_id"], PARENT_SPAN_ID);
+    assert_eq!(
+        outgoing["traceparent"],
+        format!("00-{}-{}-01", TRACE_ID, handler["span_id"].as_str().unwrap())
+    );
+}
+
+#[actix_web::test]
+async fn database_spans_carry_system_and_statement() {
+    let spans = ExportedSpans::default();
+    let (_provider, _guard) = trace_into(&spans);
+    
+    sql_span(
+        r#"
+        SELECT id, status
+        FROM orders
+        WHERE id = $1
+        "#,
+    )
+    .in_scope(|| {});
+    let filter = mongodb::bson::doc! { "_id": "42" };
+    mongo_span("find", "products", Some(&filter)).in_scope(|| {});
+    
+    let select = spans.named("SELECT orders");
+    assert_eq!(select["kind"], "client");
+    assert_eq!(select["attributes"]["db.system"], "postgresql");
+    assert_eq!(select["attributes"]["db.operation"], "SELECT");
+    assert_eq!(select["attributes"]["db.statement"], "SELECT id, status FROM orders WHERE id = $1");
+    
+    let find = spans.named("find products");
+    assert_eq!(find["kind"], "client");
+    assert_eq!(find["attributes"]["db.system"], "mongodb");
+    assert_eq!(find["attributes"]["db.mongodb.collection"], "products");
+    assert_eq!(find["attributes"]["db.statement"], r#"{ "_id": "42" }"#);
+}
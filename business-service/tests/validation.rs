@@ -0,0 +1,86 @@
+use business_service::errors::{FieldError, ServiceError};
+use business_service::models::{
+    AdjustStockDto, CreateOrderDto, CreateProductDto, UpdateOrderStatusDto, UpdateProductDto, Validate,
+};
+use serde_json::{json, Value};
+
+fn field_errors<T: Validate>(body: Value) -> Vec<FieldError> {
+    match T::validate(&body) {
+        Ok(()) => Vec::new(),
+        Err(ServiceError::FieldValidationError(errors)) => errors,
+        Err(other) => panic!("unexpected error: {}", other),
+    }
+}
+
+fn pointers(errors: &[FieldError]) -> Vec<&str> {
+    errors.iter().map(|error| error.pointer.as_str()).collect()
+}
+
+#[test]
+fn update_checks_only_the_fields_it_changes() {
+    assert!(field_errors::<UpdateProductDto>(json!({})).is_empty());
+    
+    let errors = field_errors::<UpdateProductDto>(json!({
+        "name": "x".repeat(201),
+        "price": { "amount": "-0.01", "currency": "EUR" },
+    }));
+    assert_eq!(pointers(&errors), ["/name", "/price/amount"]);
+    assert_eq!(errors[0].detail, "must be at most 200 characters, got 201");
+}
+
+#[test]
+fn order_items_are_checked_individually() {
+    let errors = field_errors::<CreateOrderDto>(json!({
+        "customer_id": "00000000-0000-0000-0000-000000000000",
+        "items": [
+            { "product_id": "1a1f7a52-6f4e-4a57-9a55-0f5b6b4f2f11", "quantity": -2 },
+            { "product_id": "00000000-0000-0000-0000-000000000000", "quantity": 3 },
+        ],
+    }));
+    assert_eq!(pointers(&errors), ["/customer_id", "/items/0/quantity", "/items/1/product_id"]);
+}
+
+#[test]
+fn status_reason_must_not_be_blank() {
+    assert!(field_errors::<UpdateOrderStatusDto>(json!({ "status": "cancelled" })).is_empty());
+    assert_eq!(
+        pointers(&field_errors::<UpdateOrderStatusDto>(json!({ "status": "cancelled", "reason": " " }))),
+        ["/reason"]
+    );
+}
+
+#[test]
+fn stock_adjustments_take_exactly_one_of_delta_and_on_hand() {
+    assert!(field_errors::<AdjustStockDto>(json!({ "delta": -3 })).is_empty());
+    assert!(field_errors::<AdjustStockDto>(json!({ "on_hand": 0 })).is_empty());
+    
+    assert_eq!(pointers(&field_errors::<AdjustStockDto>(json!({}))), ["/delta", "/on_hand"]);
+    assert_eq!(pointers(&field_errors::<AdjustStockDto>(json!({ "delta": 1, "on_hand": 2 }))), ["/on_hand"]);
+    let errors = field_errors::<AdjustStockDto>(json!({ "on_hand": -1 }));
+    assert_eq!(pointers(&errors), ["/on_hand"]);
+    assert_eq!(errors[0].detail, "must not be negative");
+    assert_eq!(pointers(&field_errors::<AdjustStockDto>(json!({ "delta": "five" }))), ["/delta"]);
+}
+
+#[test]
+fn missing_and_wrongly_typed_fields_are_reported_with_the_rest() {
+    let errors = field_errors::<CreateProductDto>(json!({
+        "description": 7,
+        "price": { "amount": "1.234" },
+        "sku": " ",
+        "category": "home",
+        "on_hand": "lots",
+    }));
+    assert_eq!(pointers(&errors), ["/name", "/description", "/price/amount", "/sku", "/on_hand"]);
+    assert_eq!(errors[0].detail, "is required");
+    assert_eq!(errors[1].detail, "invalid type: integer `7`, expected a string");
+    assert_eq!(errors[2].detail, "must have at most 2 decimal places for USD");
+    
+    let errors = field_errors::<CreateOrderDto>(json!({
+        "items": [{ "product_id": "not-a-uuid" }, "lamp"],
+    }));
+    assert_eq!(
+        pointers(&errors),
+        ["/customer_id", "/items/0/product_id", "/items/0/quantity", "/items/1/product_id", "/items/1/quantity"]
+    );
+}